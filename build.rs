@@ -1,3 +1,5 @@
 fn main() {
-    slint_build::compile("ui/video_player.slint").unwrap();
+    // 翻訳（ui/translations/<言語>/LC_MESSAGES/video-player-ui.po）は実行ファイルに埋め込む
+    let config = slint_build::CompilerConfiguration::new().with_bundled_translations("ui/translations");
+    slint_build::compile_with_config("ui/video_player.slint", config).unwrap();
 }
@@ -0,0 +1,52 @@
+// 通常モードのウィンドウの縦横比を動画に合わせる
+// リサイズされたら、動画表示エリアが動画と同じ比率になるようにウィンドウの大きさを直す
+
+// 動画表示エリア以外（右側のパネル・下のコントロール・余白）が占める大きさ
+// （ウィンドウの最小サイズ1360x700と、そのときの動画表示エリア960x600の差）
+const CHROME_WIDTH: f32 = 400.0;
+const CHROME_HEIGHT: f32 = 100.0;
+
+#[derive(Default)]
+pub struct AspectLock {
+    // 前回確認したウィンドウの大きさ（論理ピクセル）。変わったときだけ直す
+    last_size: Option<(f32, f32)>,
+    // 前回合わせた縦横比（回転や縦横比の指定で変わったら合わせ直す）
+    last_aspect: f32,
+}
+
+impl AspectLock {
+    /// 次の確認で大きさを合わせ直す（別の比率の動画を読み込んだとき・ロックし直したとき）
+    pub fn reset(&mut self) {
+        self.last_size = None;
+    }
+
+    /// ウィンドウの大きさを確認し、動画の縦横比（幅 / 高さ）からずれていれば直す
+    pub fn update(&mut self, window: &slint::Window, aspect: f32) {
+        // 最大化中はOSが大きさを決めるので触らない
+        if aspect <= 0.0 || window.is_maximized() {
+            return;
+        }
+        if aspect != self.last_aspect {
+            self.last_aspect = aspect;
+            self.last_size = None;
+        }
+        let size = window.size().to_logical(window.scale_factor());
+        let current = (size.width, size.height);
+        let previous = self.last_size.replace(current);
+        if previous == Some(current) {
+            return;
+        }
+
+        // 高さだけを変えられた場合は幅を、それ以外は高さを合わせる
+        let height_driven = previous.is_some_and(|(width, height)| (height - current.1).abs() > (width - current.0).abs());
+        let target = if height_driven {
+            (CHROME_WIDTH + (current.1 - CHROME_HEIGHT) * aspect, current.1)
+        } else {
+            (current.0, CHROME_HEIGHT + (current.0 - CHROME_WIDTH) / aspect)
+        };
+        // 最小サイズより小さくなる場合はウィンドウ側で制限される（その分は黒帯が残る）
+        if (target.0 - current.0).abs() >= 1.0 || (target.1 - current.1).abs() >= 1.0 {
+            window.set_size(slint::LogicalSize::new(target.0, target.1));
+        }
+    }
+}
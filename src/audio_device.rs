@@ -0,0 +1,47 @@
+// 音声の出力デバイスの一覧（ffplayはSDLのAUDIODEV環境変数でデバイスを選べる）
+// SDLはWASAPIのデバイスの表示名で指定するので、同じ名前を列挙する（Windows以外では既定のデバイスのみ）
+
+/// 有効な出力デバイスの名前
+pub fn output_devices() -> Vec<String> {
+    #[cfg(windows)]
+    {
+        windows_devices::output_devices()
+    }
+    #[cfg(not(windows))]
+    {
+        Vec::new()
+    }
+}
+
+#[cfg(windows)]
+mod windows_devices {
+    use windows::Win32::Devices::FunctionDiscovery::PKEY_Device_FriendlyName;
+    use windows::Win32::Media::Audio::{eRender, IMMDeviceEnumerator, MMDeviceEnumerator, DEVICE_STATE_ACTIVE};
+    use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_ALL, COINIT_APARTMENTTHREADED, STGM_READ};
+
+    pub fn output_devices() -> Vec<String> {
+        unsafe {
+            // UIスレッドでは既に初期化済みのことが多いので、結果は無視する
+            let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+            let enumerator: IMMDeviceEnumerator = match CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL) {
+                Ok(enumerator) => enumerator,
+                Err(e) => {
+                    println!("{}", crate::i18n::trf("Cannot list the audio devices: {}", &[&e]));
+                    return Vec::new();
+                }
+            };
+            let Ok(collection) = enumerator.EnumAudioEndpoints(eRender, DEVICE_STATE_ACTIVE) else {
+                return Vec::new();
+            };
+            let count = collection.GetCount().unwrap_or(0);
+            (0..count)
+                .filter_map(|i| {
+                    let device = collection.Item(i).ok()?;
+                    let store = device.OpenPropertyStore(STGM_READ).ok()?;
+                    let name = store.GetValue(&PKEY_Device_FriendlyName).ok()?;
+                    Some(name.to_string())
+                })
+                .collect()
+        }
+    }
+}
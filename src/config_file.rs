@@ -0,0 +1,52 @@
+// 設定の書き出し・読み込み用のJSONファイル（別のPCに設定を持っていくため）
+// 形式: { "format": "video-player-ui-config", "version": 1, "settings": { "キー": "値", ... } }
+use crate::i18n::{tr, trf};
+use crate::json::{self, Value};
+
+const FORMAT_NAME: &str = "video-player-ui-config";
+const FORMAT_VERSION: u32 = 1;
+
+/// 設定をJSONにする（値は設定ファイルと同じ文字列のまま書く）
+pub fn to_json(settings: &[(&str, String)]) -> String {
+    let mut json = format!("{{\n  \"format\": \"{}\",\n  \"version\": {},\n  \"settings\": {{\n", FORMAT_NAME, FORMAT_VERSION);
+    let entries: Vec<String> = settings
+        .iter()
+        .map(|(key, value)| format!("    {}: {}", quote(key), quote(value)))
+        .collect();
+    json.push_str(&entries.join(",\n"));
+    json.push_str("\n  }\n}\n");
+    json
+}
+
+/// JSONから設定を取り出す（数値・真偽値も文字列にする。配列・オブジェクト・nullの値は無視する）
+/// 新しいバージョンで増えた項目があっても読めるよう、versionは確認しない
+pub fn parse(text: &str) -> Result<Vec<(String, String)>, String> {
+    let root = json::parse(text).map_err(|pos| trf("The settings file is broken (position {})", &[&pos]))?;
+    if !matches!(root.get("format").and_then(Value::as_str), Some(format) if format == FORMAT_NAME) {
+        return Err(tr("This is not a settings file").to_string());
+    }
+    let Some(Value::Object(settings)) = root.get("settings") else {
+        return Err(tr("This is not a settings file").to_string());
+    };
+    Ok(settings
+        .iter()
+        .filter_map(|(key, value)| value.as_str().map(|value| (key.clone(), value.to_string())))
+        .collect())
+}
+
+fn quote(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
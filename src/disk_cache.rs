@@ -0,0 +1,139 @@
+// 生成に時間のかかるデータ（波形・サムネイルなど）をディスクに保存して使い回すキャッシュ
+// OSのキャッシュフォルダに置き、合計サイズが上限を超えたら最近使っていないものから消す
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+use crate::i18n::trf;
+
+// 書き込み中のファイルの拡張子（書き終わってから名前を変えるので、消す対象にも読み込む対象にもならない）
+const TEMP_EXTENSION: &str = "tmp";
+
+pub const DEFAULT_MAX_BYTES: u64 = 512 * 1024 * 1024;
+
+pub struct DiskCache {
+    dir: Option<PathBuf>,
+    max_bytes: AtomicU64,
+}
+
+impl DiskCache {
+    pub fn new(max_bytes: u64) -> Self {
+        DiskCache {
+            dir: Self::cache_dir(),
+            max_bytes: AtomicU64::new(max_bytes),
+        }
+    }
+
+    fn cache_dir() -> Option<PathBuf> {
+        // Windowsは%LOCALAPPDATA%、それ以外はXDG_CACHE_HOMEまたは~/.cache
+        let base = std::env::var_os("LOCALAPPDATA")
+            .or_else(|| std::env::var_os("XDG_CACHE_HOME"))
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+        Some(base.join("video-player-ui").join("cache"))
+    }
+
+    /// キャッシュのキー（ファイル・生成の種類・パラメーター・更新日時から作る）
+    /// ファイルが書き換えられると更新日時が変わり、古いデータは使われなくなる（上限を超えたときに消える）
+    pub fn key(path: &Path, kind: &str, params: &str) -> Option<String> {
+        let metadata = fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(SystemTime::UNIX_EPOCH).ok()?;
+        let mut hasher = DefaultHasher::new();
+        fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()).hash(&mut hasher);
+        params.hash(&mut hasher);
+        modified.as_nanos().hash(&mut hasher);
+        metadata.len().hash(&mut hasher);
+        Some(format!("{}-{:016x}", kind, hasher.finish()))
+    }
+
+    /// 保存されたデータ（なければNone。呼び出し側で生成し直してputする）
+    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let path = self.dir.as_ref()?.join(key);
+        let data = fs::read(&path).ok()?;
+        // 最近使ったものとして残るよう、更新日時を今にする
+        if let Ok(file) = fs::File::options().append(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some(data)
+    }
+
+    pub fn put(&self, key: &str, data: &[u8]) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        if let Err(e) = fs::create_dir_all(dir) {
+            eprintln!("{}", trf("Failed to create the cache folder: {}", &[&e]));
+            return;
+        }
+        // 別のスレッドが同じキーを書いていても混ざらないよう、一時ファイルに書いてから置き換える
+        let temp = dir.join(format!("{}.{:?}.{}", key, std::thread::current().id(), TEMP_EXTENSION));
+        if fs::write(&temp, data).and_then(|_| fs::rename(&temp, dir.join(key))).is_err() {
+            let _ = fs::remove_file(&temp);
+            return;
+        }
+        self.evict();
+    }
+
+    pub fn set_max_bytes(&self, max_bytes: u64) {
+        self.max_bytes.store(max_bytes, Ordering::Relaxed);
+        self.evict();
+    }
+
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes.load(Ordering::Relaxed)
+    }
+
+    /// 保存されているデータの合計サイズ（バイト）
+    pub fn size(&self) -> u64 {
+        self.entries().iter().map(|(_, len, _)| len).sum()
+    }
+
+    /// すべて消す（書き込み中のものは残す）
+    pub fn clear(&self) -> Result<(), String> {
+        for (path, _, _) in self.entries() {
+            match fs::remove_file(&path) {
+                Ok(_) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(trf("Cannot clear the cache: {}", &[&e])),
+            }
+        }
+        Ok(())
+    }
+
+    // 合計サイズが上限を超えていれば、更新日時の古いものから消す
+    fn evict(&self) {
+        let mut entries = self.entries();
+        let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        let max_bytes = self.max_bytes();
+        if total <= max_bytes {
+            return;
+        }
+        entries.sort_by_key(|(_, _, modified)| *modified);
+        for (path, len, _) in entries {
+            if total <= max_bytes {
+                break;
+            }
+            // 他のスレッドが先に消していても気にしない
+            let _ = fs::remove_file(&path);
+            total = total.saturating_sub(len);
+        }
+    }
+
+    // 書き終わったデータの一覧（パス・サイズ・更新日時）
+    fn entries(&self) -> Vec<(PathBuf, u64, SystemTime)> {
+        let Some(entries) = self.dir.as_ref().and_then(|dir| fs::read_dir(dir).ok()) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().extension().is_none_or(|extension| extension != TEMP_EXTENSION))
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok().filter(|metadata| metadata.is_file())?;
+                Some((entry.path(), metadata.len(), metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH)))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,31 @@
+// イコライザーの帯域（中心周波数Hz）とプリセット
+
+pub const BANDS: [u32; 5] = [60, 250, 1000, 4000, 12000];
+
+// 各帯域のゲインの上限（dB、±）
+pub const MAX_GAIN: f32 = 12.0;
+
+// 名前は英語（表示するときにi18n::trで翻訳する）
+pub const PRESETS: &[(&str, [f32; 5])] = &[
+    ("Flat", [0.0, 0.0, 0.0, 0.0, 0.0]),
+    ("Bass boost", [6.0, 4.0, 0.0, 0.0, 0.0]),
+    ("Vocal", [-2.0, -1.0, 3.0, 3.0, 0.0]),
+    ("Treble boost", [0.0, 0.0, 0.0, 4.0, 6.0]),
+];
+
+/// ゲインが一致するプリセットの番号（どれとも違えばカスタム扱いでNone）
+pub fn preset_index(gains: &[f32]) -> Option<usize> {
+    PRESETS
+        .iter()
+        .position(|(_, preset)| preset.iter().zip(gains).all(|(a, b)| (a - b).abs() < 0.05))
+}
+
+/// ffmpegのequalizerフィルター（0dBの帯域は省く）
+pub fn filters(gains: &[f32]) -> Vec<String> {
+    BANDS
+        .iter()
+        .zip(gains)
+        .filter(|(_, gain)| gain.abs() >= 0.05)
+        .map(|(frequency, gain)| format!("equalizer=f={}:t=o:w=2:g={:.1}", frequency, gain))
+        .collect()
+}
@@ -0,0 +1,208 @@
+// ffmpeg・ffprobe・ffplayのログ（標準エラー）を診断用に残す
+// 既定では残さずに捨てる。残す場合も最新のMAX_LINES行だけを持ち、長すぎる行は切り詰める
+use std::collections::VecDeque;
+use std::io::Read;
+use std::process::{Child, Command, Stdio};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+
+const MAX_LINES: usize = 300;
+const MAX_LINE_BYTES: usize = 400;
+
+// 残すログの詳しさ（ffmpegの-loglevelのerror / warning / infoに対応する）
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warning,
+    Info,
+}
+
+impl LogLevel {
+    pub fn code(self) -> &'static str {
+        match self {
+            LogLevel::Off => "off",
+            LogLevel::Error => "error",
+            LogLevel::Warning => "warning",
+            LogLevel::Info => "info",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        [LogLevel::Off, LogLevel::Error, LogLevel::Warning, LogLevel::Info]
+            .into_iter()
+            .find(|level| level.code() == code)
+    }
+}
+
+struct Log {
+    level: LogLevel,
+    lines: VecDeque<String>,
+    version: u64, // 行を追加・消去するたびに増やす（表示を更新するかの判定用）
+}
+
+static LOG: Mutex<Log> = Mutex::new(Log {
+    level: LogLevel::Off,
+    lines: VecDeque::new(),
+    version: 0,
+});
+
+pub fn set_level(level: LogLevel) {
+    LOG.lock().unwrap().level = level;
+}
+
+pub fn level() -> LogLevel {
+    LOG.lock().unwrap().level
+}
+
+/// 残す設定なら重要度の印（[error]など）付きでログを出させて標準エラーをパイプにし、残さないなら捨てる
+/// -loglevelはグローバルオプションなので最後に付けても効き、途中の-vより後にあるので優先される
+/// パイプにした場合は、起動後にcaptureで読む・output()の結果をrecord_outputで残すこと
+pub fn configure(command: &mut Command) -> &mut Command {
+    match level() {
+        LogLevel::Off => command.stderr(Stdio::null()),
+        level => command
+            .args(["-loglevel", &format!("level+{}", level.code())])
+            .stderr(Stdio::piped()),
+    }
+}
+
+/// 再生用のffmpegのように標準エラーを自分で読む場合の-loglevel（残さない設定なら付けない）
+/// 出力サイズやshowinfoの行も読むので、残す詳しさに関係なくinfoまで出させる（残すのは設定した詳しさまで）
+pub fn decoder_args() -> Vec<String> {
+    match level() {
+        LogLevel::Off => Vec::new(),
+        _ => vec!["-loglevel".to_string(), "level+info".to_string()],
+    }
+}
+
+/// 1つのプロセスのログの読み込み（終了後に、最後に出たエラーの行を取り出せる）
+pub struct ProcessLog(Option<JoinHandle<Option<String>>>);
+
+impl ProcessLog {
+    /// 標準エラーを読むスレッドから作る（スレッドは最後に出たエラーの行を返す）
+    pub fn new(reader: JoinHandle<Option<String>>) -> Self {
+        Self(Some(reader))
+    }
+
+    /// プロセスの終了後に呼ぶ: 最後に出たエラーの行（残していなければNone）
+    pub fn last_error(self) -> Option<String> {
+        self.0?.join().ok()?
+    }
+}
+
+/// 起動したプロセスの標準エラーを別スレッドで読んで残す
+/// 標準出力の読み込みと並行して読むので、どちらのパイプも詰まってffmpegが止まることはない
+pub fn capture(tool: &str, child: &mut Child) -> ProcessLog {
+    let Some(stderr) = child.stderr.take() else {
+        return ProcessLog(None);
+    };
+    let tool = tool.to_string();
+    ProcessLog::new(thread::spawn(move || {
+        let mut last_error = None;
+        for_each_line(stderr, |line| {
+            let (severity, line) = strip_level(line);
+            if record(&tool, severity, &line) {
+                last_error = Some(line);
+            }
+        });
+        last_error
+    }))
+}
+
+/// output()で集めた標準エラーを残し、最後に出たエラーの行を返す
+pub fn record_output(tool: &str, stderr: &[u8]) -> Option<String> {
+    let mut last_error = None;
+    for_each_line(stderr, |line| {
+        let (severity, line) = strip_level(line);
+        if record(tool, severity, &line) {
+            last_error = Some(line);
+        }
+    });
+    last_error
+}
+
+/// 1行を残す（設定より詳しい行は残さない）。エラーの行ならtrue
+pub fn record(tool: &str, severity: Option<LogLevel>, line: &str) -> bool {
+    let mut log = LOG.lock().unwrap();
+    // 印のない行（複数行にわたるメッセージの続きなど）は、設定した詳しさの行として扱う
+    let severity = severity.unwrap_or(log.level);
+    if log.level == LogLevel::Off || severity > log.level || line.trim().is_empty() {
+        return false;
+    }
+    if log.lines.len() >= MAX_LINES {
+        log.lines.pop_front();
+    }
+    log.lines.push_back(format!("{}: {}", tool, line));
+    log.version += 1;
+    severity == LogLevel::Error
+}
+
+// "-loglevel level+..."で付く"[error] "などの印を取り除き、その重要度を返す（印がなければNone）
+// 印はコンテキストの後に付く（例: "[h264 @ 0x5555] [error] ..."）
+pub fn strip_level(line: &str) -> (Option<LogLevel>, String) {
+    const TAGS: [(&str, LogLevel); 6] = [
+        ("[panic] ", LogLevel::Error),
+        ("[fatal] ", LogLevel::Error),
+        ("[error] ", LogLevel::Error),
+        ("[warning] ", LogLevel::Warning),
+        ("[info] ", LogLevel::Info),
+        ("[verbose] ", LogLevel::Info),
+    ];
+    for (tag, level) in TAGS {
+        if let Some(index) = line.find(tag) {
+            return (Some(level), format!("{}{}", &line[..index], &line[index + tag.len()..]));
+        }
+    }
+    (None, line.to_string())
+}
+
+// 改行（\n）と行頭復帰（\r、進行状況の表示に使われる）で区切って1行ずつ渡す
+// 区切りのないまま長く続く出力でもメモリを使い続けないよう、MAX_LINE_BYTESを超えた分は捨てる
+fn for_each_line(mut reader: impl Read, mut f: impl FnMut(&str)) {
+    let mut buffer = [0u8; 4096];
+    let mut line = Vec::new();
+    loop {
+        let count = match reader.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(count) => count,
+        };
+        for &byte in &buffer[..count] {
+            if byte == b'\n' || byte == b'\r' {
+                if !line.is_empty() {
+                    f(&String::from_utf8_lossy(&line));
+                    line.clear();
+                }
+            } else if line.len() < MAX_LINE_BYTES {
+                line.push(byte);
+            }
+        }
+    }
+    if !line.is_empty() {
+        f(&String::from_utf8_lossy(&line));
+    }
+}
+
+/// 最近のログ（古い順）
+pub fn recent() -> Vec<String> {
+    LOG.lock().unwrap().lines.iter().cloned().collect()
+}
+
+/// ログの版（変わっていなければ表示を作り直さなくてよい）
+pub fn version() -> u64 {
+    LOG.lock().unwrap().version
+}
+
+pub fn clear() {
+    let mut log = LOG.lock().unwrap();
+    log.lines.clear();
+    log.version += 1;
+}
+
+/// エラーメッセージにffmpegが出したエラーの行を添える（なければそのまま）
+pub fn with_detail(message: String, detail: Option<String>) -> String {
+    match detail {
+        Some(detail) => format!("{}: {}", message, detail.trim()),
+        None => message,
+    }
+}
@@ -0,0 +1,147 @@
+// フォルダを開いてプレイリストにするときの、動画ファイルの検索と並べ替え
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::i18n::trf;
+
+/// フォルダ内の動画ファイル（拡張子で判定）を自然順に並べて返す
+/// 隠しファイル・隠しフォルダは飛ばす。recursiveならサブフォルダも探す（フォルダごとにまとめて並べる）
+pub fn scan_videos(dir: &Path, extensions: &[&str], recursive: bool) -> Result<Vec<PathBuf>, String> {
+    let entries = fs::read_dir(dir).map_err(|e| trf("Cannot open the folder: {}", &[&e]))?;
+    let mut files = Vec::new();
+    let mut subdirs = Vec::new();
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        if is_hidden(&path) {
+            continue;
+        }
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            if recursive {
+                subdirs.push(path);
+            }
+        } else if has_extension(&path, extensions) {
+            files.push(path);
+        }
+    }
+    files.sort_by(|a, b| natural_cmp(&file_name(a), &file_name(b)));
+    subdirs.sort_by(|a, b| natural_cmp(&file_name(a), &file_name(b)));
+
+    // 読めないサブフォルダ（権限がないなど）は飛ばす
+    for subdir in subdirs {
+        match scan_videos(&subdir, extensions, true) {
+            Ok(videos) => files.extend(videos),
+            Err(e) => eprintln!("{}", trf("Cannot read the subfolder: {} ({})", &[&subdir.display(), &e])),
+        }
+    }
+    Ok(files)
+}
+
+/// 数字の部分を数値として比べる（ep2がep10より前になる）。それ以外は大文字小文字を区別しない
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x = take_number(&mut a);
+                let y = take_number(&mut b);
+                // 先頭の0を除いた桁数、数字の並びの順に比べる（桁数に制限がないよう文字列のまま比べる）
+                let ordering = x
+                    .trim_start_matches('0')
+                    .len()
+                    .cmp(&y.trim_start_matches('0').len())
+                    .then_with(|| x.trim_start_matches('0').cmp(y.trim_start_matches('0')))
+                    .then_with(|| x.len().cmp(&y.len()));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+fn take_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut number = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+        number.push(c);
+    }
+    number
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default()
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extensions.iter().any(|e| e.eq_ignore_ascii_case(extension)))
+}
+
+// 「.」で始まる名前、またはWindowsの隠し属性のもの
+fn is_hidden(path: &Path) -> bool {
+    if file_name(path).starts_with('.') {
+        return true;
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        if let Ok(metadata) = fs::metadata(path) {
+            return metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(names: &[&str]) -> Vec<String> {
+        let mut names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+        names.sort_by(|a, b| natural_cmp(a, b));
+        names
+    }
+
+    #[test]
+    fn numbers_compare_by_value() {
+        assert_eq!(natural_cmp("ep2", "ep10"), Ordering::Less);
+        assert_eq!(natural_cmp("ep10", "ep9"), Ordering::Greater);
+        assert_eq!(sorted(&["ep10.mp4", "ep1.mp4", "ep2.mp4"]), ["ep1.mp4", "ep2.mp4", "ep10.mp4"]);
+        // u64に収まらない桁数でも比べられる
+        assert_eq!(natural_cmp("ep99999999999999999999", "ep100000000000000000000"), Ordering::Less);
+    }
+
+    #[test]
+    fn leading_zeros_do_not_change_the_value() {
+        assert_eq!(natural_cmp("ep02", "ep10"), Ordering::Less);
+        assert_eq!(natural_cmp("ep010", "ep9"), Ordering::Greater);
+        // 値が同じなら0の少ない方が先（順序が決まるように）
+        assert_eq!(natural_cmp("ep2", "ep02"), Ordering::Less);
+        assert_eq!(sorted(&["ep010", "ep002", "ep2", "ep1"]), ["ep1", "ep2", "ep002", "ep010"]);
+    }
+
+    #[test]
+    fn letters_ignore_case() {
+        assert_eq!(natural_cmp("Ep2", "ep10"), Ordering::Less);
+        assert_eq!(natural_cmp("apple", "Banana"), Ordering::Less);
+        assert_eq!(natural_cmp("EP1", "ep1"), Ordering::Equal);
+        assert_eq!(natural_cmp("ep1", "ep1 extra"), Ordering::Less);
+    }
+}
@@ -0,0 +1,34 @@
+// 全画面表示するモニターの選択（winitのモニター一覧を使う）と、ウィンドウを前面に出す処理
+use slint::winit_030::winit::window::Fullscreen;
+use slint::winit_030::WinitWindowAccessor;
+use crate::i18n::trf;
+
+/// 接続中のモニターの名前（ウィンドウが表示される前は空）
+pub fn monitor_names(window: &slint::Window) -> Vec<String> {
+    window
+        .with_winit_window(|w| w.available_monitors().filter_map(|monitor| monitor.name()).collect())
+        .unwrap_or_default()
+}
+
+/// 全画面の切り替え。targetのモニターが見つからなければ（外された場合など）今いるモニターで全画面にする
+pub fn set_fullscreen(window: &slint::Window, enabled: bool, target: Option<&str>) {
+    // Slint側の状態も合わせておく（winitだけで切り替えると、Slintが元に戻してしまう）
+    window.set_fullscreen(enabled);
+    let (true, Some(target)) = (enabled, target) else {
+        return;
+    };
+    window.with_winit_window(|w| {
+        match w.available_monitors().find(|monitor| monitor.name().as_deref() == Some(target)) {
+            Some(monitor) => w.set_fullscreen(Some(Fullscreen::Borderless(Some(monitor)))),
+            None => println!("{}", trf("Monitor \"{}\" was not found, going fullscreen on the current monitor", &[&target])),
+        }
+    });
+}
+
+/// 最小化を解除して前面に出す（別の起動からファイルを受け取ったときなど）
+pub fn bring_to_front(window: &slint::Window) {
+    window.with_winit_window(|w| {
+        w.set_minimized(false);
+        w.focus_window();
+    });
+}
@@ -0,0 +1,55 @@
+// 表示中のフレームのRGBヒストグラム
+
+// 計算に使う画素数の上限（大きいフレームは間引いて数える）
+const MAX_SAMPLES: u32 = 65536;
+
+pub struct Histogram {
+    pub red: [u32; 256],
+    pub green: [u32; 256],
+    pub blue: [u32; 256],
+}
+
+impl Histogram {
+    /// pixelsはRGBAの生データ（width x height）
+    pub fn compute(pixels: &[u8], width: u32, height: u32) -> Self {
+        let mut histogram = Self {
+            red: [0; 256],
+            green: [0; 256],
+            blue: [0; 256],
+        };
+        
+        // 縦横を同じ間隔で間引く（960x600なら3画素おき）
+        let step = (((width * height) as f32 / MAX_SAMPLES as f32).sqrt().ceil() as u32).max(1);
+        for y in (0..height).step_by(step as usize) {
+            for x in (0..width).step_by(step as usize) {
+                let offset = ((y * width + x) * 4) as usize;
+                let Some(&[r, g, b]) = pixels.get(offset..offset + 3) else {
+                    continue;
+                };
+                histogram.red[r as usize] += 1;
+                histogram.green[g as usize] += 1;
+                histogram.blue[b as usize] += 1;
+            }
+        }
+        histogram
+    }
+    
+    /// 各チャンネルの塗りつぶし用パスコマンド（ビューボックスは1x1、3チャンネル共通の最大値で正規化）
+    pub fn path_commands(&self) -> [String; 3] {
+        let max = [&self.red, &self.green, &self.blue]
+            .iter()
+            .flat_map(|bins| bins.iter())
+            .copied()
+            .max()
+            .unwrap_or(0)
+            .max(1) as f32;
+        [&self.red, &self.green, &self.blue].map(|bins| {
+            let mut commands = String::from("M 0 1");
+            for (i, count) in bins.iter().enumerate() {
+                commands.push_str(&format!(" L {:.4} {:.4}", i as f32 / 255.0, 1.0 - *count as f32 / max));
+            }
+            commands.push_str(" L 1 1 Z");
+            commands
+        })
+    }
+}
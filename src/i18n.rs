@@ -0,0 +1,383 @@
+// 表示する文字列の翻訳（英語を元の文字列とし、日本語の訳を表から引く）
+// Slint側の文字列は@trで書き、ui/translations以下の.poファイルで翻訳する
+
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Language {
+    English,
+    Japanese,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::Japanese];
+
+    /// 設定ファイルとSlintの翻訳で使う言語コード
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::Japanese => "ja",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|language| language.code() == code)
+    }
+
+    /// システムの表示言語（日本語以外は英語）
+    pub fn system() -> Self {
+        if system_locale_is_japanese() {
+            Language::Japanese
+        } else {
+            Language::English
+        }
+    }
+}
+
+#[cfg(windows)]
+fn system_locale_is_japanese() -> bool {
+    use windows::Win32::Globalization::GetUserDefaultUILanguage;
+
+    // LANGIDの下位10ビットが主言語（0x11: 日本語）
+    let language_id = unsafe { GetUserDefaultUILanguage() };
+    language_id & 0x3ff == 0x11
+}
+
+#[cfg(not(windows))]
+fn system_locale_is_japanese() -> bool {
+    // 優先順位はLC_ALL → LC_MESSAGES → LANG（POSIXのロケールと同じ）
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .is_some_and(|value| value.starts_with("ja"))
+}
+
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+
+/// 表示言語を切り替える（以降のtr/trfの結果が変わる）
+pub fn set_language(language: Language) {
+    CURRENT.store(language as u8, Ordering::Relaxed);
+}
+
+pub fn language() -> Language {
+    match CURRENT.load(Ordering::Relaxed) {
+        1 => Language::Japanese,
+        _ => Language::English,
+    }
+}
+
+// 英語 → 日本語
+const JAPANESE: &[(&str, &str)] = &[
+    // 画面の項目
+    ("Current monitor", "現在のモニター"),
+    ("Default device", "既定のデバイス"),
+    ("Custom", "カスタム"),
+    ("Flat", "フラット"),
+    ("Bass boost", "低音強調"),
+    ("Vocal", "ボーカル"),
+    ("Treble boost", "高音強調"),
+    ("{} was not found. Install FFmpeg and add it to PATH.", "{}が見つかりません。FFmpegをインストールしてPATHに追加してください。"),
+    ("ffplay was not found, so videos will play without sound", "ffplayが見つからないため、音声なしで再生します"),
+    ("Enter a frame number as a non-negative integer", "フレーム番号は0以上の整数で入力してください"),
+    ("Skipped the intro ({}s)", "イントロをスキップしました（{}秒）"),
+    ("{} MB used", "{} MB使用中"),
+    ("Cleared the cache", "キャッシュを削除しました"),
+    ("Cannot clear the cache: {}", "キャッシュを削除できません: {}"),
+    // 読み込み
+    ("Reading video info...", "動画情報を取得中..."),
+    ("Extracting the first frame...", "最初のフレームを抽出中..."),
+    ("This file is not a valid video (the file is empty)", "このファイルは有効な動画ではありません（空のファイルです）"),
+    ("This file is not a valid video ({})", "このファイルは有効な動画ではありません（{}）"),
+    ("Cannot open the file: {}", "ファイルを開けません: {}"),
+    ("Cannot open the folder: {}", "フォルダを開けません: {}"),
+    ("Usage: video-player-ui --thumbnail <seconds> <output image> <video file>", "使い方: video-player-ui --thumbnail <秒> <出力する画像> <動画ファイル>"),
+    ("Invalid time: {}", "時刻が正しくありません: {}"),
+    ("Failed to save the image: {}", "画像の保存に失敗: {}"),
+    ("The skip amount must be between {} and {} seconds ({})", "スキップする秒数は{}〜{}秒にしてください（{}）"),
+    ("The frame rate must be between {} and {} fps ({})", "フレームレートは{}〜{}fpsにしてください（{}）"),
+    ("The decode-ahead must be between 0 and {} frames ({})", "先読みするフレーム数は0〜{}にしてください（{}）"),
+    ("Invalid file extension: {}", "拡張子が正しくありません: {}"),
+    ("Enter at least one file extension", "拡張子を1つ以上入力してください"),
+    ("This stream does not support seeking", "この配信はシークできません"),
+    ("Frame PTS {}s / position {}s (diff {} ms)", "フレームのPTS {}秒 / 再生位置 {}秒（差 {} ms）"),
+    ("Toggle fullscreen", "全画面の切り替え"),
+    ("Seek back (short step)", "戻る（短い方の秒数）"),
+    ("Seek forward (short step)", "進む（短い方の秒数）"),
+    ("Seek back (long step)", "戻る（長い方の秒数）"),
+    ("Seek forward (long step)", "進む（長い方の秒数）"),
+    ("Previous frame", "1フレーム戻る"),
+    ("Next frame", "1フレーム進む"),
+    ("Volume up", "音量を上げる"),
+    ("Volume down", "音量を下げる"),
+    ("Show subtitles 100 ms earlier", "字幕を100ms早める"),
+    ("Show subtitles 100 ms later", "字幕を100ms遅らせる"),
+    ("Show or hide the histogram", "ヒストグラムの表示/非表示"),
+    ("Rotate 90° clockwise", "時計回りに90°回転"),
+    ("Show or hide this list", "この一覧の表示/非表示"),
+    ("Close this list", "この一覧を閉じる"),
+    ("Shadowing needs subtitles to split the video into sentences", "シャドーイングには、文に区切るための字幕が必要です"),
+    ("There are no more subtitles after this position", "この位置より後に字幕がありません"),
+    ("Sentence {} ({} left)", "{}文目（残り{}回）"),
+    ("Sentence {}: repeat aloud ({} left)", "{}文目: 声に出して繰り返してください（残り{}回）"),
+    ("Cannot write over the source video", "元の動画には上書きできません"),
+    ("Choose a rotation other than 0°", "0°以外の回転を選んでください"),
+    ("Saving a rotated copy...", "回転したコピーを保存中..."),
+    ("Saving a rotated copy (lossless)...", "回転したコピーを保存中（無劣化）..."),
+    ("Re-encoding a rotated copy...", "回転したコピーを再エンコード中..."),
+    ("Saved a rotated copy without re-encoding (players that ignore rotation metadata will show it unrotated): {}", "回転したコピーを無劣化で保存しました（回転情報に対応していないプレイヤーでは元の向きで表示されます）: {}"),
+    ("Saved a rotated copy (re-encoded): {}", "回転したコピーを保存しました（再エンコード）: {}"),
+    ("This file name cannot be used for joining: {}", "このファイル名は結合に使えません: {}"),
+    ("Cannot save the settings file: {}", "設定ファイルを保存できません: {}"),
+    ("This is not a settings file", "設定ファイルではありません"),
+    ("The settings file is broken (position {})", "設定ファイルが壊れています（位置: {}）"),
+    ("Exported the settings to {}", "設定を {} に書き出しました"),
+    ("Imported {} settings", "{}項目の設定を読み込みました"),
+    ("Imported {} settings ({} had invalid values and were skipped)", "{}項目の設定を読み込みました（{}項目は値が正しくないため飛ばしました）"),
+    ("No videos were found in {}", "{} に動画が見つかりませんでした"),
+    ("Failed to start: {}", "起動に失敗: {}"),
+    ("Cancelled", "キャンセルされました"),
+    ("Failed to wait for the process: {}", "プロセスの待機に失敗: {}"),
+    ("No video stream found", "映像ストリームが見つかりません"),
+    ("No video or audio stream found (the file may not be a media file)", "映像も音声も見つかりません（動画ファイルではない可能性があります）"),
+    ("This file has no video stream (audio-only files are not supported)", "映像ストリームがありません（音声のみのファイルには対応していません）"),
+    ("Could not get the video duration", "動画のdurationを取得できませんでした"),
+    ("ffprobe could not read the file", "ffprobeで情報を取得できませんでした"),
+    ("Failed to load the image: {}", "画像の読み込みエラー: {}"),
+    ("Failed to extract the frame", "フレームの抽出に失敗"),
+    ("ffmpeg error: {}", "ffmpegエラー: {}"),
+    ("Failed to read the subtitle file: {}", "字幕ファイルの読み込みエラー: {}"),
+    ("No subtitles found (only SRT is supported)", "字幕が見つかりませんでした（SRT形式のみ対応）"),
+    // 再生
+    ("No video file is loaded", "動画ファイルが読み込まれていません"),
+    ("Failed to decode the video (ffmpeg exited abnormally)", "動画のデコードに失敗しました（ffmpegが異常終了しました）"),
+    ("Cannot go back any further", "これ以上戻れません"),
+    ("Failed to start ffmpeg: {}", "ffmpegの起動に失敗: {}"),
+    ("Failed to start ffplay: {}", "ffplayの起動に失敗: {}"),
+    ("Failed to start ffprobe: {}", "ffprobeの起動に失敗: {}"),
+    ("ffmpeg not found: {}", "ffmpegが見つかりません: {}"),
+    ("This ffmpeg does not support the hardware decoder: {}", "このffmpegはハードウェアデコード（{}）に対応していません"),
+    ("The render size is too small ({}x{})", "表示サイズが小さすぎます（{}x{}）"),
+    ("The render size is too large ({}x{})", "表示サイズが大きすぎます（{}x{}）"),
+    ("The volume must be between 0.0 and 1.0 ({})", "音量は0.0〜1.0の範囲で指定してください（{}）"),
+    ("HDR ({}): no tone mapping", "HDR（{}）: トーンマッピングなし"),
+    ("HDR ({}): cannot convert because ffmpeg lacks zscale", "HDR（{}）: ffmpegにzscaleがないため変換できません"),
+    ("HDR ({}) → SDR: {}", "HDR（{}）→ SDR: {}"),
+    ("Silent sections to skip: {}", "スキップする無音区間: {}箇所"),
+    ("Analyzing silent sections...", "無音部分を解析中..."),
+    ("Measuring loudness...", "ラウドネスを測定中..."),
+    ("Could not measure the loudness", "ラウドネスを測定できませんでした"),
+    ("Loudness gain: {} dB", "音量の補正: {} dB"),
+    ("Set point B after point A", "B点はA点より後に設定してください"),
+    ("Keyframes are still being analyzed", "キーフレームを解析中です"),
+    // 書き出し
+    ("The interval must be greater than 0", "間隔は0より大きい値を指定してください"),
+    ("Exporting frames...", "フレームを書き出し中..."),
+    ("Exported {} frames", "{}枚のフレームを書き出しました"),
+    ("The time is outside the video ({}s)", "動画の範囲外の時刻です（{}秒）"),
+    ("Saving the frame...", "フレームを保存中..."),
+    ("Saved the frame: {}", "フレームを保存しました: {}"),
+    ("The export range is invalid", "書き出す区間が正しくありません"),
+    ("A GIF can be at most {}s long (selected range: {}s)", "GIFにできるのは{}秒までです（選択区間: {}秒）"),
+    ("The GIF frame rate must be between 1 and 50", "GIFのフレームレートは1〜50の範囲で指定してください"),
+    ("Exporting GIF...", "GIFを書き出し中..."),
+    ("Exported GIF: {}", "GIFを書き出しました: {}"),
+    ("Joining needs at least two videos in the playlist", "結合するにはプレイリストに2つ以上の動画が必要です"),
+    ("Cannot write over one of the source videos", "結合元の動画と同じファイルには書き出せません"),
+    ("Checking the videos to join...", "結合する動画を確認中..."),
+    ("Joining videos (lossless)...", "動画を結合中（無劣化）..."),
+    ("Joined {} videos", "{}件の動画を結合しました"),
+    ("Re-encoding and joining because the formats differ...", "形式が異なるため再エンコードして結合中..."),
+    ("Joined {} videos (re-encoded because the formats differ)", "{}件の動画を結合しました（形式が異なるため再エンコードしました）"),
+    ("Cannot read video info: {}", "動画情報を取得できません: {}"),
+    ("No video stream found: {}", "映像ストリームが見つかりません: {}"),
+    ("Cannot create a temporary file: {}", "一時ファイルを作成できません: {}"),
+    ("Another export is already running", "別の書き出しが実行中です"),
+    ("Cannot create the output folder: {}", "出力フォルダを作成できません: {}"),
+    ("ffmpeg exited with an error ({})", "ffmpegがエラーで終了しました（{}）"),
+    ("Failed to wait for ffmpeg: {}", "ffmpegの待機に失敗: {}"),
+    // コンソールへの表示
+    ("Warning: this ffmpeg was built without zimg, so HDR tone mapping is not available", "警告: このffmpegはzimgなしでビルドされているため、HDRのトーンマッピングができません"),
+    ("Cancelled loading: {}", "読み込みをキャンセルしました: {}"),
+    ("Resizing the first frame: {}x{} → {}x{}", "最初のフレームの大きさを合わせます: {}x{} → {}x{}"),
+    ("The input is not seekable, so seeking is disabled: {}", "シークできない入力のため、シークを無効にします: {}"),
+    ("Loaded video: {} ({}s, {}x{}, {}fps)", "動画を読み込みました: {} ({}秒, {}x{}, {}fps)"),
+    ("Warning: {}", "警告: {}"),
+    ("Returning to the paused position: {}s", "一時停止していた位置に戻します: {}秒"),
+    ("Video info from the cache: {}", "動画情報をキャッシュから取得: {}"),
+    ("Opening as a still image ({})", "静止画として読み込みます（{}）"),
+    ("Video info: {}x{}, duration={}s, avg_fps={}", "動画情報: {}x{}, duration={}秒, avg_fps={}"),
+    ("Color space: {} (color_space={})", "色空間: {}（color_space={}）"),
+    ("Adjusting the width to the pixel aspect ratio {}: {} → {}", "ピクセルの縦横比 {} に合わせて横幅を変えます: {} → {}"),
+    ("Display size: {}x{} → {}x{}", "表示サイズ: {}x{} → {}x{}"),
+    ("Warning: the video size ({}x{}) cannot be used as is, so it is decoded at {}x{}", "警告: 動画の大きさ（{}x{}）をそのまま扱えないため、{}x{}にしてデコードします"),
+    ("Resuming from pause", "一時停止から再開"),
+    ("Skipping the intro: {}s", "イントロをスキップ: {}秒"),
+    ("Waiting while paused (position: {}s)", "一時停止のまま待機（位置: {}秒）"),
+    ("Failed to start audio playback (the video continues): {}", "音声再生の開始に失敗（動画は継続）: {}"),
+    ("Playing the video with ffmpeg... (start: {}s, generation: {})", "ffmpegで動画を再生中... (開始位置: {}秒, 世代: {})"),
+    ("A new playback started, ending the old playback thread (generation: {})", "新しい再生が開始されたため、古い再生スレッド（世代: {}）を終了"),
+    ("Playback stopped", "再生を停止しました"),
+    ("The generation changed before updating current_time (generation: {})", "current_time更新前に世代不一致を検出（世代: {}）"),
+    ("The output continues past the duration, cutting it off (pos: {}, duration: {})", "durationを超えても出力が続くため打ち切ります（pos: {}, duration: {}）"),
+    ("Ended because the output continued past the duration (frame: {}, pos: {}, duration: {})", "durationを超えても出力が続くため終了しました (frame: {}, pos: {}, duration: {})"),
+    ("An old thread (generation: {}) ended past the duration (flags left unchanged)", "古いスレッド（世代: {}）がduration超過で終了（フラグ設定スキップ）"),
+    ("Read error: {}", "読み込みエラー: {}"),
+    ("Repeat: back to the start (generation: {})", "リピート: 先頭に戻りました（世代: {}）"),
+    ("ffmpeg exited abnormally, restarting (position: {}s, attempt {}/{})", "ffmpegが異常終了したため再起動します（位置: {}秒, {}/{}回目）"),
+    ("Reached the end of the video (generation: {})", "動画の終端に達しました（世代: {}）"),
+    ("Restarted ffmpeg {} times but playback could not continue", "ffmpegの再起動を{}回試みましたが、再生を続けられませんでした"),
+    ("An old thread (generation: {}) ended at EOF (flags left unchanged)", "古いスレッド（世代: {}）がEOFで終了（フラグ設定スキップ）"),
+    ("Warning: reverse playback keeps every frame in memory, so it is limited to the last {}s", "警告: 逆再生は全フレームをメモリに溜めるため、直前の{}秒に制限します"),
+    ("Reverse playback: {}s → {}s (generation: {})", "逆再生: {}秒 → {}秒（世代: {}）"),
+    ("The reverse playback range has ended (generation: {})", "逆再生の区間が終わりました（世代: {}）"),
+    ("Scan: {}", "スキャン: {}"),
+    ("On", "オン"),
+    ("Off", "オフ"),
+    ("This input is not seekable, so it cannot play in reverse", "この入力はシークできないため、逆再生できません"),
+    ("Reverse playback: {}", "逆再生: {}"),
+    ("Playback speed: {}x", "再生速度: {}倍"),
+    ("Warning: motion interpolation is CPU-heavy and may not keep up at some resolutions", "警告: 動き補間はCPU負荷が高く、解像度によっては再生が追いつきません"),
+    ("UI refresh rate: {}", "UIの更新頻度: {}"),
+    ("Match the video", "動画に合わせる"),
+    ("Removing a missing file from the playlist: {}", "見つからないためプレイリストから外します: {}"),
+    ("Restoring the previous session: {} ({}s)", "前回のセッションに戻します: {} ({}秒)"),
+    ("The video open last time was not found: {}", "前回開いていた動画が見つかりません: {}"),
+    ("Opening as an image sequence: {} ({} images, {}fps)", "連番の画像として開きます: {}（{}枚, {}fps）"),
+    ("Warning: the ffmpeg output size differs from the expected size, adjusting (expected: {}x{}, actual: {}x{})", "警告: ffmpegの出力サイズが想定と異なるため合わせます（想定: {}x{}, 実際: {}x{}）"),
+    ("Paused (position: {}s)", "一時停止（位置: {}秒）"),
+    ("Stopped", "停止"),
+    ("This input is not seekable ({}s)", "この入力はシークできません（{}秒）"),
+    ("Seek: {}s", "シーク: {}秒"),
+    ("Extracting previews is slow ({}ms), so the preview while dragging is turned off", "プレビューの抽出に時間がかかるため（{}ms）、ドラッグ中のプレビューをやめます"),
+    ("Fast forward/rewind ended: {}s", "早送り・巻き戻しを終了: {}秒"),
+    ("Fast forward started", "早送りを開始"),
+    ("Rewind started", "巻き戻しを開始"),
+    ("Warning: the extracted frame is not the expected size ({}x{}), so it is not used", "警告: 抽出したフレームの大きさが想定（{}x{}）と異なるため使いません"),
+    ("Frame read error: {}", "フレームの読み込みエラー: {}"),
+    ("Volume: {}%", "音量を設定: {}%"),
+    ("Volume boost: {}%", "音量ブーストを設定: {}%"),
+    ("Warning: boosting above 100% can cause clipping", "警告: 100%を超えるブーストは音割れ（クリッピング）の原因になります"),
+    ("Audio offset: {}ms", "音声オフセットを設定: {}ms"),
+    ("Loudness normalization: {}", "音量の均一化: {}"),
+    ("Match loudness across videos: {}", "動画ごとの音量の揃え: {}"),
+    ("Loudness matching gain: {}dB", "音量を揃えるゲイン: {}dB"),
+    ("Could not measure the loudness: {}", "ラウドネスを測れませんでした: {}"),
+    ("Could not play the cue tone: {}", "合図の音を鳴らせませんでした: {}"),
+    ("Audio output device: {}", "音声の出力デバイス: {}"),
+    ("Fullscreen monitor: {}", "全画面表示のモニター: {}"),
+    ("Rotation: {}°", "回転: {}°"),
+    ("Mono playback: {}", "モノラル再生: {}"),
+    ("Balance: {}", "左右バランスを設定: {}"),
+    ("Subtitle offset: {}s", "字幕オフセットを設定: {}秒"),
+    ("Moving to the start of subtitle {}: {}s", "字幕 {} の始まりへ移動: {}秒"),
+    ("Seek mode: {}", "シーク方法: {}"),
+    ("Skip intro (this video only): {}s", "イントロスキップ（この動画のみ）: {}秒"),
+    ("Skip intro: back to the global setting ({}s)", "イントロスキップ: 全体の設定（{}秒）に戻しました"),
+    ("Skip intro: {}s", "イントロスキップ: {}秒"),
+    ("Skip silence: {}", "無音スキップ: {}"),
+    ("Found {} silent sections", "無音区間を解析しました: {}箇所"),
+    ("Could not analyze the silent sections: {}", "無音区間を解析できませんでした: {}"),
+    ("Skipping silence: {}s → {}s", "無音区間をスキップ: {}秒 → {}秒"),
+    ("Set point A: {}s", "A点を設定: {}秒"),
+    ("Set point B: {}s (A/B repeat: {}–{}s)", "B点を設定: {}秒（A/Bリピート: {}〜{}秒）"),
+    ("Cleared the A/B repeat", "A/Bリピートを解除"),
+    ("A/B repeat count: infinite", "A/Bリピート回数: 無限"),
+    ("A/B repeat count: {}", "A/Bリピート回数: {}回"),
+    ("A/B repeat ({} left)", "A/Bリピート（残り: {}回）"),
+    ("The A/B repeat count was reached, continuing past point B", "A/Bリピートが指定回数に達したので、B点から先へ進みます"),
+    ("Shadowing ended", "シャドーイングを終了"),
+    ("Shadowing: finished the last sentence", "シャドーイング: 最後の文まで終わりました"),
+    ("Added to the playlist: {} ({} in total)", "プレイリストに追加: {}件（合計{}件）"),
+    ("Replaced the playlist: {} items", "プレイリストを入れ替え: {}件"),
+    ("Removing a deleted file from the history: {}", "削除されたファイルを履歴から外します: {}"),
+    ("Starting audio (position: {}s, offset: {}ms, volume: {}%, generation: {})", "音声再生を開始（位置: {}秒, オフセット: {}ms, 音量: {}%, 世代: {}）"),
+    ("Audio device \"{}\" was not found, playing on the default device", "音声デバイス「{}」が見つからないため、既定のデバイスで再生します"),
+    ("Failed to start audio playback: {}", "音声再生の開始に失敗: {}"),
+    ("Exported settings: {} ({} items)", "設定を書き出しました: {}（{}項目）"),
+    ("Importing settings: ignoring an unknown key ({})", "設定の読み込み: 知らない項目を無視します（{}）"),
+    ("Importing settings: skipping an invalid value ({}={})", "設定の読み込み: 値が正しくないため飛ばします（{}={}）"),
+    ("Imported settings: {} ({} items, {} skipped)", "設定を読み込みました: {}（{}項目、{}項目は飛ばしました）"),
+    ("Found {} keyframes", "キーフレームを解析しました: {}個"),
+    ("Previous frame: {}s", "1フレーム戻る: {}秒"),
+    ("Previous frame (cached): {}s", "1フレーム戻る（キャッシュ）: {}秒"),
+    ("Failed to start ffmpeg for the waveform: {}", "波形生成のためのffmpeg起動に失敗: {}"),
+    ("Waveform read error: {}", "波形データの読み込みエラー: {}"),
+    ("Waveform: not shown because there is no audio or it is silent", "波形: 音声がないか無音のため表示しません"),
+    ("Generated the waveform ({} sections, {} samples)", "波形を生成しました（{}区間, {}サンプル）"),
+    ("Saved the frame: {}s → {}", "フレームを保存しました: {}秒 → {}"),
+    ("Saved a rotated copy (rotation metadata only, {}°): {}", "回転したコピーを保存しました（回転情報のみ・{}°）: {}"),
+    ("Warning: players that ignore rotation metadata will show it in the original orientation", "警告: 回転情報を無視するプレイヤーでは元の向きのまま表示されます"),
+    ("Warning: could not write the rotation metadata, re-encoding instead", "警告: 回転情報を書き込めなかったため再エンコードします"),
+    ("Saved a rotated copy (re-encoded, {}°): {}", "回転したコピーを保存しました（再エンコード・{}°）: {}"),
+    ("Joined the videos (lossless): {}", "動画を結合しました（無劣化）: {}"),
+    ("Warning: the video formats differ, re-encoding to join them", "警告: 動画の形式が一致しないため再エンコードして結合します"),
+    ("Joined the videos (re-encoded): {}", "動画を結合しました（再エンコード）: {}"),
+    ("Exported {} frames → {}", "フレームを書き出しました: {}枚 → {}"),
+    ("Stopped the audio process", "音声プロセスを停止しました"),
+    ("Warning: {} threads did not finish, exiting without waiting", "警告: {}個のスレッドが終わらなかったため、待たずに終了します"),
+    ("Worker threads finished", "ワーカースレッドを終了しました"),
+    ("Cleaning up VideoPlayer...", "VideoPlayerをクリーンアップ中..."),
+    ("Failed to switch the translation: {}", "翻訳の切り替えに失敗: {}"),
+    ("Error: {}", "エラー: {}"),
+    ("Saved the thumbnail: {}", "サムネイルを保存しました: {}"),
+    ("Passed the files to the running window", "起動中のウィンドウにファイルを渡しました"),
+    ("Error: {} was not found", "エラー: {}が見つかりません"),
+    ("Playback error: {}", "再生エラー: {}"),
+    ("Repeat: infinite", "リピート: 無限"),
+    ("Repeat count: {}", "リピート回数: {}回"),
+    ("Equalizer: {}", "イコライザー: {}"),
+    ("Selected video: {}", "動画を選択しました: {}"),
+    ("Repeating (infinite)", "リピート再生（無限）"),
+    ("Repeating ({} left)", "リピート再生（残り: {}回）"),
+    ("Next video in the playlist", "プレイリストの次の動画へ"),
+    ("Back to the start of the playlist", "プレイリストの最初に戻る"),
+    ("Repeating the last video in the playlist", "プレイリストの最後の動画をリピート"),
+    ("Playback finished", "再生完了"),
+    ("Video player UI started", "動画プレイヤーUIを起動しました"),
+    ("Select a video to play", "動画を選択して再生してください"),
+    ("[Required] Make sure FFmpeg is on PATH", "【必要】FFmpegがPATHに設定されていることを確認してください"),
+    ("Exiting the application...", "アプリケーションを終了します..."),
+    ("Cleanup complete", "クリーンアップ完了"),
+    ("Cannot list the audio devices: {}", "音声デバイスを列挙できません: {}"),
+    ("Failed to create the cache folder: {}", "キャッシュフォルダの作成に失敗: {}"),
+    ("Cannot read the subfolder: {} ({})", "サブフォルダを読み込めません: {} ({})"),
+    ("Monitor \"{}\" was not found, going fullscreen on the current monitor", "モニター「{}」が見つからないため、現在のモニターで全画面表示します"),
+    ("Failed to save the session: {}", "セッションの保存に失敗: {}"),
+    ("Failed to save the settings: {}", "設定の保存に失敗: {}"),
+    ("Cannot connect to the running instance, removing the stale lock file: {}", "起動中のインスタンスに接続できないため、古いロックファイルを削除します: {}"),
+    ("Cannot start listening for other launches: {}", "単一インスタンスの待ち受けを開始できません: {}"),
+    ("Cannot write the lock file: {}", "ロックファイルを書き込めません: {}"),
+    ("Received {} files from another launch", "別の起動からファイルを受け取りました: {}件"),
+    ("Loaded subtitles: {} ({} cues)", "字幕を読み込みました: {} ({}件)"),
+    ("Cannot show the taskbar progress: {}", "タスクバーの進行状況を表示できません: {}"),
+];
+
+/// 現在の言語に翻訳する（訳がなければ英語のまま）
+pub fn tr(text: &'static str) -> &'static str {
+    match language() {
+        Language::English => text,
+        Language::Japanese => JAPANESE
+            .iter()
+            .find(|(english, _)| *english == text)
+            .map_or(text, |(_, japanese)| japanese),
+    }
+}
+
+/// 翻訳してから`{}`を順に引数で置き換える
+pub fn trf(text: &'static str, args: &[&dyn Display]) -> String {
+    let mut result = String::new();
+    let mut args = args.iter();
+    let mut parts = tr(text).split("{}");
+    if let Some(first) = parts.next() {
+        result.push_str(first);
+    }
+    for part in parts {
+        if let Some(arg) = args.next() {
+            result.push_str(&arg.to_string());
+        }
+        result.push_str(part);
+    }
+    result
+}
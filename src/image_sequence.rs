@@ -0,0 +1,129 @@
+// 連番の画像（frame_0001.pngなど）を動画として開くための、パターンの作成とffmpegの入力オプション
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// 開ける画像の拡張子（1枚だけなら静止画として表示する）
+pub const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "bmp", "tif", "tiff", "webp"];
+
+// 開いた連番のパターンと、そのfps・開始番号（-iの前に-framerate・-start_numberとして付ける）
+static SEQUENCES: Mutex<Vec<(PathBuf, f32, u32)>> = Mutex::new(Vec::new());
+
+pub struct ImageSequence {
+    pub pattern: PathBuf, // ffmpegのimage2に渡すパターン（例: frame_%04d.png）
+    pub start_number: u32,
+    pub count: usize,
+}
+
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| IMAGE_EXTENSIONS.iter().any(|e| e.eq_ignore_ascii_case(extension)))
+}
+
+/// 番号の付いた画像なら、同じフォルダの連番をまとめたパターンを返す
+/// 選んだ画像を含む、途切れずに続く2枚以上の連番がある場合のみ（先頭は一番小さい番号）
+pub fn detect(path: &Path) -> Option<ImageSequence> {
+    if !is_image(path) {
+        return None;
+    }
+    let extension = path.extension()?.to_str()?;
+    let (prefix, digits) = split_number(path.file_stem()?.to_str()?)?;
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+
+    // 同じ接頭辞・拡張子で、残りが数字だけのファイルを集める
+    let mut numbers: Vec<(u32, String)> = fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            if !path.extension()?.to_str()?.eq_ignore_ascii_case(extension) {
+                return None;
+            }
+            let (other_prefix, other_digits) = split_number(path.file_stem()?.to_str()?)?;
+            if other_prefix != prefix {
+                return None;
+            }
+            Some((other_digits.parse::<u32>().ok()?, other_digits.to_string()))
+        })
+        .collect();
+    numbers.sort();
+
+    // 桁数が揃っていれば0埋め（%04d）、揃っていなければ0埋めなし（%d。0で始まる番号は含めない）
+    let padded = numbers.iter().all(|(_, other_digits)| other_digits.len() == digits.len());
+    let numbers: Vec<u32> = numbers
+        .into_iter()
+        .filter(|(number, other_digits)| padded || *other_digits == number.to_string())
+        .map(|(number, _)| number)
+        .collect();
+
+    // image2は番号が途切れたところで終わるので、選んだ画像を含む途切れない範囲にする
+    let selected = digits.parse::<u32>().ok()?;
+    let index = numbers.iter().position(|&number| number == selected)?;
+    let mut first = index;
+    while first > 0 && numbers[first - 1] + 1 == numbers[first] {
+        first -= 1;
+    }
+    let mut last = index;
+    while last + 1 < numbers.len() && numbers[last] + 1 == numbers[last + 1] {
+        last += 1;
+    }
+    if last == first {
+        return None;
+    }
+
+    // ファイル名の「%」は連番の指定と解釈されるので「%%」にする
+    let number_format = if padded { format!("%0{}d", digits.len()) } else { "%d".to_string() };
+    let name = format!("{}{}.{}", prefix.replace('%', "%%"), number_format, extension);
+    Some(ImageSequence {
+        pattern: dir.join(name),
+        start_number: numbers[first],
+        count: last - first + 1,
+    })
+}
+
+/// パターンを開くときのfps・開始番号を登録する（同じパターンなら置き換える）
+pub fn register(sequence: &ImageSequence, fps: f32) {
+    let mut sequences = SEQUENCES.lock().unwrap();
+    sequences.retain(|(pattern, _, _)| pattern != &sequence.pattern);
+    sequences.push((sequence.pattern.clone(), fps, sequence.start_number));
+}
+
+/// 登録済みのパターンのfpsを変える
+pub fn set_fps(path: &Path, fps: f32) {
+    for (pattern, sequence_fps, _) in SEQUENCES.lock().unwrap().iter_mut() {
+        if pattern == path {
+            *sequence_fps = fps;
+        }
+    }
+}
+
+/// 登録した連番のパターンか（ファイルとしては存在しない）
+pub fn is_sequence(path: &Path) -> bool {
+    SEQUENCES.lock().unwrap().iter().any(|(pattern, _, _)| pattern == path)
+}
+
+/// -iの前に付ける入力オプション（連番でなければ空）
+pub fn input_options(path: &Path) -> Vec<String> {
+    SEQUENCES
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|(pattern, _, _)| pattern == path)
+        .map(|(_, fps, start_number)| {
+            vec![
+                "-framerate".to_string(),
+                fps.to_string(),
+                "-start_number".to_string(),
+                start_number.to_string(),
+            ]
+        })
+        .unwrap_or_default()
+}
+
+// 末尾の数字とそれより前に分ける（"frame_0001" → ("frame_", "0001")）
+fn split_number(stem: &str) -> Option<(&str, &str)> {
+    let digits_start = stem.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    let digits = &stem[digits_start..];
+    (!digits.is_empty() && digits.len() <= 9).then(|| (&stem[..digits_start], digits))
+}
@@ -0,0 +1,308 @@
+// 小さなJSONパーサー（設定ファイルの読み込みと、ffprobeの-print_format jsonの出力に使う）
+// 文字列・数値・真偽値はまとめて文字列として扱う
+
+pub enum Value {
+    Null,
+    Scalar(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// オブジェクトの項目（オブジェクトでない・項目がなければNone）
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        match self {
+            Value::Object(fields) => fields.iter().find(|(key, _)| key == name).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Scalar(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// 配列の要素（配列でなければ空）
+    pub fn items(&self) -> &[Value] {
+        match self {
+            Value::Array(items) => items,
+            _ => &[],
+        }
+    }
+}
+
+/// JSONを読む（失敗したら壊れている位置（文字数）を返す）
+pub fn parse(text: &str) -> Result<Value, usize> {
+    let mut parser = Parser { chars: text.chars().collect(), pos: 0 };
+    let root = parser.value()?;
+    parser.skip_whitespace();
+    if parser.pos < parser.chars.len() {
+        return Err(parser.pos);
+    }
+    Ok(root)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn error(&self) -> usize {
+        self.pos
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), usize> {
+        self.skip_whitespace();
+        if self.chars.get(self.pos) != Some(&expected) {
+            return Err(self.error());
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Value, usize> {
+        self.skip_whitespace();
+        match self.chars.get(self.pos) {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => Ok(Value::Scalar(self.string()?)),
+            Some(_) => self.literal(),
+            None => Err(self.error()),
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, usize> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.chars.get(self.pos) == Some(&'}') {
+            self.pos += 1;
+            return Ok(Value::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(':')?;
+            fields.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.chars.get(self.pos) {
+                Some(',') => self.pos += 1,
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(Value::Object(fields));
+                }
+                _ => return Err(self.error()),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, usize> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.chars.get(self.pos) == Some(&']') {
+            self.pos += 1;
+            return Ok(Value::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.chars.get(self.pos) {
+                Some(',') => self.pos += 1,
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(Value::Array(items));
+                }
+                _ => return Err(self.error()),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, usize> {
+        if self.chars.get(self.pos) != Some(&'"') {
+            return Err(self.error());
+        }
+        self.pos += 1;
+        let mut text = String::new();
+        loop {
+            let Some(&c) = self.chars.get(self.pos) else {
+                return Err(self.error());
+            };
+            self.pos += 1;
+            match c {
+                '"' => return Ok(text),
+                '\\' => {
+                    let Some(&escaped) = self.chars.get(self.pos) else {
+                        return Err(self.error());
+                    };
+                    self.pos += 1;
+                    match escaped {
+                        'n' => text.push('\n'),
+                        'r' => text.push('\r'),
+                        't' => text.push('\t'),
+                        'b' => text.push('\u{8}'),
+                        'f' => text.push('\u{c}'),
+                        'u' => {
+                            // 16進数ちょうど4桁（from_str_radixは「+041」のような符号付きも読んでしまう）
+                            let hex: String = self.chars.iter().skip(self.pos).take(4).collect();
+                            if hex.len() != 4 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                                return Err(self.error());
+                            }
+                            let code = u32::from_str_radix(&hex, 16).map_err(|_| self.error())?;
+                            self.pos += 4;
+                            // サロゲートペアは置き換え文字にする（設定の値・ffprobeの項目には出てこない）
+                            text.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                        }
+                        c => text.push(c),
+                    }
+                }
+                c => text.push(c),
+            }
+        }
+    }
+
+    // 数値・true・false・null
+    fn literal(&mut self) -> Result<Value, usize> {
+        let start = self.pos;
+        while self.chars.get(self.pos).is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.')) {
+            self.pos += 1;
+        }
+        let literal: String = self.chars[start..self.pos].iter().collect();
+        match literal.as_str() {
+            "null" => Ok(Value::Null),
+            "true" | "false" => Ok(Value::Scalar(literal)),
+            _ if is_number(&literal) => Ok(Value::Scalar(literal)),
+            _ => Err(self.error()),
+        }
+    }
+}
+
+// JSONの数値の書き方か（parse::<f64>が読めるnan・inf・「+1」・「.5」などは数値ではない）
+// 桁が多すぎてf64に収まらない値（1e999など）も断る
+fn is_number(text: &str) -> bool {
+    let mut rest = text.strip_prefix('-').unwrap_or(text);
+    let digits = |text: &str| text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let integer = digits(rest);
+    if integer == 0 || (integer > 1 && rest.starts_with('0')) {
+        return false;
+    }
+    rest = &rest[integer..];
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let count = digits(fraction);
+        if count == 0 {
+            return false;
+        }
+        rest = &fraction[count..];
+    }
+    if let Some(exponent) = rest.strip_prefix(['e', 'E']) {
+        let exponent = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+        let count = digits(exponent);
+        if count == 0 {
+            return false;
+        }
+        rest = &exponent[count..];
+    }
+    rest.is_empty() && text.parse::<f64>().is_ok_and(f64::is_finite)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ffprobe -show_entries ... -print_format json の出力と同じ形
+    const FFPROBE: &str = r#"{
+    "programs": [
+
+    ],
+    "streams": [
+        {
+            "codec_name": "h264",
+            "width": 1920,
+            "height": 1080,
+            "sample_aspect_ratio": "1:1",
+            "avg_frame_rate": "30000/1001",
+            "color_space": "bt709",
+            "tags": {
+                "title": "caf\u00e9 \"demo\""
+            }
+        }
+    ],
+    "format": {
+        "format_name": "mov,mp4,m4a,3gp,3g2,mj2",
+        "duration": "12.345000"
+    }
+}"#;
+
+    #[test]
+    fn reads_ffprobe_output() {
+        let root = parse(FFPROBE).unwrap();
+        let stream = &root.get("streams").unwrap().items()[0];
+        assert_eq!(stream.get("width").and_then(Value::as_str), Some("1920"));
+        assert_eq!(stream.get("height").and_then(Value::as_str), Some("1080"));
+        assert_eq!(stream.get("avg_frame_rate").and_then(Value::as_str), Some("30000/1001"));
+        assert_eq!(stream.get("tags").and_then(|tags| tags.get("title")).and_then(Value::as_str), Some("café \"demo\""));
+        assert_eq!(root.get("format").and_then(|format| format.get("duration")).and_then(Value::as_str), Some("12.345000"));
+        assert!(root.get("programs").unwrap().items().is_empty());
+    }
+
+    #[test]
+    fn not_available_values_stay_strings_for_the_caller_to_skip() {
+        let root = parse(r#"{"streams": [{"width": 640, "duration": "N/A", "color_space": "unknown", "side": null}]}"#).unwrap();
+        let stream = &root.get("streams").unwrap().items()[0];
+        let duration = stream.get("duration").and_then(Value::as_str);
+        assert_eq!(duration, Some("N/A"));
+        assert_eq!(duration.and_then(|value| value.parse::<f32>().ok()), None);
+        assert_eq!(stream.get("color_space").and_then(Value::as_str), Some("unknown"));
+        assert!(matches!(stream.get("side"), Some(Value::Null)));
+        assert_eq!(stream.get("side").and_then(Value::as_str), None);
+    }
+
+    #[test]
+    fn missing_fields_are_none() {
+        let root = parse(r#"{"streams": [{"codec_name": "mjpeg"}]}"#).unwrap();
+        let stream = &root.get("streams").unwrap().items()[0];
+        assert!(stream.get("width").is_none());
+        assert!(stream.get("height").is_none());
+        assert!(root.get("format").is_none());
+        // 配列・オブジェクトでない値に聞いても空
+        assert!(stream.get("codec_name").unwrap().get("width").is_none());
+        assert!(stream.get("codec_name").unwrap().items().is_empty());
+    }
+
+    #[test]
+    fn numbers_follow_the_json_grammar() {
+        for number in ["0", "-0", "12", "-3.25", "1e3", "1E+3", "2.5e-3", "0.5"] {
+            assert_eq!(parse(number).ok().as_ref().and_then(Value::as_str), Some(number), "{}", number);
+        }
+        for number in ["nan", "NaN", "inf", "-inf", "infinity", "1e999", "+1", ".5", "1.", "01", "-", "1e", "1e+", "0x10", "1_000"] {
+            assert!(parse(number).is_err(), "{}", number);
+        }
+    }
+
+    #[test]
+    fn unicode_escapes_need_four_hex_digits() {
+        assert_eq!(parse(r#""\u0041\u00e9""#).ok().as_ref().and_then(Value::as_str), Some("Aé"));
+        for text in [r#""\u+041""#, r#""\u-041""#, r#""\u41""#, r#""\u004""#, r#""\u00g1""#] {
+            assert!(parse(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn malformed_input_reports_the_position() {
+        assert_eq!(parse(r#"{"width": 1920,}"#).err(), Some(15));
+        assert_eq!(parse(r#"{"width" 1920}"#).err(), Some(9));
+        assert!(parse(r#"{"title": "unterminated"#).is_err());
+        assert!(parse(r#"[1, 2"#).is_err());
+        assert!(parse(r#"{"width": 1920} trailing"#).is_err());
+        assert!(parse("").is_err());
+    }
+}
@@ -0,0 +1,15 @@
+// 動画の再生部分（ffmpegでのデコード・音声・字幕・設定）。UIなしでも使えるようライブラリにしている
+// ウィンドウ・キー入力などUIに関わるモジュールはmain.rs側に置く
+pub mod audio_device;
+pub mod config_file;
+pub mod disk_cache;
+pub mod equalizer;
+pub mod ffmpeg_log;
+pub mod i18n;
+pub mod image_sequence;
+pub mod json;
+pub mod player;
+pub mod session;
+pub mod settings;
+pub mod subtitle;
+pub mod video_info;
@@ -1,162 +1,2034 @@
 use slint::*;
+use std::cell::{Cell, RefCell};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use device_query::{DeviceQuery, DeviceState, Keycode};
 
-mod player;
-use player::VideoPlayer;
+mod aspect_lock;
+mod folder_scan;
+mod fullscreen;
+mod histogram;
+mod shortcuts;
+mod single_instance;
+mod taskbar;
+mod timecode;
+mod toast;
+// 再生部分はライブラリ側（lib.rs）。crate::i18nなどのパスはここでの取り込みで解決する
+use video_player_ui::{audio_device, equalizer, ffmpeg_log, i18n, image_sequence, player, settings};
+use aspect_lock::AspectLock;
+use ffmpeg_log::LogLevel;
+use histogram::Histogram;
+use i18n::{tr, trf, Language};
+use player::{AspectMode, PlaybackState, PlaylistEnd, SeekMode, SlowMotionSmoothing, ToneMapping, VideoAdjustments, VideoPlayer};
+use shortcuts::Action;
+use single_instance::{Instance, SingleInstance};
+use taskbar::{TaskbarProgress, TaskbarState};
+use toast::Toasts;
 
 slint::include_modules!();
 
+// 波形の区間数（シークバーの幅に対して十分な解像度）
+const WAVEFORM_SAMPLES: usize = 480;
+
+// イコライザーの表示（各帯域のゲインと、一致するプリセット）を更新
+fn update_equalizer_view(ui: &VideoPlayerUI, player: &VideoPlayer) {
+    let gains = player.get_equalizer();
+    // モデルを作り直すとスライダーも作り直され、操作で切れたバインディングが元に戻る
+    ui.set_eq_gains(ModelRc::new(VecModel::from(gains.to_vec())));
+    // どのプリセットとも一致しなければ末尾の「カスタム」
+    ui.set_eq_preset(equalizer::preset_index(gains).unwrap_or(equalizer::PRESETS.len()) as i32);
+}
+
+// キーフレームの時刻を、タイムライン上の縦線のPathコマンドへ変換（viewbox: 幅1 x 高さ1）
+fn keyframe_tick_commands(keyframes: &[f32], duration: f32) -> String {
+    if duration <= 0.0 {
+        return String::new();
+    }
+    
+    let mut commands = String::new();
+    let mut last_x = -1.0;
+    for time in keyframes {
+        let x = (time / duration).clamp(0.0, 1.0);
+        // 表示上重なる目盛りは省く（長い動画でコマンドが膨らまないように）
+        if x - last_x < 0.002 {
+            continue;
+        }
+        commands.push_str(&format!("M {:.4} 0 L {:.4} 1 ", x, x));
+        last_x = x;
+    }
+    commands
+}
+
+// 区間・動画の終わりの合図で画面を光らせておく時間
+const CUE_FLASH_DURATION: Duration = Duration::from_millis(150);
+
+// 「イントロをスキップしました」の表示時間
+const INTRO_NOTICE_DURATION: Duration = Duration::from_secs(5);
+
+// ↑/↓キーで変える音量の幅と、変えた後に音量を表示しておく時間
+const VOLUME_KEY_STEP: f32 = 0.05;
+const VOLUME_OSD_DURATION: Duration = Duration::from_millis(1200);
+
+// 全画面で操作したときに、ファイル名・時刻・音量を表示しておく時間
+// （これより大きく再生位置が飛んだらシークしたとみなす）
+const NOW_PLAYING_OSD_DURATION: Duration = Duration::from_secs(2);
+const NOW_PLAYING_SEEK_JUMP: f32 = 1.0;
+
+// プレイリストの自動送りで暗転する時間（Slint側のvideo-opacityのanimateと合わせる）
+const FADE_DURATION: Duration = Duration::from_millis(250);
+
+// 再生中に「続きから見る」の位置を保存する間隔（異常終了しても大きくは戻らないように）
+const POSITION_SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+// ヒストグラムを計算し直す間隔（毎フレーム計算すると重いため）
+const HISTOGRAM_INTERVAL: Duration = Duration::from_millis(200);
+
+// 時間バーを補間して動かす間隔（UIの更新の間も60Hzで滑らかに進める）
+const TIME_BAR_SMOOTHING_INTERVAL: Duration = Duration::from_millis(16);
+
+// キーボードを監視する間隔（UIの更新頻度を下げても取りこぼさないよう別のタイマーで固定）
+const KEY_POLL_INTERVAL: Duration = Duration::from_millis(33);
+
+// UIの更新頻度の選択肢（UIのComboBoxの並び順と一致させる。0は動画のfpsに合わせる）
+const UI_REFRESH_RATES: [u32; 6] = [0, 10, 15, 24, 30, 60];
+
+// 先読みするフレーム数の選択肢（UIのComboBoxの並び順と一致させる。0は先読みなし）
+const DECODE_AHEAD_CHOICES: [usize; 5] = [0, 2, 4, 8, 16];
+
+// ディスクキャッシュの上限の選択肢（MB。UIのComboBoxの並び順と一致させる）
+const CACHE_SIZES_MB: [u64; 5] = [128, 256, 512, 1024, 2048];
+
+// 動画を開くファイル選択ダイアログ（設定した拡張子で絞り込み、最後に開いたフォルダから始める）
+// 「All Files」は拡張子の設定にかかわらず常に選べるようにする
+fn open_video_dialog(player: &Mutex<VideoPlayer>) -> rfd::FileDialog {
+    let player = player.lock().unwrap();
+    let mut dialog = rfd::FileDialog::new()
+        .add_filter("Video Files", player.get_video_extensions())
+        .add_filter("Images", image_sequence::IMAGE_EXTENSIONS)
+        .add_filter("All Files", &["*"]);
+    if let Some(dir) = player.get_open_directory() {
+        dialog = dialog.set_directory(dir);
+    }
+    dialog
+}
+
+// 全画面表示のモニターの選択肢を更新（先頭は「現在のモニター」）
+// 保存したモニターが外されていても設定は残し、選択肢では「現在のモニター」として表示する
+fn update_monitor_list(ui: &VideoPlayerUI, player: &VideoPlayer) {
+    let names = fullscreen::monitor_names(ui.window());
+    let selected = player
+        .get_fullscreen_monitor()
+        .and_then(|target| names.iter().position(|name| *name == target))
+        .map_or(0, |index| index + 1);
+    let mut items: Vec<SharedString> = vec![tr("Current monitor").into()];
+    items.extend(names.into_iter().map(SharedString::from));
+    ui.set_fullscreen_monitors(ModelRc::new(VecModel::from(items)));
+    ui.set_fullscreen_monitor(selected as i32);
+}
+
+// 音声の出力デバイスの選択肢を更新（先頭は「既定のデバイス」）
+fn update_audio_device_list(ui: &VideoPlayerUI, player: &VideoPlayer) {
+    let names = audio_device::output_devices();
+    let selected = player
+        .get_audio_device()
+        .and_then(|target| names.iter().position(|name| *name == target))
+        .map_or(0, |index| index + 1);
+    let mut items: Vec<SharedString> = vec![tr("Default device").into()];
+    items.extend(names.into_iter().map(SharedString::from));
+    ui.set_audio_devices(ModelRc::new(VecModel::from(items)));
+    ui.set_audio_device(selected as i32);
+}
+
+// イコライザーのプリセット名を更新（末尾は「カスタム」）
+fn update_equalizer_presets(ui: &VideoPlayerUI, player: &VideoPlayer) {
+    let mut preset_names: Vec<SharedString> = equalizer::PRESETS.iter().map(|(name, _)| tr(name).into()).collect();
+    preset_names.push(tr("Custom").into());
+    ui.set_eq_preset_names(ModelRc::new(VecModel::from(preset_names)));
+    update_equalizer_view(ui, player);
+}
+
+// 表示言語を切り替える（Slintの@trの文字列と、Rust側で作った選択肢の両方）
+fn apply_language(ui: &VideoPlayerUI, player: &VideoPlayer, language: Language) {
+    i18n::set_language(language);
+    if let Err(e) = slint::select_bundled_translation(language.code()) {
+        eprintln!("{}", trf("Failed to switch the translation: {}", &[&e]));
+    }
+    update_monitor_list(ui, player);
+    update_audio_device_list(ui, player);
+    update_equalizer_presets(ui, player);
+    update_shortcut_list(ui);
+}
+
+// ショートカットの一覧（?・F1で表示する）をショートカットの表から作る
+fn update_shortcut_list(ui: &VideoPlayerUI) {
+    let items: Vec<ShortcutItem> = shortcuts::SHORTCUTS
+        .iter()
+        .map(|shortcut| ShortcutItem {
+            keys: shortcut.keys.label().into(),
+            action: shortcut.description().into(),
+        })
+        .collect();
+    ui.set_shortcut_items(ModelRc::new(VecModel::from(items)));
+}
+
+// 字幕の表示（ファイル名・オフセット・一覧）を更新
+fn update_subtitle_view(ui: &VideoPlayerUI, player: &VideoPlayer) {
+    ui.set_subtitle_path(player.get_subtitle_path().map(|p| p.display().to_string()).unwrap_or_default().into());
+    ui.set_subtitle_offset(player.get_subtitle_offset());
+    // 複数行の字幕も一覧では1行にまとめる
+    let lines: Vec<SubtitleLine> = player
+        .get_subtitle_cues()
+        .into_iter()
+        .map(|(start, text)| SubtitleLine { start, text: text.replace('\n', " ").into() })
+        .collect();
+    ui.set_subtitle_lines(ModelRc::new(VecModel::from(lines)));
+}
+
+// プレイリストの表示（ファイル名の一覧と再生中の位置）を更新
+fn update_playlist_view(ui: &VideoPlayerUI, player: &VideoPlayer) {
+    let items: Vec<SharedString> = player
+        .get_playlist()
+        .iter()
+        .map(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.display().to_string())
+                .into()
+        })
+        .collect();
+    ui.set_playlist_items(ModelRc::new(VecModel::from(items)));
+    ui.set_playlist_index(player.get_playlist_index().map_or(-1, |index| index as i32));
+}
+
+// 回転・縦横比・色の調整の表示を更新（読み込んだ動画に保存されていた値を反映する）
+fn update_adjustments_view(ui: &VideoPlayerUI, player: &VideoPlayer) {
+    let adjustments = player.get_adjustments();
+    ui.set_rotation((adjustments.rotation / 90) as i32);
+    ui.set_aspect_mode(AspectMode::ALL.iter().position(|&mode| mode == adjustments.aspect).unwrap_or(0) as i32);
+    ui.set_brightness(adjustments.brightness);
+    ui.set_contrast(adjustments.contrast);
+    ui.set_saturation(adjustments.saturation);
+    ui.set_video_aspect_override(adjustments.aspect.ratio().unwrap_or(0.0));
+}
+
+// ディスクキャッシュの使用量の表示を更新
+fn update_cache_usage(ui: &VideoPlayerUI, player: &VideoPlayer) {
+    let megabytes = player.get_cache_usage() as f32 / (1024.0 * 1024.0);
+    ui.set_cache_usage(trf("{} MB used", &[&format!("{:.1}", megabytes)]).into());
+}
+
+// 時間バーの補間の基準（UIの更新のたびに実際の再生位置で置き換える）
+#[derive(Clone, Copy)]
+struct TimeAnchor {
+    time: f32,           // 基準にした実際の再生位置
+    at: Instant,         // それを読んだ時刻
+    rate: f32,           // 1秒あたりに進む秒数
+    end: f32,            // これより先には進めない（動画の長さ）
+    max_ahead: Duration, // 次の更新が遅れても、基準からこれ以上は先に進めない
+    written: f32,        // 最後に表示した位置（ほかで書き換えられたら補間をやめる）
+}
+
+// 保存されている設定をUIに反映（起動時と、設定ファイルを読み込んだとき）
+fn update_settings_view(ui: &VideoPlayerUI, player: &VideoPlayer) {
+    ui.set_accurate_seek(player.get_seek_mode() == SeekMode::Accurate);
+    ui.set_slow_motion_smoothing(match player.get_slow_motion_smoothing() {
+        SlowMotionSmoothing::Off => 0,
+        SlowMotionSmoothing::Blend => 1,
+        SlowMotionSmoothing::Interpolate => 2,
+    });
+    ui.set_color_correction(player.get_color_correction());
+    ui.set_raw_pixel_aspect(player.get_raw_pixel_aspect());
+    ui.set_aspect_lock(player.get_aspect_lock());
+    ui.set_fade_transition(player.get_fade_transition());
+    ui.set_scan_subfolders(player.get_scan_subfolders());
+    ui.set_playlist_end(match player.get_playlist_end() {
+        PlaylistEnd::Stop => 0,
+        PlaylistEnd::RepeatAll => 1,
+        PlaylistEnd::RepeatOne => 2,
+    });
+    ui.set_ffmpeg_log_level(match player.get_ffmpeg_log_level() {
+        LogLevel::Off => 0,
+        LogLevel::Error => 1,
+        LogLevel::Warning => 2,
+        LogLevel::Info => 3,
+    });
+    ui.set_restore_paused(player.get_restore_paused());
+    let (restore_session, restore_session_play) = player.get_restore_session();
+    ui.set_restore_session(restore_session);
+    ui.set_restore_session_play(restore_session_play);
+    ui.set_cue_sound(player.get_cue_sound());
+    ui.set_cue_flash(player.get_cue_flash());
+    ui.set_video_extensions(player.get_video_extensions().join(", ").into());
+    ui.set_image_sequence(player.get_image_sequence());
+    ui.set_image_sequence_fps(player.get_image_sequence_fps());
+    ui.set_ui_refresh_rate(UI_REFRESH_RATES.iter().position(|&rate| rate == player.get_ui_refresh_rate()).unwrap_or(0) as i32);
+    // 設定ファイルで選択肢にない数にしていれば、それ以下で一番近いものを表示する
+    ui.set_decode_ahead(DECODE_AHEAD_CHOICES.iter().rposition(|&frames| frames <= player.get_decode_ahead()).unwrap_or(0) as i32);
+    ui.set_cache_size(CACHE_SIZES_MB.iter().position(|&size| size == player.get_cache_size_mb()).unwrap_or(2) as i32);
+    update_cache_usage(ui, player);
+    ui.set_tone_mapping(match player.get_tone_mapping() {
+        ToneMapping::Off => 0,
+        ToneMapping::Hable => 1,
+        ToneMapping::Mobius => 2,
+        ToneMapping::Reinhard => 3,
+    });
+    ui.set_denoise_strength(player.get_denoise().1);
+    ui.set_sharpen_strength(player.get_sharpen().1);
+    ui.set_skip_intro(player.get_skip_intro().0);
+    ui.set_skip_silence(player.get_skip_silence());
+    ui.set_match_loudness(player.get_match_loudness());
+    ui.set_min_silence(player.get_min_silence());
+    let (seek_step_short, seek_step_long) = player.get_seek_steps();
+    ui.set_seek_step_short(seek_step_short);
+    ui.set_seek_step_long(seek_step_long);
+    let (shadowing_repeats, shadowing_gap) = player.get_shadowing_options();
+    ui.set_shadowing_repeats(shadowing_repeats as i32);
+    ui.set_shadowing_gap(shadowing_gap);
+    
+    ui.set_eq_band_labels(ModelRc::new(VecModel::from(
+        equalizer::BANDS
+            .iter()
+            .map(|frequency| {
+                if *frequency >= 1000 {
+                    format!("{}k", frequency / 1000).into()
+                } else {
+                    frequency.to_string().into()
+                }
+            })
+            .collect::<Vec<SharedString>>(),
+    )));
+    
+    // 表示言語（選択肢の0番はシステムの言語に合わせる）
+    // 出力デバイス・イコライザーのプリセットなどの選択肢もここで作る
+    let language = player.get_language();
+    ui.set_language(language.map_or(0, |language| Language::ALL.iter().position(|l| *l == language).unwrap_or(0) + 1) as i32);
+    apply_language(ui, player, language.unwrap_or_else(Language::system));
+    
+    // 配色（起動時は表示する前に反映して、最初の描画から正しい色にする）
+    ui.set_theme(match player.get_dark_theme() {
+        None => 0,
+        Some(false) => 1,
+        Some(true) => 2,
+    });
+    ui.invoke_apply_theme();
+}
+
+// A/Bリピートの区間と残り回数を表示に反映
+fn update_loop_view(ui: &VideoPlayerUI, player: &VideoPlayer) {
+    let (a, b) = player.get_loop_points();
+    ui.set_loop_a(a.unwrap_or(-1.0));
+    ui.set_loop_b(b.unwrap_or(-1.0));
+    ui.set_loop_remaining(player.get_loop_remaining());
+}
+
+// 「続きから見る」の一覧を作り直し、サムネイルの生成を始める
+fn update_history_view(ui: &VideoPlayerUI, player: &mut VideoPlayer) {
+    let entries = player.watch_history();
+    let items: Vec<HistoryItem> = entries
+        .iter()
+        .map(|entry| HistoryItem {
+            path: entry.path.display().to_string().into(),
+            name: entry
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| entry.path.display().to_string())
+                .into(),
+            progress: entry.progress(),
+            finished: entry.finished(),
+            thumbnail: Image::default(),
+        })
+        .collect();
+    ui.set_history_items(ModelRc::new(VecModel::from(items)));
+    player.start_history_thumbnails(&entries);
+}
+
+fn update_toast_view(ui: &VideoPlayerUI, toasts: &Toasts) {
+    let items: Vec<ToastItem> = toasts
+        .items()
+        .iter()
+        .map(|toast| ToastItem {
+            id: toast.id,
+            text: toast.message.clone().into(),
+            error: toast.error,
+            persistent: toast.is_persistent(),
+        })
+        .collect();
+    ui.set_toasts(ModelRc::new(VecModel::from(items)));
+}
+
+// タイトルバーに出すファイル名の最大文字数（超えた分は拡張子を残して途中を「…」にする）
+const MAX_TITLE_FILE_NAME: usize = 60;
+
+// タイトルバーの文字列（「movie.mp4 — 1920x1080 H.264 23.976fps」。概要がなければファイル名だけ）
+fn window_title(path: &Path, summary: Option<&str>) -> String {
+    let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let chars: Vec<char> = name.chars().collect();
+    let name = if chars.len() > MAX_TITLE_FILE_NAME {
+        let tail_len = 12;
+        let head: String = chars[..MAX_TITLE_FILE_NAME - tail_len - 1].iter().collect();
+        let tail: String = chars[chars.len() - tail_len..].iter().collect();
+        format!("{}…{}", head, tail)
+    } else {
+        name
+    };
+    match summary {
+        Some(summary) => format!("{} — {}", name, summary),
+        None => name,
+    }
+}
+
+// ピーク値の列を、上下対称に塗りつぶすPathコマンドへ変換（viewbox: 幅1 x 高さ2）
+fn waveform_path_commands(peaks: &[f32]) -> String {
+    if peaks.is_empty() {
+        return String::new();
+    }
+    
+    let step = 1.0 / peaks.len() as f32;
+    let mut commands = String::from("M 0 1");
+    // 上半分を左から右へ
+    for (i, peak) in peaks.iter().enumerate() {
+        commands.push_str(&format!(" L {:.4} {:.4}", i as f32 * step, 1.0 - peak));
+    }
+    // 下半分を右から左へ
+    for (i, peak) in peaks.iter().enumerate().rev() {
+        commands.push_str(&format!(" L {:.4} {:.4}", i as f32 * step, 1.0 + peak));
+    }
+    commands.push_str(" Z");
+    commands
+}
+
+// --thumbnail: 指定した時刻のフレームを画像として保存する（GUIなし）。戻り値は終了コード
+fn run_thumbnail(time: Option<std::ffi::OsString>, out: Option<std::ffi::OsString>, files: &[PathBuf]) -> i32 {
+    let (Some(time), Some(out), [file]) = (time, out, files) else {
+        eprintln!("{}", tr("Usage: video-player-ui --thumbnail <seconds> <output image> <video file>"));
+        return 2;
+    };
+    let Some(time) = time.to_str().and_then(|time| time.parse::<f32>().ok()).filter(|time| time.is_finite()) else {
+        eprintln!("{}", trf("Error: {}", &[&trf("Invalid time: {}", &[&time.to_string_lossy()])]));
+        return 2;
+    };
+    let out = PathBuf::from(out);
+    
+    let result = player::extract_frame(file, time, None).and_then(|image| {
+        image.save(&out).map_err(|e| trf("Failed to save the image: {}", &[&e]))
+    });
+    match result {
+        Ok(()) => {
+            println!("{}", trf("Saved the thumbnail: {}", &[&out.display()]));
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", trf("Error: {}", &[&e]));
+            1
+        }
+    }
+}
+
 fn main() {
     // 高DPIスケーリングを無効化（実ピクセルで動作）
     std::env::set_var("SLINT_SCALE_FACTOR", "1.0");
     std::env::set_var("WINIT_HIDPI_FACTOR", "1.0");
     
-    let ui = VideoPlayerUI::new().unwrap();
+    // コマンドライン引数: 動画ファイル（複数可）と --single-instance、--thumbnail <時刻> <出力先>
+    let mut single_instance_mode = false;
+    let mut thumbnail: Option<(Option<std::ffi::OsString>, Option<std::ffi::OsString>)> = None;
+    let mut files: Vec<PathBuf> = Vec::new();
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--single-instance" {
+            single_instance_mode = true;
+        } else if arg == "--thumbnail" {
+            thumbnail = Some((args.next(), args.next()));
+        } else {
+            files.push(PathBuf::from(arg));
+        }
+    }
+    
+    // サムネイルの書き出しだけならウィンドウを作らずに終了する
+    if let Some((time, out)) = thumbnail {
+        std::process::exit(run_thumbnail(time, out, &files));
+    }
+    
+    // 単一インスタンス: 既に起動していればファイルを渡して終了する
+    // 待ち受けはタイマーに渡し、終了時にタイマーと一緒に破棄されるとロックファイルも消える
+    let instance = if single_instance_mode {
+        match SingleInstance::acquire(&files) {
+            Some(Instance::Forwarded) => {
+                println!("{}", tr("Passed the files to the running window"));
+                return;
+            }
+            Some(Instance::Primary(instance)) => Some(instance),
+            None => None,
+        }
+    } else {
+        None
+    };
+    
+    let ui = VideoPlayerUI::new().unwrap();
+    
+    // 動画プレイヤーのインスタンスを作成
+    let video_player = Arc::new(Mutex::new(VideoPlayer::new()));
+    
+    // 画面に表示する通知（コールバックとタイマーから積み、タイマーで表示を更新する）
+    let toasts = Rc::new(RefCell::new(Toasts::default()));
+    let missing_tools = player::missing_tools();
+    for name in &missing_tools {
+        eprintln!("{}", trf("Error: {} was not found", &[&name]));
+        toasts.borrow_mut().persistent_error(trf("{} was not found. Install FFmpeg and add it to PATH.", &[&name]));
+    }
+    // ffplayだけがない最小構成のFFmpegでは、音声なしで再生する
+    let audio_available = video_player.lock().unwrap().is_audio_available();
+    ui.set_audio_available(audio_available);
+    if !audio_available && !missing_tools.contains(&"ffmpeg") {
+        eprintln!("{}", trf("Warning: {}", &[&tr("ffplay was not found, so videos will play without sound")]));
+        toasts.borrow_mut().error(tr("ffplay was not found, so videos will play without sound"));
+    }
+    
+    let ui_weak = ui.as_weak();
+    let toasts_clone = Rc::clone(&toasts);
+    ui.on_dismiss_toast(move |id| {
+        let ui = ui_weak.unwrap();
+        let mut toasts = toasts_clone.borrow_mut();
+        toasts.dismiss(id);
+        update_toast_view(&ui, &toasts);
+    });
+    
+    // 再生状態が変わったら（終端に達したときなども）次のタイマーを待たずに再生ボタンの表示を切り替える
+    // コールバックは再生スレッドからも呼ばれるので、UIの更新はイベントループに渡す
+    let ui_weak = ui.as_weak();
+    video_player.lock().unwrap().on_state_changed(move |state| {
+        let _ = ui_weak.upgrade_in_event_loop(move |ui| ui.set_is_playing(state == PlaybackState::Playing));
+    });
+    
+    // 保存されている設定をUIに反映
+    update_settings_view(&ui, &video_player.lock().unwrap());
+    
+    // コマンドラインで渡されたファイルはプレイリストに入れ、最初のものを開く
+    if !files.is_empty() {
+        let mut player = video_player.lock().unwrap();
+        player.add_to_playlist(files);
+        update_playlist_view(&ui, &player);
+    } else {
+        // ファイルを渡されずに起動したときだけ、前回のセッションに戻す（有効な場合）
+        let mut player = video_player.lock().unwrap();
+        if player.restore_session() {
+            ui.set_volume(player.get_volume());
+            ui.set_denoise(player.get_denoise().0);
+            ui.set_sharpen(player.get_sharpen().0);
+            update_playlist_view(&ui, &player);
+        }
+    }
+    update_history_view(&ui, &mut video_player.lock().unwrap());
+    
+    // 動画選択コールバック
+    let player_clone = Arc::clone(&video_player);
+    ui.on_select_video(move || {
+        if let Some(path) = open_video_dialog(&player_clone).pick_file() {
+            // 読み込みはバックグラウンドで行い、完了はタイマーで検出する
+            let mut player = player_clone.lock().unwrap();
+            player.remember_open_directory(&path);
+            player.load_video(path);
+        }
+    });
+    
+    // 再生/一時停止コールバック
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    let toasts_clone = Rc::clone(&toasts);
+    ui.on_play_pause(move || {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        
+        if player.is_playing() {
+            player.pause();
+            player.save_position();
+            ui.set_is_playing(false);
+        } else {
+            match player.play() {
+                Ok(_) => {
+                    ui.set_is_playing(true);
+                }
+                Err(e) => {
+                    eprintln!("{}", trf("Playback error: {}", &[&e]));
+                    toasts_clone.borrow_mut().error(e);
+                }
+            }
+        }
+    });
+    
+    // キーフレーム移動コールバック
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    ui.on_prev_keyframe(move || {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        match player.prev_keyframe() {
+            Ok(_) => ui.set_current_time(player.get_current_time()),
+            Err(e) => println!("{}", e),
+        }
+    });
+    
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    ui.on_next_keyframe(move || {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        match player.next_keyframe() {
+            Ok(_) => ui.set_current_time(player.get_current_time()),
+            Err(e) => println!("{}", e),
+        }
+    });
+    
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    ui.on_step_backward(move || {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        player.step_backward();
+        ui.set_current_time(player.get_current_time());
+    });
+    
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    ui.on_step_forward(move || {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        player.step_forward();
+        ui.set_current_time(player.get_current_time());
+    });
+    
+    // 設定した秒数だけ移動
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    ui.on_seek_by(move |seconds| {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        player.seek_by(seconds);
+        ui.set_current_time(player.get_current_time());
+    });
+    
+    // スキップする秒数の変更（範囲外なら元の値に戻す）
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    let toasts_clone = Rc::clone(&toasts);
+    ui.on_seek_steps_changed(move |short, long| {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        if let Err(e) = player.set_seek_steps(short, long) {
+            eprintln!("{}", trf("Error: {}", &[&e]));
+            toasts_clone.borrow_mut().error(e);
+        }
+        let (short, long) = player.get_seek_steps();
+        ui.set_seek_step_short(short);
+        ui.set_seek_step_long(long);
+    });
+    
+    // シーク方法の切り替えコールバック
+    let player_clone = Arc::clone(&video_player);
+    ui.on_accurate_seek_changed(move |accurate| {
+        let mut player = player_clone.lock().unwrap();
+        player.set_seek_mode(if accurate { SeekMode::Accurate } else { SeekMode::Fast });
+    });
+    
+    // 再生速度の変更コールバック
+    let player_clone = Arc::clone(&video_player);
+    ui.on_playback_speed_changed(move |speed| {
+        let mut player = player_clone.lock().unwrap();
+        player.set_playback_speed(speed);
+    });
+    
+    // スロー再生の補間方法の変更コールバック
+    let player_clone = Arc::clone(&video_player);
+    ui.on_slow_motion_smoothing_changed(move |index| {
+        let mut player = player_clone.lock().unwrap();
+        player.set_slow_motion_smoothing(match index {
+            1 => SlowMotionSmoothing::Blend,
+            2 => SlowMotionSmoothing::Interpolate,
+            _ => SlowMotionSmoothing::Off,
+        });
+    });
+    
+    // UIの更新頻度の変更コールバック（タイマーの間隔は次のティックで反映される）
+    let player_clone = Arc::clone(&video_player);
+    ui.on_ui_refresh_rate_changed(move |index| {
+        let mut player = player_clone.lock().unwrap();
+        player.set_ui_refresh_rate(UI_REFRESH_RATES.get(index as usize).copied().unwrap_or(0));
+    });
+    
+    // 先読みするフレーム数の変更コールバック（次の再生・シークから反映される）
+    let player_clone = Arc::clone(&video_player);
+    let toasts_clone = Rc::clone(&toasts);
+    ui.on_decode_ahead_changed(move |index| {
+        let mut player = player_clone.lock().unwrap();
+        if let Err(e) = player.set_decode_ahead(DECODE_AHEAD_CHOICES.get(index as usize).copied().unwrap_or(0)) {
+            eprintln!("{}", trf("Error: {}", &[&e]));
+            toasts_clone.borrow_mut().error(e);
+        }
+    });
+    
+    // ディスクキャッシュの上限の変更・削除コールバック
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    ui.on_cache_size_changed(move |index| {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        player.set_cache_size_mb(CACHE_SIZES_MB.get(index as usize).copied().unwrap_or(CACHE_SIZES_MB[2]));
+        update_cache_usage(&ui, &player);
+    });
+    
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    let toasts_clone = Rc::clone(&toasts);
+    ui.on_clear_cache(move || {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        match player.clear_cache() {
+            Ok(_) => toasts_clone.borrow_mut().info(tr("Cleared the cache")),
+            Err(e) => {
+                eprintln!("{}", trf("Error: {}", &[&e]));
+                toasts_clone.borrow_mut().error(e);
+            }
+        }
+        update_cache_usage(&ui, &player);
+    });
+    
+    // 設定の書き出し・読み込みコールバック（読み込んだ設定はすぐに反映する）
+    let player_clone = Arc::clone(&video_player);
+    let toasts_clone = Rc::clone(&toasts);
+    ui.on_export_config(move || {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("JSON", &["json"])
+            .set_file_name("video-player-settings.json")
+            .save_file()
+        else {
+            return;
+        };
+        let player = player_clone.lock().unwrap();
+        match player.export_config(&path) {
+            Ok(_) => toasts_clone.borrow_mut().info(trf("Exported the settings to {}", &[&path.display()])),
+            Err(e) => {
+                eprintln!("{}", trf("Error: {}", &[&e]));
+                toasts_clone.borrow_mut().error(e);
+            }
+        }
+    });
+    
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    let toasts_clone = Rc::clone(&toasts);
+    ui.on_import_config(move || {
+        let ui = ui_weak.unwrap();
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("JSON", &["json"])
+            .add_filter("All Files", &["*"])
+            .pick_file()
+        else {
+            return;
+        };
+        let mut player = player_clone.lock().unwrap();
+        match player.import_config(&path) {
+            Ok((applied, 0)) => toasts_clone.borrow_mut().info(trf("Imported {} settings", &[&applied])),
+            Ok((applied, skipped)) => toasts_clone
+                .borrow_mut()
+                .error(trf("Imported {} settings ({} had invalid values and were skipped)", &[&applied, &skipped])),
+            Err(e) => {
+                eprintln!("{}", trf("Error: {}", &[&e]));
+                toasts_clone.borrow_mut().error(e);
+            }
+        }
+        update_settings_view(&ui, &player);
+    });
+    
+    let player_clone = Arc::clone(&video_player);
+    ui.on_aspect_lock_changed(move |enabled| {
+        let mut player = player_clone.lock().unwrap();
+        player.set_aspect_lock(enabled);
+    });
+    
+    let player_clone = Arc::clone(&video_player);
+    ui.on_fade_transition_changed(move |enabled| {
+        let mut player = player_clone.lock().unwrap();
+        player.set_fade_transition(enabled);
+    });
+    
+    // 表示言語の変更コールバック（0: システムの言語, 1以降: Language::ALLの順）
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    ui.on_language_changed(move |index| {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        let language = Language::ALL.get((index - 1).max(0) as usize).copied().filter(|_| index > 0);
+        player.set_language(language);
+        apply_language(&ui, &player, language.unwrap_or_else(Language::system));
+    });
+    
+    // 配色の変更コールバック（UI側で反映済みなので保存だけする）
+    let player_clone = Arc::clone(&video_player);
+    ui.on_theme_changed(move |index| {
+        let mut player = player_clone.lock().unwrap();
+        player.set_dark_theme(match index {
+            1 => Some(false),
+            2 => Some(true),
+            _ => None,
+        });
+    });
+    
+    // 色空間の補正の切り替えコールバック
+    let player_clone = Arc::clone(&video_player);
+    ui.on_color_correction_changed(move |enabled| {
+        let mut player = player_clone.lock().unwrap();
+        player.set_color_correction(enabled);
+    });
+    
+    // ピクセルの縦横比を無視するかの切り替え（開いている動画は開き直す）
+    let player_clone = Arc::clone(&video_player);
+    ui.on_raw_pixel_aspect_changed(move |enabled| {
+        player_clone.lock().unwrap().set_raw_pixel_aspect(enabled);
+    });
+    
+    // 音声の出力デバイスの選択コールバック（空文字なら既定のデバイス）
+    let player_clone = Arc::clone(&video_player);
+    ui.on_audio_device_selected(move |name| {
+        let mut player = player_clone.lock().unwrap();
+        player.set_audio_device(if name.is_empty() { None } else { Some(name.to_string()) });
+    });
+    
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    ui.on_refresh_audio_devices(move || {
+        let ui = ui_weak.unwrap();
+        let player = player_clone.lock().unwrap();
+        update_audio_device_list(&ui, &player);
+    });
+    
+    // 全画面表示のモニターの選択コールバック（空文字なら現在のモニター）
+    let player_clone = Arc::clone(&video_player);
+    ui.on_fullscreen_monitor_selected(move |name| {
+        let mut player = player_clone.lock().unwrap();
+        player.set_fullscreen_monitor(if name.is_empty() { None } else { Some(name.to_string()) });
+    });
+    
+    // HDRのトーンマッピングの変更コールバック
+    let player_clone = Arc::clone(&video_player);
+    ui.on_tone_mapping_changed(move |index| {
+        let mut player = player_clone.lock().unwrap();
+        player.set_tone_mapping(match index {
+            1 => ToneMapping::Hable,
+            2 => ToneMapping::Mobius,
+            3 => ToneMapping::Reinhard,
+            _ => ToneMapping::Off,
+        });
+    });
+    
+    // ノイズ除去・シャープのコールバック（反映はタイマーでまとめて行う）
+    let player_clone = Arc::clone(&video_player);
+    ui.on_denoise_changed(move |enabled, strength| {
+        let mut player = player_clone.lock().unwrap();
+        player.set_denoise(enabled, strength);
+    });
+    
+    let player_clone = Arc::clone(&video_player);
+    ui.on_sharpen_changed(move |enabled, strength| {
+        let mut player = player_clone.lock().unwrap();
+        player.set_sharpen(enabled, strength);
+    });
+    
+    // 回転・縦横比・色の調整のコールバック（動画ごとに保存し、次に開いたときにも使う）
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    ui.on_adjustments_changed(move |rotation, aspect, brightness, contrast, saturation| {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        player.set_adjustments(VideoAdjustments {
+            rotation: rotation.max(0) as u32 * 90,
+            aspect: AspectMode::ALL.get(aspect.max(0) as usize).copied().unwrap_or(AspectMode::Auto),
+            brightness,
+            contrast,
+            saturation,
+        });
+        update_adjustments_view(&ui, &player);
+    });
+    
+    // 時計回りに90度回す（0°→90°→180°→270°と切り替わる）
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    ui.on_rotate_clockwise(move || {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        player.rotate_clockwise();
+        update_adjustments_view(&ui, &player);
+    });
+    
+    let player_clone = Arc::clone(&video_player);
+    let toasts_clone = Rc::clone(&toasts);
+    ui.on_save_adjustments_for_folder(move || {
+        let mut player = player_clone.lock().unwrap();
+        if let Err(e) = player.save_adjustments_for_folder() {
+            eprintln!("{}", trf("Error: {}", &[&e]));
+            toasts_clone.borrow_mut().error(e);
+        }
+    });
+    
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    ui.on_reset_adjustments(move || {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        player.reset_adjustments();
+        update_adjustments_view(&ui, &player);
+    });
+    
+    // 表示時刻の確認（デバッグ用）の切り替え
+    let player_clone = Arc::clone(&video_player);
+    ui.on_pts_debug_changed(move |enabled| {
+        player_clone.lock().unwrap().set_pts_debug(enabled);
+    });
+    
+    // ffmpegのログを残す詳しさの変更
+    let player_clone = Arc::clone(&video_player);
+    ui.on_ffmpeg_log_level_changed(move |index| {
+        player_clone.lock().unwrap().set_ffmpeg_log_level(match index {
+            1 => LogLevel::Error,
+            2 => LogLevel::Warning,
+            3 => LogLevel::Info,
+            _ => LogLevel::Off,
+        });
+    });
+    
+    // 残したffmpegのログを消す
+    ui.on_clear_ffmpeg_log(ffmpeg_log::clear);
+    
+    // スキャン（実時間に合わせず最速で表示）の切り替えコールバック
+    let player_clone = Arc::clone(&video_player);
+    ui.on_scan_mode_changed(move |enabled| {
+        player_clone.lock().unwrap().set_scan_mode(enabled);
+    });
+    
+    // 逆再生の切り替えコールバック
+    let player_clone = Arc::clone(&video_player);
+    ui.on_reverse_changed(move |enabled| {
+        let mut player = player_clone.lock().unwrap();
+        player.set_reverse(enabled);
+    });
+    
+    let player_clone = Arc::clone(&video_player);
+    ui.on_reverse_audio_changed(move |enabled| {
+        let mut player = player_clone.lock().unwrap();
+        player.set_reverse_audio(enabled);
+    });
+    
+    // 停止コールバック
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    ui.on_stop(move || {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        player.stop();
+        ui.set_is_playing(false);
+        ui.set_current_time(0.0);
+    });
+    
+    // シークコールバック
+    let player_clone = Arc::clone(&video_player);
+    ui.on_seek(move |time| {
+        let mut player = player_clone.lock().unwrap();
+        player.seek(time);
+    });
+    
+    let player_clone = Arc::clone(&video_player);
+    ui.on_scrub(move |time| {
+        let mut player = player_clone.lock().unwrap();
+        player.scrub(time);
+    });
+    
+    // 指定したフレーム番号へ移動（範囲外は最後のフレームに丸める）
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    let toasts_clone = Rc::clone(&toasts);
+    ui.on_go_to_frame(move |text| {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        let Ok(frame) = text.trim().parse::<u64>() else {
+            toasts_clone.borrow_mut().error(tr("Enter a frame number as a non-negative integer"));
+            return;
+        };
+        let last_frame = timecode::total_frames(player.duration, player.get_fps()).saturating_sub(1);
+        let time = timecode::time_of_frame(frame.min(last_frame), player.get_fps());
+        player.seek(time);
+        ui.set_current_time(time);
+    });
+    
+    // リピート回数変更コールバック
+    let player_clone = Arc::clone(&video_player);
+    ui.on_repeat_changed(move |count| {
+        // 無限リピートのときは停止せずに先頭へつなげる
+        player_clone.lock().unwrap().set_seamless_loop(count == -1);
+        if count == -1 {
+            println!("{}", tr("Repeat: infinite"));
+        } else {
+            println!("{}", trf("Repeat count: {}", &[&count]));
+        }
+    });
+    
+    // ボリューム変更コールバック
+    let player_clone = Arc::clone(&video_player);
+    ui.on_volume_changed(move |volume| {
+        let mut player = player_clone.lock().unwrap();
+        player.set_volume(volume);
+    });
+    
+    // 音声オフセット変更コールバック
+    let player_clone = Arc::clone(&video_player);
+    ui.on_audio_offset_changed(move |offset| {
+        let mut player = player_clone.lock().unwrap();
+        player.set_audio_offset(offset);
+    });
+    let player_clone = Arc::clone(&video_player);
+    ui.on_audio_offset_released(move || {
+        player_clone.lock().unwrap().save_audio_offset();
+    });
+    
+    // 音量ブースト変更コールバック
+    let player_clone = Arc::clone(&video_player);
+    ui.on_volume_boost_changed(move |boost| {
+        let mut player = player_clone.lock().unwrap();
+        player.set_volume_boost(boost);
+    });
+    let player_clone = Arc::clone(&video_player);
+    ui.on_volume_boost_released(move || {
+        player_clone.lock().unwrap().save_volume_boost();
+    });
+    
+    // 音量均一化の切り替えコールバック
+    let player_clone = Arc::clone(&video_player);
+    ui.on_normalize_loudness_changed(move |enabled| {
+        let mut player = player_clone.lock().unwrap();
+        player.set_normalize_loudness(enabled);
+    });
+    
+    // 動画ごとの音量の揃えの切り替えコールバック
+    let player_clone = Arc::clone(&video_player);
+    ui.on_match_loudness_changed(move |enabled| {
+        let mut player = player_clone.lock().unwrap();
+        player.set_match_loudness(enabled);
+    });
+    
+    // モノラル再生の切り替えコールバック
+    let player_clone = Arc::clone(&video_player);
+    ui.on_mono_changed(move |mono| {
+        let mut player = player_clone.lock().unwrap();
+        player.set_mono(mono);
+    });
+    
+    // 左右バランス変更コールバック
+    let player_clone = Arc::clone(&video_player);
+    ui.on_balance_changed(move |balance| {
+        let mut player = player_clone.lock().unwrap();
+        player.set_balance(balance);
+    });
+    
+    // イントロスキップの設定変更コールバック
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    ui.on_skip_intro_changed(move |seconds, this_file_only| {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        player.set_skip_intro(seconds, this_file_only);
+        let (intro, intro_per_file) = player.get_skip_intro();
+        ui.set_skip_intro(intro);
+        ui.set_skip_intro_per_file(intro_per_file);
+    });
+    
+    // イントロスキップの取り消しコールバック（先頭に戻る）
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    ui.on_undo_skip_intro(move || {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        player.seek(0.0);
+        ui.set_current_time(0.0);
+        ui.set_intro_notice("".into());
+    });
+    
+    // イコライザーのプリセット選択コールバック（「カスタム」を選んだ場合は何もしない）
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    ui.on_equalizer_preset_selected(move |index| {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        if let Some((name, gains)) = equalizer::PRESETS.get(index.max(0) as usize) {
+            player.set_equalizer(gains.to_vec());
+            println!("{}", trf("Equalizer: {}", &[&tr(name)]));
+        }
+        update_equalizer_view(&ui, &player);
+    });
+    
+    // イコライザーの帯域ごとのゲイン変更コールバック
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    ui.on_equalizer_band_changed(move |band, gain| {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        let mut gains = player.get_equalizer().to_vec();
+        if let Some(value) = gains.get_mut(band.max(0) as usize) {
+            *value = gain;
+            player.set_equalizer(gains);
+        }
+        // スライダーは操作中なので作り直さず、プリセットの表示だけ合わせる
+        ui.set_eq_preset(equalizer::preset_index(player.get_equalizer()).unwrap_or(equalizer::PRESETS.len()) as i32);
+    });
+    
+    // 無音スキップの切り替えコールバック
+    let player_clone = Arc::clone(&video_player);
+    ui.on_skip_silence_changed(move |enabled| {
+        let mut player = player_clone.lock().unwrap();
+        player.set_skip_silence(enabled);
+    });
+    
+    // 無音とみなす最短の長さの変更コールバック
+    let player_clone = Arc::clone(&video_player);
+    ui.on_min_silence_changed(move |seconds| {
+        let mut player = player_clone.lock().unwrap();
+        player.set_min_silence(seconds);
+    });
+    
+    // 字幕選択コールバック
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    let toasts_clone = Rc::clone(&toasts);
+    ui.on_select_subtitle(move || {
+        let ui = ui_weak.unwrap();
+        
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("Subtitles", &["srt"])
+            .add_filter("All Files", &["*"])
+            .pick_file()
+        {
+            let mut player = player_clone.lock().unwrap();
+            match player.load_subtitles(path) {
+                Ok(_) => update_subtitle_view(&ui, &player),
+                Err(e) => {
+                    eprintln!("{}", trf("Error: {}", &[&e]));
+                    toasts_clone.borrow_mut().error(e);
+                }
+            }
+        }
+    });
+    
+    // 字幕オフセット変更コールバック
+    let player_clone = Arc::clone(&video_player);
+    ui.on_subtitle_offset_changed(move |offset| {
+        let mut player = player_clone.lock().unwrap();
+        player.set_subtitle_offset(offset);
+    });
+    let player_clone = Arc::clone(&video_player);
+    ui.on_subtitle_offset_released(move || {
+        player_clone.lock().unwrap().save_subtitle_offset();
+    });
+    
+    // A/Bリピートのコールバック
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    ui.on_set_loop_a(move || {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        player.set_loop_a();
+        update_loop_view(&ui, &player);
+    });
+    
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    let toasts_clone = Rc::clone(&toasts);
+    ui.on_set_loop_b(move || {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        match player.set_loop_b() {
+            Ok(_) => update_loop_view(&ui, &player),
+            Err(e) => {
+                eprintln!("{}", trf("Error: {}", &[&e]));
+                toasts_clone.borrow_mut().error(e);
+            }
+        }
+    });
+    
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    ui.on_clear_loop(move || {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        player.clear_loop();
+        update_loop_view(&ui, &player);
+    });
+    
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    ui.on_loop_count_changed(move |count| {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        player.set_loop_count(count);
+        update_loop_view(&ui, &player);
+    });
+    
+    // シャドーイングのコールバック（状態の表示はタイマーで更新する）
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    let toasts_clone = Rc::clone(&toasts);
+    ui.on_toggle_shadowing(move || {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        if player.is_shadowing() {
+            player.stop_shadowing();
+        } else if let Err(e) = player.start_shadowing() {
+            eprintln!("{}", trf("Error: {}", &[&e]));
+            toasts_clone.borrow_mut().error(e);
+        }
+        update_loop_view(&ui, &player);
+        ui.set_shadowing(player.is_shadowing());
+        ui.set_is_playing(player.is_playing());
+    });
+    
+    let player_clone = Arc::clone(&video_player);
+    ui.on_shadowing_skip(move |delta| {
+        player_clone.lock().unwrap().skip_shadowing(delta);
+    });
+    
+    // 前後の字幕の始まりへ移る
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    ui.on_subtitle_step(move |delta| {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        player.seek_to_adjacent_cue(delta);
+        ui.set_current_time(player.get_current_time());
+    });
+    
+    // 字幕の一覧でクリックした字幕の始まりへ移る
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    ui.on_subtitle_line_clicked(move |index| {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        player.seek_to_cue(index.max(0) as usize);
+        ui.set_current_time(player.get_current_time());
+    });
+    
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    ui.on_shadowing_options_changed(move |repeats, gap| {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        player.set_shadowing_options(repeats.max(1) as u32, gap);
+        let (repeats, gap) = player.get_shadowing_options();
+        ui.set_shadowing_repeats(repeats as i32);
+        ui.set_shadowing_gap(gap);
+    });
+    
+    // フレーム一括書き出しコールバック
+    let player_clone = Arc::clone(&video_player);
+    let toasts_clone = Rc::clone(&toasts);
+    ui.on_export_frames(move |interval| {
+        if let Some(out_dir) = rfd::FileDialog::new().pick_folder() {
+            let player = player_clone.lock().unwrap();
+            if let Err(e) = player.export_frames(interval, out_dir) {
+                eprintln!("{}", trf("Error: {}", &[&e]));
+                toasts_clone.borrow_mut().error(e);
+            }
+        }
+    });
+    
+    // GIF書き出しコールバック（区間はA/Bリピートの区間、未設定なら現在位置から5秒）
+    let player_clone = Arc::clone(&video_player);
+    let toasts_clone = Rc::clone(&toasts);
+    ui.on_export_gif(move |fps, width| {
+        if let Some(out) = rfd::FileDialog::new()
+            .add_filter("GIF", &["gif"])
+            .set_file_name("clip.gif")
+            .save_file()
+        {
+            let player = player_clone.lock().unwrap();
+            let (start, end) = match player.get_loop_points() {
+                (Some(a), Some(b)) => (a, b),
+                _ => {
+                    let current = player.get_current_time();
+                    (current, current + 5.0)
+                }
+            };
+            if let Err(e) = player.export_gif(start, end, fps.max(0) as u32, width.max(0) as u32, out) {
+                eprintln!("{}", trf("Error: {}", &[&e]));
+                toasts_clone.borrow_mut().error(e);
+            }
+        }
+    });
     
-    // 動画プレイヤーのインスタンスを作成
-    let video_player = Arc::new(Mutex::new(VideoPlayer::new()));
+    // 回転したコピーを保存するコールバック（角度は表示の調整で選んでいる回転）
+    let player_clone = Arc::clone(&video_player);
+    let toasts_clone = Rc::clone(&toasts);
+    ui.on_save_rotated_copy(move |rotation| {
+        let Some(path) = player_clone.lock().unwrap().get_video_path().map(Path::to_path_buf) else {
+            return;
+        };
+        let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        // 回転情報だけで保存できるよう、MP4・MOVは元の拡張子のままにする
+        let extension = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .filter(|e| ["mp4", "m4v", "mov"].contains(&e.as_str()))
+            .unwrap_or_else(|| "mp4".to_string());
+        let mut dialog = rfd::FileDialog::new()
+            .add_filter("MP4 / MOV", &["mp4", "m4v", "mov"])
+            .add_filter("MKV", &["mkv"])
+            .set_file_name(format!("{}_rotated.{}", stem, extension));
+        if let Some(dir) = path.parent() {
+            dialog = dialog.set_directory(dir);
+        }
+        if let Some(out) = dialog.save_file() {
+            if let Err(e) = player_clone.lock().unwrap().save_rotated_copy(rotation.max(0) as u32 * 90, out) {
+                eprintln!("{}", trf("Error: {}", &[&e]));
+                toasts_clone.borrow_mut().error(e);
+            }
+        }
+    });
+    
+    // 「続きから見る」のコールバック
+    let player_clone = Arc::clone(&video_player);
+    ui.on_resume_history_item(move |path| {
+        let mut player = player_clone.lock().unwrap();
+        player.resume(PathBuf::from(path.as_str()));
+    });
     
-    // 動画選択コールバック
     let ui_weak = ui.as_weak();
     let player_clone = Arc::clone(&video_player);
-    ui.on_select_video(move || {
+    ui.on_remove_history_item(move |path| {
         let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        player.remove_from_history(Path::new(path.as_str()));
+        update_history_view(&ui, &mut player);
+    });
+    
+    // 表示中の位置のフレームをPNGで保存するコールバック
+    let player_clone = Arc::clone(&video_player);
+    let toasts_clone = Rc::clone(&toasts);
+    ui.on_save_frame(move |original_size| {
+        let player = player_clone.lock().unwrap();
+        let time = player.get_current_time();
+        let stem = player
+            .get_video_path()
+            .and_then(|path| path.file_stem())
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| "frame".to_string());
+        let file_name = format!("{}_{}.png", stem, timecode::format_timecode(time, player.get_fps()).replace(':', "-"));
+        drop(player); // ダイアログを開いている間に再生が止まらないように
         
-        if let Some(path) = rfd::FileDialog::new()
-            .add_filter("Video Files", &["mp4", "avi", "mkv", "mov", "wmv", "flv", "webm"])
-            .add_filter("All Files", &["*"])
-            .pick_file()
+        if let Some(out) = rfd::FileDialog::new()
+            .add_filter("PNG", &["png"])
+            .set_file_name(&file_name)
+            .save_file()
         {
+            let player = player_clone.lock().unwrap();
+            if let Err(e) = player.save_frame_at(time, out, original_size) {
+                eprintln!("{}", trf("Error: {}", &[&e]));
+                toasts_clone.borrow_mut().error(e);
+            }
+        }
+    });
+    
+    // プレイリストへの追加コールバック
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    ui.on_add_to_playlist(move || {
+        let ui = ui_weak.unwrap();
+        
+        if let Some(paths) = open_video_dialog(&player_clone).pick_files() {
             let mut player = player_clone.lock().unwrap();
-            match player.load_video(path.clone()) {
-                Ok(_) => {
-                    ui.set_video_path(path.display().to_string().into());
-                    ui.set_duration(player.duration);
-                    
-                    // 最初のフレームを表示
-                    if let Some(frame) = player.get_current_frame() {
-                        let width = frame.width();
-                        let height = frame.height();
-                        let buffer = SharedPixelBuffer::<Rgba8Pixel>::clone_from_slice(
-                            frame.as_raw(),
-                            width,
-                            height,
-                        );
-                        ui.set_video_frame(Image::from_rgba8(buffer));
-                    }
-                    
-                    println!("動画を選択しました: {}", path.display());
-                }
-                Err(e) => {
-                    eprintln!("エラー: {}", e);
-                }
+            if let Some(path) = paths.first() {
+                player.remember_open_directory(path);
             }
+            player.add_to_playlist(paths);
+            update_playlist_view(&ui, &player);
         }
     });
     
-    // 再生/一時停止コールバック
+    // フォルダを開いてプレイリストにするコールバック
     let ui_weak = ui.as_weak();
     let player_clone = Arc::clone(&video_player);
-    ui.on_play_pause(move || {
+    let toasts_clone = Rc::clone(&toasts);
+    ui.on_open_folder(move || {
         let ui = ui_weak.unwrap();
+        let start_dir = player_clone.lock().unwrap().get_open_directory();
+        let mut dialog = rfd::FileDialog::new();
+        if let Some(start_dir) = start_dir {
+            dialog = dialog.set_directory(start_dir);
+        }
+        let Some(dir) = dialog.pick_folder() else {
+            return;
+        };
         let mut player = player_clone.lock().unwrap();
-        
-        if player.is_playing() {
-            player.pause();
-            ui.set_is_playing(false);
-        } else {
-            match player.play() {
-                Ok(_) => {
-                    ui.set_is_playing(true);
-                }
-                Err(e) => {
-                    eprintln!("再生エラー: {}", e);
-                }
+        player.remember_open_directory(&dir);
+        let extensions: Vec<&str> = player.get_video_extensions().iter().map(String::as_str).collect();
+        match folder_scan::scan_videos(&dir, &extensions, player.get_scan_subfolders()) {
+            Ok(paths) if paths.is_empty() => {
+                toasts_clone.borrow_mut().error(trf("No videos were found in {}", &[&dir.display()]));
+            }
+            Ok(paths) => {
+                player.replace_playlist(paths);
+                ui.set_playlist_selected(-1);
+                update_playlist_view(&ui, &player);
+            }
+            Err(e) => {
+                eprintln!("{}", trf("Error: {}", &[&e]));
+                toasts_clone.borrow_mut().error(e);
             }
         }
     });
     
-    // 停止コールバック
+    let player_clone = Arc::clone(&video_player);
+    ui.on_scan_subfolders_changed(move |enabled| {
+        player_clone.lock().unwrap().set_scan_subfolders(enabled);
+    });
+    
+    // プレイリストの最後まで再生したときの動作の変更コールバック
+    let player_clone = Arc::clone(&video_player);
+    ui.on_playlist_end_changed(move |index| {
+        player_clone.lock().unwrap().set_playlist_end(match index {
+            1 => PlaylistEnd::RepeatAll,
+            2 => PlaylistEnd::RepeatOne,
+            _ => PlaylistEnd::Stop,
+        });
+    });
+    
+    // 動画とみなす拡張子の変更（空にすると既定の一覧に戻す。不正なら元の一覧を表示し直す）
     let ui_weak = ui.as_weak();
     let player_clone = Arc::clone(&video_player);
-    ui.on_stop(move || {
+    let toasts_clone = Rc::clone(&toasts);
+    ui.on_video_extensions_changed(move |text| {
         let ui = ui_weak.unwrap();
         let mut player = player_clone.lock().unwrap();
-        player.stop();
-        ui.set_is_playing(false);
-        ui.set_current_time(0.0);
+        if let Err(e) = player.set_video_extensions(&text) {
+            eprintln!("{}", trf("Error: {}", &[&e]));
+            toasts_clone.borrow_mut().error(e);
+        }
+        ui.set_video_extensions(player.get_video_extensions().join(", ").into());
     });
     
-    // シークコールバック
+    // 区間・動画の終わりの合図（音・画面を光らせる）の切り替え
     let player_clone = Arc::clone(&video_player);
-    ui.on_seek(move |time| {
+    ui.on_cue_sound_changed(move |enabled| {
+        player_clone.lock().unwrap().set_cue_sound(enabled);
+    });
+    
+    let player_clone = Arc::clone(&video_player);
+    ui.on_cue_flash_changed(move |enabled| {
+        player_clone.lock().unwrap().set_cue_flash(enabled);
+    });
+    
+    // 一時停止していた動画を開き直したときに止めた位置に戻すかの切り替え
+    let player_clone = Arc::clone(&video_player);
+    ui.on_restore_paused_changed(move |enabled| {
+        player_clone.lock().unwrap().set_restore_paused(enabled);
+    });
+    
+    // 前回のセッションに戻すかの切り替え
+    let player_clone = Arc::clone(&video_player);
+    ui.on_restore_session_changed(move |enabled, play| {
+        player_clone.lock().unwrap().set_restore_session(enabled, play);
+    });
+    
+    // 連番の画像を動画として開くかの切り替え
+    let player_clone = Arc::clone(&video_player);
+    ui.on_image_sequence_changed(move |enabled| {
+        player_clone.lock().unwrap().set_image_sequence(enabled);
+    });
+    
+    // 連番の画像のfpsの変更（範囲外なら元の値に戻す）
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    let toasts_clone = Rc::clone(&toasts);
+    ui.on_image_sequence_fps_changed(move |fps| {
+        let ui = ui_weak.unwrap();
         let mut player = player_clone.lock().unwrap();
-        player.seek(time);
+        if let Err(e) = player.set_image_sequence_fps(fps) {
+            eprintln!("{}", trf("Error: {}", &[&e]));
+            toasts_clone.borrow_mut().error(e);
+        }
+        ui.set_image_sequence_fps(player.get_image_sequence_fps());
     });
     
-    // リピート回数変更コールバック
-    ui.on_repeat_changed(move |count| {
-        if count == -1 {
-            println!("リピート: 無限");
-        } else {
-            println!("リピート回数: {}回", count);
+    // プレイリストから削除コールバック
+    let ui_weak = ui.as_weak();
+    let player_clone = Arc::clone(&video_player);
+    ui.on_remove_from_playlist(move |index| {
+        let ui = ui_weak.unwrap();
+        let mut player = player_clone.lock().unwrap();
+        if index >= 0 {
+            player.remove_from_playlist(index as usize);
         }
+        update_playlist_view(&ui, &player);
     });
     
-    // ボリューム変更コールバック
+    // プレイリストのクリアコールバック
+    let ui_weak = ui.as_weak();
     let player_clone = Arc::clone(&video_player);
-    ui.on_volume_changed(move |volume| {
+    ui.on_clear_playlist(move || {
+        let ui = ui_weak.unwrap();
         let mut player = player_clone.lock().unwrap();
-        player.set_volume(volume);
+        player.clear_playlist();
+        update_playlist_view(&ui, &player);
+    });
+    
+    // プレイリストの項目を再生コールバック
+    let player_clone = Arc::clone(&video_player);
+    ui.on_play_playlist_item(move |index| {
+        let mut player = player_clone.lock().unwrap();
+        if index >= 0 {
+            player.play_playlist_item(index as usize);
+        }
+    });
+    
+    // 前/次の動画コールバック
+    let player_clone = Arc::clone(&video_player);
+    ui.on_play_previous(move || {
+        let mut player = player_clone.lock().unwrap();
+        player.play_previous();
+    });
+    
+    let player_clone = Arc::clone(&video_player);
+    ui.on_play_next(move || {
+        let mut player = player_clone.lock().unwrap();
+        player.play_next();
+    });
+    
+    // プレイリストの結合コールバック
+    let player_clone = Arc::clone(&video_player);
+    let toasts_clone = Rc::clone(&toasts);
+    ui.on_concat_playlist(move || {
+        if let Some(out) = rfd::FileDialog::new()
+            .add_filter("MP4", &["mp4"])
+            .add_filter("All Files", &["*"])
+            .set_file_name("joined.mp4")
+            .save_file()
+        {
+            let player = player_clone.lock().unwrap();
+            if let Err(e) = player.concat_playlist(out) {
+                eprintln!("{}", trf("Error: {}", &[&e]));
+                toasts_clone.borrow_mut().error(e);
+            }
+        }
     });
     
     // キーボード状態を監視するためのデバイス
     let device_state = DeviceState::new();
     
-    // Ctrl+Alt押下の前回状態を記憶（連続トグル防止）
-    let last_fullscreen_key_pressed = Arc::new(Mutex::new(false));
+    // Ctrl+Altが押された（UIのタイマーで全画面を切り替えるまで保持）
+    let fullscreen_key_requested = Arc::new(Mutex::new(false));
+    
+    // Shiftが押されているか（←/→で長い方のスキップにする）
+    let shift_key_held = Arc::new(Mutex::new(false));
+    
+    // 押された瞬間を検出した単独キー（UIのタイマーで処理するまで溜めておく）
+    let pressed_keys = Arc::new(Mutex::new(Vec::<Keycode>::new()));
+    
+    // キーボード監視用タイマー（押された瞬間だけを記録する）
+    let fullscreen_requested = Arc::clone(&fullscreen_key_requested);
+    let pressed = Arc::clone(&pressed_keys);
+    let shift_held = Arc::clone(&shift_key_held);
+    // Ctrl+Alt押下の前回状態と、前回押されていたキー（エッジ検出用）
+    let mut last_fullscreen_key_pressed = false;
+    let mut previous_keys: Vec<Keycode> = Vec::new();
+    let key_timer = Timer::default();
+    key_timer.start(TimerMode::Repeated, KEY_POLL_INTERVAL, move || {
+        let keys = device_state.get_keys();
+        let ctrl_pressed = keys.contains(&Keycode::LControl) || keys.contains(&Keycode::RControl);
+        let alt_pressed = keys.contains(&Keycode::LAlt) || keys.contains(&Keycode::RAlt);
+        let fullscreen_key_combo = ctrl_pressed && alt_pressed;
+        if fullscreen_key_combo && !last_fullscreen_key_pressed {
+            *fullscreen_requested.lock().unwrap() = true;
+        }
+        last_fullscreen_key_pressed = fullscreen_key_combo;
+        *shift_held.lock().unwrap() = keys.contains(&Keycode::LShift) || keys.contains(&Keycode::RShift);
+        
+        let mut pressed = pressed.lock().unwrap();
+        for key in &keys {
+            if !previous_keys.contains(key) && !pressed.contains(key) {
+                pressed.push(*key);
+            }
+        }
+        previous_keys = keys;
+    });
     
-    // 再生時間とフレーム更新用タイマー
+    // 再生時間とフレーム更新用タイマー（間隔は設定と動画のfpsに合わせて変える）
     let ui_weak = ui.as_weak();
     let player_clone = Arc::clone(&video_player);
-    let last_key_pressed = Arc::clone(&last_fullscreen_key_pressed);
-    let timer = Timer::default();
+    let fullscreen_requested = Arc::clone(&fullscreen_key_requested);
+    let pressed = Arc::clone(&pressed_keys);
+    let shift_held = Arc::clone(&shift_key_held);
+    // タスクバーの進行状況（ウィンドウが表示されてから初期化する）
+    let mut taskbar: Option<TaskbarProgress> = None;
+    // キーで変えた音量の表示を消す時刻
+    let mut volume_osd_until: Option<Instant> = None;
+    // 全画面の「再生中」表示を消す時刻と、操作を見分けるための前回の状態（再生中か, 音量, 位置）
+    let mut now_playing_osd_until: Option<Instant> = None;
+    let mut now_playing_last: Option<(bool, f32, f32)> = None;
+    // イントロスキップの通知を消す時刻
+    let mut intro_notice_until: Option<Instant> = None;
+    // 合図で光らせた画面を戻す時刻
+    let mut cue_flash_until: Option<Instant> = None;
+    // ヒストグラムを最後に計算した時刻
+    let mut histogram_updated_at: Option<Instant> = None;
+    // モニターの一覧はウィンドウが表示されてから取得する
+    let mut monitors_listed = false;
+    let toasts_clone = Rc::clone(&toasts);
+    // ウィンドウの縦横比を動画に合わせる
+    let mut aspect_lock = AspectLock::default();
+    // 自動送りの暗転が終わる時刻（それまでは次の動画に切り替えない）
+    let mut fade_out_until: Option<Instant> = None;
+    // 時間バーの補間の基準（補間用のタイマーと共有する）
+    let time_anchor: Rc<Cell<Option<TimeAnchor>>> = Rc::new(Cell::new(None));
+    let smoothing_anchor = Rc::clone(&time_anchor);
+    // 再生位置を最後に保存した時刻
+    let mut position_saved_at = Instant::now();
+    // 表示しているffmpegのログの版
+    let mut ffmpeg_log_version = 0;
+    let timer = Rc::new(Timer::default());
+    let mut timer_interval = video_player.lock().unwrap().ui_refresh_interval();
+    let timer_weak = Rc::downgrade(&timer);
     timer.start(
         TimerMode::Repeated,
-        std::time::Duration::from_millis(33), // 約30fps
+        timer_interval,
         move || {
             let ui = ui_weak.unwrap();
-            let player = player_clone.lock().unwrap();
+            let mut player = player_clone.lock().unwrap();
+            
+            // 設定や動画のfpsが変わっていればタイマーの間隔を合わせる
+            let interval = player.ui_refresh_interval();
+            if interval != timer_interval {
+                if let Some(timer) = timer_weak.upgrade() {
+                    timer.set_interval(interval);
+                }
+                timer_interval = interval;
+            }
+            
+            // 読み込みの進行状況を表示し、完了していれば新しい動画に切り替える
+            let load_status = player.get_load_status().unwrap_or_default();
+            if ui.get_load_status() != load_status.as_str() {
+                ui.set_load_status(load_status.into());
+            }
+            let fading_out = fade_out_until.is_some_and(|until| Instant::now() < until);
+            let loaded = if fading_out { None } else { player.poll_load() };
+            if let Some(result) = loaded {
+                // 暗転していれば、次の動画の最初のフレームをフェードインする（失敗した場合も元に戻す）
+                fade_out_until = None;
+                ui.set_transition_dimmed(false);
+                match result {
+                    Ok(path) => {
+                        ui.set_video_path(path.display().to_string().into());
+                        ui.set_now_playing_name(path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default().into());
+                        ui.set_window_title(window_title(&path, player.get_video_summary()).into());
+                        ui.set_duration(player.duration);
+                        ui.set_seekable(player.is_seekable());
+                        ui.set_duration_timecode(timecode::format_timecode(player.duration, player.get_fps()).into());
+                        ui.set_variable_frame_rate(player.is_variable_frame_rate());
+                        ui.set_total_frames(timecode::total_frames(player.duration, player.get_fps()) as i32);
+                        ui.set_current_time(0.0);
+                        ui.set_is_playing(false);
+                        ui.set_video_zoom(1.0);
+                        aspect_lock.reset();
+                        ui.set_video_pan_x(0.0);
+                        ui.set_video_pan_y(0.0);
+                        update_adjustments_view(&ui, &player);
+                        ui.set_audio_offset(player.get_audio_offset());
+                        ui.set_volume_boost(player.get_volume_boost());
+                        update_subtitle_view(&ui, &player);
+                        update_loop_view(&ui, &player);
+                        let (intro, intro_per_file) = player.get_skip_intro();
+                        ui.set_skip_intro(intro);
+                        ui.set_skip_intro_per_file(intro_per_file);
+                        update_playlist_view(&ui, &player);
+                        update_history_view(&ui, &mut player);
+                        
+                        // 波形をバックグラウンドで生成（完了までは非表示）
+                        ui.set_waveform_commands("".into());
+                        player.start_waveform_generation(WAVEFORM_SAMPLES);
+                        ui.set_keyframe_commands("".into());
+                        player.start_keyframe_scan();
+                        
+                        println!("{}", trf("Selected video: {}", &[&path.display()]));
+                    }
+                    Err(e) => {
+                        // 読み込みに失敗しても、現在の動画はそのまま再生を続ける
+                        eprintln!("{}", trf("Error: {}", &[&e]));
+                        toasts_clone.borrow_mut().error(e);
+                    }
+                }
+            }
+            
+            // 別の起動から受け取ったファイルをプレイリストに追加し、ウィンドウを前面に出す
+            if let Some(files) = instance.as_ref().and_then(|instance| instance.take_files()) {
+                player.add_to_playlist(files);
+                update_playlist_view(&ui, &player);
+                fullscreen::bring_to_front(ui.window());
+            }
+            
+            // バッファリング中の表示と、タスクバーの進行状況
+            let taskbar = taskbar.get_or_insert_with(|| TaskbarProgress::new(ui.window()));
+            if !monitors_listed {
+                update_monitor_list(&ui, &player);
+                monitors_listed = true;
+            }
+            let ratio = if player.duration > 0.0 { player.get_current_time() / player.duration } else { 0.0 };
+            let is_buffering = player.is_buffering();
+            if ui.get_is_buffering() != is_buffering {
+                ui.set_is_buffering(is_buffering);
+            }
+            taskbar.set_state(if ui.get_load_status() != "" || is_buffering {
+                TaskbarState::Indeterminate
+            } else {
+                match player.get_state() {
+                    PlaybackState::Playing => TaskbarState::Normal(ratio),
+                    PlaybackState::Paused => TaskbarState::Paused(ratio),
+                    PlaybackState::Stopped => TaskbarState::None,
+                }
+            });
+            
+            // 書き出し処理の進行状況と結果を表示
+            match player.get_task_progress() {
+                Some(task) => {
+                    ui.set_task_label(task.label.into());
+                    // 長さが分からない処理は進み具合を出さず、動いていることだけを表示する
+                    ui.set_task_indeterminate(task.progress.is_none());
+                    ui.set_task_progress(task.progress.unwrap_or(0.0));
+                }
+                None if ui.get_task_progress() >= 0.0 => {
+                    ui.set_task_progress(-1.0);
+                }
+                None => {}
+            }
+            if let Some(result) = player.take_task_result() {
+                match result {
+                    Ok(message) => {
+                        toasts_clone.borrow_mut().info(message.clone());
+                        ui.set_task_label(message.into());
+                    }
+                    Err(e) => {
+                        eprintln!("{}", trf("Error: {}", &[&e]));
+                        ui.set_task_label("".into());
+                        toasts_clone.borrow_mut().error(e);
+                    }
+                }
+            }
+            
+            // 再生中のエラーを表示
+            // 続けられなくなったときは、止まった最後のフレームの上にもエラーを表示する（シーク・再生・読み込みで消える）
+            if let Some(error) = player.take_playback_error() {
+                toasts_clone.borrow_mut().error(error);
+            }
+            let playback_failure = player.get_playback_failure().unwrap_or_default();
+            if ui.get_playback_error() != playback_failure {
+                ui.set_playback_error(playback_failure.into());
+            }
+            
+            // 通知の表示時間が過ぎたら消す（新しい通知もここで表示する）
+            let mut toasts = toasts_clone.borrow_mut();
+            if toasts.update() {
+                update_toast_view(&ui, &toasts);
+            }
+            drop(toasts);
+            
+            // 波形の生成が完了していれば反映
+            if let Some(peaks) = player.take_waveform() {
+                ui.set_waveform_commands(waveform_path_commands(&peaks).into());
+                update_cache_usage(&ui, &player);
+            }
+            
+            // 「続きから見る」のサムネイルが届いていれば該当する行に反映
+            let thumbnails = player.take_history_thumbnails();
+            if !thumbnails.is_empty() {
+                let items = ui.get_history_items();
+                for (path, thumbnail) in thumbnails {
+                    let path = path.display().to_string();
+                    if let Some(row) = (0..items.row_count()).find(|&row| items.row_data(row).is_some_and(|item| item.path == path.as_str())) {
+                        let mut item = items.row_data(row).unwrap();
+                        item.thumbnail = Image::from_rgba8(SharedPixelBuffer::<Rgba8Pixel>::clone_from_slice(thumbnail.as_raw(), thumbnail.width(), thumbnail.height()));
+                        items.set_row_data(row, item);
+                    }
+                }
+            }
+            
+            // 再生中は一定間隔で位置を保存する
+            if player.is_playing() && position_saved_at.elapsed() >= POSITION_SAVE_INTERVAL {
+                player.save_position();
+                position_saved_at = Instant::now();
+            }
+            
+            // キーフレームの解析が完了していれば目盛りを表示
+            if let Some(keyframes) = player.take_keyframes() {
+                ui.set_keyframe_commands(keyframe_tick_commands(&keyframes, player.duration).into());
+            }
             
             // キーボードショートカットチェック（Ctrl + Alt）
-            let keys = device_state.get_keys();
-            let ctrl_pressed = keys.contains(&Keycode::LControl) || keys.contains(&Keycode::RControl);
-            let alt_pressed = keys.contains(&Keycode::LAlt) || keys.contains(&Keycode::RAlt);
-            let fullscreen_key_combo = ctrl_pressed && alt_pressed;
-            
-            let mut last_pressed = last_key_pressed.lock().unwrap();
-            if fullscreen_key_combo && !*last_pressed {
-                // Ctrl+Altが押された（エッジ検出）
+            if std::mem::take(&mut *fullscreen_requested.lock().unwrap()) {
+                // Ctrl+Altが押された（キーボード監視用タイマーでエッジ検出済み）
                 let current_mode = ui.get_fullscreen_mode();
                 let new_mode = !current_mode;
                 ui.set_fullscreen_mode(new_mode);
-                // モニタ全体のフルスクリーン切替
-                ui.window().set_fullscreen(new_mode);
-                *last_pressed = true;
-            } else if !fullscreen_key_combo {
-                // キーが離された
-                *last_pressed = false;
+                // 選択したモニターでフルスクリーン切替（モニターの抜き差しに備えて一覧も更新）
+                fullscreen::set_fullscreen(ui.window(), new_mode, player.get_fullscreen_monitor().as_deref());
+                update_monitor_list(&ui, &player);
+            }
+            
+            // ウィンドウの縦横比を動画に合わせる（ロックを外している間と全画面中は合わせない）
+            match player.get_video_aspect() {
+                Some(aspect) if player.get_aspect_lock() && !ui.get_fullscreen_mode() => aspect_lock.update(ui.window(), aspect),
+                _ => aspect_lock.reset(),
+            }
+            
+            // 単独キーのショートカット（押された瞬間のみ反応。割り当てはshortcuts::SHORTCUTS）
+            let pressed = std::mem::take(&mut *pressed.lock().unwrap());
+            let shift = *shift_held.lock().unwrap();
+            let triggered = |action: Action| shortcuts::triggered(action, |key: Keycode| pressed.contains(&key), shift);
+            
+            // ?・F1でショートカットの一覧を表示/非表示、Escで閉じる
+            if triggered(Action::ToggleHelp) {
+                ui.set_shortcut_help_visible(!ui.get_shortcut_help_visible());
+            } else if triggered(Action::CloseHelp) {
+                ui.set_shortcut_help_visible(false);
+            }
+            
+            // ヒストグラムの表示を切り替え
+            if triggered(Action::ToggleHistogram) {
+                ui.set_show_histogram(!ui.get_show_histogram());
+                histogram_updated_at = None;
+            }
+            
+            // 時計回りに90度回す（ボタンと同じ）
+            if triggered(Action::RotateClockwise) && player.get_video_path().is_some() {
+                player.rotate_clockwise();
+                update_adjustments_view(&ui, &player);
+            }
+            
+            // 1フレームずつ戻る・進む
+            if triggered(Action::StepBackward) {
+                player.step_backward();
+                ui.set_current_time(player.get_current_time());
+            } else if triggered(Action::StepForward) {
+                player.step_forward();
+                ui.set_current_time(player.get_current_time());
+            }
+            
+            // 設定した秒数だけ移動する（Shiftを押していれば長い方）
+            let (short, long) = player.get_seek_steps();
+            let seek_step = if triggered(Action::SeekBackward) {
+                -short
+            } else if triggered(Action::SeekForward) {
+                short
+            } else if triggered(Action::SeekBackwardLong) {
+                -long
+            } else if triggered(Action::SeekForwardLong) {
+                long
+            } else {
+                0.0
+            };
+            if seek_step != 0.0 {
+                player.seek_by(seek_step);
+                ui.set_current_time(player.get_current_time());
+            }
+            
+            // 音量を5%ずつ変える
+            let volume_step = if triggered(Action::VolumeUp) {
+                VOLUME_KEY_STEP
+            } else if triggered(Action::VolumeDown) {
+                -VOLUME_KEY_STEP
+            } else {
+                0.0
+            };
+            if volume_step != 0.0 {
+                // 5%刻みに揃えてから動かす（スライダーで半端な値にしていた場合）
+                let steps = (player.get_volume() / VOLUME_KEY_STEP).round() + volume_step.signum();
+                let volume = (steps * VOLUME_KEY_STEP).clamp(0.0, 1.0);
+                player.set_volume(volume);
+                ui.set_volume(volume);
+                ui.set_volume_osd_visible(true);
+                volume_osd_until = Some(Instant::now() + VOLUME_OSD_DURATION);
+            }
+            if volume_osd_until.is_some_and(|until| Instant::now() >= until) {
+                ui.set_volume_osd_visible(false);
+                volume_osd_until = None;
+            }
+            
+            // 全画面では、再生/一時停止・シーク・音量の変化（と全画面にしたとき）にファイル名・時刻・音量を表示する
+            // 操作はキー・ボタン・スライダーのどこからでも来るので、前回からの状態の変化で見分ける
+            if ui.get_fullscreen_mode() {
+                let state = (player.is_playing(), player.get_volume(), player.get_current_time());
+                let interacted = match now_playing_last {
+                    Some((playing, volume, position)) => {
+                        playing != state.0 || volume != state.1 || (state.2 - position).abs() > NOW_PLAYING_SEEK_JUMP
+                    }
+                    None => true,
+                };
+                now_playing_last = Some(state);
+                if interacted {
+                    ui.set_now_playing_osd_visible(true);
+                    now_playing_osd_until = Some(Instant::now() + NOW_PLAYING_OSD_DURATION);
+                }
+            } else {
+                now_playing_last = None;
+            }
+            if now_playing_osd_until.is_some_and(|until| Instant::now() >= until) {
+                ui.set_now_playing_osd_visible(false);
+                now_playing_osd_until = None;
+            }
+            
+            // 区間・動画の終わりに達した合図（前回の更新でA/B区間や終了の処理から届いたもの）
+            if player.take_cue_flash() {
+                ui.set_cue_flash_visible(true);
+                cue_flash_until = Some(Instant::now() + CUE_FLASH_DURATION);
+            }
+            if cue_flash_until.is_some_and(|until| Instant::now() >= until) {
+                ui.set_cue_flash_visible(false);
+                cue_flash_until = None;
+            }
+            
+            // 字幕タイミングを100msずつ調整
+            let subtitle_nudge = if triggered(Action::SubtitleEarlier) {
+                -0.1
+            } else if triggered(Action::SubtitleLater) {
+                0.1
+            } else {
+                0.0
+            };
+            if subtitle_nudge != 0.0 && player.get_subtitle_path().is_some() {
+                let offset = player.get_subtitle_offset() + subtitle_nudge;
+                player.set_subtitle_offset(offset);
+                player.save_subtitle_offset();
+                ui.set_subtitle_offset(player.get_subtitle_offset());
+            }
+            
+            // 早送り・巻き戻し（ボタンを押している間だけ。離したらその位置から元の再生状態に戻る）
+            if player.update_trickplay(ui.get_trickplay_direction()) {
+                ui.set_current_time(player.get_scrub_time().unwrap_or_else(|| player.get_current_time()));
+            }
+            let trickplay_speed = player.get_trickplay_speed();
+            if ui.get_trickplay_speed() != trickplay_speed {
+                ui.set_trickplay_speed(trickplay_speed);
+            }
+            
+            // シークバーのドラッグの終わりが届かなかった場合の確定
+            player.update_scrub();
+            
+            // フレーム番号とタイムコード（シーク・停止時も位置が変わるので毎回計算する。ドラッグ中はその位置）
+            let shown_time = player.get_scrub_time().unwrap_or_else(|| player.get_current_time());
+            let frame_number = timecode::frame_at(shown_time, player.get_fps()) as i32;
+            if ui.get_current_frame_number() != frame_number {
+                ui.set_current_frame_number(frame_number);
+            }
+            if ui.get_timecode_mode() {
+                let timecode = timecode::format_timecode(shown_time, player.get_fps());
+                if ui.get_current_timecode() != timecode.as_str() {
+                    ui.set_current_timecode(timecode.into());
+                }
+            }
+            
+            // 字幕を更新
+            let subtitle_text = player.get_subtitle_text();
+            if ui.get_subtitle_text() != subtitle_text.as_str() {
+                ui.set_subtitle_text(subtitle_text.into());
+            }
+            let subtitle_line_index = player.current_cue_index().map_or(-1, |index| index as i32);
+            if ui.get_subtitle_line_index() != subtitle_line_index {
+                ui.set_subtitle_line_index(subtitle_line_index);
             }
-            drop(last_pressed);
             
             // 現在の再生時間を更新（再生中のみ）
-            let is_playing = player.is_playing();
+            // 再生ボタンの表示などUIの状態は、ここで読んだ再生状態だけから決める
+            let is_playing = player.get_state() == PlaybackState::Playing;
             let current = player.get_current_time();
             let video_finished = player.is_video_finished();
             if is_playing {
                 ui.set_current_time(current);
             }
+            // 次の更新までは時間バーを補間して進める（一時停止中・ドラッグ中は補間しない）
+            let rate = player.get_time_rate();
+            time_anchor.set((rate > 0.0 && player.get_scrub_time().is_none()).then(|| TimeAnchor {
+                time: current,
+                at: Instant::now(),
+                rate,
+                end: if player.duration > 0.0 { player.duration } else { f32::MAX },
+                max_ahead: interval * 3 / 2,
+                written: current,
+            }));
+            
+            // 先読み済みの位置（シークバーに表示）
+            let buffered = player.get_buffered_time();
+            if ui.get_buffered_time() != buffered {
+                ui.set_buffered_time(buffered);
+            }
+            
+            // イントロを飛ばしたら、取り消しボタン付きの通知をしばらく表示
+            if let Some(seconds) = player.take_intro_skipped() {
+                ui.set_intro_notice(trf("Skipped the intro ({}s)", &[&seconds]).into());
+                intro_notice_until = Some(Instant::now() + INTRO_NOTICE_DURATION);
+            }
+            if intro_notice_until.is_some_and(|until| Instant::now() >= until) {
+                ui.set_intro_notice("".into());
+                intro_notice_until = None;
+            }
+            
+            // イコライザーの変更を（少し待ってから）音声に反映
+            player.update_audio_restart();
+            
+            // 測り終わったラウドネスのゲインを音声に反映し、状態を表示
+            player.update_loudness_gain();
+            let loudness_status = player.get_loudness_status();
+            if ui.get_loudness_status() != loudness_status.as_str() {
+                ui.set_loudness_status(loudness_status.into());
+            }
+            
+            // 色空間の補正・ノイズ除去・シャープの変更を（少し待ってから）映像に反映
+            player.update_video_restart();
+            
+            // 無限リピートで先頭に戻った（継ぎ目なし）
+            if player.update_seamless_loop() {
+                ui.set_current_time(0.0);
+            }
+            
+            // HDRの動画ならトーンマッピングの状態を表示
+            let hdr_status = player.get_hdr_status();
+            if ui.get_hdr_status() != hdr_status.as_str() {
+                ui.set_hdr_status(hdr_status.into());
+            }
+            
+            // 無音スキップの状態を表示し、無音区間に入っていれば飛ばす
+            let silence_status = player.get_silence_status();
+            if ui.get_silence_status() != silence_status.as_str() {
+                ui.set_silence_status(silence_status.into());
+            }
+            if player.update_silence_skip() {
+                ui.set_current_time(player.get_current_time());
+            }
+            
+            // シャドーイング: 文の終わりで止めて間を空け、繰り返すか次の文へ進む（リピートより優先）
+            if ui.get_shadowing() != player.is_shadowing() {
+                ui.set_shadowing(player.is_shadowing());
+            }
+            let shadowing_status = player.get_shadowing_status();
+            if ui.get_shadowing_status() != shadowing_status.as_str() {
+                ui.set_shadowing_status(shadowing_status.into());
+            }
+            if player.update_shadowing() {
+                ui.set_current_time(player.get_current_time());
+                ui.set_is_playing(player.is_playing());
+                return;
+            }
+            
+            // A/Bリピート: B点に達したらA点に戻る（通常のリピートより優先）
+            if player.update_ab_loop() {
+                update_loop_view(&ui, &player);
+                ui.set_current_time(player.get_current_time());
+                ui.set_is_playing(true);
+                return;
+            }
             
             // 動画終了時: シークバーを最後まで移動 & リピート処理
             if video_finished {
@@ -167,15 +2039,18 @@ fn main() {
                 
                 let mut player = player_clone.lock().unwrap();
                 player.clear_video_finished();
+                player.mark_finished();
+                player.cue();
                 
                 let repeat_count = ui.get_repeat_count();
+                let fade = player.get_fade_transition() && !player.next_is_same_file();
                 if repeat_count == -1 {
                     // 無限リピート
                     player.stop();
                     let _ = player.play();
                     ui.set_is_playing(true);
                     ui.set_current_time(0.0); // シークバーを先頭に戻す
-                    println!("リピート再生（無限）");
+                    println!("{}", tr("Repeating (infinite)"));
                 } else if repeat_count > 1 {
                     // リピートカウント減少
                     ui.set_repeat_count(repeat_count - 1);
@@ -183,13 +2058,38 @@ fn main() {
                     let _ = player.play();
                     ui.set_is_playing(true);
                     ui.set_current_time(0.0); // シークバーを先頭に戻す
-                    println!("リピート再生（残り: {}回）", repeat_count - 1);
+                    println!("{}", trf("Repeating ({} left)", &[&(repeat_count - 1)]));
+                } else if player.play_next() {
+                    // プレイリストの次の動画へ（読み込みが終わると再生が始まる）
+                    // 最後のフレームを暗転させ、読み込みが終わっても暗転しきるまでは切り替えない
+                    if fade {
+                        ui.set_transition_dimmed(true);
+                        fade_out_until = Some(Instant::now() + FADE_DURATION);
+                    }
+                    ui.set_current_time(0.0);
+                    println!("{}", tr("Next video in the playlist"));
+                } else if player.playlist_end_action() == PlaylistEnd::RepeatAll {
+                    // プレイリストの最初に戻る
+                    player.play_playlist_item(0);
+                    if fade {
+                        ui.set_transition_dimmed(true);
+                        fade_out_until = Some(Instant::now() + FADE_DURATION);
+                    }
+                    ui.set_current_time(0.0);
+                    println!("{}", tr("Back to the start of the playlist"));
+                } else if player.playlist_end_action() == PlaylistEnd::RepeatOne {
+                    // 最後の項目を繰り返す
+                    player.stop();
+                    let _ = player.play();
+                    ui.set_is_playing(true);
+                    ui.set_current_time(0.0);
+                    println!("{}", tr("Repeating the last video in the playlist"));
                 } else {
                     // 再生終了 - 映像が先頭に戻るのでシークバーも先頭に戻す
                     player.stop();
                     ui.set_current_time(0.0);
                     ui.set_is_playing(false);
-                    println!("再生完了");
+                    println!("{}", tr("Playback finished"));
                 }
                 return; // このティックはここで終了
             }
@@ -199,8 +2099,8 @@ fn main() {
                 ui.set_is_playing(is_playing);
             }
             
-            // フレームを更新
-            if let Some(frame) = player.get_current_frame() {
+            // 新しいフレームが届いていれば表示を更新（frame_formatに関係なく、表示用のフレームは常にRGBA）
+            if let Some(frame) = player.take_frame() {
                 let width = frame.width();
                 let height = frame.height();
                 let buffer = SharedPixelBuffer::<Rgba8Pixel>::clone_from_slice(
@@ -210,18 +2110,78 @@ fn main() {
                 );
                 ui.set_video_frame(Image::from_rgba8(buffer));
             }
+            // 表示時刻の確認（デバッグ用）: フレーム自身の表示時刻と、計算した再生位置の差
+            let pts_debug_text = match player.get_frame_pts() {
+                Some((pts, position)) => trf("Frame PTS {}s / position {}s (diff {} ms)", &[
+                    &format!("{:.3}", pts),
+                    &format!("{:.3}", position),
+                    &format!("{:+.0}", (pts - position) * 1000.0),
+                ]),
+                None => String::new(),
+            };
+            if ui.get_pts_debug_text() != pts_debug_text.as_str() {
+                ui.set_pts_debug_text(pts_debug_text.into());
+            }
+            if ffmpeg_log::version() != ffmpeg_log_version {
+                ffmpeg_log_version = ffmpeg_log::version();
+                ui.set_ffmpeg_log(ffmpeg_log::recent().join("\n").into());
+            }
+            
+            let dropped_frames = player.get_dropped_frames() as i32;
+            if ui.get_dropped_frames() != dropped_frames {
+                ui.set_dropped_frames(dropped_frames);
+            }
+            
+            // ヒストグラム（表示中のみ、一定間隔で計算し直す）
+            let due = histogram_updated_at.is_none_or(|at| at.elapsed() >= HISTOGRAM_INTERVAL);
+            if ui.get_show_histogram() && due {
+                // ロックしている間は集計だけにして、パスの生成はロックを外してから行う
+                let mut histogram = None;
+                player.with_current_frame(|pixels, width, height| {
+                    histogram = Some(Histogram::compute(pixels, width, height));
+                });
+                if let Some(histogram) = histogram {
+                    let [red, green, blue] = histogram.path_commands();
+                    ui.set_histogram_red(red.into());
+                    ui.set_histogram_green(green.into());
+                    ui.set_histogram_blue(blue.into());
+                    histogram_updated_at = Some(Instant::now());
+                }
+            }
         },
     );
     
-    println!("動画プレイヤーUIを起動しました");
-    println!("動画を選択して再生してください");
-    println!("【必要】FFmpegがPATH に設定されていることを確認してください");
+    // 時間バーの補間: UIの更新の間も、最後に読んだ再生位置から経過時間の分だけ進めて表示する
+    // シークなどで表示が書き換えられていたら、次の更新で実際の位置に合わせ直すまで何もしない
+    let ui_weak = ui.as_weak();
+    let smoothing_timer = Timer::default();
+    smoothing_timer.start(TimerMode::Repeated, TIME_BAR_SMOOTHING_INTERVAL, move || {
+        let Some(mut anchor) = smoothing_anchor.get() else {
+            return;
+        };
+        let ui = ui_weak.unwrap();
+        if ui.get_current_time() != anchor.written {
+            smoothing_anchor.set(None);
+            return;
+        }
+        let elapsed = anchor.at.elapsed().min(anchor.max_ahead).as_secs_f32();
+        let time = (anchor.time + elapsed * anchor.rate).min(anchor.end);
+        ui.set_current_time(time);
+        anchor.written = time;
+        smoothing_anchor.set(Some(anchor));
+    });
+    
+    println!("{}", tr("Video player UI started"));
+    println!("{}", tr("Select a video to play"));
+    println!("{}", tr("[Required] Make sure FFmpeg is on PATH"));
     
     ui.run().unwrap();
     
     // アプリ終了時のクリーンアップ
-    println!("アプリケーションを終了します...");
+    println!("{}", tr("Exiting the application..."));
     let mut player = video_player.lock().unwrap();
-    player.stop();
-    println!("クリーンアップ完了");
+    player.save_position();
+    player.save_session();
+    player.shutdown();
+    println!("{}", tr("Cleanup complete"));
 }
@@ -1,623 +1,7311 @@
-use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::cmp::Reverse;
+use std::collections::{HashMap, VecDeque};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::thread;
-use std::time::{Duration, Instant};
-use std::process::{Command, Stdio, Child};
-use std::io::Read;
+use std::time::{Duration, Instant, SystemTime};
+use std::process::{Command, Stdio, Child, ChildStdout, Output};
+use std::io::{BufRead, BufReader, Read};
 use image::RgbaImage;
+use crate::audio_device;
+use crate::config_file;
+use crate::disk_cache::{self, DiskCache};
+use crate::equalizer;
+use crate::ffmpeg_log;
+use crate::i18n::{tr, trf, Language};
+use crate::image_sequence;
+use crate::json;
+use crate::session::Session;
+use crate::settings::Settings;
+use crate::subtitle::Subtitles;
+use crate::video_info::{ColorInfo, ProbeCache, VideoInfo};
+
+// バックグラウンドで読み込んだ動画（メインスレッドで適用する）
+struct LoadedVideo {
+    path: PathBuf,
+    info: VideoInfo,
+    first_frame: RgbaImage,
+    adjustments: VideoAdjustments,
+}
+
+// 読み込みの完了を待っている結果（読み込みの世代番号と結果。poll_loadで受け取る）
+type PendingLoad = Arc<Mutex<Option<(u64, Result<LoadedVideo, String>)>>>;
+
+// 動画を読み込むときの表示の設定（読み込みを始めた時点の設定をワーカースレッドに渡す）
+struct LoadOptions {
+    color_correction: bool,
+    tone_mapping: ToneMapping,
+    max_render_size: (u32, u32),
+    raw_pixel_aspect: bool,
+    adjustments: VideoAdjustments, // 保存された回転・色の調整
+}
+
+// 再生用に起動したffmpeg（標準エラーから読み取った情報はチャンネルで届く）
+struct Decoder {
+    child: Child,
+    timestamps: Option<Receiver<f32>>, // 各フレームの表示時刻（可変フレームレートの場合のみ）
+    debug_pts: Option<Receiver<f32>>,  // 表示時刻の確認用に、固定フレームレートでも各フレームの表示時刻を受け取る
+    output_size: Receiver<(u32, u32)>, // 実際の出力サイズ（出力の開始前に1度だけ届く）
+    log: ffmpeg_log::ProcessLog,       // 標準エラーの読み込み（終了後に最後のエラーの行を取り出す）
+}
+
+// 再生用のffmpegの起動設定（同じ再生の中で起動し直すときも同じ設定を使う）
+struct DecoderOptions {
+    path: PathBuf,
+    fps: f32,
+    video_filters: String,
+    seek_mode: SeekMode,
+    frame_timestamps: bool,  // 元のフレームをそのまま出力し、各フレームの表示時刻を受け取る
+    pts_debug: bool,         // 出力fpsに揃えたフレームの表示時刻を受け取る（表示時刻の確認用）
+    hwaccel: Option<String>, // ハードウェアデコードの方式
+    frame_format: FrameFormat,
+}
+
+// 無音区間の（開始, 終了）の一覧（秒）
+type SilentRegions = Vec<(f32, f32)>;
+
+// 最後に表示したフレームの（表示時刻, 再生位置）。表示時刻の確認用
+type FramePts = Arc<Mutex<Option<(f32, f32)>>>;
+
+// 再生スレッドがプレイヤーと共有する状態と、再生中は変わらない設定（再生を始めるときに1度だけ組み立てて渡す）
+struct PlaybackContext {
+    generation: u64, // このスレッドの再生世代
+    state: Arc<Mutex<PlaybackState>>,
+    observers: Observers,
+    playback_generation: Arc<Mutex<u64>>,
+    decoders: Arc<Mutex<Vec<(u64, Child)>>>,
+    current_time: Arc<Mutex<f32>>,
+    buffered_time: Arc<Mutex<f32>>,
+    current_frame: Arc<Mutex<FrameQueue>>,
+    frame_history: Arc<Mutex<FrameHistory>>,
+    frame_wait_started: Arc<Mutex<Option<(u64, Instant)>>>,
+    frame_pts: Option<FramePts>, // 表示時刻の確認が有効なときだけ
+    playback_error: Arc<Mutex<Option<String>>>,
+    video_finished: Arc<Mutex<bool>>,
+    reverse_finished: Arc<Mutex<bool>>,
+    seamless_loop: Arc<Mutex<bool>>,
+    loop_seam: Arc<Mutex<bool>>,
+    duration: f32,
+    speed: f32,
+    decode_ahead: usize,
+    scan_mode: bool,
+}
+
+// 再生中の動画の終わりの判定（VideoPlayer::stream_endの結果）
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum StreamEnd {
+    Continue,    // まだ続く
+    Finished,    // 終端に達した
+    Overshot,    // durationを大きく超えても出力が続く（終端とみなして打ち切る）
+    Interrupted, // 終端の手前でffmpegが異常終了した（途中から起動し直す）
+}
+
+// 「続きから見る」の一覧の項目（ファイルごとの設定に保存した再生位置から作る）
+pub struct WatchEntry {
+    pub path: PathBuf,
+    pub position: f32,
+    pub duration: f32,
+    pub last_watched: u64, // 最後に見た日時（UNIX時間の秒）
+}
+
+impl WatchEntry {
+    /// 最後まで（またはエンドロール程度を残して）見たか
+    pub fn finished(&self) -> bool {
+        self.duration > 0.0 && self.position >= self.duration - WATCHED_END_MARGIN
+    }
+    
+    /// 見た割合（0.0〜1.0）
+    pub fn progress(&self) -> f32 {
+        if self.duration > 0.0 {
+            (self.position / self.duration).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+// 結合する動画ごとのストリーム情報
+struct ConcatSource {
+    path: PathBuf,
+    signature: String, // コーデックや解像度など、無劣化で結合できるかの判定に使う
+    duration: f32,
+    has_audio: bool,
+    width: u32,
+    height: u32,
+    fps: f32,
+}
+
+// 再生状態（再生スレッドとメインスレッドで共有する）
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PlaybackState {
+    Stopped,
+    Playing,
+    Paused, // 再生スレッドとffmpegは生かしたまま待機している
+}
+
+// シーク方法（プレビュー・映像・音声で共通）
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SeekMode {
+    Fast,     // -ssを-iの前に置く（キーフレームから探すので速い）
+    Accurate, // -ssを-iの後に置く（先頭からデコードするので遅いが正確）
+    Disabled, // -ssを付けない（ライブ配信などシークできない入力。設定では選べない）
+}
+
+impl SeekMode {
+    // ffmpegの入力指定（-ssと-iの順序）を生成（連番の画像ならfps・開始番号、ネットワークなら再接続などのオプションも付ける）
+    fn input_args(self, path: &Path, time: f32) -> Vec<OsString> {
+        let ss = ["-ss".into(), time.to_string().into()];
+        let mut input: Vec<OsString> = network_input_options(path).into_iter().map(OsString::from).collect();
+        input.extend(image_sequence::input_options(path).into_iter().map(OsString::from));
+        input.extend(["-i".into(), path.as_os_str().to_os_string()]);
+        match self {
+            SeekMode::Fast => ss.into_iter().chain(input).collect(),
+            SeekMode::Accurate => input.into_iter().chain(ss).collect(),
+            SeekMode::Disabled => input,
+        }
+    }
+}
+
+// ネットワークから読む入力のプロトコル（ライブ配信専用のものはシークできない）
+const NETWORK_PROTOCOLS: &[&str] = &["http", "https", "rtsp", "rtsps", "rtmp", "rtmps", "rtp", "udp", "srt", "tcp"];
+const LIVE_PROTOCOLS: &[&str] = &["rtmp", "rtmps", "rtp", "udp", "srt", "tcp"];
+
+// 「https://...」のようなURLならプロトコル名（小文字）
+fn network_protocol(path: &Path) -> Option<String> {
+    let (scheme, _) = path.to_str()?.split_once("://")?;
+    let scheme = scheme.to_ascii_lowercase();
+    NETWORK_PROTOCOLS.contains(&scheme.as_str()).then_some(scheme)
+}
+
+/// http・rtspなど、ネットワークから読む入力か
+pub fn is_network_source(path: &Path) -> bool {
+    network_protocol(path).is_some()
+}
+
+// ネットワークの入力で-iの前に付けるオプション（httpは途切れたら再接続、rtspはパケットが落ちないようTCPで受ける）
+fn network_input_options(path: &Path) -> Vec<String> {
+    let options: &[&str] = match network_protocol(path).as_deref() {
+        Some("http" | "https") => &["-reconnect", "1", "-reconnect_streamed", "1", "-reconnect_delay_max", "5"],
+        Some("rtsp" | "rtsps") => &["-rtsp_transport", "tcp"],
+        _ => &[],
+    };
+    options.iter().map(|option| option.to_string()).collect()
+}
+
+/// 再生中にffmpegから受け取るフレームの形式（on_raw_frameにはこの形式のまま渡す）
+/// 画面の表示・on_frame・フレームの履歴には、常にRGBAに変換したものを使う
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FrameFormat {
+    Rgba, // 既定（変換しない）
+    Bgra, // GPUのテクスチャによく使われる並び
+    Nv12, // Yの面の後にUVを交互に並べた面（ハードウェアのテクスチャにそのまま渡せる。BT.601・リミテッドレンジ）
+}
+
+impl FrameFormat {
+    fn pix_fmt(self) -> &'static str {
+        match self {
+            FrameFormat::Rgba => "rgba",
+            FrameFormat::Bgra => "bgra",
+            FrameFormat::Nv12 => "nv12",
+        }
+    }
+    
+    /// 1フレームのバイト数（NV12は1ピクセルあたり1.5バイト。幅・高さが奇数なら色差は切り上げた大きさになる）
+    pub fn frame_size(self, width: u32, height: u32) -> usize {
+        let (width, height) = (width as usize, height as usize);
+        match self {
+            FrameFormat::Rgba | FrameFormat::Bgra => width * height * 4,
+            FrameFormat::Nv12 => width * height + width.div_ceil(2) * height.div_ceil(2) * 2,
+        }
+    }
+    
+    // 表示用にRGBAへ変換する（NV12はデコード時にBT.601・リミテッドレンジへ揃えているので、その係数で戻す）
+    fn to_rgba(self, data: &[u8], width: u32, height: u32) -> Option<RgbaImage> {
+        if data.len() < self.frame_size(width, height) {
+            return None;
+        }
+        match self {
+            FrameFormat::Rgba => RgbaImage::from_raw(width, height, data[..self.frame_size(width, height)].to_vec()),
+            FrameFormat::Bgra => {
+                let mut rgba = data[..self.frame_size(width, height)].to_vec();
+                for pixel in rgba.chunks_exact_mut(4) {
+                    pixel.swap(0, 2);
+                }
+                RgbaImage::from_raw(width, height, rgba)
+            }
+            FrameFormat::Nv12 => {
+                let (w, h) = (width as usize, height as usize);
+                let (luma, chroma) = data.split_at(w * h);
+                let chroma_stride = w.div_ceil(2) * 2;
+                let mut rgba = Vec::with_capacity(w * h * 4);
+                for y in 0..h {
+                    for x in 0..w {
+                        let c = (y / 2) * chroma_stride + (x / 2) * 2;
+                        let luma = (luma[y * w + x] as f32 - 16.0) * 1.164;
+                        let u = chroma[c] as f32 - 128.0;
+                        let v = chroma[c + 1] as f32 - 128.0;
+                        rgba.extend_from_slice(&[
+                            (luma + 1.596 * v).clamp(0.0, 255.0) as u8,
+                            (luma - 0.392 * u - 0.813 * v).clamp(0.0, 255.0) as u8,
+                            (luma + 2.017 * u).clamp(0.0, 255.0) as u8,
+                            255,
+                        ]);
+                    }
+                }
+                RgbaImage::from_raw(width, height, rgba)
+            }
+        }
+    }
+}
+
+// スロー再生時に中間フレームを作る方法（どちらもCPU負荷が高いので既定はオフ）
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SlowMotionSmoothing {
+    Off,         // 同じフレームを繰り返す（カクカクするが軽い）
+    Blend,       // 前後のフレームを混ぜる（framerateフィルター）
+    Interpolate, // 動きを推定して補間する（minterpolate、非常に重い）
+}
+
+// プレイリストの最後の項目を再生し終えたときの動作（項目ごとのリピート回数を使い切った後に決める）
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PlaylistEnd {
+    Stop,      // 止める
+    RepeatAll, // 最初の項目に戻る
+    RepeatOne, // 最後の項目を繰り返す
+}
+
+impl SlowMotionSmoothing {
+    // setptsで引き延ばした後に掛けるフィルター（fpsは出力のフレームレート）
+    fn filter(self, fps: f32) -> Option<String> {
+        match self {
+            SlowMotionSmoothing::Off => None,
+            SlowMotionSmoothing::Blend => Some(format!("framerate=fps={}", fps)),
+            SlowMotionSmoothing::Interpolate => Some(format!("minterpolate=fps={}:mi_mode=mci", fps)),
+        }
+    }
+}
+
+// HDR動画をSDRで表示するときのトーンマッピング（HDRの動画にだけ適用する）
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ToneMapping {
+    Off,
+    Hable,    // 暗部を残しつつハイライトを滑らかに圧縮する
+    Mobius,   // 明るさ・色をなるべく保つ
+    Reinhard, // 単純で全体がやや暗くなる
+}
+
+impl ToneMapping {
+    pub fn name(self) -> &'static str {
+        match self {
+            ToneMapping::Off => "off",
+            ToneMapping::Hable => "hable",
+            ToneMapping::Mobius => "mobius",
+            ToneMapping::Reinhard => "reinhard",
+        }
+    }
+    
+    fn from_name(name: &str) -> Option<Self> {
+        [ToneMapping::Off, ToneMapping::Hable, ToneMapping::Mobius, ToneMapping::Reinhard]
+            .into_iter()
+            .find(|mode| mode.name() == name)
+    }
+}
+
+// 表示する縦横比（縦横比の情報が間違っているファイル用。デコードはそのままで表示だけを変える）
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AspectMode {
+    Auto, // 動画の縦横比のまま
+    Ratio4x3,
+    Ratio16x9,
+    Ratio21x9,
+}
+
+impl AspectMode {
+    pub const ALL: [AspectMode; 4] = [AspectMode::Auto, AspectMode::Ratio4x3, AspectMode::Ratio16x9, AspectMode::Ratio21x9];
+    
+    pub fn name(self) -> &'static str {
+        match self {
+            AspectMode::Auto => "auto",
+            AspectMode::Ratio4x3 => "4:3",
+            AspectMode::Ratio16x9 => "16:9",
+            AspectMode::Ratio21x9 => "21:9",
+        }
+    }
+    
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+    
+    /// 幅 / 高さ（Autoは動画に従うのでNone）
+    pub fn ratio(self) -> Option<f32> {
+        match self {
+            AspectMode::Auto => None,
+            AspectMode::Ratio4x3 => Some(4.0 / 3.0),
+            AspectMode::Ratio16x9 => Some(16.0 / 9.0),
+            AspectMode::Ratio21x9 => Some(21.0 / 9.0),
+        }
+    }
+}
+
+// 動画ごとに覚えておく表示の調整（ファイルごと、またはフォルダ単位で保存する）
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct VideoAdjustments {
+    pub rotation: u32,   // 時計回りの角度（0・90・180・270）
+    pub aspect: AspectMode,
+    pub brightness: f32, // -1.0〜1.0（0で変更なし）
+    pub contrast: f32,   // 0.0〜2.0（1で変更なし）
+    pub saturation: f32, // 0.0〜3.0（1で変更なし）
+}
+
+impl Default for VideoAdjustments {
+    fn default() -> Self {
+        VideoAdjustments {
+            rotation: 0,
+            aspect: AspectMode::Auto,
+            brightness: 0.0,
+            contrast: 1.0,
+            saturation: 1.0,
+        }
+    }
+}
+
+impl VideoAdjustments {
+    const KEYS: [&'static str; 5] = ["rotation", "aspect", "brightness", "contrast", "saturation"];
+    
+    // 範囲外の値を直す（回転は90度単位に丸める）
+    fn clamped(self) -> Self {
+        VideoAdjustments {
+            rotation: (self.rotation + 45) / 90 % 4 * 90,
+            aspect: self.aspect,
+            brightness: self.brightness.clamp(-1.0, 1.0),
+            contrast: self.contrast.clamp(0.0, 2.0),
+            saturation: self.saturation.clamp(0.0, 3.0),
+        }
+    }
+    
+    /// 90度・270度の回転では幅と高さが入れ替わる
+    pub fn swaps_dimensions(&self) -> bool {
+        self.rotation % 180 == 90
+    }
+    
+    // 表示サイズに縮小した後に掛けるフィルター（色の調整 → 回転）
+    fn filters(&self) -> Vec<String> {
+        let mut filters = Vec::new();
+        if self.brightness != 0.0 || self.contrast != 1.0 || self.saturation != 1.0 {
+            filters.push(format!("eq=brightness={:.2}:contrast={:.2}:saturation={:.2}", self.brightness, self.contrast, self.saturation));
+        }
+        match self.rotation {
+            90 => filters.push("transpose=1".to_string()),
+            180 => filters.push("hflip,vflip".to_string()),
+            270 => filters.push("transpose=2".to_string()),
+            _ => {}
+        }
+        filters
+    }
+    
+    // 保存された調整を読む（項目ごとにファイル → フォルダの順に探し、どちらにもなければ既定値）
+    fn load(settings: &Settings, path: &Path) -> Self {
+        let folder = path.parent();
+        let get = |key: &str| {
+            settings
+                .get_file_value(path, key)
+                .or_else(|| folder.and_then(|folder| settings.get_file_value(folder, key)))
+        };
+        let f32_value = |key: &str, default: f32| get(key).and_then(|v| v.parse::<f32>().ok()).unwrap_or(default);
+        let defaults = Self::default();
+        VideoAdjustments {
+            rotation: get("rotation").and_then(|v| v.parse::<u32>().ok()).unwrap_or(defaults.rotation),
+            aspect: get("aspect").and_then(AspectMode::from_name).unwrap_or(defaults.aspect),
+            brightness: f32_value("brightness", defaults.brightness),
+            contrast: f32_value("contrast", defaults.contrast),
+            saturation: f32_value("saturation", defaults.saturation),
+        }
+        .clamped()
+    }
+    
+    // pathはファイルでもフォルダでもよい
+    fn save(&self, settings: &mut Settings, path: &Path) {
+        settings.set_file_value(path, "rotation", self.rotation);
+        settings.set_file_value(path, "aspect", self.aspect.name());
+        settings.set_file_value(path, "brightness", format!("{:.2}", self.brightness));
+        settings.set_file_value(path, "contrast", format!("{:.2}", self.contrast));
+        settings.set_file_value(path, "saturation", format!("{:.2}", self.saturation));
+    }
+    
+    fn remove(settings: &mut Settings, path: &Path) {
+        for key in Self::KEYS {
+            settings.remove_file_value(path, key);
+        }
+    }
+}
+
+// ffmpegの場所（NoneならPATHから探す）。ffprobe・ffplayも同じフォルダのものを使う
+// ワーカースレッドからも使うのでプロセス全体で1つ（VideoPlayerBuilder::ffmpeg_pathで変える）
+static FFMPEG_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+// ffmpeg・ffprobe・ffplayを実行するコマンドを作る
+fn ffmpeg_command(name: &str) -> Command {
+    match FFMPEG_PATH.lock().unwrap().as_ref() {
+        Some(ffmpeg) if name == "ffmpeg" => Command::new(ffmpeg),
+        Some(ffmpeg) => Command::new(ffmpeg.with_file_name(format!("{}{}", name, std::env::consts::EXE_SUFFIX))),
+        None => Command::new(name),
+    }
+}
+
+/// 見つからない外部コマンド（起動時に確認し、なければ画面に表示する）
+/// ffplayは音声だけに使うので、なくても映像は再生できる（VideoPlayer::is_audio_availableで確認する）
+pub fn missing_tools() -> Vec<&'static str> {
+    ["ffmpeg", "ffprobe"]
+        .into_iter()
+        .filter(|name| !tool_available(name))
+        .collect()
+}
+
+/// 動画の情報を調べる（GUIなしで使える。width/heightは元の解像度）
+pub fn probe_video(path: &Path) -> Result<VideoInfo, String> {
+    let probe_cache = Mutex::new(ProbeCache::default());
+    VideoPlayer::get_video_info(&path.to_path_buf(), &probe_cache, (u32::MAX, u32::MAX), false, &|| false)
+}
+
+/// 指定した時刻のフレームを抽出する（GUIなしで使える。色の変換は再生時と同じ。0秒なら最初のフレーム）
+/// max_sizeを指定すると、縦横比を保ってその大きさに収まるよう縮小する
+pub fn extract_frame(path: &Path, time: f32, max_size: Option<(u32, u32)>) -> Result<RgbaImage, String> {
+    let info = probe_video(path).map_err(|e| trf("This file is not a valid video ({})", &[&e]))?;
+    if time < 0.0 || (info.duration > 0.0 && time > info.duration) {
+        return Err(trf("The time is outside the video ({}s)", &[&format!("{:.3}", time)]));
+    }
+    let (width, height) = match max_size {
+        Some(max_size) => VideoPlayer::calculate_scaled_size(info.width, info.height, max_size),
+        None => (info.width, info.height),
+    };
+    let filter = VideoPlayer::scale_filter(width, height, &info.color, true, ToneMapping::Hable, "");
+    
+    // 正確な時刻のフレームにするため、-ssは-iの後に置く
+    let output = ffmpeg_log::configure(ffmpeg_command("ffmpeg")
+        .args(SeekMode::Accurate.input_args(path, time))
+        .args(&[
+            "-vf", &filter,
+            "-frames:v", "1",
+            "-f", "image2pipe",
+            "-vcodec", "ppm",
+            "-",
+        ]))
+        .output()
+        .map_err(|e| trf("Failed to start ffmpeg: {}", &[&e]))?;
+    let detail = ffmpeg_log::record_output("ffmpeg", &output.stderr);
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(ffmpeg_log::with_detail(tr("Failed to extract the frame").to_string(), detail));
+    }
+    image::load_from_memory(&output.stdout)
+        .map(|image| image.to_rgba8())
+        .map_err(|e| trf("Failed to load the image: {}", &[&e]))
+}
+
+fn tool_available(name: &str) -> bool {
+    ffmpeg_command(name)
+        .arg("-version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok()
+}
+
+// zscaleフィルター（zimg）が使えるか（ffmpegのビルドによってはない。最初の確認結果を使い回す）
+fn zscale_available() -> bool {
+    static AVAILABLE: OnceLock<bool> = OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        let available = ffmpeg_command("ffmpeg")
+            .args(&["-hide_banner", "-filters"])
+            .stderr(Stdio::null())
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).lines().any(|line| line.split_whitespace().nth(1) == Some("zscale")))
+            .unwrap_or(false);
+        if !available {
+            println!("{}", tr("Warning: this ffmpeg was built without zimg, so HDR tone mapping is not available"));
+        }
+        available
+    })
+}
+
+// 書き出しなど時間のかかる処理の進行状況
+#[derive(Clone)]
+pub struct TaskProgress {
+    pub label: String,
+    pub progress: Option<f32>, // 0.0〜1.0（全体の長さが分からない処理はNone）
+}
+
+// ffmpegの進行状況の分母
+#[derive(Clone, Copy)]
+enum ProgressTotal {
+    Duration(f32), // 出力の長さ（秒）。out_time_usから計算する
+    Frames(u64),   // 出力するフレーム数。frameから計算する
+    Unknown,       // 長さが分からない（ライブ配信など）
+}
+
+impl ProgressTotal {
+    // 長さが取れなかった（0以下）場合は不明として扱う
+    fn duration(seconds: f32) -> Self {
+        if seconds > 0.0 {
+            ProgressTotal::Duration(seconds)
+        } else {
+            ProgressTotal::Unknown
+        }
+    }
+}
+
+// 状態・位置・フレームの変化を通知するコールバック
+type StateCallback = Arc<dyn Fn(PlaybackState) + Send + Sync>;
+type PositionCallback = Arc<dyn Fn(f32) + Send + Sync>;
+type FrameCallback = Arc<dyn Fn(&RgbaImage) + Send + Sync>;
+type RawFrameCallback = Arc<dyn Fn(&[u8], u32, u32, FrameFormat) + Send + Sync>;
+
+// 登録されたコールバックの一覧（再生スレッドと共有する）
+// 呼び出し中にコールバックの登録でデッドロックしないよう、一覧を複製してロックを外してから呼ぶ
+#[derive(Clone, Default)]
+struct Observers {
+    state: Arc<Mutex<Vec<StateCallback>>>,
+    position: Arc<Mutex<Vec<PositionCallback>>>,
+    frame: Arc<Mutex<Vec<FrameCallback>>>,
+    raw_frame: Arc<Mutex<Vec<RawFrameCallback>>>,
+    no_display: Arc<AtomicBool>, // 表示用のフレーム（take_frame・コマ戻しの履歴）を作らない
+}
+
+impl Observers {
+    fn notify_state(&self, state: PlaybackState) {
+        let callbacks = self.state.lock().unwrap().clone();
+        for callback in callbacks {
+            callback(state);
+        }
+    }
+    
+    fn notify_position(&self, position: f32) {
+        let callbacks = self.position.lock().unwrap().clone();
+        for callback in callbacks {
+            callback(position);
+        }
+    }
+    
+    fn notify_frame(&self, frame: &RgbaImage) {
+        let callbacks = self.frame.lock().unwrap().clone();
+        for callback in callbacks {
+            callback(frame);
+        }
+    }
+    
+    // ffmpegから読み込んだフレームをそのままの形式で通知してから、RGBAに変換して通知する
+    // 表示せず、on_frameも登録されていなければ、RGBAに変換せずにNoneを返す
+    fn notify_decoded_frame(&self, data: &[u8], width: u32, height: u32, format: FrameFormat) -> Option<RgbaImage> {
+        let callbacks = self.raw_frame.lock().unwrap().clone();
+        for callback in callbacks {
+            callback(data, width, height, format);
+        }
+        if self.no_display.load(Ordering::Relaxed) && self.frame.lock().unwrap().is_empty() {
+            return None;
+        }
+        let frame = format.to_rgba(data, width, height)?;
+        self.notify_frame(&frame);
+        Some(frame)
+    }
+}
+
+// シーク位置のフレームの読み込み依頼（連続したシークでは最後の依頼だけを処理する）
+struct FrameRequest {
+    path: PathBuf,
+    time: f32,
+    width: u32,
+    height: u32,
+    image_filters: String,
+    seek_mode: SeekMode,
+}
+
+// シークバーをドラッグしている間のプレビュー（ドラッグ中は抽出したフレームを表示するだけで、離したときにシークする）
+#[derive(Default)]
+struct ScrubPreview {
+    request: Option<(u64, FrameRequest)>, // まだ処理していない最新の依頼（依頼したときの世代つき）
+    generation: u64,                      // 依頼・終了のたびに増やす（抽出が終わったときに古くなっていれば表示しない）
+    loader_running: bool,
+    slow: bool, // 抽出が遅すぎたので、このドラッグではプレビューをやめて離したときのシークだけにする
+}
+
+// 直近に表示したフレーム（1フレーム戻るときにデコードし直さずに使う）。再生の世代が変わったら無効
+#[derive(Default)]
+struct FrameHistory {
+    generation: u64,
+    frames: VecDeque<(f32, RgbaImage)>,
+}
+
+impl FrameHistory {
+    // 同じ位置より後のフレーム（一時停止からの再開で重複したものなど）は置き換える
+    fn push(&mut self, time: f32, frame: RgbaImage, frame_duration: f32) {
+        while self.frames.back().is_some_and(|(t, _)| *t > time - frame_duration * 0.5) {
+            self.frames.pop_back();
+        }
+        self.frames.push_back((time, frame));
+        while self.frames.len() > FRAME_HISTORY_LEN {
+            self.frames.pop_front();
+        }
+    }
+}
+
+// 生成した波形（ファイルと区間数ごと）。最近使ったものを先頭に置き、上限を超えたら古いものから捨てる
+#[derive(Default)]
+struct WaveformCache {
+    entries: Vec<((PathBuf, usize), Vec<f32>)>,
+}
+
+impl WaveformCache {
+    fn get(&mut self, key: &(PathBuf, usize)) -> Option<Vec<f32>> {
+        let index = self.entries.iter().position(|(k, _)| k == key)?;
+        let entry = self.entries.remove(index);
+        let peaks = entry.1.clone();
+        self.entries.insert(0, entry);
+        Some(peaks)
+    }
+
+    fn insert(&mut self, key: (PathBuf, usize), peaks: Vec<f32>) {
+        self.entries.retain(|(k, _)| *k != key);
+        self.entries.insert(0, (key, peaks));
+        self.entries.truncate(MAX_WAVEFORM_CACHE_ENTRIES);
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+// デコードしたフレームの受け渡し（再生スレッド → UI）
+// 容量を超えたら古いものから捨てるので、モーダルダイアログなどでUIが止まっていてもメモリは増えない
+#[derive(Default)]
+pub struct FrameQueue {
+    pending: VecDeque<RgbaImage>,  // まだUIに渡していないフレーム（古い順）
+    displayed: Option<RgbaImage>, // 最後にUIに渡したフレーム
+    dropped: u64,                  // 表示されずに捨てたフレームの数
+    closed: bool,                  // 終了処理の後は、残っているスレッドから届いたフレームを受け取らない
+}
+
+impl FrameQueue {
+    // 再生中のフレームを追加する（いっぱいなら一番古いものを捨てる）
+    fn push(&mut self, frame: RgbaImage) {
+        if self.closed {
+            return;
+        }
+        self.pending.push_back(frame);
+        while self.pending.len() > FRAME_QUEUE_CAPACITY {
+            self.pending.pop_front();
+            self.dropped += 1;
+        }
+    }
+    
+    // 読み込み・シークで表示するフレームを差し替える（溜まっていた古いフレームは表示しない）
+    fn replace(&mut self, frame: RgbaImage) {
+        if self.closed {
+            return;
+        }
+        self.pending.clear();
+        self.pending.push_back(frame);
+    }
+    
+    // 終了処理: 溜まっているフレームを捨て、以降は受け取らない
+    fn close(&mut self) {
+        self.closed = true;
+        self.pending.clear();
+    }
+    
+    // まだ表示していないフレームを捨てる（シーク前の位置のフレームが一瞬表示されないように）
+    fn flush(&mut self) {
+        self.pending.clear();
+    }
+    
+    // 一番新しいフレームを取り出す（UIの更新が追いつかずに飛ばしたフレームは捨てた数に数える）
+    fn take_latest(&mut self) -> Option<RgbaImage> {
+        let latest = self.pending.pop_back()?;
+        self.dropped += self.pending.len() as u64;
+        self.pending.clear();
+        self.displayed = Some(latest.clone());
+        Some(latest)
+    }
+    
+    // 表示中（または次に表示する）フレーム
+    fn current(&self) -> Option<&RgbaImage> {
+        self.pending.back().or(self.displayed.as_ref())
+    }
+}
+
+// ffmpegの出力を別スレッドで先読みする（UIへの受け渡しが一瞬遅れても、次のフレームがすぐ使えるように）
+// 溜めるのはdepth枚まで。0なら受け取られるまで次を読まない（先読みなし）
+struct FrameReader {
+    frames: Receiver<std::io::Result<Vec<u8>>>,
+    recycled: Sender<Vec<u8>>, // 使い終わったバッファを読み込みスレッドに返して使い回す
+}
+
+impl FrameReader {
+    fn spawn(mut stdout: ChildStdout, frame_size: usize, depth: usize) -> Self {
+        let (frame_sender, frames) = mpsc::sync_channel(depth);
+        let (recycled, recycled_buffers) = mpsc::channel::<Vec<u8>>();
+        // ffmpegが止められると読み込みが終わり、受け取り側がいなくなると送信に失敗して終わる
+        thread::spawn(move || loop {
+            let mut buffer = recycled_buffers.try_recv().unwrap_or_default();
+            buffer.resize(frame_size, 0);
+            let frame = match VideoPlayer::read_frame(&mut stdout, &mut buffer) {
+                Ok(true) => Ok(buffer),
+                Ok(false) => break,
+                Err(e) => Err(e),
+            };
+            let failed = frame.is_err();
+            if frame_sender.send(frame).is_err() || failed {
+                break;
+            }
+        });
+        FrameReader { frames, recycled }
+    }
+    
+    // 次のフレームをframe_bufferに受け取る（read_frameと同じく、出力が終わっていればfalse）
+    fn read(&self, frame_buffer: &mut Vec<u8>) -> std::io::Result<bool> {
+        match self.frames.recv() {
+            Ok(Ok(frame)) => {
+                let used = std::mem::replace(frame_buffer, frame);
+                let _ = self.recycled.send(used);
+                Ok(true)
+            }
+            Ok(Err(e)) => Err(e),
+            Err(_) => Ok(false),
+        }
+    }
+    
+    // 先読みする枚数（解像度が高くてもメモリがMAX_DECODE_AHEAD_BYTESを超えないように減らす）
+    fn depth(decode_ahead: usize, frame_size: usize) -> usize {
+        if decode_ahead == 0 {
+            return 0;
+        }
+        decode_ahead.min((MAX_DECODE_AHEAD_BYTES / frame_size.max(1)).max(1))
+    }
+}
+
+// 再生中のffplayに掛けている音量の変化（起動した時刻からduration掛けてfromからtoへ移る）
+// ffplayの音量は起動後に変えられないので、音量を変えたら再起動し、聞こえていた大きさから新しい音量へ滑らかにつなぐ
+#[derive(Clone, Copy)]
+struct VolumeRamp {
+    from: f32,
+    to: f32,
+    started: Instant,
+    duration: Duration,
+}
+
+impl VolumeRamp {
+    fn constant(volume: f32) -> Self {
+        VolumeRamp {
+            from: volume,
+            to: volume,
+            started: Instant::now(),
+            duration: Duration::ZERO,
+        }
+    }
+    
+    // nowの時点で聞こえている音量（変化の途中で次の音量に変えたときは、ここから移り始める）
+    fn level_at(&self, now: Instant) -> f32 {
+        if self.duration.is_zero() {
+            return self.to;
+        }
+        let progress = (now.saturating_duration_since(self.started).as_secs_f32() / self.duration.as_secs_f32()).min(1.0);
+        self.from + (self.to - self.from) * progress
+    }
+    
+    // 音量のフィルター（変化の途中ならフレームごとに計算する。時刻は開始位置によらず0から数える）
+    fn filter(&self, boost: f32) -> String {
+        if self.duration.is_zero() || self.from == self.to {
+            return format!("volume={}", self.to * boost);
+        }
+        let (from, to) = (self.from * boost, self.to * boost);
+        format!(
+            "asetpts=PTS-STARTPTS,volume='{:.3}+({:.3}-{:.3})*min(t/{:.3},1)':eval=frame",
+            from, to, from, self.duration.as_secs_f32()
+        )
+    }
+}
+
+// シャドーイング: 字幕の1文を決めた回数だけ繰り返し、毎回の後に声に出すための無音の間を空けてから次の文へ進む
+struct Shadowing {
+    cue: usize,                  // 繰り返している字幕の番号
+    remaining: u32,              // この文の残りの再生回数
+    gap_until: Option<Instant>,  // 間を空けている間は、再び再生を始める時刻
+}
+
+// 早送り・巻き戻しの状態（ボタンを押している間だけ）
+struct Trickplay {
+    forward: bool,
+    started: Instant, // 押し始めた時刻（速さを上げる基準）
+    stepped: Instant, // 最後に位置を進めた時刻
+    position: f32,
+}
 
 pub struct VideoPlayer {
     pub duration: f32,
     pub fps: f32,
-    pub is_playing: Arc<Mutex<bool>>,
-    pub is_paused: Arc<Mutex<bool>>,
+    variable_frame_rate: bool,
+    state: Arc<Mutex<PlaybackState>>,
+    observers: Observers,
     pub video_finished: Arc<Mutex<bool>>,
     pub current_time: Arc<Mutex<f32>>,
-    pub current_frame: Arc<Mutex<Option<RgbaImage>>>,
+    // デコード済みの最も先の位置（シークバーの先読み表示用）
+    buffered_time: Arc<Mutex<f32>>,
+    pub current_frame: Arc<Mutex<FrameQueue>>,
     pub seek_time: Arc<Mutex<Option<f32>>>,
     playback_generation: Arc<Mutex<u64>>,
     audio_process: Arc<Mutex<Option<Child>>>,
+    seamless_loop: Arc<Mutex<bool>>,
+    loop_seam: Arc<Mutex<bool>>,
+    audio_looping: bool,
+    audio_available: bool, // ffplayがあるか（なければ音声なしで再生する）
     audio_generation: Arc<Mutex<u64>>,
     pub volume: Arc<Mutex<f32>>,
+    volume_boost: f32,
+    audio_offset: f32,
+    normalize_loudness: bool,
+    match_loudness: bool,      // 動画ごとに測ったラウドネスから音量を揃える
+    loudness_gain: Option<f32>, // 現在の動画に掛けるゲイン（dB。測り終わるまではNone）
+    pending_loudness_gain: Arc<Mutex<Option<Option<f32>>>>, // 測り終わった結果（測れなかった場合はSome(None)）
+    loudness_generation: Arc<Mutex<u64>>,
+    loudness_scan_started: bool,
+    loudness_measuring: bool,
+    mono: bool,
+    balance: f32,
+    equalizer: Vec<f32>,
+    audio_restart_at: Option<Instant>,
+    volume_envelope: VolumeRamp,   // 再生中のffplayに掛けている音量
+    volume_ramp: Option<Duration>, // 次に音声を起動するとき、聞こえていた音量から新しい音量へ移す時間
+    video_color: ColorInfo,
+    video_summary: Option<String>, // タイトルバーに出す解像度・コーデック・fps
+    color_correction: bool,
+    tone_mapping: ToneMapping,
+    raw_pixel_aspect: bool, // ピクセルの縦横比（SAR）を無視して、画素数のままの大きさで表示する
+    denoise: bool,
+    denoise_strength: f32,
+    sharpen: bool,
+    sharpen_strength: f32,
+    adjustments: VideoAdjustments,
+    ui_refresh_rate: u32,
+    aspect_lock: bool,
+    fade_transition: bool,
+    scan_subfolders: bool, // フォルダを開くときにサブフォルダの動画も入れる
+    playlist_end: PlaylistEnd,
+    restore_paused: bool,  // 一時停止したまま閉じた動画を開き直したら、その位置で止めた状態に戻す
+    restore_session: bool,      // 起動時に前回終了したときの動画・プレイリスト・位置・音量・フィルターに戻す
+    restore_session_play: bool, // 戻したあとそのまま再生する（オフなら止めた状態で開く）
+    video_extensions: Vec<String>, // ファイル選択ダイアログ・フォルダを開くときに動画とみなす拡張子
+    last_open_dir: Option<PathBuf>, // 最後に動画を開いたフォルダ（次のダイアログはここから始める）
+    image_sequence: bool,    // 番号の付いた画像を開いたら、連番全体を動画として開く
+    image_sequence_fps: f32, // 連番の画像を動画にするときのfps
+    video_restart_at: Option<Instant>,
+    audio_channels: u32,
+    subtitles: Option<Subtitles>,
+    subtitle_offset: f32,
+    loop_a: Option<f32>,
+    loop_b: Option<f32>,
+    loop_count: i32,     // A/B区間を再生する回数（-1なら無限）
+    loop_remaining: i32, // 残りの回数（0になったら区間を解除してB点から先へ進む）
+    shadowing: Option<Shadowing>,
+    shadowing_repeats: u32, // 1文を再生する回数
+    shadowing_gap: f32,     // 毎回の再生の後に空ける秒数
+    cue_sound: bool,         // A/B区間・リピートで先頭に戻ったときと、動画の終わりで短い音を鳴らす
+    cue_flash: bool,         // 同じタイミングで画面を一瞬光らせる
+    cue_flash_pending: bool, // UIがまだ光らせていない合図
+    playlist: Vec<PathBuf>,
+    playlist_index: Option<usize>,
+    play_when_loaded: bool,
+    seek_mode: SeekMode,
+    seekable: bool, // 開いている入力がシークできるか（ライブ配信などではシークバーを無効にする）
+    max_render_size: (u32, u32),
+    hwaccel: Option<String>,
+    frame_format: FrameFormat,
+    decode_ahead: usize, // 再生スレッドが先読みしておくフレームの数（0で先読みなし）
+    pts_debug: bool,     // 各フレームの表示時刻を調べて、計算した再生位置と並べて表示する（デバッグ用）
+    scan_mode: bool,     // 実時間に合わせて待たず、デコードできた速さでフレームを表示する（音声なし）
+    frame_pts: FramePts, // 最後に表示したフレームの（表示時刻, 再生位置）
+    playback_speed: f32,
+    slow_motion_smoothing: SlowMotionSmoothing,
+    reverse: bool,
+    reverse_audio: bool,
+    reverse_window_start: f32,
+    reverse_finished: Arc<Mutex<bool>>,
+    skip_intro: f32,
+    seek_step_short: f32,
+    seek_step_long: f32,
+    intro_pending: bool,
+    intro_skipped: Option<f32>,
+    skip_silence: bool,
+    min_silence: f32,
+    silence_scan_started: bool,
+    silent_regions: Arc<Mutex<Option<SilentRegions>>>,
+    silence_cache: Arc<Mutex<HashMap<PathBuf, SilentRegions>>>,
+    silence_generation: Arc<Mutex<u64>>,
+    settings: Settings,
     video_path: Option<PathBuf>,
     video_width: u32,
     video_height: u32,
+    waveform_cache: Arc<Mutex<WaveformCache>>,
+    disk_cache: Arc<DiskCache>, // 起動し直しても使えるよう、生成した波形などを保存する
+    waveform_generation: Arc<Mutex<u64>>,
+    pending_waveform: Arc<Mutex<Option<Vec<f32>>>>,
+    pending_thumbnails: Arc<Mutex<Vec<(PathBuf, RgbaImage)>>>, // 「続きから見る」のサムネイル
+    resume_at: Option<f32>, // 読み込みが終わったらこの位置にシークする
+    keyframes: Arc<Mutex<Option<Vec<f32>>>>,
+    keyframe_cache: Arc<Mutex<HashMap<PathBuf, Vec<f32>>>>,
+    keyframe_generation: Arc<Mutex<u64>>,
+    pending_keyframes: Arc<Mutex<Option<Vec<f32>>>>,
+    playback_error: Arc<Mutex<Option<String>>>,
+    playback_failure: Option<String>, // 取り出した後も、やり直すまで表示し続けるエラー
+    frame_wait_started: Arc<Mutex<Option<(u64, Instant)>>>,
+    // 再生スレッドのffmpeg（世代ごと）。新しい再生・シークのときにメインスレッドからすぐに止める
+    decoders: Arc<Mutex<Vec<(u64, Child)>>>,
+    frame_request: Arc<Mutex<Option<FrameRequest>>>,
+    frame_loader_running: Arc<Mutex<bool>>,
+    scrub_preview: Arc<Mutex<ScrubPreview>>,
+    scrub_time: Option<f32>,           // ドラッグ中の位置（ドラッグしていなければNone）
+    scrub_updated_at: Instant,         // 最後にドラッグ位置が変わった時刻
+    resume_after_scrub: bool,          // ドラッグ前に再生していたか（シークしたら再生に戻す）
+    trickplay: Option<Trickplay>,
+    frame_history: Arc<Mutex<FrameHistory>>,
+    load_generation: Arc<Mutex<u64>>,
+    load_status: Arc<Mutex<Option<String>>>,
+    pending_load: PendingLoad,
+    probe_cache: Arc<Mutex<ProbeCache>>,
+    task_progress: Arc<Mutex<Option<TaskProgress>>>,
+    task_result: Arc<Mutex<Option<Result<String, String>>>>,
+    // 終了処理で待つワーカースレッドと、終了を知らせるフラグ
+    workers: Arc<Mutex<Vec<thread::JoinHandle<()>>>>,
+    shutting_down: Arc<Mutex<bool>>,
 }
 
-impl VideoPlayer {
-    pub fn new() -> Self {
-        Self {
-            duration: 0.0,
-            fps: 30.0,
-            is_playing: Arc::new(Mutex::new(false)),
-            is_paused: Arc::new(Mutex::new(false)),
-            video_finished: Arc::new(Mutex::new(false)),
-            current_time: Arc::new(Mutex::new(0.0)),
-            current_frame: Arc::new(Mutex::new(None)),
-            seek_time: Arc::new(Mutex::new(None)),
-            playback_generation: Arc::new(Mutex::new(0)),
-            audio_process: Arc::new(Mutex::new(None)),
-            audio_generation: Arc::new(Mutex::new(0)),
-            volume: Arc::new(Mutex::new(1.0)),
-            video_path: None,
-            video_width: 960,
-            video_height: 600,
+// 1フレーム戻るために残しておくフレームの数（960x600で1枚約2.3MB）
+const FRAME_HISTORY_LEN: usize = 30;
+
+// 終了時にワーカースレッドの終了を待つ上限（ffmpegが応答しなくても終了できるように）
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+// UIに渡す前に溜めておくフレームの上限
+const FRAME_QUEUE_CAPACITY: usize = 3;
+
+// 再生スレッドが先読みしておくフレームの数の既定値と上限、先読みに使うメモリの上限
+const DEFAULT_DECODE_AHEAD: usize = 4;
+const MAX_DECODE_AHEAD: usize = 16;
+const MAX_DECODE_AHEAD_BYTES: usize = 256 * 1024 * 1024;
+
+// 無限リピートで、終端のこの秒数前から次の周回のffmpegを起動しておく
+const LOOP_PREROLL: f32 = 1.0;
+
+// 終端はffmpegの出力が終わった時点とし、durationをこれ以上超えても出力が続く場合だけ打ち切る
+const END_OVERSHOOT_GRACE: f32 = 2.0;
+// 終端のこの秒数前より後でffmpegが異常終了した場合は、起動し直さずに終端とみなす
+const NEAR_END_MARGIN: f32 = 1.0;
+
+// 可変フレームレートの動画で、フレームの表示時刻（showinfoの出力）を待つ時間。届かなければ平均fpsで進める
+const FRAME_TIMESTAMP_WAIT: Duration = Duration::from_millis(200);
+
+// ffmpegの出力サイズ（標準エラーの出力ストリームの情報）を待つ時間。届かなければ想定どおりのサイズとみなす
+const OUTPUT_SIZE_WAIT: Duration = Duration::from_secs(10);
+
+// シークバーのドラッグ中にプレビューを抽出する最短の間隔（これより速くはffmpegを起動しない）
+const SCRUB_PREVIEW_INTERVAL: Duration = Duration::from_millis(100);
+// 1フレームの抽出にこれ以上かかる動画では、ドラッグ中のプレビューをやめる
+const SCRUB_SLOW_EXTRACTION: Duration = Duration::from_millis(600);
+// ドラッグの終わり（離したとき）が届かなくても、位置がこの時間変わらなければシークする（キーボード操作など）
+const SCRUB_COMMIT_IDLE: Duration = Duration::from_millis(1500);
+// 早送り・巻き戻しの速さ（ボタンを押し続けるとTRICKPLAY_SPEED_UPごとに次の速さに上げる）
+// 位置はTRICKPLAY_STEPごとに進め、ドラッグ中と同じプレビューで表示する
+const TRICKPLAY_SPEEDS: [f32; 3] = [2.0, 4.0, 8.0];
+const TRICKPLAY_SPEED_UP: Duration = Duration::from_millis(1500);
+const TRICKPLAY_STEP: Duration = Duration::from_millis(100);
+
+// 設定の書き出し・読み込みの対象（ファイルごとの設定・再生位置・キャッシュの中身は含めない）
+const CONFIG_KEYS: &[&str] = &[
+    "seek_mode", "slow_motion_smoothing", "ui_refresh_rate", "aspect_lock", "fade_transition", "scan_subfolders", "restore_paused", "playlist_end",
+    "restore_session", "restore_session_play", "ffmpeg_log_level",
+    "video_extensions", "cue_sound", "cue_flash",
+    "equalizer", "audio_device", "fullscreen_monitor", "theme", "language", "color_correction", "tone_mapping",
+    "raw_pixel_aspect",
+    "denoise_strength", "sharpen_strength", "skip_intro", "skip_silence", "min_silence", "cache_size_mb",
+    "shadowing_repeats", "shadowing_gap",
+    "seek_step_short", "seek_step_long", "match_loudness", "image_sequence", "image_sequence_fps", "decode_ahead",
+];
+
+// ファイル選択ダイアログ・フォルダを開くときに動画とみなす拡張子の既定値
+const DEFAULT_VIDEO_EXTENSIONS: &[&str] = &["mp4", "avi", "mkv", "mov", "wmv", "flv", "webm"];
+
+// 読み込むイントロスキップ・キャッシュの上限の範囲
+const MAX_SKIP_INTRO: f32 = 3600.0;
+const MAX_CACHE_SIZE_MB: u64 = 65536;
+
+// ←/→（Shiftで長い方）とスキップボタンで移動する秒数の既定値と範囲
+const DEFAULT_SEEK_STEP_SHORT: f32 = 10.0;
+const DEFAULT_SEEK_STEP_LONG: f32 = 60.0;
+const MIN_SEEK_STEP: f32 = 0.1;
+const MAX_SEEK_STEP: f32 = 3600.0;
+
+// ffmpegが再生途中で異常終了した場合に再起動を試みる回数
+const MAX_DECODE_RETRIES: u32 = 3;
+
+// フレームの到着がこれ以上遅れたらバッファリング中とみなす
+const BUFFERING_THRESHOLD: Duration = Duration::from_millis(500);
+
+// 再生速度の範囲（atempoは1段で0.5〜2.0倍なので、0.5未満は2段に分ける）
+const MIN_PLAYBACK_SPEED: f32 = 0.25;
+const MAX_PLAYBACK_SPEED: f32 = 2.0;
+
+// ノイズ除去（hqdn3dの空間方向の強さ）とシャープ（unsharpの輝度の強さ）の既定値と上限
+const DEFAULT_DENOISE_STRENGTH: f32 = 4.0;
+const MAX_DENOISE_STRENGTH: f32 = 10.0;
+const DEFAULT_SHARPEN_STRENGTH: f32 = 1.0;
+const MAX_SHARPEN_STRENGTH: f32 = 2.0;
+
+// UIの更新頻度（fps）の範囲と既定値（0は動画のfpsに合わせる）
+const MIN_UI_REFRESH_RATE: f32 = 10.0;
+const MAX_UI_REFRESH_RATE: f32 = 60.0;
+const DEFAULT_UI_REFRESH_RATE: u32 = 30;
+
+// 逆再生できる区間の上限（秒）。reverseフィルターは区間の全フレームをメモリに溜めるため
+const MAX_REVERSE_WINDOW: f32 = 5.0;
+
+// 表示用に縮小する最大サイズ（これより大きい動画はアスペクト比を保って縮小する）
+const DEFAULT_MAX_RENDER_SIZE: (u32, u32) = (960, 600);
+
+// デコードする大きさの上限（ffmpegのスケーラーが扱える幅・高さに収める）
+const MAX_FRAME_DIMENSION: u32 = 16384;
+
+// NV12で出力するときのscaleの色の指定（BT.709・フルレンジの動画もBT.601・リミテッドレンジに変換する）
+const NV12_SCALE_OUTPUT: &str = ":out_color_matrix=bt601:out_range=tv";
+
+// 音量ブーストの上限（これ以上は音割れがひどくなるため）
+const MAX_VOLUME_BOOST: f32 = 3.0;
+
+// シャドーイングで1文を繰り返す回数・間の秒数の既定値と上限
+// 文の前後は少しだけ広げて再生する（字幕のタイミングがぴったりでなくても頭と語尾が切れないように）
+const DEFAULT_SHADOWING_REPEATS: u32 = 3;
+const MAX_SHADOWING_REPEATS: u32 = 20;
+const DEFAULT_SHADOWING_GAP: f32 = 2.0;
+const MAX_SHADOWING_GAP: f32 = 30.0;
+const SHADOWING_LEAD_IN: f32 = 0.1;
+const SHADOWING_TAIL: f32 = 0.2;
+// 字幕の始まりへシークした直後の位置を、その字幕の中とみなす幅（秒。時刻の丸めで1つ前の字幕と判定しないように）
+const CUE_POSITION_TOLERANCE: f32 = 0.05;
+
+// 音量を変えたとき（消音も含む）に、前の音量から移るのに掛ける時間
+const VOLUME_RAMP_DURATION: Duration = Duration::from_millis(150);
+
+// 区間の先頭に戻ったとき・動画の終わりに鳴らす音（周波数Hz・長さ秒・音量スライダーに対する大きさ）
+const CUE_TONE_FREQUENCY: u32 = 880;
+const CUE_TONE_DURATION: f32 = 0.12;
+const CUE_TONE_VOLUME: f32 = 0.3;
+
+// 連番の画像を動画にするときのfpsの既定値と範囲
+const DEFAULT_IMAGE_SEQUENCE_FPS: f32 = 24.0;
+const MIN_IMAGE_SEQUENCE_FPS: f32 = 1.0;
+const MAX_IMAGE_SEQUENCE_FPS: f32 = 240.0;
+
+// 動画ごとの音量を揃えるときの目標の統合ラウドネス（LUFS）と、掛けるゲインの上限（dB）
+const TARGET_LOUDNESS: f32 = -16.0;
+const MAX_LOUDNESS_GAIN: f32 = 12.0;
+
+// 波形解析用のサンプリングレート（表示用なので低めで十分）
+const WAVEFORM_SAMPLE_RATE: u32 = 8000;
+// メモリに残す波形の数の上限（ディスクキャッシュにもあるので、開き直したときの分だけで足りる）
+const MAX_WAVEFORM_CACHE_ENTRIES: usize = 16;
+
+// 無音とみなす音量と、解析時に検出する最短の長さ（実際に飛ばす長さは再生時に絞り込む）
+const SILENCE_NOISE_DB: i32 = -40;
+const SILENCE_DETECT_MIN: f32 = 0.5;
+
+// GIFに書き出せる区間の上限（秒）
+const MAX_GIF_DURATION: f32 = 30.0;
+
+// 「続きから見る」に表示する件数
+const WATCH_HISTORY_LEN: usize = 20;
+
+// 残りがこれより短ければ見終わったものとして扱う（エンドロールなど）
+const WATCHED_END_MARGIN: f32 = 10.0;
+
+// 「続きから見る」のサムネイルの幅
+const HISTORY_THUMBNAIL_WIDTH: u32 = 160;
+
+impl VideoPlayer {
+    pub fn new() -> Self {
+        Self::with_settings(Settings::load())
+    }
+    
+    // 設定を渡して作る（テストでは設定ファイルを読み書きしない設定を渡す）
+    fn with_settings(settings: Settings) -> Self {
+        let mut player = Self {
+            duration: 0.0,
+            fps: 30.0,
+            variable_frame_rate: false,
+            state: Arc::new(Mutex::new(PlaybackState::Stopped)),
+            observers: Observers::default(),
+            video_finished: Arc::new(Mutex::new(false)),
+            current_time: Arc::new(Mutex::new(0.0)),
+            buffered_time: Arc::new(Mutex::new(0.0)),
+            current_frame: Arc::new(Mutex::new(FrameQueue::default())),
+            seek_time: Arc::new(Mutex::new(None)),
+            playback_generation: Arc::new(Mutex::new(0)),
+            audio_process: Arc::new(Mutex::new(None)),
+            seamless_loop: Arc::new(Mutex::new(false)),
+            loop_seam: Arc::new(Mutex::new(false)),
+            audio_looping: false,
+            audio_available: tool_available("ffplay"),
+            audio_generation: Arc::new(Mutex::new(0)),
+            volume: Arc::new(Mutex::new(1.0)),
+            volume_boost: 1.0,
+            audio_offset: 0.0,
+            normalize_loudness: false,
+            match_loudness: false,
+            loudness_gain: None,
+            pending_loudness_gain: Arc::new(Mutex::new(None)),
+            loudness_generation: Arc::new(Mutex::new(0)),
+            loudness_scan_started: false,
+            loudness_measuring: false,
+            mono: false,
+            balance: 0.0,
+            equalizer: vec![0.0; equalizer::BANDS.len()],
+            audio_restart_at: None,
+            volume_envelope: VolumeRamp::constant(1.0),
+            volume_ramp: None,
+            video_color: ColorInfo::from_probe(None, None, None, 0),
+            video_summary: None,
+            color_correction: true,
+            tone_mapping: ToneMapping::Hable,
+            raw_pixel_aspect: false,
+            denoise: false,
+            denoise_strength: DEFAULT_DENOISE_STRENGTH,
+            sharpen: false,
+            sharpen_strength: DEFAULT_SHARPEN_STRENGTH,
+            adjustments: VideoAdjustments::default(),
+            ui_refresh_rate: DEFAULT_UI_REFRESH_RATE,
+            fade_transition: true,
+            scan_subfolders: false,
+            playlist_end: PlaylistEnd::Stop,
+            restore_paused: false,
+            restore_session: false,
+            restore_session_play: false,
+            video_extensions: DEFAULT_VIDEO_EXTENSIONS.iter().map(|extension| extension.to_string()).collect(),
+            last_open_dir: None,
+            image_sequence: true,
+            image_sequence_fps: DEFAULT_IMAGE_SEQUENCE_FPS,
+            aspect_lock: true,
+            video_restart_at: None,
+            audio_channels: 0,
+            subtitles: None,
+            subtitle_offset: 0.0,
+            loop_a: None,
+            loop_b: None,
+            loop_count: -1,
+            loop_remaining: -1,
+            shadowing: None,
+            shadowing_repeats: DEFAULT_SHADOWING_REPEATS,
+            shadowing_gap: DEFAULT_SHADOWING_GAP,
+            cue_sound: false,
+            cue_flash: false,
+            cue_flash_pending: false,
+            playlist: Vec::new(),
+            playlist_index: None,
+            play_when_loaded: false,
+            seek_mode: SeekMode::Fast,
+            seekable: true,
+            max_render_size: DEFAULT_MAX_RENDER_SIZE,
+            hwaccel: None,
+            frame_format: FrameFormat::Rgba,
+            decode_ahead: DEFAULT_DECODE_AHEAD,
+            pts_debug: false,
+            scan_mode: false,
+            frame_pts: Arc::new(Mutex::new(None)),
+            playback_speed: 1.0,
+            slow_motion_smoothing: SlowMotionSmoothing::Off,
+            reverse: false,
+            reverse_audio: false,
+            reverse_window_start: 0.0,
+            reverse_finished: Arc::new(Mutex::new(false)),
+            skip_intro: 0.0,
+            seek_step_short: DEFAULT_SEEK_STEP_SHORT,
+            seek_step_long: DEFAULT_SEEK_STEP_LONG,
+            intro_pending: false,
+            intro_skipped: None,
+            skip_silence: false,
+            min_silence: 2.0,
+            silence_scan_started: false,
+            silent_regions: Arc::new(Mutex::new(None)),
+            silence_cache: Arc::new(Mutex::new(HashMap::new())),
+            silence_generation: Arc::new(Mutex::new(0)),
+            settings,
+            video_path: None,
+            video_width: 960,
+            video_height: 600,
+            waveform_cache: Arc::new(Mutex::new(WaveformCache::default())),
+            disk_cache: Arc::new(DiskCache::new(disk_cache::DEFAULT_MAX_BYTES)),
+            waveform_generation: Arc::new(Mutex::new(0)),
+            pending_waveform: Arc::new(Mutex::new(None)),
+            pending_thumbnails: Arc::new(Mutex::new(Vec::new())),
+            resume_at: None,
+            keyframes: Arc::new(Mutex::new(None)),
+            keyframe_cache: Arc::new(Mutex::new(HashMap::new())),
+            keyframe_generation: Arc::new(Mutex::new(0)),
+            pending_keyframes: Arc::new(Mutex::new(None)),
+            playback_error: Arc::new(Mutex::new(None)),
+            playback_failure: None,
+            frame_wait_started: Arc::new(Mutex::new(None)),
+            decoders: Arc::new(Mutex::new(Vec::new())),
+            frame_request: Arc::new(Mutex::new(None)),
+            frame_loader_running: Arc::new(Mutex::new(false)),
+            scrub_preview: Arc::new(Mutex::new(ScrubPreview::default())),
+            scrub_time: None,
+            trickplay: None,
+            scrub_updated_at: Instant::now(),
+            resume_after_scrub: false,
+            frame_history: Arc::new(Mutex::new(FrameHistory::default())),
+            load_generation: Arc::new(Mutex::new(0)),
+            load_status: Arc::new(Mutex::new(None)),
+            pending_load: Arc::new(Mutex::new(None)),
+            probe_cache: Arc::new(Mutex::new(ProbeCache::default())),
+            task_progress: Arc::new(Mutex::new(None)),
+            task_result: Arc::new(Mutex::new(None)),
+            workers: Arc::new(Mutex::new(Vec::new())),
+            shutting_down: Arc::new(Mutex::new(false)),
+        };
+        
+        // 前回選んだシーク方法を復元（既定はスクラブしやすい高速シーク）
+        if player.settings.get_value("seek_mode") == Some("accurate") {
+            player.seek_mode = SeekMode::Accurate;
+        }
+        player.color_correction = player.settings.get_value("color_correction") != Some("false");
+        player.raw_pixel_aspect = player.settings.get_value("raw_pixel_aspect") == Some("true");
+        player.aspect_lock = player.settings.get_value("aspect_lock") != Some("false");
+        player.fade_transition = player.settings.get_value("fade_transition") != Some("false");
+        player.scan_subfolders = player.settings.get_value("scan_subfolders") == Some("true");
+        player.playlist_end = match player.settings.get_value("playlist_end") {
+            Some("repeat_all") => PlaylistEnd::RepeatAll,
+            Some("repeat_one") => PlaylistEnd::RepeatOne,
+            _ => PlaylistEnd::Stop,
+        };
+        player.restore_paused = player.settings.get_value("restore_paused") == Some("true");
+        player.restore_session = player.settings.get_value("restore_session") == Some("true");
+        player.restore_session_play = player.settings.get_value("restore_session_play") == Some("true");
+        player.cue_sound = player.settings.get_value("cue_sound") == Some("true");
+        player.cue_flash = player.settings.get_value("cue_flash") == Some("true");
+        if let Some(extensions) = player.settings.get_value("video_extensions").and_then(|v| Self::parse_extensions(v).ok()) {
+            player.video_extensions = extensions;
+        }
+        player.last_open_dir = player.settings.get_value("last_open_dir").map(PathBuf::from);
+        player.image_sequence = player.settings.get_value("image_sequence") != Some("false");
+        if let Some(frames) = player.settings.get_value("decode_ahead").and_then(|v| v.parse::<usize>().ok()) {
+            player.decode_ahead = frames.min(MAX_DECODE_AHEAD);
+        }
+        if let Some(fps) = player.settings.get_value("image_sequence_fps").and_then(|v| v.parse::<f32>().ok()).filter(|v| v.is_finite()) {
+            player.image_sequence_fps = fps.clamp(MIN_IMAGE_SEQUENCE_FPS, MAX_IMAGE_SEQUENCE_FPS);
+        }
+        if let Some(mode) = player.settings.get_value("tone_mapping").and_then(ToneMapping::from_name) {
+            player.tone_mapping = mode;
+        }
+        if let Some(level) = player.settings.get_value("ffmpeg_log_level").and_then(ffmpeg_log::LogLevel::from_code) {
+            ffmpeg_log::set_level(level);
+        }
+        
+        // ノイズ除去・シャープの強さ（オン/オフは重いので起動ごとにオフから始める）
+        if let Some(strength) = player.settings.get_value("denoise_strength").and_then(|v| v.parse::<f32>().ok()) {
+            player.denoise_strength = strength.clamp(0.0, MAX_DENOISE_STRENGTH);
+        }
+        if let Some(strength) = player.settings.get_value("sharpen_strength").and_then(|v| v.parse::<f32>().ok()) {
+            player.sharpen_strength = strength.clamp(0.0, MAX_SHARPEN_STRENGTH);
+        }
+        if let Some(rate) = player.settings.get_value("ui_refresh_rate").and_then(|v| v.parse::<u32>().ok()) {
+            player.ui_refresh_rate = rate;
+        }
+        player.slow_motion_smoothing = match player.settings.get_value("slow_motion_smoothing") {
+            Some("blend") => SlowMotionSmoothing::Blend,
+            Some("interpolate") => SlowMotionSmoothing::Interpolate,
+            _ => SlowMotionSmoothing::Off,
+        };
+        if let Some(seconds) = player.settings.get_value("skip_intro").and_then(|v| v.parse::<f32>().ok()) {
+            player.skip_intro = seconds.max(0.0);
+        }
+        player.skip_silence = player.settings.get_value("skip_silence") == Some("true");
+        player.match_loudness = player.settings.get_value("match_loudness") == Some("true");
+        if let Some(seconds) = player.settings.get_value("seek_step_short").and_then(|v| v.parse::<f32>().ok()).filter(|v| v.is_finite()) {
+            player.seek_step_short = seconds.clamp(MIN_SEEK_STEP, MAX_SEEK_STEP);
+        }
+        if let Some(seconds) = player.settings.get_value("seek_step_long").and_then(|v| v.parse::<f32>().ok()).filter(|v| v.is_finite()) {
+            player.seek_step_long = seconds.clamp(MIN_SEEK_STEP, MAX_SEEK_STEP);
+        }
+        if let Some(value) = player.settings.get_value("equalizer") {
+            let gains: Vec<f32> = value.split(',').filter_map(|gain| gain.trim().parse::<f32>().ok()).collect();
+            if gains.len() == equalizer::BANDS.len() {
+                player.equalizer = gains;
+            }
+        }
+        if let Some(repeats) = player.settings.get_value("shadowing_repeats").and_then(|v| v.parse::<u32>().ok()) {
+            player.shadowing_repeats = repeats.clamp(1, MAX_SHADOWING_REPEATS);
+        }
+        if let Some(gap) = player.settings.get_value("shadowing_gap").and_then(|v| v.parse::<f32>().ok()).filter(|v| v.is_finite()) {
+            player.shadowing_gap = gap.clamp(0.0, MAX_SHADOWING_GAP);
+        }
+        if let Some(seconds) = player.settings.get_value("min_silence").and_then(|v| v.parse::<f32>().ok()) {
+            player.min_silence = seconds.clamp(SILENCE_DETECT_MIN, 10.0);
+        }
+        if let Some(megabytes) = player.settings.get_value("cache_size_mb").and_then(|v| v.parse::<u64>().ok()) {
+            player.disk_cache.set_max_bytes(megabytes * 1024 * 1024);
+        }
+        player
+    }
+
+    /// 動画の読み込みをバックグラウンドで開始する（結果はpoll_loadで受け取る）
+    /// 読み込み中に別のファイルを選ぶと、前の読み込みは中断される
+    pub fn load_video(&mut self, path: PathBuf) {
+        let path = self.resolve_image_sequence(path);
+        // 世代番号をインクリメント（前の読み込みをキャンセル）
+        let generation = {
+            let mut gen = self.load_generation.lock().unwrap();
+            *gen += 1;
+            *gen
+        };
+        *self.pending_load.lock().unwrap() = None;
+        self.resume_at = None;
+        *self.load_status.lock().unwrap() = Some(tr("Reading video info...").to_string());
+        
+        let load_generation = Arc::clone(&self.load_generation);
+        let load_status = Arc::clone(&self.load_status);
+        let pending_load = Arc::clone(&self.pending_load);
+        let probe_cache = Arc::clone(&self.probe_cache);
+        let options = LoadOptions {
+            color_correction: self.color_correction,
+            tone_mapping: self.tone_mapping,
+            max_render_size: self.max_render_size,
+            raw_pixel_aspect: self.raw_pixel_aspect,
+            // 保存された回転・色の調整は最初のフレームから反映する（読み込み後にデコードし直さないように）
+            adjustments: VideoAdjustments::load(&self.settings, &path),
+        };
+        
+        self.spawn_worker(move || {
+            let cancelled = || *load_generation.lock().unwrap() != generation;
+            let result = Self::prepare_video(&path, &probe_cache, &load_status, options, &cancelled);
+            
+            // キャンセルされた読み込みの結果は捨てる（新しい動画のフレームを上書きしないように）
+            if !cancelled() {
+                *pending_load.lock().unwrap() = Some((generation, result));
+                *load_status.lock().unwrap() = None;
+            } else {
+                println!("{}", trf("Cancelled loading: {}", &[&path.display()]));
+            }
+        });
+    }
+    
+    pub fn get_load_status(&self) -> Option<String> {
+        self.load_status.lock().unwrap().clone()
+    }
+    
+    /// 読み込みが完了していれば新しい動画に切り替えて結果を返す（読み込み中・未開始ならNone）
+    pub fn poll_load(&mut self) -> Option<Result<PathBuf, String>> {
+        let (generation, result) = self.pending_load.lock().unwrap().take()?;
+        if generation != *self.load_generation.lock().unwrap() {
+            return None;
+        }
+        
+        match result {
+            Ok(loaded) => {
+                let path = loaded.path.clone();
+                self.apply_loaded_video(loaded);
+                // プレイリストの自動送りでは読み込み後すぐに再生する
+                if std::mem::take(&mut self.play_when_loaded) {
+                    let _ = self.play();
+                }
+                Some(Ok(path))
+            }
+            Err(e) => {
+                self.play_when_loaded = false;
+                Some(Err(e))
+            }
+        }
+    }
+    
+    // ワーカースレッドで実行: ファイルを検証し、動画情報と最初のフレームを取得する
+    fn prepare_video(
+        path: &PathBuf,
+        probe_cache: &Mutex<ProbeCache>,
+        load_status: &Mutex<Option<String>>,
+        options: LoadOptions,
+        cancelled: &(dyn Fn() -> bool + Sync),
+    ) -> Result<LoadedVideo, String> {
+        let LoadOptions { color_correction, tone_mapping, max_render_size, raw_pixel_aspect, adjustments } = options;
+        // 先にファイルを検証する（失敗した場合は現在の動画をそのまま残す。連番のパターンとURLはファイルとしては存在しない）
+        match std::fs::metadata(path) {
+            _ if image_sequence::is_sequence(path) || is_network_source(path) => {}
+            Ok(metadata) if metadata.len() == 0 => {
+                return Err(tr("This file is not a valid video (the file is empty)").to_string());
+            }
+            Ok(_) => {}
+            Err(e) => return Err(trf("Cannot open the file: {}", &[&e])),
+        }
+        
+        // 動画情報の取得（ffprobe）と最初のフレームの抽出（ffmpeg）は同時に始める
+        // フレームは動画情報を待たずに、表示サイズをffmpegの式で同じ規則で決めて取り出しておく
+        let abandoned = AtomicBool::new(false);
+        let (info, provisional) = thread::scope(|scope| {
+            let extraction = scope.spawn(|| {
+                let mut filters = vec![Self::provisional_scale_filter(max_render_size, raw_pixel_aspect, color_correction)];
+                filters.extend(adjustments.filters());
+                Self::extract_first_frame(path, &filters.join(","), &|| cancelled() || abandoned.load(Ordering::Relaxed))
+            });
+            let info = Self::get_video_info(path, probe_cache, max_render_size, raw_pixel_aspect, cancelled);
+            // 動画として読めなければフレームの抽出も止める
+            if info.is_err() {
+                abandoned.store(true, Ordering::Relaxed);
+            } else if !extraction.is_finished() {
+                *load_status.lock().unwrap() = Some(tr("Extracting the first frame...").to_string());
+            }
+            (info, extraction.join().unwrap_or_else(|_| Err(tr("Failed to extract the frame").to_string())))
+        });
+        let mut info = info.map_err(|e| trf("This file is not a valid video ({})", &[&e]))?;
+        let expected = if adjustments.swaps_dimensions() { (info.height, info.width) } else { (info.width, info.height) };
+        
+        // 色の変換が同じになる場合だけ先に取り出したフレームを使う（大きさだけ違えば縮小し直す）
+        // それ以外（HDRのトーンマッピング、色空間の指定がない動画の色補正）と、抽出に失敗した場合は取り出し直す
+        // 途中で壊れているファイルはffprobeを通っても最初のフレームが取れないので、ここで弾く
+        let first_frame = match provisional {
+            Ok(frame) if Self::provisional_frame_usable(&info.color, color_correction, tone_mapping) => {
+                if frame.dimensions() == expected {
+                    frame
+                } else {
+                    println!("{}", trf("Resizing the first frame: {}x{} → {}x{}", &[&frame.width(), &frame.height(), &expected.0, &expected.1]));
+                    image::imageops::resize(&frame, expected.0, expected.1, image::imageops::FilterType::Triangle)
+                }
+            }
+            _ => {
+                if cancelled() {
+                    return Err(tr("Cancelled").to_string());
+                }
+                *load_status.lock().unwrap() = Some(tr("Extracting the first frame...").to_string());
+                let mut filters = vec![Self::scale_filter(info.width, info.height, &info.color, color_correction, tone_mapping, "")];
+                filters.extend(adjustments.filters());
+                Self::extract_first_frame(path, &filters.join(","), cancelled)
+                    .map_err(|e| trf("This file is not a valid video ({})", &[&e]))?
+            }
+        };
+        
+        // 実際の出力サイズが想定と違えば、以降のデコードも実際のサイズで読む
+        if Self::output_size_mismatch(expected, first_frame.dimensions()) {
+            (info.width, info.height) = if adjustments.swaps_dimensions() {
+                (first_frame.height(), first_frame.width())
+            } else {
+                first_frame.dimensions()
+            };
+        }
+        
+        Ok(LoadedVideo {
+            path: path.clone(),
+            info,
+            first_frame,
+            adjustments,
+        })
+    }
+    
+    // メインスレッドで実行: 現在の再生を止めて新しい動画に切り替える
+    fn apply_loaded_video(&mut self, loaded: LoadedVideo) {
+        // 見ていた動画の位置を「続きから見る」用に残す
+        self.save_position();
+        self.end_scrub();
+        self.advance_playback_generation();
+        self.set_state(PlaybackState::Stopped);
+        *self.video_finished.lock().unwrap() = false;
+        *self.current_time.lock().unwrap() = 0.0;
+        *self.seek_time.lock().unwrap() = None;
+        *self.playback_error.lock().unwrap() = None;
+        self.playback_failure = None;
+        self.stop_audio();
+        
+        let path = loaded.path;
+        self.video_path = Some(path.clone());
+        self.duration = loaded.info.duration;
+        self.fps = loaded.info.fps;
+        self.variable_frame_rate = loaded.info.variable_frame_rate;
+        self.video_width = loaded.info.width;
+        self.video_height = loaded.info.height;
+        self.audio_channels = loaded.info.audio_channels;
+        self.video_summary = Some(loaded.info.summary());
+        self.video_color = loaded.info.color;
+        self.seekable = Self::source_seekable(&path, self.duration);
+        if !self.seekable {
+            println!("{}", trf("The input is not seekable, so seeking is disabled: {}", &[&path.display()]));
+        }
+        self.adjustments = loaded.adjustments;
+        self.current_frame.lock().unwrap().replace(loaded.first_frame);
+        self.loop_a = None;
+        self.loop_b = None;
+        self.loop_remaining = self.loop_count;
+        self.shadowing = None;
+        self.trickplay = None;
+        self.playlist_index = self.playlist.iter().position(|p| p == &path);
+        self.intro_pending = true;
+        self.intro_skipped = None;
+        
+        // 無音区間は動画ごとに解析し直す（スキップが有効な場合のみ）
+        *self.silence_generation.lock().unwrap() += 1;
+        *self.silent_regions.lock().unwrap() = None;
+        self.silence_scan_started = false;
+        if self.skip_silence && self.seekable {
+            self.start_silence_detection();
+        }
+        
+        // ラウドネスも動画ごとに測り直す（測り終わるまではゲインなしで再生する）
+        *self.loudness_generation.lock().unwrap() += 1;
+        *self.pending_loudness_gain.lock().unwrap() = None;
+        self.loudness_gain = None;
+        self.loudness_scan_started = false;
+        self.loudness_measuring = false;
+        if self.match_loudness && self.seekable {
+            self.start_loudness_measurement();
+        }
+        println!("{}", trf("Loaded video: {} ({}s, {}x{}, {}fps)", &[&path.display(), &self.duration, &self.video_width, &self.video_height, &format!("{:.2}", self.fps)]));
+        
+        // ファイルごとに保存された音声オフセットを復元
+        self.audio_offset = self.settings.get_file_f32(&path, "audio_offset").unwrap_or(0.0);
+        self.volume_boost = self.settings.get_file_f32(&path, "volume_boost").unwrap_or(1.0).clamp(1.0, MAX_VOLUME_BOOST);
+        
+        // 同じ名前の字幕ファイルがあれば自動で読み込む
+        self.subtitles = None;
+        self.subtitle_offset = 0.0;
+        if let Some(subtitle_path) = Subtitles::find_sidecar(&path) {
+            if let Err(e) = self.load_subtitles(subtitle_path) {
+                println!("{}", trf("Warning: {}", &[&e]));
+            }
+        }
+        
+        // 一時停止したままだった動画は、その位置のフレームを表示して止めておく
+        // （プレイリストの自動送りでは止めずに先頭から再生する）
+        let user_opened = self.resume_at.is_some() || !self.play_when_loaded;
+        let paused_at = self
+            .settings
+            .get_file_f32(&path, "position")
+            .filter(|_| self.restore_paused && user_opened && self.settings.get_file_value(&path, "paused") == Some("true"));
+        if let Some(position) = paused_at {
+            println!("{}", trf("Returning to the paused position: {}s", &[&format!("{:.2}", position)]));
+            self.resume_at = None;
+            self.play_when_loaded = false;
+            self.seek(position);
+        } else if let Some(position) = self.resume_at.take() {
+            // 「続きから見る」で開いた場合は保存した位置から
+            self.seek(position);
+        }
+    }
+    
+    // 外部コマンドを実行し、キャンセルされたら子プロセスを終了させる
+    // ログを残す設定なら、結果のstderrには最後に出たエラーの行だけを入れる（失敗の理由として添える。failure_detailで取り出す）
+    fn output_cancelable(command: &mut Command, cancelled: &dyn Fn() -> bool) -> Result<Output, String> {
+        let tool = Path::new(command.get_program()).file_stem().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        let mut child = ffmpeg_log::configure(command)
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| trf("Failed to start: {}", &[&e]))?;
+        let log = ffmpeg_log::capture(&tool, &mut child);
+        
+        // パイプが詰まらないよう、標準出力は別スレッドで読み切る
+        let mut stdout = child.stdout.take().unwrap();
+        let reader = thread::spawn(move || {
+            let mut buffer = Vec::new();
+            let _ = stdout.read_to_end(&mut buffer);
+            buffer
+        });
+        
+        loop {
+            if cancelled() {
+                let _ = child.kill();
+                let _ = child.wait();
+                let _ = reader.join();
+                return Err(tr("Cancelled").to_string());
+            }
+            match child.try_wait() {
+                Ok(Some(status)) => {
+                    let stdout = reader.join().unwrap_or_default();
+                    let stderr = log.last_error().unwrap_or_default().into_bytes();
+                    return Ok(Output { status, stdout, stderr });
+                }
+                Ok(None) => thread::sleep(Duration::from_millis(20)),
+                Err(e) => {
+                    let _ = child.kill();
+                    return Err(trf("Failed to wait for the process: {}", &[&e]));
+                }
+            }
+        }
+    }
+
+    // raw_pixelsならピクセルの縦横比（SAR）を無視して、保存されている画素数のまま表示する
+    fn get_video_info(path: &PathBuf, probe_cache: &Mutex<ProbeCache>, max_render_size: (u32, u32), raw_pixels: bool, cancelled: &dyn Fn() -> bool) -> Result<VideoInfo, String> {
+        // 同じファイル（更新日時・サイズも同じ）なら前回の結果を使う（表示する大きさは設定に合わせて決め直す）
+        if let Some(mut info) = probe_cache.lock().unwrap().get(path) {
+            println!("{}", trf("Video info from the cache: {}", &[&path.display()]));
+            Self::fit_render_size(&mut info, max_render_size, raw_pixels);
+            return Ok(info);
+        }
+        
+        // 拡張子は信用せず、中身に映像か音声のストリームがあるかを先に確認する
+        let (has_video, has_audio) = Self::probe_stream_types(path, cancelled)?;
+        if !has_video && !has_audio {
+            return Err(tr("No video or audio stream found (the file may not be a media file)").to_string());
+        }
+        if !has_video {
+            return Err(tr("This file has no video stream (audio-only files are not supported)").to_string());
+        }
+        
+        // ffprobeで動画情報を取得（avg_frame_rateで正確なfpsを取得）
+        let output = Self::output_cancelable(ffmpeg_command("ffprobe")
+            .args(network_input_options(path))
+            .args(image_sequence::input_options(path))
+            .args(&[
+                "-v", "error",
+                "-select_streams", "v:0",
+                "-show_entries", "stream=codec_name,width,height,sample_aspect_ratio,display_aspect_ratio,avg_frame_rate,r_frame_rate,color_space,color_range,color_transfer",
+                "-show_entries", "stream_tags=rotate:stream_side_data=rotation",
+                "-show_entries", "format=duration,format_name",
+                "-print_format", "json",
+            ])
+            .arg(path), cancelled);
+
+        match output {
+            Ok(output) if output.status.success() => {
+                // 項目を名前で読む（ない項目・"N/A"・"unknown"は未設定として扱う）
+                let root = json::parse(&String::from_utf8_lossy(&output.stdout))
+                    .map_err(|pos| format!("ffprobe: JSON ({})", pos))?;
+                let stream = root.get("streams").and_then(|streams| streams.items().first());
+                let format = root.get("format");
+                let text = |value: Option<&json::Value>, name: &str| {
+                    value
+                        .and_then(|value| value.get(name))
+                        .and_then(json::Value::as_str)
+                        .filter(|value| !value.is_empty() && *value != "N/A" && *value != "unknown")
+                        .map(str::to_string)
+                };
+                let number = |value: Option<&json::Value>, name: &str| text(value, name).and_then(|value| value.parse::<f32>().ok());
+                
+                // 音声だけのストリームなどでは幅・高さがない（0にして下で弾く）
+                let coded_width = number(stream, "width").map_or(0, |w| w as u32);
+                let coded_height = number(stream, "height").map_or(0, |h| h as u32);
+                let mut duration = number(format, "duration").filter(|d| *d > 0.0).unwrap_or(0.0);
+                let fps = text(stream, "avg_frame_rate").and_then(|v| Self::parse_frame_rate(&v)).unwrap_or(30.0);
+                let mut base_fps = text(stream, "r_frame_rate").and_then(|v| Self::parse_frame_rate(&v));
+                let color_space = text(stream, "color_space");
+                let color_range = text(stream, "color_range");
+                let color_transfer = text(stream, "color_transfer");
+                let format_name = text(format, "format_name").unwrap_or_default();
+                let codec = text(stream, "codec_name");
+                
+                // ピクセルの縦横比（"8:9"など。"0:1"は不明）。SARがなければDARと画素数から求める
+                let ratio = |name: &str| {
+                    let value = text(stream, name)?;
+                    let (num, den) = value.split_once(':')?;
+                    let (num, den) = (num.parse::<f32>().ok()?, den.parse::<f32>().ok()?);
+                    (num > 0.0 && den > 0.0).then(|| num / den)
+                };
+                let sample_aspect = ratio("sample_aspect_ratio")
+                    .or_else(|| ratio("display_aspect_ratio").filter(|_| coded_width > 0).map(|dar| dar * coded_height as f32 / coded_width as f32))
+                    .unwrap_or(1.0);
+                
+                // ffmpegは回転の情報に合わせて出力するので、90度・270度なら幅と高さを入れ替えて扱う（ピクセルの縦横比も逆になる）
+                let (width, height, pixel_aspect) = match stream.map_or(0, Self::stream_rotation) {
+                    90 | 270 => (coded_height, coded_width, 1.0 / sample_aspect),
+                    _ => (coded_width, coded_height, sample_aspect),
+                };
+                
+                // 静止画（image2やpng_pipeなど）にはdurationがないので、1フレームだけの動画として扱う
+                let still_image = duration <= 0.0 && (format_name == "image2" || format_name.ends_with("_pipe"));
+                if still_image {
+                    duration = 1.0 / fps;
+                    base_fps = None;
+                    println!("{}", trf("Opening as a still image ({})", &[&format_name]));
+                }
+                duration = Self::playable_duration(duration, fps);
+                
+                if width == 0 || height == 0 {
+                    Err(tr("No video stream found").to_string())
+                } else if duration > 0.0 {
+                    println!("{}", trf("Video info: {}x{}, duration={}s, avg_fps={}", &[&width, &height, &format!("{:.2}", duration), &format!("{:.2}", fps)]));
+                    
+                    // 音声のチャンネル数（モノラル化・左右バランスのフィルター選択に使用）
+                    let audio_channels = Self::probe_audio_channels(path, cancelled);
+                    
+                    let color = ColorInfo::from_probe(color_space.as_deref(), color_range.as_deref(), color_transfer.as_deref(), coded_height);
+                    println!("{}", trf("Color space: {} (color_space={})", &[&format!("{:?}", color), &format!("{:?}", color_space)]));
+                    
+                    // 基準のフレームレートと平均が1%以上ずれていれば可変フレームレートとみなす
+                    let variable_frame_rate = base_fps.is_some_and(|base| (base - fps).abs() > fps * 0.01);
+                    
+                    let mut info = VideoInfo {
+                        duration,
+                        fps,
+                        variable_frame_rate,
+                        width,
+                        height,
+                        source_width: width,
+                        source_height: height,
+                        pixel_aspect,
+                        codec,
+                        audio_channels,
+                        color,
+                    };
+                    Self::fit_render_size(&mut info, max_render_size, raw_pixels);
+                    // キャンセルされた場合はチャンネル数が不完全なのでキャッシュしない
+                    if !cancelled() {
+                        probe_cache.lock().unwrap().insert(path, info.clone());
+                    }
+                    Ok(info)
+                } else {
+                    Err(tr("Could not get the video duration").to_string())
+                }
+            }
+            Ok(output) => Err(ffmpeg_log::with_detail(tr("ffprobe could not read the file").to_string(), Self::failure_detail(&output))),
+            Err(e) => Err(format!("ffprobe: {}", e)),
+        }
+    }
+    
+    // 1フレームより短いと最初のフレームを読む前に終端とみなされるので、最低1フレーム分にする（長さが不明な0はそのまま）
+    fn playable_duration(duration: f32, fps: f32) -> f32 {
+        if duration > 0.0 {
+            duration.max(1.0 / fps)
+        } else {
+            duration
+        }
+    }
+    
+    // 再生を始める位置（終端にシークしてから再生しても、最後のフレームは表示する）
+    fn clamp_start_position(position: f32, duration: f32, fps: f32) -> f32 {
+        position.min(duration - 1.0 / fps).max(0.0)
+    }
+    
+    // 含まれるストリームの種類を調べる（映像があるか, 音声があるか）
+    fn probe_stream_types(path: &PathBuf, cancelled: &dyn Fn() -> bool) -> Result<(bool, bool), String> {
+        let output = Self::output_cancelable(ffmpeg_command("ffprobe")
+            .args(network_input_options(path))
+            .args(image_sequence::input_options(path))
+            .args(&[
+                "-v", "error",
+                "-show_entries", "stream=codec_type",
+                "-of", "default=noprint_wrappers=1:nokey=1",
+            ])
+            .arg(path), cancelled)
+            .map_err(|e| format!("ffprobe: {}", e))?;
+        if !output.status.success() {
+            return Err(ffmpeg_log::with_detail(tr("ffprobe could not read the file").to_string(), Self::failure_detail(&output)));
+        }
+        
+        let types = String::from_utf8_lossy(&output.stdout);
+        let has_video = types.lines().any(|t| t.trim() == "video");
+        let has_audio = types.lines().any(|t| t.trim() == "audio");
+        Ok((has_video, has_audio))
+    }
+    
+    // 最初の音声ストリームのチャンネル数を取得（音声がなければ0）
+    fn probe_audio_channels(path: &PathBuf, cancelled: &dyn Fn() -> bool) -> u32 {
+        let output = Self::output_cancelable(ffmpeg_command("ffprobe")
+            .args(&[
+                "-v", "error",
+                "-select_streams", "a:0",
+                "-show_entries", "stream=channels",
+                "-of", "default=noprint_wrappers=1:nokey=1",
+            ])
+            .arg(path), cancelled);
+        
+        match output {
+            Ok(output) if output.status.success() => {
+                String::from_utf8_lossy(&output.stdout).trim().parse::<u32>().unwrap_or(0)
+            }
+            _ => 0,
+        }
+    }
+    
+    fn parse_frame_rate(s: &str) -> Option<f32> {
+        if let Some((num_str, den_str)) = s.split_once('/') {
+            if let (Ok(num), Ok(den)) = (num_str.trim().parse::<f32>(), den_str.trim().parse::<f32>()) {
+                if den > 0.0 {
+                    return Some(num / den);
+                }
+            }
+        }
+        // 分数でなければ直接パース
+        s.trim().parse::<f32>().ok().filter(|&v| v > 0.0)
+    }
+
+    // 表示する大きさを決める（ピクセルが正方形でなければ、先に横幅を伸縮して円が円に見える大きさにしてから縮小する）
+    fn fit_render_size(info: &mut VideoInfo, max_render_size: (u32, u32), raw_pixels: bool) {
+        let mut width = info.source_width;
+        if !raw_pixels && (info.pixel_aspect - 1.0).abs() > 0.001 {
+            width = (width as f32 * info.pixel_aspect).round() as u32;
+            println!("{}", trf("Adjusting the width to the pixel aspect ratio {}: {} → {}", &[&format!("{:.3}", info.pixel_aspect), &info.source_width, &width]));
+        }
+        (info.width, info.height) = Self::calculate_scaled_size(width, info.source_height, max_render_size);
+        println!("{}", trf("Display size: {}x{} → {}x{}", &[&info.source_width, &info.source_height, &info.width, &info.height]));
+    }
+    
+    fn calculate_scaled_size(orig_width: u32, orig_height: u32, (max_width, max_height): (u32, u32)) -> (u32, u32) {        
+        let (max_width, max_height) = (max_width.min(MAX_FRAME_DIMENSION), max_height.min(MAX_FRAME_DIMENSION));
+        let (new_width, new_height) = if orig_width <= max_width && orig_height <= max_height {
+            (orig_width, orig_height)
+        } else {
+            let width_ratio = max_width as f32 / orig_width as f32;
+            let height_ratio = max_height as f32 / orig_height as f32;
+            let ratio = width_ratio.min(height_ratio);
+            ((orig_width as f32 * ratio) as u32, (orig_height as f32 * ratio) as u32)
+        };
+        
+        let size = Self::valid_frame_size(new_width, new_height);
+        if size != (new_width, new_height) {
+            println!("{}", trf("Warning: the video size ({}x{}) cannot be used as is, so it is decoded at {}x{}", &[&orig_width, &orig_height, &size.0, &size.1]));
+        }
+        size
+    }
+    
+    // ffmpegが受け付ける大きさに直す（偶数にし、極端に細長い動画や1x1の動画でも0にならないよう2以上にする）
+    fn valid_frame_size(width: u32, height: u32) -> (u32, u32) {
+        let fix = |length: u32| (length.min(MAX_FRAME_DIMENSION) & !1).max(2);
+        (fix(width), fix(height))
+    }
+
+    fn load_first_frame(&mut self, path: &PathBuf) -> Result<(), String> {
+        let rgba = Self::extract_first_frame(path, &self.build_image_filters(), &|| false)?;
+        Self::output_size_mismatch(self.output_size(), rgba.dimensions());
+        self.current_frame.lock().unwrap().replace(rgba);
+        Ok(())
+    }
+
+    fn extract_first_frame(path: &PathBuf, image_filters: &str, cancelled: &dyn Fn() -> bool) -> Result<RgbaImage, String> {
+        // 最初のフレームを抽出
+        let output = Self::output_cancelable(ffmpeg_command("ffmpeg")
+            .args(network_input_options(path))
+            .args(image_sequence::input_options(path))
+            .arg("-i")
+            .arg(path)
+            .args(&[
+                "-vf", image_filters,
+                "-vframes", "1",
+                "-f", "image2pipe",
+                "-vcodec", "ppm",
+                "-"
+            ]), cancelled);
+
+        match output {
+            Ok(output) if output.status.success() && !output.stdout.is_empty() => {
+                match image::load_from_memory(&output.stdout) {
+                    Ok(img) => Ok(img.to_rgba8()),
+                    Err(e) => Err(trf("Failed to load the image: {}", &[&e]))
+                }
+            }
+            Ok(output) => Err(ffmpeg_log::with_detail(tr("Failed to extract the frame").to_string(), Self::failure_detail(&output))),
+            Err(e) => Err(trf("ffmpeg error: {}", &[&e]))
+        }
+    }
+    
+    // output_cancelableの結果に入れた、最後に出たエラーの行（ログを残していなければNone）
+    fn failure_detail(output: &Output) -> Option<String> {
+        Some(String::from_utf8_lossy(&output.stderr).to_string()).filter(|detail| !detail.is_empty())
+    }
+
+    pub fn play(&mut self) -> Result<(), String> {
+        // 逆再生は一時停止からの再開も含め、現在位置から区間を切り出し直す
+        if self.reverse {
+            return self.play_reverse();
+        }
+        
+        // 一時停止からの再開
+        if self.is_paused() {
+            self.set_state(PlaybackState::Playing);
+            self.start_audio_playback()?;
+            println!("{}", tr("Resuming from pause"));
+            return Ok(());
+        }
+        
+        // 読み込んだばかりの動画を先頭から再生する場合はイントロを飛ばす
+        // （シーク済み・保存位置から再開する場合はseek_timeが設定されているので飛ばさない）
+        if std::mem::take(&mut self.intro_pending) {
+            let intro = self.get_skip_intro().0;
+            let from_start = self.seek_time.lock().unwrap().is_none() && self.get_current_time() == 0.0;
+            if from_start && intro > 0.0 && intro < self.duration {
+                *self.seek_time.lock().unwrap() = Some(intro);
+                *self.current_time.lock().unwrap() = intro;
+                self.intro_skipped = Some(intro);
+                println!("{}", trf("Skipping the intro: {}s", &[&intro]));
+            }
+        }
+        
+        self.start_playback(false)
+    }
+    
+    // 再生スレッドを起動する（pausedなら一時停止した状態で待たせ、音声は再開時に起動する）
+    fn start_playback(&mut self, paused: bool) -> Result<(), String> {
+        self.playback_failure = None;
+        if let Some(path) = &self.video_path {
+            self.set_state(if paused { PlaybackState::Paused } else { PlaybackState::Playing });
+            *self.video_finished.lock().unwrap() = false;
+            
+            // メインスレッドでseek_timeを読み取り・クリア（レースコンディション防止）
+            // 終端にシークしてから再生した場合も最後のフレームは表示する（短い動画ですぐに終了しないように）
+            let start_position = {
+                let mut seek = self.seek_time.lock().unwrap();
+                let pos = seek.unwrap_or(0.0);
+                *seek = None;
+                Self::clamp_start_position(pos, self.duration, self.fps)
+            };
+            
+            // 再生世代をインクリメント
+            let generation = self.advance_playback_generation();
+            
+            let context = self.playback_context(generation);
+            let size = self.output_size();
+            let decoder = DecoderOptions {
+                path: path.clone(),
+                fps: self.fps,
+                video_filters: self.build_video_filters(),
+                seek_mode: self.effective_seek_mode(),
+                // 可変フレームレートの動画は各フレームの実際の表示時刻で進める（速度を変えているときは出力fpsに揃える）
+                frame_timestamps: self.variable_frame_rate && context.speed == 1.0,
+                pts_debug: context.frame_pts.is_some(),
+                hwaccel: self.hwaccel.clone(),
+                frame_format: self.frame_format,
+            };
+            *self.frame_pts.lock().unwrap() = None;
+            *self.buffered_time.lock().unwrap() = start_position;
+            
+            // 別スレッドで動画を再生（start_positionをパラメータとして渡す）
+            // 音声より先にスレッドを生成（音声失敗で動画が止まるのを防止）
+            self.spawn_worker(move || {
+                Self::play_video_with_frames(context, &decoder, size, start_position);
+            });
+            
+            // 音声再生を開始（失敗しても動画は再生し続ける）
+            if paused {
+                println!("{}", trf("Waiting while paused (position: {}s)", &[&format!("{:.2}", start_position)]));
+            } else if let Err(e) = self.start_audio_playback() {
+                println!("{}", trf("Failed to start audio playback (the video continues): {}", &[&e]));
+            }
+            
+            Ok(())
+        } else {
+            Err(tr("No video file is loaded").to_string())
+        }
+    }
+
+    fn play_video_with_frames(context: PlaybackContext, decoder: &DecoderOptions, (width, height): (u32, u32), start_position: f32) {
+        // start_positionはplay()のメインスレッドで確定済み（レースコンディション防止）
+        let PlaybackContext {
+            generation: my_generation,
+            state,
+            observers,
+            playback_generation,
+            decoders,
+            current_time,
+            buffered_time,
+            current_frame,
+            frame_history,
+            frame_wait_started,
+            frame_pts,
+            playback_error,
+            video_finished,
+            seamless_loop,
+            loop_seam,
+            duration,
+            speed,
+            decode_ahead,
+            scan_mode,
+            ..
+        } = context;
+        let DecoderOptions { fps, seek_mode, frame_format, .. } = *decoder;
+        
+        println!("{}", trf("Playing the video with ffmpeg... (start: {}s, generation: {})", &[&start_position, &my_generation]));
+        
+        let Decoder { mut child, mut timestamps, mut debug_pts, output_size, mut log } = match Self::spawn_decoder(decoder, start_position) {
+            Ok(decoder) => decoder,
+            Err(e) => {
+                println!("{}", e);
+                Self::change_state(&state, &observers, PlaybackState::Stopped);
+                return;
+            }
+        };
+
+        let stdout = child.stdout.take().unwrap();
+        Self::register_decoder(&decoders, &playback_generation, my_generation, child);
+        
+        // ffmpegが異常終了した場合の再起動回数と、現在のffmpegの開始位置
+        let mut retries: u32 = 0;
+        let mut segment_start = start_position;
+        
+        // フレームカウントと壁時計を併用した時間追跡
+        // （可変フレームレートでは、最後に表示したフレームの表示時刻を使う）
+        let mut frame_count: u64 = 0;
+        let mut timestamp_pos: Option<f32> = timestamps.as_ref().map(|_| start_position);
+        let frame_duration_secs = 1.0_f64 / fps as f64;
+        let mut playback_start = Instant::now();
+        let mut paused = false;
+        
+        // 1フレームのサイズは形式から計算する（RGBAなら4バイト/ピクセル）
+        // scaleで大きさを指定していても、念のためffmpegが実際に出力するサイズを確かめてから読む
+        let mut frame_buffer = Vec::new();
+        let ((mut width, mut height), mut reader) = Self::attach_output(stdout, &output_size, (width, height), frame_format, decode_ahead, &mut frame_buffer);
+        
+        // 無限リピート用に先に起動しておいた、先頭からのffmpeg
+        let mut next_child: Option<Decoder> = None;
+
+        loop {
+            // 世代番号をチェック（新しいシークや再生があれば、このスレッドは古くなっている）
+            if *playback_generation.lock().unwrap() != my_generation {
+                Self::stop_decoder(&decoders, my_generation);
+                println!("{}", trf("A new playback started, ending the old playback thread (generation: {})", &[&my_generation]));
+                break;
+            }
+            
+            // 一時停止チェック: ffmpegプロセスは生かしたまま待機（停止チェックより先）
+            if *state.lock().unwrap() == PlaybackState::Paused {
+                paused = true;
+                thread::sleep(Duration::from_millis(30));
+                continue;
+            } else if std::mem::take(&mut paused) {
+                // 一時停止から復帰: 壁時計の基準を、読み込み済みのフレームの位置から決め直す
+                // （止めていた時間を足していくと、一時停止を繰り返すたびに誤差が溜まって表示とずれる）
+                let decoded_secs = match timestamp_pos {
+                    Some(pos) => (pos - segment_start) as f64,
+                    None => frame_count as f64 * frame_duration_secs,
+                };
+                let now = Instant::now();
+                playback_start = now.checked_sub(Duration::from_secs_f64(decoded_secs.max(0.0))).unwrap_or(now);
+            }
+            
+            // 停止チェック（一時停止でない場合のみ到達）
+            if *state.lock().unwrap() == PlaybackState::Stopped {
+                Self::stop_decoder(&decoders, my_generation);
+                println!("{}", tr("Playback stopped"));
+                break;
+            }
+
+            // フレーム数ベースで現在の動画内位置を計算（1フレームで進む動画内の時間は再生速度倍）
+            let current_pos = timestamp_pos.unwrap_or(segment_start + (frame_count as f32) * (frame_duration_secs as f32) * speed);
+            
+            // 世代が一致する場合のみcurrent_timeを更新（古いスレッドが新しい再生の時間を上書きしないように）
+            if *playback_generation.lock().unwrap() != my_generation {
+                Self::stop_decoder(&decoders, my_generation);
+                println!("{}", trf("The generation changed before updating current_time (generation: {})", &[&my_generation]));
+                break;
+            }
+            // durationは目安なので表示上はdurationで止める（終端の判定はffmpegの出力のEOFで行う）
+            let position = if duration > 0.0 { current_pos.min(duration) } else { current_pos };
+            *current_time.lock().unwrap() = position;
+            observers.notify_position(position);
+            
+            // 無限リピート: 終端の少し前に次の周回のffmpegを起動し、最初のフレームを用意させておく
+            let looping = *seamless_loop.lock().unwrap();
+            if looping && next_child.is_none() && duration > 0.0 && duration - current_pos < LOOP_PREROLL {
+                match Self::spawn_decoder(decoder, 0.0) {
+                    Ok(new_child) => next_child = Some(new_child),
+                    Err(e) => println!("{}", e),
+                }
+            }
+            
+            // フォールバック: durationを大きく超えても出力が続く場合は終端とみなす
+            // （無限リピートならffmpegを止めて、下のEOFの処理で次の周回に切り替える）
+            let overshoot = Self::stream_end(current_pos, duration, None) == StreamEnd::Overshot;
+            if overshoot && looping && next_child.is_some() {
+                println!("{}", trf("The output continues past the duration, cutting it off (pos: {}, duration: {})", &[&format!("{:.2}", current_pos), &format!("{:.2}", duration)]));
+                Self::stop_decoder(&decoders, my_generation);
+            } else if overshoot {
+                // 世代が一致する場合のみ終了フラグを設定（古いスレッドが新しい再生を壊さないように）
+                if *playback_generation.lock().unwrap() == my_generation {
+                    *current_time.lock().unwrap() = duration;
+                    Self::change_state(&state, &observers, PlaybackState::Stopped);
+                    *video_finished.lock().unwrap() = true;
+                    println!("{}", trf("Ended because the output continued past the duration (frame: {}, pos: {}, duration: {})", &[&frame_count, &format!("{:.2}", current_pos), &format!("{:.2}", duration)]));
+                } else {
+                    println!("{}", trf("An old thread (generation: {}) ended past the duration (flags left unchanged)", &[&my_generation]));
+                }
+                Self::stop_decoder(&decoders, my_generation);
+                break;
+            }
+
+            // フレームを読み込む（正確なサイズを読み取る）
+            // 読み込みで待たされている間はメインスレッドからバッファリング中と判断できるよう、待ち始めた時刻を記録
+            *frame_wait_started.lock().unwrap() = Some((my_generation, Instant::now()));
+            let frame_complete = match reader.read(&mut frame_buffer) {
+                Ok(complete) => complete,
+                Err(e) => {
+                    println!("{}", trf("Read error: {}", &[&e]));
+                    Self::stop_decoder(&decoders, my_generation);
+                    false
+                }
+            };
+            {
+                let mut wait = frame_wait_started.lock().unwrap();
+                if wait.is_some_and(|(generation, _)| generation == my_generation) {
+                    *wait = None;
+                }
+            }
+            
+            // 読み込めたフレームの終わりまでをデコード済みとする（ローカルファイルでは再生位置とほぼ同じ）
+            if frame_complete && *playback_generation.lock().unwrap() == my_generation {
+                let decoded = (current_pos + frame_duration_secs as f32 * speed).min(duration.max(0.0));
+                let mut buffered = buffered_time.lock().unwrap();
+                *buffered = buffered.max(decoded);
+            }
+            
+            if !frame_complete {
+                // 出力が途切れた: ffmpegの終了コードで正常な終端かクラッシュかを判別
+                let exited_normally = Self::wait_decoder(&decoders, my_generation);
+                let reached_end = Self::stream_end(current_pos, duration, Some(exited_normally)) == StreamEnd::Finished;
+                let is_current = *playback_generation.lock().unwrap() == my_generation;
+                
+                // 無限リピート: 終端に達したら、用意しておいたffmpegに切り替えて先頭から続ける（停止・再読み込みをしない）
+                if reached_end && is_current && *seamless_loop.lock().unwrap() {
+                    if let Some(Decoder { child: mut new_child, timestamps: new_timestamps, debug_pts: new_debug_pts, output_size, log: new_log }) = next_child.take() {
+                        let stdout = new_child.stdout.take().unwrap();
+                        Self::register_decoder(&decoders, &playback_generation, my_generation, new_child);
+                        ((width, height), reader) = Self::attach_output(stdout, &output_size, (width, height), frame_format, decode_ahead, &mut frame_buffer);
+                        // 壁時計の基準は前の周回の終端に合わせる（継ぎ目で時間がずれないように）
+                        let segment_secs = match timestamp_pos {
+                            Some(pos) => (pos - segment_start) as f64 + frame_duration_secs,
+                            None => frame_count as f64 * frame_duration_secs,
+                        };
+                        playback_start += Duration::from_secs_f64(segment_secs.max(0.0));
+                        segment_start = 0.0;
+                        frame_count = 0;
+                        timestamps = new_timestamps;
+                        debug_pts = new_debug_pts;
+                        log = new_log;
+                        timestamp_pos = timestamps.as_ref().map(|_| 0.0);
+                        retries = 0;
+                        *current_time.lock().unwrap() = 0.0;
+                        *buffered_time.lock().unwrap() = 0.0;
+                        *loop_seam.lock().unwrap() = true;
+                        println!("{}", trf("Repeat: back to the start (generation: {})", &[&my_generation]));
+                        continue;
+                    }
+                }
+                
+                // 終端付近でなければ、現在位置からffmpegを起動し直す（回数制限あり）
+                if !reached_end && is_current && retries < MAX_DECODE_RETRIES {
+                    retries += 1;
+                    println!("{}", trf("ffmpeg exited abnormally, restarting (position: {}s, attempt {}/{})", &[&format!("{:.2}", current_pos), &retries, &MAX_DECODE_RETRIES]));
+                    match Self::spawn_decoder(decoder, current_pos) {
+                        Ok(Decoder { child: mut new_child, timestamps: new_timestamps, debug_pts: new_debug_pts, output_size, log: new_log }) => {
+                            let stdout = new_child.stdout.take().unwrap();
+                            Self::register_decoder(&decoders, &playback_generation, my_generation, new_child);
+                            ((width, height), reader) = Self::attach_output(stdout, &output_size, (width, height), frame_format, decode_ahead, &mut frame_buffer);
+                            segment_start = current_pos;
+                            frame_count = 0;
+                            timestamps = new_timestamps;
+                            debug_pts = new_debug_pts;
+                            log = new_log;
+                            timestamp_pos = timestamps.as_ref().map(|_| current_pos);
+                            playback_start = Instant::now();
+                            continue;
+                        }
+                        Err(e) => println!("{}", e),
+                    }
+                }
+                
+                // 世代が一致する場合のみ終了フラグを設定
+                if is_current {
+                    Self::change_state(&state, &observers, PlaybackState::Stopped);
+                    if reached_end {
+                        *current_time.lock().unwrap() = duration;
+                        *video_finished.lock().unwrap() = true;
+                        println!("{}", trf("Reached the end of the video (generation: {})", &[&my_generation]));
+                    } else {
+                        let message = tr("Failed to decode the video (ffmpeg exited abnormally)").to_string();
+                        *playback_error.lock().unwrap() = Some(ffmpeg_log::with_detail(message, log.last_error()));
+                        println!("{}", trf("Restarted ffmpeg {} times but playback could not continue", &[&retries]));
+                    }
+                } else {
+                    println!("{}", trf("An old thread (generation: {}) ended at EOF (flags left unchanged)", &[&my_generation]));
+                }
+                if let Some(Decoder { child: mut next, .. }) = next_child {
+                    let _ = next.kill();
+                    let _ = next.wait();
+                }
+                return;
+            }
+
+            // 可変フレームレート: このフレームの表示時刻まで待ってから、その時刻を再生位置にする
+            let mut frame_pos = position;
+            // 正確なシークではシーク位置より前のフレームもフィルターを通るので、その表示時刻は読み飛ばす
+            let pts_base = if seek_mode == SeekMode::Accurate { 0.0 } else { segment_start };
+            let min_pos = segment_start - frame_duration_secs as f32 * 0.5;
+            if let Some(receiver) = &timestamps {
+                let pos = Self::next_frame_pts(receiver, pts_base, 1.0, min_pos).unwrap_or(current_pos + frame_duration_secs as f32);
+                let pos = pos.max(current_pos);
+                let expected_elapsed = Duration::from_secs_f32((pos - segment_start).max(0.0));
+                let actual_elapsed = playback_start.elapsed();
+                if expected_elapsed > actual_elapsed && !scan_mode {
+                    thread::sleep(expected_elapsed - actual_elapsed);
+                }
+                if *playback_generation.lock().unwrap() != my_generation {
+                    continue;
+                }
+                timestamp_pos = Some(pos);
+                frame_pos = if duration > 0.0 { pos.min(duration) } else { pos };
+                *current_time.lock().unwrap() = frame_pos;
+                observers.notify_position(frame_pos);
+            }
+            
+            // 表示時刻の確認: フレーム自身の表示時刻と、計算した再生位置を並べて残す
+            // （可変フレームレートでは再生位置がフレームの表示時刻そのもの。速度を変えていれば元の動画の時刻に直す）
+            if let Some(frame_pts) = &frame_pts {
+                let pts = match &debug_pts {
+                    Some(receiver) => Self::next_frame_pts(receiver, pts_base, speed, min_pos),
+                    None => timestamp_pos,
+                };
+                if let Some(pts) = pts.filter(|_| *playback_generation.lock().unwrap() == my_generation) {
+                    *frame_pts.lock().unwrap() = Some((pts, frame_pos));
+                }
+            }
+            
+            // フレームを通知し、表示用のRgbaImageに変換
+            if let Some(rgba_image) = observers.notify_decoded_frame(&frame_buffer, width, height, frame_format) {
+                // 速度を変えているときは元の動画のフレームが飛ぶので残さない
+                if speed == 1.0 {
+                    let mut history = frame_history.lock().unwrap();
+                    if history.generation == my_generation {
+                        history.push(frame_pos, rgba_image.clone(), frame_duration_secs as f32);
+                    }
+                }
+                // 世代の確認と追加はまとめて行う（回転などでやり直した後に、前の向きのフレームが混ざらないように）
+                let mut queue = current_frame.lock().unwrap();
+                if *playback_generation.lock().unwrap() == my_generation {
+                    queue.push(rgba_image);
+                }
+            }
+            
+            // フレームカウントをインクリメント（時間追跡用）
+            frame_count += 1;
+
+            // 自前フレームペーシング: 次のフレームの表示タイミングまでスリープ
+            // （可変フレームレートでは表示前に待っているので不要。スキャンでは待たずに次のフレームを読む）
+            if timestamps.is_some() || scan_mode {
+                continue;
+            }
+            let expected_elapsed = Duration::from_secs_f64(frame_count as f64 * frame_duration_secs);
+            let actual_elapsed = playback_start.elapsed();
+            if expected_elapsed > actual_elapsed {
+                thread::sleep(expected_elapsed - actual_elapsed);
+            }
+        }
+
+        Self::wait_decoder(&decoders, my_generation);
+        if let Some(Decoder { child: mut next, .. }) = next_child {
+            let _ = next.kill();
+            let _ = next.wait();
+        }
+    }
+
+    // 再生位置とffmpegの出力の状態から、動画の終わりかを判定する
+    // output_endedは出力が途切れたときのffmpegの終了コードが正常だったか（出力が続いていればNone）
+    // 終端は出力が終わった時点（EOF）で、durationは目安。大きく超えても出力が続く場合だけ打ち切る
+    fn stream_end(position: f32, duration: f32, output_ended: Option<bool>) -> StreamEnd {
+        let known = duration > 0.0;
+        match output_ended {
+            None if known && position >= duration + END_OVERSHOOT_GRACE => StreamEnd::Overshot,
+            None => StreamEnd::Continue,
+            Some(exited_normally) if exited_normally || !known || position >= duration - NEAR_END_MARGIN => StreamEnd::Finished,
+            Some(_) => StreamEnd::Interrupted,
+        }
+    }
+    
+    // 現在位置から逆向きに再生する（区間はA/B区間、なければ直前のMAX_REVERSE_WINDOW秒）
+    fn play_reverse(&mut self) -> Result<(), String> {
+        let Some(path) = self.video_path.clone() else {
+            return Err(tr("No video file is loaded").to_string());
+        };
+        self.playback_failure = None;
+        
+        let current = self.get_current_time();
+        let (start, end) = match (self.loop_a, self.loop_b) {
+            // A/B区間内ならそこからA点まで、区間外ならB点から逆再生する
+            (Some(a), Some(b)) => (a, if current > a && current <= b { current } else { b }),
+            _ => ((current - MAX_REVERSE_WINDOW).max(0.0), current),
+        };
+        let start = if end - start > MAX_REVERSE_WINDOW {
+            println!("{}", trf("Warning: reverse playback keeps every frame in memory, so it is limited to the last {}s", &[&MAX_REVERSE_WINDOW]));
+            end - MAX_REVERSE_WINDOW
+        } else {
+            start
+        };
+        if end - start < 0.05 {
+            return Err(tr("Cannot go back any further").to_string());
+        }
+        
+        self.set_state(PlaybackState::Playing);
+        *self.video_finished.lock().unwrap() = false;
+        *self.reverse_finished.lock().unwrap() = false;
+        *self.seek_time.lock().unwrap() = None;
+        *self.current_time.lock().unwrap() = end;
+        self.reverse_window_start = start;
+        
+        let generation = self.advance_playback_generation();
+        
+        let context = self.playback_context(generation);
+        let size = self.output_size();
+        let fps = self.fps;
+        let seek_mode = self.seek_mode;
+        let image_filters = self.build_image_filters();
+        
+        println!("{}", trf("Reverse playback: {}s → {}s (generation: {})", &[&format!("{:.2}", end), &format!("{:.2}", start), &generation]));
+        self.spawn_worker(move || {
+            Self::play_reverse_frames(context, &path, size, fps, &image_filters, (start, end), seek_mode);
+        });
+        
+        if let Err(e) = self.start_audio_playback() {
+            println!("{}", trf("Failed to start audio playback (the video continues): {}", &[&e]));
+        }
+        Ok(())
+    }
+    
+    fn play_reverse_frames(context: PlaybackContext, path: &Path, (width, height): (u32, u32), fps: f32, image_filters: &str, (start, end): (f32, f32), seek_mode: SeekMode) {
+        let PlaybackContext { generation: my_generation, state, observers, playback_generation, decoders, current_time, current_frame, reverse_finished, .. } = context;
+        // 縮小してから反転する（元の解像度のままだとメモリを使いすぎる）
+        let mut child = match ffmpeg_log::configure(ffmpeg_command("ffmpeg")
+            .args(seek_mode.input_args(path, start))
+            .args([
+                "-t", &(end - start).to_string(),
+                "-vf", &format!("{},reverse", image_filters),
+                "-r", &fps.to_string(),
+                "-f", "rawvideo",
+                "-pix_fmt", "rgba",
+                "-",
+            ]))
+            .stdout(Stdio::piped())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) => {
+                println!("{}", trf("Failed to start ffmpeg: {}", &[&e]));
+                Self::change_state(&state, &observers, PlaybackState::Stopped);
+                return;
+            }
+        };
+        ffmpeg_log::capture("ffmpeg", &mut child);
+        let mut stdout = child.stdout.take().unwrap();
+        Self::register_decoder(&decoders, &playback_generation, my_generation, child);
+        
+        let frame_duration_secs = 1.0_f64 / fps as f64;
+        let mut frame_buffer = vec![0u8; FrameFormat::Rgba.frame_size(width, height)];
+        let mut frame_count: u64 = 0;
+        let mut playback_start = Instant::now();
+        
+        loop {
+            if *playback_generation.lock().unwrap() != my_generation {
+                Self::stop_decoder(&decoders, my_generation);
+                break;
+            }
+            
+            // 一時停止中はffmpegを生かしたまま待機し、再開時は経過時間の基準をずらす
+            if *state.lock().unwrap() == PlaybackState::Paused {
+                let pause_start = Instant::now();
+                while *state.lock().unwrap() == PlaybackState::Paused && *playback_generation.lock().unwrap() == my_generation {
+                    thread::sleep(Duration::from_millis(30));
+                }
+                playback_start += pause_start.elapsed();
+                continue;
+            }
+            if *state.lock().unwrap() == PlaybackState::Stopped {
+                Self::stop_decoder(&decoders, my_generation);
+                break;
+            }
+            
+            // reverseフィルターは区間を読み終えるまで最初のフレームを出さない
+            match Self::read_frame(&mut stdout, &mut frame_buffer) {
+                Ok(true) => {}
+                _ => {
+                    if *playback_generation.lock().unwrap() == my_generation {
+                        Self::change_state(&state, &observers, PlaybackState::Stopped);
+                        *current_time.lock().unwrap() = start;
+                        *reverse_finished.lock().unwrap() = true;
+                        println!("{}", trf("The reverse playback range has ended (generation: {})", &[&my_generation]));
+                    }
+                    break;
+                }
+            }
+            if frame_count == 0 {
+                playback_start = Instant::now();
+            }
+            
+            if *playback_generation.lock().unwrap() != my_generation {
+                Self::stop_decoder(&decoders, my_generation);
+                break;
+            }
+            if let Some(rgba_image) = RgbaImage::from_raw(width, height, frame_buffer.clone()) {
+                observers.notify_frame(&rgba_image);
+                current_frame.lock().unwrap().push(rgba_image);
+            }
+            frame_count += 1;
+            let position = (end - frame_count as f32 * frame_duration_secs as f32).max(start);
+            *current_time.lock().unwrap() = position;
+            observers.notify_position(position);
+            
+            let expected_elapsed = Duration::from_secs_f64(frame_count as f64 * frame_duration_secs);
+            let actual_elapsed = playback_start.elapsed();
+            if expected_elapsed > actual_elapsed {
+                thread::sleep(expected_elapsed - actual_elapsed);
+            }
+        }
+        
+        Self::wait_decoder(&decoders, my_generation);
+    }
+    
+    /// スキャン: 実時間に合わせずにデコードできた速さで表示し、場面を素早く探す（音声は止める）
+    /// 再生中なら現在位置から切り替え、オフにすると現在位置から通常の再生に戻る
+    pub fn set_scan_mode(&mut self, enabled: bool) {
+        if self.scan_mode == enabled {
+            return;
+        }
+        self.scan_mode = enabled;
+        println!("{}", trf("Scan: {}", &[&if enabled { tr("On") } else { tr("Off") }]));
+        self.restart_from_current();
+    }
+    
+    /// 逆再生の切り替え（再生中なら現在位置から向きを変えて再生し直す）
+    pub fn set_reverse(&mut self, enabled: bool) {
+        if self.reverse == enabled {
+            return;
+        }
+        // 逆再生は区間ごとにシークして読むので、シークできない入力ではできない
+        if enabled && !self.seekable {
+            println!("{}", tr("This input is not seekable, so it cannot play in reverse"));
+            return;
+        }
+        self.reverse = enabled;
+        println!("{}", trf("Reverse playback: {}", &[&if enabled { tr("On") } else { tr("Off") }]));
+        self.restart_from_current();
+    }
+    
+    // 再生中・一時停止中なら、現在位置から新しい設定で再生し直す
+    fn restart_from_current(&mut self) {
+        let current = self.get_current_time();
+        if self.is_playing() {
+            self.advance_playback_generation();
+            self.set_state(PlaybackState::Stopped);
+            self.stop_audio();
+            *self.seek_time.lock().unwrap() = Some(current);
+            let _ = self.play();
+        } else if self.is_paused() {
+            // 一時停止中は一時停止のまま、表示中のフレームも新しい設定で読み直す
+            self.seek(current);
+        }
+    }
+    
+    /// 再生速度を設定する（0.25〜2.0倍、逆再生には適用しない）
+    pub fn set_playback_speed(&mut self, speed: f32) {
+        let speed = speed.clamp(MIN_PLAYBACK_SPEED, MAX_PLAYBACK_SPEED);
+        if self.playback_speed == speed {
+            return;
+        }
+        self.playback_speed = speed;
+        println!("{}", trf("Playback speed: {}x", &[&speed]));
+        if !self.reverse {
+            self.restart_from_current();
+        }
+    }
+    
+    /// 再生中に再生位置が1秒間に進む秒数（時間バーの補間用。再生中でなければ0、逆再生も補間しないので0）
+    pub fn get_time_rate(&self) -> f32 {
+        // スキャンではデコードの速さで進むので、補間せずに届いた位置を表示する
+        if !self.is_playing() || self.reverse || self.scan_mode {
+            return 0.0;
+        }
+        self.playback_speed
+    }
+    
+    /// スロー再生（1倍未満）のときの中間フレームの作り方
+    pub fn set_slow_motion_smoothing(&mut self, smoothing: SlowMotionSmoothing) {
+        if self.slow_motion_smoothing == smoothing {
+            return;
+        }
+        self.slow_motion_smoothing = smoothing;
+        let value = match smoothing {
+            SlowMotionSmoothing::Off => "off",
+            SlowMotionSmoothing::Blend => "blend",
+            SlowMotionSmoothing::Interpolate => "interpolate",
+        };
+        self.settings.set_value("slow_motion_smoothing", value);
+        if smoothing == SlowMotionSmoothing::Interpolate {
+            println!("{}", tr("Warning: motion interpolation is CPU-heavy and may not keep up at some resolutions"));
+        }
+        if self.playback_speed < 1.0 && !self.reverse {
+            self.restart_from_current();
+        }
+    }
+    
+    pub fn get_slow_motion_smoothing(&self) -> SlowMotionSmoothing {
+        self.slow_motion_smoothing
+    }
+    
+    /// 読み込んでいる動画のfps（未読み込みなら0）
+    pub fn get_fps(&self) -> f32 {
+        self.fps
+    }
+    
+    /// 読み込んだ動画の概要（例: "1920x1080 H.264 23.976fps"）
+    pub fn get_video_summary(&self) -> Option<&str> {
+        self.video_summary.as_deref()
+    }
+    
+    /// 読み込んでいる動画のパス
+    pub fn get_video_path(&self) -> Option<&Path> {
+        self.video_path.as_deref()
+    }
+    
+    /// UIの更新頻度（fps）を設定する（0なら動画のfpsに合わせる）
+    pub fn set_ui_refresh_rate(&mut self, rate: u32) {
+        self.ui_refresh_rate = rate;
+        self.settings.set_value("ui_refresh_rate", rate);
+        println!("{}", trf("UI refresh rate: {}", &[&if rate == 0 { tr("Match the video").to_string() } else { format!("{}fps", rate) }]));
+    }
+    
+    pub fn get_ui_refresh_rate(&self) -> u32 {
+        self.ui_refresh_rate
+    }
+    
+    /// ウィンドウの縦横比を動画に合わせるか
+    pub fn set_aspect_lock(&mut self, enabled: bool) {
+        self.aspect_lock = enabled;
+        self.settings.set_value("aspect_lock", enabled);
+    }
+    
+    pub fn get_aspect_lock(&self) -> bool {
+        self.aspect_lock
+    }
+    
+    /// プレイリストの自動送りで暗転を挟むか
+    pub fn set_fade_transition(&mut self, enabled: bool) {
+        self.fade_transition = enabled;
+        self.settings.set_value("fade_transition", enabled);
+    }
+    
+    pub fn get_fade_transition(&self) -> bool {
+        self.fade_transition
+    }
+    
+    pub fn set_scan_subfolders(&mut self, enabled: bool) {
+        self.scan_subfolders = enabled;
+        self.settings.set_value("scan_subfolders", enabled);
+    }
+    
+    pub fn get_scan_subfolders(&self) -> bool {
+        self.scan_subfolders
+    }
+    
+    pub fn set_playlist_end(&mut self, policy: PlaylistEnd) {
+        self.playlist_end = policy;
+        let value = match policy {
+            PlaylistEnd::Stop => "stop",
+            PlaylistEnd::RepeatAll => "repeat_all",
+            PlaylistEnd::RepeatOne => "repeat_one",
+        };
+        self.settings.set_value("playlist_end", value);
+    }
+    
+    pub fn get_playlist_end(&self) -> PlaylistEnd {
+        self.playlist_end
+    }
+    
+    /// 最後の項目を再生し終えたときにすること（プレイリスト外の動画なら常にStop）
+    /// 項目が1つだけならRepeatAllでも同じ項目を繰り返すだけなので、読み込み直さずにRepeatOneとして扱う
+    pub fn playlist_end_action(&self) -> PlaylistEnd {
+        if self.playlist_index.is_none() {
+            return PlaylistEnd::Stop;
+        }
+        match self.playlist_end {
+            PlaylistEnd::RepeatAll if self.playlist.len() <= 1 => PlaylistEnd::RepeatOne,
+            policy => policy,
+        }
+    }
+    
+    /// 一時停止したまま別の動画に切り替えた・終了した動画を開き直したときに、先頭からではなく止めた位置・フレームに戻すか
+    pub fn set_restore_paused(&mut self, enabled: bool) {
+        self.restore_paused = enabled;
+        self.settings.set_value("restore_paused", enabled);
+    }
+    
+    pub fn get_restore_paused(&self) -> bool {
+        self.restore_paused
+    }
+    
+    /// 起動時に前回のセッションに戻すか（playは戻したあとに再生を始めるか）
+    pub fn set_restore_session(&mut self, enabled: bool, play: bool) {
+        self.restore_session = enabled;
+        self.restore_session_play = play;
+        self.settings.set_value("restore_session", enabled);
+        self.settings.set_value("restore_session_play", play);
+    }
+    
+    pub fn get_restore_session(&self) -> (bool, bool) {
+        (self.restore_session, self.restore_session_play)
+    }
+    
+    /// 終了時に呼ぶ: 開いている動画・プレイリスト・位置・音量・フィルターをセッションファイルに書く
+    /// （回転・色の調整は動画ごとの設定に保存済みなので、開き直せば戻る）
+    pub fn save_session(&self) {
+        let position = if self.get_state() == PlaybackState::Stopped { 0.0 } else { self.get_current_time() };
+        Session {
+            playlist: self.playlist.clone(),
+            current: self.video_path.clone(),
+            position,
+            volume: self.get_volume(),
+            denoise: self.denoise,
+            sharpen: self.sharpen,
+        }
+        .save();
+    }
+    
+    /// 前回のセッションに戻す（無効・保存がなければfalse）
+    /// 消えたファイルはプレイリストから外し、開いていた動画が消えていれば動画は開かない
+    pub fn restore_session(&mut self) -> bool {
+        if !self.restore_session {
+            return false;
+        }
+        let Some(session) = Session::load() else {
+            return false;
+        };
+        let exists = |path: &PathBuf| image_sequence::is_sequence(path) || is_network_source(path) || path.exists();
+        
+        *self.volume.lock().unwrap() = session.volume.clamp(0.0, 1.0);
+        self.denoise = session.denoise;
+        self.sharpen = session.sharpen;
+        let (playlist, missing): (Vec<PathBuf>, Vec<PathBuf>) = session.playlist.into_iter().partition(exists);
+        for path in missing {
+            println!("{}", trf("Removing a missing file from the playlist: {}", &[&path.display()]));
+        }
+        self.playlist = playlist;
+        
+        match session.current {
+            Some(path) if exists(&path) => {
+                println!("{}", trf("Restoring the previous session: {} ({}s)", &[&path.display(), &format!("{:.2}", session.position)]));
+                self.load_video(path);
+                if session.position > 0.0 {
+                    self.resume_at = Some(session.position);
+                }
+                self.play_when_loaded = self.restore_session_play;
+            }
+            Some(path) => println!("{}", trf("The video open last time was not found: {}", &[&path.display()])),
+            None => {}
+        }
+        true
+    }
+    
+    /// 動画とみなす拡張子を「mp4, mkv」のような一覧で設定する（空なら既定の一覧に戻す）
+    pub fn set_video_extensions(&mut self, text: &str) -> Result<(), String> {
+        let extensions = if text.trim().is_empty() {
+            DEFAULT_VIDEO_EXTENSIONS.iter().map(|extension| extension.to_string()).collect()
+        } else {
+            Self::parse_extensions(text)?
+        };
+        self.settings.set_value("video_extensions", extensions.join(","));
+        self.video_extensions = extensions;
+        Ok(())
+    }
+    
+    pub fn get_video_extensions(&self) -> &[String] {
+        &self.video_extensions
+    }
+    
+    // 「,」・空白区切りの拡張子を読む（「*.mp4」「.mp4」も受け付け、小文字に揃えて重複を除く）
+    fn parse_extensions(text: &str) -> Result<Vec<String>, String> {
+        let mut extensions: Vec<String> = Vec::new();
+        for item in text.split(|c: char| c == ',' || c == ';' || c.is_whitespace()).filter(|item| !item.is_empty()) {
+            let extension = item.trim_start_matches('*').trim_start_matches('.').to_lowercase();
+            if extension.is_empty() || !extension.chars().all(|c| c.is_ascii_alphanumeric()) {
+                return Err(trf("Invalid file extension: {}", &[&item]));
+            }
+            if !extensions.contains(&extension) {
+                extensions.push(extension);
+            }
+        }
+        if extensions.is_empty() {
+            return Err(tr("Enter at least one file extension").to_string());
+        }
+        Ok(extensions)
+    }
+    
+    /// ファイル選択ダイアログを開く場所（最後に開いたフォルダ。なくなっていればホームフォルダ）
+    pub fn get_open_directory(&self) -> Option<PathBuf> {
+        self.last_open_dir
+            .clone()
+            .filter(|dir| dir.is_dir())
+            .or_else(|| std::env::var_os("USERPROFILE").or_else(|| std::env::var_os("HOME")).map(PathBuf::from))
+    }
+    
+    /// 開いたファイル（またはフォルダ）の場所を、次のダイアログの開始位置として覚えておく
+    pub fn remember_open_directory(&mut self, path: &Path) {
+        let dir = if path.is_dir() { Some(path) } else { path.parent() };
+        let Some(dir) = dir.filter(|dir| !dir.as_os_str().is_empty()) else {
+            return;
+        };
+        self.settings.set_value("last_open_dir", dir.display());
+        self.last_open_dir = Some(dir.to_path_buf());
+    }
+    
+    /// 番号の付いた画像（frame_0001.pngなど）を開いたときに、連番全体を動画として開くか（オフなら1枚だけ表示する）
+    pub fn set_image_sequence(&mut self, enabled: bool) {
+        self.image_sequence = enabled;
+        self.settings.set_value("image_sequence", enabled);
+    }
+    
+    pub fn get_image_sequence(&self) -> bool {
+        self.image_sequence
+    }
+    
+    /// 連番の画像を動画にするときのfps。連番を開いていれば新しいfpsで開き直す
+    pub fn set_image_sequence_fps(&mut self, fps: f32) -> Result<(), String> {
+        if !fps.is_finite() || !(MIN_IMAGE_SEQUENCE_FPS..=MAX_IMAGE_SEQUENCE_FPS).contains(&fps) {
+            return Err(trf("The frame rate must be between {} and {} fps ({})", &[&MIN_IMAGE_SEQUENCE_FPS, &MAX_IMAGE_SEQUENCE_FPS, &fps]));
+        }
+        self.image_sequence_fps = fps;
+        self.settings.set_value("image_sequence_fps", fps);
+        if let Some(path) = self.video_path.clone().filter(|path| image_sequence::is_sequence(path)) {
+            image_sequence::set_fps(&path, fps);
+            self.load_video(path);
+        }
+        Ok(())
+    }
+    
+    pub fn get_image_sequence_fps(&self) -> f32 {
+        self.image_sequence_fps
+    }
+    
+    /// 再生中に先読みしておくフレームの数（0で先読みなし）。次の再生・シークから使われる
+    pub fn set_decode_ahead(&mut self, frames: usize) -> Result<(), String> {
+        if frames > MAX_DECODE_AHEAD {
+            return Err(trf("The decode-ahead must be between 0 and {} frames ({})", &[&MAX_DECODE_AHEAD, &frames]));
+        }
+        self.decode_ahead = frames;
+        self.settings.set_value("decode_ahead", frames);
+        Ok(())
+    }
+    
+    pub fn get_decode_ahead(&self) -> usize {
+        self.decode_ahead
+    }
+    
+    /// 各フレームの表示時刻（showinfo）を調べて、計算した再生位置と比べられるようにする（デバッグ用。保存しない）
+    /// 再生中なら現在位置からデコードし直す
+    pub fn set_pts_debug(&mut self, enabled: bool) {
+        if self.pts_debug == enabled {
+            return;
+        }
+        self.pts_debug = enabled;
+        *self.frame_pts.lock().unwrap() = None;
+        self.restart_from_current();
+    }
+    
+    /// 最後に表示したフレームの（表示時刻, そのときの再生位置）。確認が無効か、まだ届いていなければNone
+    pub fn get_frame_pts(&self) -> Option<(f32, f32)> {
+        self.frame_pts.lock().unwrap().filter(|_| self.pts_debug)
+    }
+    
+    /// ffmpegのログを残す詳しさ（次に起動するffmpegから反映される）
+    pub fn set_ffmpeg_log_level(&mut self, level: ffmpeg_log::LogLevel) {
+        ffmpeg_log::set_level(level);
+        self.settings.set_value("ffmpeg_log_level", level.code());
+    }
+    
+    pub fn get_ffmpeg_log_level(&self) -> ffmpeg_log::LogLevel {
+        ffmpeg_log::level()
+    }
+    
+    // 連番の画像の1枚なら、連番全体のパターンにする（設定で無効にしていれば、その1枚を静止画として開く）
+    fn resolve_image_sequence(&self, path: PathBuf) -> PathBuf {
+        if !self.image_sequence {
+            return path;
+        }
+        match image_sequence::detect(&path) {
+            Some(sequence) => {
+                image_sequence::register(&sequence, self.image_sequence_fps);
+                println!("{}", trf("Opening as an image sequence: {} ({} images, {}fps)", &[&sequence.pattern.display(), &sequence.count, &self.image_sequence_fps]));
+                sequence.pattern
+            }
+            None => path,
+        }
+    }
+    
+    /// 表示する縦横比（幅 / 高さ）。縦横比を指定していればその値、なければ回転後の動画の比率
+    /// 動画を読み込んでいなければNone
+    pub fn get_video_aspect(&self) -> Option<f32> {
+        if self.video_path.is_none() || self.video_height == 0 {
+            return None;
+        }
+        let (width, height) = self.output_size();
+        Some(self.adjustments.aspect.ratio().unwrap_or(width as f32 / height as f32))
+    }
+    
+    // デコードしたフレームの大きさ（90度・270度の回転では幅と高さが入れ替わる）
+    fn output_size(&self) -> (u32, u32) {
+        if self.adjustments.swaps_dimensions() {
+            (self.video_height, self.video_width)
+        } else {
+            (self.video_width, self.video_height)
+        }
+    }
+    
+    /// UIを更新するタイマーの間隔（10〜60fps相当に制限）
+    pub fn ui_refresh_interval(&self) -> Duration {
+        let rate = match self.ui_refresh_rate {
+            // 動画を読み込んでいなければ既定値、速度を変えていればその分だけ速く
+            0 if self.fps > 0.0 => self.fps * self.playback_speed.max(1.0),
+            0 => DEFAULT_UI_REFRESH_RATE as f32,
+            rate => rate as f32,
+        };
+        Duration::from_secs_f32(1.0 / rate.clamp(MIN_UI_REFRESH_RATE, MAX_UI_REFRESH_RATE))
+    }
+    
+    /// 逆再生中に音声も逆向きに再生するか
+    pub fn set_reverse_audio(&mut self, enabled: bool) {
+        self.reverse_audio = enabled;
+        if self.reverse && self.is_playing() {
+            let _ = self.start_audio_playback();
+        }
+    }
+    
+    // 表示サイズへの縮小とRGBへの変換（ピクセルの縦横比は大きさに織り込み済みなので、出力は正方形のピクセルにする）
+    // 色空間を指定しないとswscaleはBT.601として変換するため、HD（BT.709）の色が少しずれる
+    // HDRの動画はトーンマッピングでBT.709（リミテッドレンジ）に変換してから縮小する
+    // outputはscaleの出力の色の指定（NV12_SCALE_OUTPUTなど。RGBAで取り出すなら空）
+    fn scale_filter(width: u32, height: u32, color: &ColorInfo, color_correction: bool, tone_mapping: ToneMapping, output: &str) -> String {
+        if tone_mapping != ToneMapping::Off && color.hdr_kind().is_some() && zscale_available() {
+            format!(
+                "zscale=t=linear:npl=100,format=gbrpf32le,zscale=p=bt709,tonemap={}:desat=0,zscale=t=bt709:m=bt709:r=tv,format=yuv420p,scale={}:{}:in_color_matrix=bt709:in_range=limited{}",
+                tone_mapping.name(), width, height, output
+            ) + ",setsar=1"
+        } else if color_correction {
+            let range = if color.full_range { "full" } else { "limited" };
+            format!("scale={}:{}:in_color_matrix={}:in_range={}{},setsar=1", width, height, color.matrix, range, output)
+        } else {
+            format!("scale={}:{}{},setsar=1", width, height, output)
+        }
+    }
+    
+    // 動画情報を取得する前に最初のフレームを取り出すための縮小フィルター
+    // 大きさはfit_render_size・calculate_scaled_sizeと同じ規則（SARで横幅を伸縮 → 最大サイズに収まるよう縮小 → 偶数）をffmpegの式で書く
+    fn provisional_scale_filter((max_width, max_height): (u32, u32), raw_pixels: bool, color_correction: bool) -> String {
+        let (max_width, max_height) = (max_width.min(MAX_FRAME_DIMENSION), max_height.min(MAX_FRAME_DIMENSION));
+        let width = if raw_pixels { "iw" } else { "round(iw*sar)" };
+        let ratio = format!("min(1,min({}/{},{}/ih))", max_width, width, max_height);
+        let mut filter = format!(
+            "scale=w='max(2,trunc({}*{}/2)*2)':h='max(2,trunc(ih*{}/2)*2)'",
+            width, ratio, ratio
+        );
+        if color_correction {
+            filter.push_str(":in_color_matrix=auto");
+        }
+        filter + ",setsar=1"
+    }
+    
+    // provisional_scale_filterで取り出したフレームの色が、動画情報から作ったscale_filterと同じになるか
+    fn provisional_frame_usable(color: &ColorInfo, color_correction: bool, tone_mapping: ToneMapping) -> bool {
+        let tone_mapped = tone_mapping != ToneMapping::Off && color.hdr_kind().is_some() && zscale_available();
+        !tone_mapped && (!color_correction || color.tagged)
+    }
+    
+    // 画質調整のフィルターチェーン（再生・静止画で共通）
+    // ノイズ除去もシャープも表示サイズに縮小してから掛ける（元の解像度で処理すると重く、
+    // シャープは最後に掛けないと縮小でぼやけてしまう）
+    fn build_image_filters(&self) -> String {
+        self.image_filters("")
+    }
+
+    fn image_filters(&self, scale_output: &str) -> String {
+        let mut filters = vec![Self::scale_filter(self.video_width, self.video_height, &self.video_color, self.color_correction, self.tone_mapping, scale_output)];
+        if self.denoise && self.denoise_strength > 0.0 {
+            filters.push(format!("hqdn3d={:.1}", self.denoise_strength));
+        }
+        if self.sharpen && self.sharpen_strength > 0.0 {
+            filters.push(format!("unsharp=5:5:{:.2}", self.sharpen_strength));
+        }
+        filters.extend(self.adjustments.filters());
+        filters.join(",")
+    }
+    
+    // 再生用の映像フィルターチェーンを組み立てる（画質調整の後に速度を変えて補間する）
+    // NV12で受け取るときは、動画の色空間によらずto_rgba・on_raw_frameの前提の色に変換させる
+    fn build_video_filters(&self) -> String {
+        let scale_output = if self.frame_format == FrameFormat::Nv12 { NV12_SCALE_OUTPUT } else { "" };
+        let mut filters = vec![self.image_filters(scale_output)];
+        
+        if self.playback_speed != 1.0 {
+            filters.push(format!("setpts=PTS/{}", self.playback_speed));
+            // 補間は引き延ばした後のタイムスタンプに対して出力fpsぶんのフレームを作る
+            if self.playback_speed < 1.0 {
+                filters.extend(self.slow_motion_smoothing.filter(self.fps));
+            }
+        }
+        
+        filters.join(",")
+    }
+    
+    // 再生スレッドに渡す共有状態と設定をまとめる（generationはそのスレッドの再生世代）
+    fn playback_context(&self, generation: u64) -> PlaybackContext {
+        PlaybackContext {
+            generation,
+            state: Arc::clone(&self.state),
+            observers: self.observers.clone(),
+            playback_generation: Arc::clone(&self.playback_generation),
+            decoders: Arc::clone(&self.decoders),
+            current_time: Arc::clone(&self.current_time),
+            buffered_time: Arc::clone(&self.buffered_time),
+            current_frame: Arc::clone(&self.current_frame),
+            frame_history: Arc::clone(&self.frame_history),
+            frame_wait_started: Arc::clone(&self.frame_wait_started),
+            // 表示時刻の確認が有効なときだけshowinfoを通す（標準エラーの解析が増えるため）
+            frame_pts: self.pts_debug.then(|| Arc::clone(&self.frame_pts)),
+            playback_error: Arc::clone(&self.playback_error),
+            video_finished: Arc::clone(&self.video_finished),
+            reverse_finished: Arc::clone(&self.reverse_finished),
+            seamless_loop: Arc::clone(&self.seamless_loop),
+            loop_seam: Arc::clone(&self.loop_seam),
+            duration: self.duration,
+            speed: self.playback_speed,
+            decode_ahead: self.decode_ahead,
+            scan_mode: self.scan_mode,
+        }
+    }
+    
+    // 再生世代を進め、古い世代のffmpegを止める（フレームの読み込みで待たされているスレッドもすぐに抜けられる）
+    fn advance_playback_generation(&self) -> u64 {
+        let mut generation = self.playback_generation.lock().unwrap();
+        *generation += 1;
+        let new_generation = *generation;
+        let stale = std::mem::take(&mut *self.decoders.lock().unwrap());
+        drop(generation);
+        *self.frame_history.lock().unwrap() = FrameHistory { generation: new_generation, frames: VecDeque::new() };
+        self.current_frame.lock().unwrap().flush();
+        for (_, mut child) in stale {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        new_generation
+    }
+    
+    // 再生スレッドのffmpegを登録する（既に新しい世代が始まっていれば登録せずに止める）
+    fn register_decoder(decoders: &Mutex<Vec<(u64, Child)>>, playback_generation: &Mutex<u64>, my_generation: u64, mut child: Child) {
+        let generation = playback_generation.lock().unwrap();
+        if *generation == my_generation {
+            decoders.lock().unwrap().push((my_generation, child));
+        } else {
+            drop(generation);
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+    
+    // 自分の世代のffmpegを登録から外す（既にメインスレッドが止めていればNone）
+    fn unregister_decoder(decoders: &Mutex<Vec<(u64, Child)>>, my_generation: u64) -> Option<Child> {
+        let mut decoders = decoders.lock().unwrap();
+        let index = decoders.iter().position(|(generation, _)| *generation == my_generation)?;
+        Some(decoders.swap_remove(index).1)
+    }
+    
+    // 自分の世代のffmpegを止める
+    fn stop_decoder(decoders: &Mutex<Vec<(u64, Child)>>, my_generation: u64) {
+        if let Some(mut child) = Self::unregister_decoder(decoders, my_generation) {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+    
+    // 自分の世代のffmpegの終了を待ち、正常に終了したか返す（止められていた場合はfalse）
+    fn wait_decoder(decoders: &Mutex<Vec<(u64, Child)>>, my_generation: u64) -> bool {
+        Self::unregister_decoder(decoders, my_generation)
+            .is_some_and(|mut child| matches!(child.wait(), Ok(status) if status.success()))
+    }
+
+    fn spawn_decoder(options: &DecoderOptions, start_position: f32) -> Result<Decoder, String> {
+        let DecoderOptions { ref path, fps, ref video_filters, seek_mode, frame_timestamps, pts_debug, ref hwaccel, frame_format } = *options;
+        // ffmpegでrawvideo形式でフレームを出力（形式はframe_format。既定はRGBA）
+        // -r で出力fps固定、自前でフレームタイミングを制御
+        // frame_timestampsなら元のフレームをそのまま出力し、showinfoで各フレームの表示時刻を標準エラーに出す
+        // pts_debugなら-rの代わりにfpsフィルターで揃えてからshowinfoを通す（出力する各フレームの表示時刻がわかる）
+        // ハードウェアデコードは入力の前に指定する（フレームはフィルターの前にメモリへ戻される）
+        let mut command = ffmpeg_command("ffmpeg");
+        command.args(ffmpeg_log::decoder_args());
+        if let Some(hwaccel) = hwaccel {
+            command.args(["-hwaccel", hwaccel.as_str()]);
+        }
+        command.args(seek_mode.input_args(path, start_position));
+        if frame_timestamps {
+            command.args(["-vf", &format!("{},showinfo", video_filters), "-fps_mode", "passthrough"]);
+        } else if pts_debug {
+            command.args(["-vf", &format!("{},fps={},showinfo", video_filters, fps), "-fps_mode", "passthrough"]);
+        } else {
+            command.args(["-vf", video_filters.as_str(), "-r", &fps.to_string()]);
+        }
+        command.args(["-f", "rawvideo", "-pix_fmt", frame_format.pix_fmt(), "-"]);
+        
+        let mut child = command
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| trf("Failed to start ffmpeg: {}", &[&e]))?;
+        
+        // 標準エラーは別スレッドで読み切り、出力ストリームのサイズとshowinfoの行のpts_timeを送る
+        // （受け取る側が先に終わっても、ffmpegが書き込みで詰まらないよう最後まで読む）
+        // それ以外の行はログを残す設定なら残し、最後に出たエラーの行をスレッドの結果にする
+        let stderr = child.stderr.take().unwrap();
+        let (size_sender, output_size) = mpsc::channel();
+        let (pts_sender, pts_receiver) = mpsc::channel();
+        let send_pts = frame_timestamps || pts_debug;
+        let log = ffmpeg_log::ProcessLog::new(thread::spawn(move || {
+            let mut in_output = false;
+            let mut last_error = None;
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                let (severity, line) = ffmpeg_log::strip_level(&line);
+                if line.starts_with("Output #") {
+                    in_output = true;
+                } else if let Some(size) = Self::parse_stream_size(&line).filter(|_| in_output) {
+                    let _ = size_sender.send(size);
+                    in_output = false;
+                } else if let Some(pts) = Self::parse_showinfo_pts(&line).filter(|_| send_pts) {
+                    let _ = pts_sender.send(pts);
+                } else if ffmpeg_log::record("ffmpeg", severity, &line) {
+                    last_error = Some(line);
+                }
+            }
+            last_error
+        }));
+        let (timestamps, debug_pts) = if frame_timestamps {
+            (Some(pts_receiver), None)
+        } else {
+            (None, pts_debug.then_some(pts_receiver))
+        };
+        Ok(Decoder {
+            child,
+            timestamps,
+            debug_pts,
+            output_size,
+            log,
+        })
+    }
+    
+    // 出力ストリームの行（"Stream #0:0: Video: rawvideo (RGBA / 0x41424752), rgba, 1280x720, ..."）から幅と高さを取り出す
+    fn parse_stream_size(line: &str) -> Option<(u32, u32)> {
+        let info = line.trim_start().strip_prefix("Stream #")?.split("Video:").nth(1)?;
+        info.split([',', ' ', '[', ']', '(', ')']).find_map(|token| {
+            let (width, height) = token.split_once('x')?;
+            let size = (width.parse::<u32>().ok()?, height.parse::<u32>().ok()?);
+            (size.0 > 0 && size.1 > 0).then_some(size)
+        })
+    }
+    
+    // showinfoから届く次のフレームの表示時刻（元の動画での秒。min_posより前のものは読み飛ばし、届かなければNone）
+    fn next_frame_pts(receiver: &Receiver<f32>, pts_base: f32, speed: f32, min_pos: f32) -> Option<f32> {
+        loop {
+            match receiver.recv_timeout(FRAME_TIMESTAMP_WAIT) {
+                Ok(pts) if pts_base + pts * speed >= min_pos => return Some(pts_base + pts * speed),
+                Ok(_) => continue,
+                Err(_) => return None,
+            }
+        }
+    }
+    
+    // ffmpegの出力を読み始める: 実際の大きさを確かめ、その大きさでフレームのバッファと先読みを作り直す
+    // 大きさとバッファは必ずここで一緒に変える（片方だけ変わると、フレームの区切りがずれて映像が崩れる）
+    // 前の出力の読み込みは捨てるので、前の大きさで先読みしたフレームが混ざることもない
+    fn attach_output(
+        stdout: ChildStdout,
+        output_size: &Receiver<(u32, u32)>,
+        expected: (u32, u32),
+        frame_format: FrameFormat,
+        decode_ahead: usize,
+        frame_buffer: &mut Vec<u8>,
+    ) -> ((u32, u32), FrameReader) {
+        let (width, height) = Self::confirm_output_size(output_size, expected);
+        frame_buffer.clear();
+        frame_buffer.resize(frame_format.frame_size(width, height), 0);
+        let reader = FrameReader::spawn(stdout, frame_buffer.len(), FrameReader::depth(decode_ahead, frame_buffer.len()));
+        ((width, height), reader)
+    }
+    
+    fn confirm_output_size(output_size: &Receiver<(u32, u32)>, expected: (u32, u32)) -> (u32, u32) {
+        match output_size.recv_timeout(OUTPUT_SIZE_WAIT) {
+            Ok(actual) if Self::output_size_mismatch(expected, actual) => actual,
+            _ => expected,
+        }
+    }
+    
+    // 想定していたフレームのサイズと実際のサイズが違えば警告を出す（違っていればtrue）
+    fn output_size_mismatch(expected: (u32, u32), actual: (u32, u32)) -> bool {
+        if expected == actual {
+            return false;
+        }
+        println!(
+            "{}",
+            trf("Warning: the ffmpeg output size differs from the expected size, adjusting (expected: {}x{}, actual: {}x{})", &[&expected.0, &expected.1, &actual.0, &actual.1])
+        );
+        true
+    }
+    
+    // showinfoの1フレーム分の行（"n:   0 pts:  0 pts_time:0 ..."）から表示時刻を取り出す
+    fn parse_showinfo_pts(line: &str) -> Option<f32> {
+        if !line.contains("Parsed_showinfo") || !line.contains(" n:") {
+            return None;
+        }
+        let value = line.split("pts_time:").nth(1)?.split_whitespace().next()?;
+        value.parse::<f32>().ok()
+    }
+
+    // 1フレーム分を読み込む（途中でEOFに達した場合はfalse）
+    fn read_frame(stdout: &mut impl Read, frame_buffer: &mut [u8]) -> std::io::Result<bool> {
+        let mut pos = 0;
+        while pos < frame_buffer.len() {
+            match stdout.read(&mut frame_buffer[pos..])? {
+                0 => return Ok(false),
+                n => pos += n,
+            }
+        }
+        Ok(true)
+    }
+
+    pub fn pause(&mut self) {
+        // 再生スレッドは生かしたまま待機させる（再生中でなければ何もしない）
+        if !self.is_playing() {
+            return;
+        }
+        self.set_state(PlaybackState::Paused);
+        self.stop_audio();
+        let current = *self.current_time.lock().unwrap();
+        println!("{}", trf("Paused (position: {}s)", &[&format!("{:.2}", current)]));
+    }
+
+    pub fn stop(&mut self) {
+        self.end_scrub();
+        self.set_state(PlaybackState::Stopped);
+        self.frame_history.lock().unwrap().frames.clear();
+        *self.video_finished.lock().unwrap() = false;
+        *self.current_time.lock().unwrap() = 0.0;
+        *self.buffered_time.lock().unwrap() = 0.0;
+        self.stop_audio();
+        
+        // 最初のフレームを再読み込み
+        if let Some(path) = self.video_path.clone() {
+            let _ = self.load_first_frame(&path);
+        }
+        
+        println!("{}", tr("Stopped"));
+    }
+
+    pub fn seek(&mut self, time: f32) {
+        // ライブ配信などシークできない入力では何もしない（シークバーも無効にしている）
+        if !self.seekable {
+            println!("{}", trf("This input is not seekable ({}s)", &[&format!("{:.2}", time)]));
+            return;
+        }
+        self.playback_failure = None;
+        
+        // ドラッグ中のプレビューは終わりにする（ドラッグ前に再生していれば再生に戻す）
+        let resume = self.end_scrub();
+        let previous_state = if resume { PlaybackState::Playing } else { self.get_state() };
+        
+        // 世代番号をインクリメント（古いスレッドを無効化）
+        self.advance_playback_generation();
+        
+        // 現在の再生を停止（音声も含む）
+        self.set_state(PlaybackState::Stopped);
+        self.stop_audio();
+        
+        // シーク時刻を設定
+        *self.current_time.lock().unwrap() = time;
+        *self.buffered_time.lock().unwrap() = time;
+        *self.seek_time.lock().unwrap() = Some(time);
+        
+        println!("{}", trf("Seek: {}s", &[&time]));
+        
+        // 指定された位置のフレームを非同期で読み込む
+        // （連続してシークしてもスレッドは1本だけで、まだ処理していない古い依頼は最新の依頼で上書きする）
+        if let Some(path) = self.video_path.clone() {
+            *self.frame_request.lock().unwrap() = Some(FrameRequest {
+                path,
+                time,
+                width: self.output_size().0,
+                height: self.output_size().1,
+                image_filters: self.build_image_filters(),
+                seek_mode: self.effective_seek_mode(),
+            });
+            
+            let mut running = self.frame_loader_running.lock().unwrap();
+            if !*running {
+                *running = true;
+                let frame_request = Arc::clone(&self.frame_request);
+                let frame_loader_running = Arc::clone(&self.frame_loader_running);
+                let current_frame = Arc::clone(&self.current_frame);
+                self.spawn_worker(move || loop {
+                    // 依頼がなくなったら終了（runningを下ろすのと依頼の確認を同じロックの中で行う）
+                    let request = {
+                        let mut running = frame_loader_running.lock().unwrap();
+                        match frame_request.lock().unwrap().take() {
+                            Some(request) => request,
+                            None => {
+                                *running = false;
+                                break;
+                            }
+                        }
+                    };
+                    Self::load_frame_at_time_async(&request.path, request.time, Arc::clone(&current_frame), request.width, request.height, &request.image_filters, request.seek_mode);
+                });
+            }
+        }
+        
+        match previous_state {
+            // 再生中だった場合は、シーク位置から即座に再生を再開
+            PlaybackState::Playing => {
+                let _ = self.play();
+            }
+            // 一時停止中だった場合は、シーク位置で一時停止したまま待つ（表示は上で読み込んだフレーム）
+            // 逆再生は再開時に区間を切り出し直すので、一時停止の状態だけを残す
+            PlaybackState::Paused if self.reverse => self.set_state(PlaybackState::Paused),
+            PlaybackState::Paused => {
+                let _ = self.start_playback(true);
+            }
+            PlaybackState::Stopped => {}
+        }
+    }
+    
+    /// シークバーのドラッグ中: その位置のフレームを表示するだけで、シークは離したとき（seek）に行う
+    /// ドラッグ中は再生を一時停止し、プレビューの抽出は古い依頼を捨てながら一定の間隔で行う
+    pub fn scrub(&mut self, time: f32) {
+        let Some(path) = self.video_path.clone().filter(|_| self.seekable) else {
+            return;
+        };
+        if self.scrub_time.is_none() {
+            self.resume_after_scrub = self.is_playing();
+            self.pause();
+            self.scrub_preview.lock().unwrap().slow = false;
+        }
+        self.scrub_time = Some(time);
+        self.scrub_updated_at = Instant::now();
+        
+        let mut preview = self.scrub_preview.lock().unwrap();
+        if preview.slow {
+            return;
+        }
+        preview.generation += 1;
+        // 速さを優先してキーフレームへのシークで抽出する
+        preview.request = Some((preview.generation, FrameRequest {
+            path,
+            time,
+            width: self.output_size().0,
+            height: self.output_size().1,
+            image_filters: self.build_image_filters(),
+            seek_mode: SeekMode::Fast,
+        }));
+        if preview.loader_running {
+            return;
+        }
+        preview.loader_running = true;
+        drop(preview);
+        
+        let scrub_preview = Arc::clone(&self.scrub_preview);
+        let current_frame = Arc::clone(&self.current_frame);
+        self.spawn_worker(move || loop {
+            // 依頼がなくなったら終了（途中の依頼は上書きされているので、最新のものだけを抽出する）
+            let (generation, request) = {
+                let mut preview = scrub_preview.lock().unwrap();
+                match preview.request.take() {
+                    Some(request) => request,
+                    None => {
+                        preview.loader_running = false;
+                        break;
+                    }
+                }
+            };
+            let started = Instant::now();
+            let frame = Self::extract_frame_at(&request.path, request.time, request.width, request.height, &request.image_filters, request.seek_mode);
+            let elapsed = started.elapsed();
+            
+            let mut preview = scrub_preview.lock().unwrap();
+            if elapsed > SCRUB_SLOW_EXTRACTION && !preview.slow {
+                preview.slow = true;
+                println!("{}", trf("Extracting previews is slow ({}ms), so the preview while dragging is turned off", &[&elapsed.as_millis()]));
+            }
+            // ドラッグが終わっていたり、新しい依頼が来ていれば表示しない
+            if preview.generation == generation {
+                if let Some(frame) = frame {
+                    current_frame.lock().unwrap().replace(frame);
+                }
+            }
+            drop(preview);
+            if let Some(rest) = SCRUB_PREVIEW_INTERVAL.checked_sub(elapsed) {
+                thread::sleep(rest);
+            }
+        });
+    }
+    
+    /// ドラッグ中の位置（ドラッグしていなければNone）
+    pub fn get_scrub_time(&self) -> Option<f32> {
+        self.scrub_time
+    }
+    
+    /// 早送り・巻き戻しを進める（タイマーから呼ぶ。directionは押しているボタン: 1で早送り、-1で巻き戻し、0で離した）
+    /// 押している間は一時停止して（音声も止まる）ドラッグ中と同じようにプレビューし、離したらその位置にシークして元の再生状態に戻す
+    /// 位置が変わったらtrue
+    pub fn update_trickplay(&mut self, direction: i32) -> bool {
+        if direction == 0 {
+            let Some(trickplay) = self.trickplay.take() else {
+                return false;
+            };
+            println!("{}", trf("Fast forward/rewind ended: {}s", &[&format!("{:.2}", trickplay.position)]));
+            self.seek(trickplay.position);
+            return true;
+        }
+        
+        let forward = direction > 0;
+        if self.trickplay.as_ref().is_none_or(|trickplay| trickplay.forward != forward) {
+            if self.video_path.is_none() || !self.seekable {
+                return false;
+            }
+            let position = self.scrub_time.unwrap_or_else(|| self.get_current_time());
+            self.trickplay = Some(Trickplay {
+                forward,
+                started: Instant::now(),
+                stepped: Instant::now(),
+                position,
+            });
+            println!("{}", if forward { tr("Fast forward started") } else { tr("Rewind started") });
+            self.scrub(position);
+            return true;
+        }
+        
+        let speed = self.get_trickplay_speed();
+        let Some(trickplay) = self.trickplay.as_mut() else {
+            return false;
+        };
+        let elapsed = trickplay.stepped.elapsed();
+        if elapsed < TRICKPLAY_STEP {
+            return false;
+        }
+        trickplay.stepped = Instant::now();
+        let position = (trickplay.position + elapsed.as_secs_f32() * speed).clamp(0.0, self.duration);
+        if position == trickplay.position {
+            // 先頭・終わりに着いたら止めておく（押している間はドラッグの確定と同じシークをさせない）
+            self.scrub_updated_at = Instant::now();
+            return false;
+        }
+        trickplay.position = position;
+        self.scrub(position);
+        true
+    }
+    
+    /// 早送り・巻き戻しの速さ（巻き戻しは負。していなければ0）
+    pub fn get_trickplay_speed(&self) -> f32 {
+        let Some(trickplay) = &self.trickplay else {
+            return 0.0;
+        };
+        let level = (trickplay.started.elapsed().as_secs_f32() / TRICKPLAY_SPEED_UP.as_secs_f32()) as usize;
+        let speed = TRICKPLAY_SPEEDS[level.min(TRICKPLAY_SPEEDS.len() - 1)];
+        if trickplay.forward { speed } else { -speed }
+    }
+    
+    /// 定期的に呼び出す: ドラッグの終わりが届かないまま位置が変わらなくなっていればシークする
+    pub fn update_scrub(&mut self) {
+        if let Some(time) = self.scrub_time {
+            if self.scrub_updated_at.elapsed() >= SCRUB_COMMIT_IDLE {
+                self.seek(time);
+            }
+        }
+    }
+    
+    // ドラッグ中のプレビューを終える（まだ抽出中のものは表示しない）。ドラッグ前に再生していたらtrue
+    fn end_scrub(&mut self) -> bool {
+        if self.scrub_time.take().is_none() {
+            return false;
+        }
+        let mut preview = self.scrub_preview.lock().unwrap();
+        preview.generation += 1;
+        preview.request = None;
+        std::mem::take(&mut self.resume_after_scrub)
+    }
+    
+    fn load_frame_at_time_async(
+        path: &PathBuf,
+        time: f32,
+        current_frame: Arc<Mutex<FrameQueue>>,
+        width: u32,
+        height: u32,
+        image_filters: &str,
+        seek_mode: SeekMode,
+    ) {
+        if let Some(rgba_image) = Self::extract_frame_at(path, time, width, height, image_filters, seek_mode) {
+            current_frame.lock().unwrap().replace(rgba_image);
+        }
+    }
+    
+    // 指定された時刻のフレームを1枚抽出する（大きさが合わなければNone）
+    fn extract_frame_at(
+        path: &PathBuf,
+        time: f32,
+        width: u32,
+        height: u32,
+        image_filters: &str,
+        seek_mode: SeekMode,
+    ) -> Option<RgbaImage> {
+        // 指定された時刻のフレームを抽出（-ssの位置はシーク方法に従う）
+        let output = ffmpeg_log::configure(ffmpeg_command("ffmpeg")
+            .args(seek_mode.input_args(path, time))
+            .args(&[
+                "-vf", image_filters,
+                "-vframes", "1",
+                "-f", "rawvideo",
+                "-pix_fmt", "rgba",
+                "-"
+            ]))
+            .stdout(Stdio::piped())
+            .output();
+        if let Ok(output) = &output {
+            ffmpeg_log::record_output("ffmpeg", &output.stderr);
+        }
+
+        match output {
+            Ok(output) if output.status.success() && !output.stdout.is_empty() => {
+                // 1フレームだけなので、大きさが違えば長さも合わない（違う大きさで並べると崩れるので使わない）
+                let frame_size = FrameFormat::Rgba.frame_size(width, height);
+                if output.stdout.len() == frame_size {
+                    return RgbaImage::from_raw(width, height, output.stdout);
+                }
+                println!("{}", trf("Warning: the extracted frame is not the expected size ({}x{}), so it is not used", &[&width, &height]));
+                None
+            }
+            Err(e) => {
+                println!("{}", trf("Frame read error: {}", &[&e]));
+                None
+            }
+            _ => None,
+        }
+    }
+
+    pub fn get_current_time(&self) -> f32 {
+        *self.current_time.lock().unwrap()
+    }
+    
+    /// デコード済み（先読み済み）の位置。長さが不明な動画では0
+    pub fn get_buffered_time(&self) -> f32 {
+        if self.duration <= 0.0 {
+            return 0.0;
+        }
+        self.buffered_time.lock().unwrap().max(self.get_current_time())
+    }
+    
+    /// 可変フレームレートの動画か（fpsは平均値なので、フレーム単位の表示は近似になる）
+    pub fn is_variable_frame_rate(&self) -> bool {
+        self.variable_frame_rate
+    }
+
+    /// 現在の再生状態
+    pub fn get_state(&self) -> PlaybackState {
+        *self.state.lock().unwrap()
+    }
+    
+    fn set_state(&self, state: PlaybackState) {
+        Self::change_state(&self.state, &self.observers, state);
+    }
+    
+    // 再生状態を変更し、変わった場合だけ通知する（ロックを外してからコールバックを呼ぶ）
+    fn change_state(state: &Mutex<PlaybackState>, observers: &Observers, new_state: PlaybackState) {
+        let changed = std::mem::replace(&mut *state.lock().unwrap(), new_state) != new_state;
+        if changed {
+            observers.notify_state(new_state);
+        }
+    }
+    
+    /// 再生状態が変わったときに呼ばれるコールバックを登録する
+    ///
+    /// コールバックは再生スレッドやメインスレッドから呼ばれる（UIの操作はイベントループに渡すこと）。
+    /// メインスレッドからはVideoPlayerのロック中に呼ばれるので、コールバックの中でVideoPlayerをロックしないこと
+    pub fn on_state_changed(&self, callback: impl Fn(PlaybackState) + Send + Sync + 'static) {
+        self.observers.state.lock().unwrap().push(Arc::new(callback));
+    }
+    
+    /// 再生位置（秒）が進むたびに再生スレッドから呼ばれるコールバックを登録する（注意点はon_state_changedと同じ）
+    pub fn on_position(&self, callback: impl Fn(f32) + Send + Sync + 'static) {
+        self.observers.position.lock().unwrap().push(Arc::new(callback));
+    }
+    
+    /// 新しいフレームを表示するたびに再生スレッドから呼ばれるコールバックを登録する
+    /// 1フレームごとに呼ばれるので、重い処理は別スレッドに回すこと
+    pub fn on_frame(&self, callback: impl Fn(&RgbaImage) + Send + Sync + 'static) {
+        self.observers.frame.lock().unwrap().push(Arc::new(callback));
+    }
+    
+    /// 再生中のフレームをffmpegから読み込んだ形式（frame_format）のまま受け取るコールバックを登録する
+    /// 引数はデータ・幅・高さ・形式（NV12は動画の色空間によらずBT.601・リミテッドレンジ）。逆再生・シーク・コマ送りで表示するフレームでは呼ばれない
+    pub fn on_raw_frame(&self, callback: impl Fn(&[u8], u32, u32, FrameFormat) + Send + Sync + 'static) {
+        self.observers.raw_frame.lock().unwrap().push(Arc::new(callback));
+    }
+    
+    /// 再生中にffmpegから受け取るフレームの形式を変える（再生中ならその位置から読み込み直す）
+    pub fn set_frame_format(&mut self, format: FrameFormat) {
+        if self.frame_format == format {
+            return;
+        }
+        self.frame_format = format;
+        if self.is_playing() || self.is_paused() {
+            self.restart_from_current();
+        }
+    }
+    
+    pub fn get_frame_format(&self) -> FrameFormat {
+        self.frame_format
+    }
+    
+    /// 再生中のフレームを表示用（take_frame・コマ戻しの履歴）にRGBAへ変換するか（既定はtrue）
+    /// falseにしてon_frameを登録しなければ、on_raw_frameに渡すだけでRGBAへの変換を省く
+    pub fn set_display_frames(&self, enabled: bool) {
+        self.observers.no_display.store(!enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.get_state() == PlaybackState::Playing
+    }
+    
+    /// フレームの到着が遅れているか（低速なディスクやネットワーク上のファイル）
+    /// 一時停止中・終端に達した後は読み込みを待っていないのでfalse
+    pub fn is_buffering(&self) -> bool {
+        if !self.is_playing() {
+            return false;
+        }
+        let current_generation = *self.playback_generation.lock().unwrap();
+        match *self.frame_wait_started.lock().unwrap() {
+            Some((generation, started)) => generation == current_generation && started.elapsed() >= BUFFERING_THRESHOLD,
+            None => false,
+        }
+    }
+    
+    pub fn is_paused(&self) -> bool {
+        self.get_state() == PlaybackState::Paused
+    }
+    
+    pub fn is_video_finished(&self) -> bool {
+        *self.video_finished.lock().unwrap()
+    }
+    
+    pub fn clear_video_finished(&mut self) {
+        *self.video_finished.lock().unwrap() = false;
+    }
+
+    /// 再生中に発生した回復できないエラーを一度だけ取り出す
+    /// 取り出したエラーは、シーク・再生・読み込みに成功するまでget_playback_failureでも返す
+    pub fn take_playback_error(&mut self) -> Option<String> {
+        let error = self.playback_error.lock().unwrap().take();
+        if error.is_some() {
+            self.playback_failure = error.clone();
+        }
+        error
+    }
+    
+    /// 再生が止まったままになっているエラー（映像の上に表示する）
+    pub fn get_playback_failure(&self) -> Option<&str> {
+        self.playback_failure.as_deref()
+    }
+
+    /// 表示中のフレームのRGBAの生データと幅・高さをfに渡す（コピーしない。フレームがあればtrue）
+    /// fを実行している間はフレームをロックしたままで、デコードが待たされるので、短い処理にすること
+    pub fn with_current_frame<F: FnOnce(&[u8], u32, u32)>(&self, f: F) -> bool {
+        let queue = self.current_frame.lock().unwrap();
+        match queue.current() {
+            Some(frame) => {
+                f(frame.as_raw(), frame.width(), frame.height());
+                true
+            }
+            None => false,
+        }
+    }
+    
+    /// 前回から新しいフレームが届いていれば、一番新しいものを取り出す（UIに表示する）
+    pub fn take_frame(&mut self) -> Option<RgbaImage> {
+        self.current_frame.lock().unwrap().take_latest()
+    }
+    
+    /// 表示されずに捨てたフレームの数（UIの更新が追いつかなかった分を含む）
+    pub fn get_dropped_frames(&self) -> u64 {
+        self.current_frame.lock().unwrap().dropped
+    }
+    
+    /// 音声を再生できるか（ffplayが見つからなければfalse）
+    pub fn is_audio_available(&self) -> bool {
+        self.audio_available
+    }
+    
+    /// 音量を設定する（VOLUME_RAMP_DURATIONで滑らかに変える）
+    pub fn set_volume(&mut self, volume: f32) {
+        self.ramp_volume(volume, VOLUME_RAMP_DURATION);
+    }
+    
+    /// 音量をdurationかけて変える（0への消音も同じく徐々に下げる）
+    /// キーの連打やスライダー操作で音声を何度も再起動しないよう、少し待って最後の値だけを反映する
+    /// 変化の途中で変えた場合は、そのとき聞こえている大きさから新しい音量へ向かう
+    pub fn ramp_volume(&mut self, volume: f32, duration: Duration) {
+        *self.volume.lock().unwrap() = volume.clamp(0.0, 1.0);
+        println!("{}", trf("Volume: {}%", &[&((volume * 100.0) as i32)]));
+        self.volume_ramp = Some(duration);
+        self.audio_restart_at = Some(Instant::now() + Duration::from_millis(300));
+    }
+    
+    pub fn get_volume(&self) -> f32 {
+        *self.volume.lock().unwrap()
+    }
+    
+    /// 音量スライダー（0〜100%）に上乗せする倍率（1.0〜3.0）
+    /// スライダー操作中に音声を何度も再起動しないよう、少し待って最後の値だけを反映する（保存はsave_volume_boost）
+    pub fn set_volume_boost(&mut self, boost: f32) {
+        self.volume_boost = boost.clamp(1.0, MAX_VOLUME_BOOST);
+        self.audio_restart_at = Some(Instant::now() + Duration::from_millis(300));
+    }
+    
+    /// 音量ブーストをファイルごとに保存する（次回読み込み時に再適用。スライダーを離したときに呼ぶ）
+    pub fn save_volume_boost(&mut self) {
+        println!("{}", trf("Volume boost: {}%", &[&((self.volume_boost * 100.0).round() as i32)]));
+        if self.volume_boost > 1.0 {
+            println!("{}", tr("Warning: boosting above 100% can cause clipping"));
+        }
+        if let Some(path) = &self.video_path {
+            self.settings.set_file_value(path, "volume_boost", self.volume_boost);
+        }
+    }
+    
+    pub fn get_volume_boost(&self) -> f32 {
+        self.volume_boost
+    }
+    
+    /// 映像に対する音声のずれを補正（正の値で音声を遅らせ、負の値で早める）
+    /// スライダー操作中に音声を何度も再起動しないよう、少し待って最後の値だけを反映する（保存はsave_audio_offset）
+    pub fn set_audio_offset(&mut self, seconds: f32) {
+        self.audio_offset = seconds.clamp(-2.0, 2.0);
+        self.audio_restart_at = Some(Instant::now() + Duration::from_millis(300));
+    }
+    
+    /// 音声オフセットをファイルごとに保存する（スライダーを離したときに呼ぶ）
+    pub fn save_audio_offset(&mut self) {
+        println!("{}", trf("Audio offset: {}ms", &[&format!("{:+.0}", self.audio_offset * 1000.0)]));
+        if let Some(path) = &self.video_path {
+            self.settings.set_file_value(path, "audio_offset", self.audio_offset);
+        }
+    }
+    
+    pub fn get_audio_offset(&self) -> f32 {
+        self.audio_offset
+    }
+    
+    /// 音量の自動均一化（dynaudnorm）の切り替え
+    /// 2パスのloudnormは事前解析が必要なため、リアルタイムに反映できる1パスのdynaudnormを使用
+    pub fn set_normalize_loudness(&mut self, enabled: bool) {
+        self.normalize_loudness = enabled;
+        println!("{}", trf("Loudness normalization: {}", &[&if enabled { tr("On") } else { tr("Off") }]));
+        
+        // 再生中の場合は現在位置から音声を再起動
+        if self.is_playing() {
+            self.stop_audio();
+            let _ = self.start_audio_playback();
+        }
+    }
+    
+    /// 動画ごとの音量の揃え（統合ラウドネスを一度だけ測ってゲインを決める）の切り替え
+    /// 測ったゲインは音量スライダー・ブーストの前に掛けるので、手動の音量調整はそのまま効く
+    pub fn set_match_loudness(&mut self, enabled: bool) {
+        self.match_loudness = enabled;
+        self.settings.set_value("match_loudness", enabled);
+        println!("{}", trf("Match loudness across videos: {}", &[&if enabled { tr("On") } else { tr("Off") }]));
+        if enabled && !self.loudness_scan_started {
+            self.start_loudness_measurement();
+        }
+        if self.loudness_gain.is_some() && self.is_playing() {
+            self.stop_audio();
+            let _ = self.start_audio_playback();
+        }
+    }
+    
+    pub fn get_match_loudness(&self) -> bool {
+        self.match_loudness
+    }
+    
+    /// 音量の揃えの状態（無効なら空文字列）
+    pub fn get_loudness_status(&self) -> String {
+        if !self.match_loudness || self.video_path.is_none() {
+            return String::new();
+        }
+        match self.loudness_gain {
+            Some(gain) => trf("Loudness gain: {} dB", &[&format!("{:+.1}", gain)]),
+            None if self.loudness_measuring => tr("Measuring loudness...").to_string(),
+            None if self.loudness_scan_started => tr("Could not measure the loudness").to_string(),
+            None => String::new(),
+        }
+    }
+    
+    /// 測り終わったゲインを反映する（タイマーから呼ぶ）。再生中なら現在位置から音声を起動し直す
+    pub fn update_loudness_gain(&mut self) {
+        let Some(result) = self.pending_loudness_gain.lock().unwrap().take() else {
+            return;
+        };
+        self.loudness_measuring = false;
+        let Some(gain) = result else {
+            return;
+        };
+        self.loudness_gain = Some(gain);
+        println!("{}", trf("Loudness matching gain: {}dB", &[&format!("{:+.1}", gain)]));
+        if self.match_loudness && self.is_playing() {
+            self.stop_audio();
+            let _ = self.start_audio_playback();
+        }
+    }
+    
+    // 統合ラウドネスの測定をワーカースレッドで開始（結果はファイルの更新日時ごとにディスクキャッシュに残す）
+    fn start_loudness_measurement(&mut self) {
+        let Some(path) = self.video_path.clone() else {
+            return;
+        };
+        if self.audio_channels == 0 {
+            return;
+        }
+        self.loudness_scan_started = true;
+        self.loudness_measuring = true;
+        let generation = *self.loudness_generation.lock().unwrap();
+        let loudness_generation = Arc::clone(&self.loudness_generation);
+        let pending_loudness_gain = Arc::clone(&self.pending_loudness_gain);
+        let disk_cache = Arc::clone(&self.disk_cache);
+        
+        self.spawn_worker(move || {
+            let key = DiskCache::key(&path, "loudness", &TARGET_LOUDNESS.to_string());
+            let cached = key
+                .as_deref()
+                .and_then(|key| disk_cache.get(key))
+                .and_then(|data| String::from_utf8(data).ok())
+                .and_then(|text| text.parse::<f32>().ok());
+            let gain = cached.or_else(|| {
+                let Some(loudness) = Self::measure_loudness(&path) else {
+                    println!("{}", trf("Could not measure the loudness: {}", &[&path.display()]));
+                    return None;
+                };
+                let gain = (TARGET_LOUDNESS - loudness).clamp(-MAX_LOUDNESS_GAIN, MAX_LOUDNESS_GAIN);
+                if let Some(key) = &key {
+                    disk_cache.put(key, gain.to_string().as_bytes());
+                }
+                Some(gain)
+            });
+            if *loudness_generation.lock().unwrap() == generation {
+                *pending_loudness_gain.lock().unwrap() = Some(gain);
+            }
+        });
+    }
+    
+    // loudnormの解析（1パス目）の結果（stderrのJSON）から統合ラウドネス（input_i）を取り出す
+    // 無音のファイルは-infになるのでNone
+    fn measure_loudness(path: &Path) -> Option<f32> {
+        let output = ffmpeg_command("ffmpeg")
+            .args(&["-hide_banner", "-nostats", "-i"])
+            .arg(path)
+            .args(&[
+                "-vn",
+                "-af", &format!("loudnorm=I={}:print_format=json", TARGET_LOUDNESS),
+                "-f", "null",
+                "-",
+            ])
+            .stdout(Stdio::null())
+            .output()
+            .ok()?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let value = stderr.split("\"input_i\"").nth(1)?.split('"').nth(1)?;
+        value.parse::<f32>().ok().filter(|loudness| loudness.is_finite())
+    }
+    
+    /// イコライザーの各帯域のゲイン（dB）を設定する
+    /// スライダー操作中に音声を何度も再起動しないよう、少し待ってからまとめて反映する（保存も反映するときに行う）
+    pub fn set_equalizer(&mut self, bands: Vec<f32>) {
+        if bands.len() != equalizer::BANDS.len() {
+            return;
+        }
+        self.equalizer = bands.iter().map(|gain| gain.clamp(-equalizer::MAX_GAIN, equalizer::MAX_GAIN)).collect();
+        self.audio_restart_at = Some(Instant::now() + Duration::from_millis(300));
+    }
+    
+    // イコライザーのゲインを保存する（保存済みの値と同じなら書き込まない）
+    fn save_equalizer(&mut self) {
+        let value = self.equalizer.iter().map(|gain| format!("{:.1}", gain)).collect::<Vec<_>>().join(",");
+        if self.settings.get_value("equalizer") != Some(value.as_str()) {
+            self.settings.set_value("equalizer", value);
+        }
+    }
+    
+    pub fn get_equalizer(&self) -> &[f32] {
+        &self.equalizer
+    }
+    
+    /// 保留中の音声の再起動を、待ち時間が過ぎていれば実行する（タイマーから呼ぶ）
+    pub fn update_audio_restart(&mut self) {
+        if self.audio_restart_at.is_some_and(|at| Instant::now() >= at) {
+            self.audio_restart_at = None;
+            self.save_equalizer();
+            if self.is_playing() {
+                self.stop_audio();
+                let _ = self.start_audio_playback();
+            } else {
+                // 止まっている間に変えた音量は、次の再生の最初から掛ける
+                self.volume_ramp = None;
+            }
+        }
+    }
+    
+    /// 無限リピートを継ぎ目なしで行うか（リピート回数が無限のときにオン）
+    pub fn set_seamless_loop(&mut self, enabled: bool) {
+        if *self.seamless_loop.lock().unwrap() == enabled {
+            return;
+        }
+        *self.seamless_loop.lock().unwrap() = enabled;
+        // 再生中の音声がループしないままだと継ぎ目で止まるので、次の継ぎ目で起動し直す
+        if !enabled && self.audio_looping && self.is_playing() {
+            let _ = self.start_audio_playback();
+        }
+    }
+    
+    /// 映像が継ぎ目なしで先頭に戻っていれば、必要に応じて音声も先頭から起動し直す（タイマーから呼ぶ）
+    /// 戻ったらtrue
+    pub fn update_seamless_loop(&mut self) -> bool {
+        if !std::mem::take(&mut *self.loop_seam.lock().unwrap()) {
+            return false;
+        }
+        if !self.audio_looping && self.is_playing() {
+            let _ = self.start_audio_playback();
+        }
+        self.cue();
+        true
+    }
+    
+    /// A/B区間・リピートで先頭に戻ったときと動画の終わりに鳴らす音（手動のシークでは鳴らさない）
+    pub fn set_cue_sound(&mut self, enabled: bool) {
+        self.cue_sound = enabled;
+        self.settings.set_value("cue_sound", enabled);
+    }
+    
+    pub fn get_cue_sound(&self) -> bool {
+        self.cue_sound
+    }
+    
+    /// 鳴らす音と同じタイミングで画面を一瞬光らせるか
+    pub fn set_cue_flash(&mut self, enabled: bool) {
+        self.cue_flash = enabled;
+        self.settings.set_value("cue_flash", enabled);
+    }
+    
+    pub fn get_cue_flash(&self) -> bool {
+        self.cue_flash
+    }
+    
+    /// 区間・動画の終わりに達した合図（設定に応じて音を鳴らし、UIに光らせるよう伝える）
+    pub fn cue(&mut self) {
+        if self.cue_flash {
+            self.cue_flash_pending = true;
+        }
+        if !self.cue_sound || !self.audio_available {
+            return;
+        }
+        // 動画の音声とは別のffplayで鳴らす（動画の音声を止めたり、フィルターを変えたりしない）
+        let volume = *self.volume.lock().unwrap() * CUE_TONE_VOLUME;
+        let tone = format!("sine=frequency={}:duration={},afade=t=out:st={}:d=0.03,volume={:.2}", CUE_TONE_FREQUENCY, CUE_TONE_DURATION, CUE_TONE_DURATION - 0.03, volume);
+        let mut command = ffmpeg_command("ffplay");
+        if let Some(device) = self.get_audio_device().filter(|device| audio_device::output_devices().contains(device)) {
+            command.env("AUDIODEV", device);
+        }
+        let child = command
+            .args(&["-v", "error", "-nodisp", "-autoexit", "-f", "lavfi", "-i", &tone])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        match child {
+            Ok(mut child) => self.spawn_worker(move || {
+                let _ = child.wait();
+            }),
+            Err(e) => println!("{}", trf("Could not play the cue tone: {}", &[&e])),
+        }
+    }
+    
+    /// UIが光らせる合図が届いていればtrue（一度だけ）
+    pub fn take_cue_flash(&mut self) -> bool {
+        std::mem::take(&mut self.cue_flash_pending)
+    }
+    
+    /// 音声の出力デバイス（Noneなら既定のデバイス）。再生中なら現在位置から切り替える
+    pub fn set_audio_device(&mut self, name: Option<String>) {
+        self.settings.set_value("audio_device", name.as_deref().unwrap_or(""));
+        println!("{}", trf("Audio output device: {}", &[&name.as_deref().unwrap_or(tr("Default device"))]));
+        if self.is_playing() {
+            let _ = self.start_audio_playback();
+        }
+    }
+    
+    pub fn get_audio_device(&self) -> Option<String> {
+        self.settings.get_value("audio_device").filter(|name| !name.is_empty()).map(|name| name.to_string())
+    }
+    
+    /// 全画面表示に使うモニターの名前（Noneなら今ウィンドウがあるモニター）
+    pub fn set_fullscreen_monitor(&mut self, name: Option<String>) {
+        self.settings.set_value("fullscreen_monitor", name.as_deref().unwrap_or(""));
+        println!("{}", trf("Fullscreen monitor: {}", &[&name.as_deref().unwrap_or(tr("Current monitor"))]));
+    }
+    
+    pub fn get_fullscreen_monitor(&self) -> Option<String> {
+        self.settings.get_value("fullscreen_monitor").filter(|name| !name.is_empty()).map(|name| name.to_string())
+    }
+    
+    /// 配色（Some(true)ならダーク、Noneならシステムの設定に合わせる）
+    pub fn set_dark_theme(&mut self, dark: Option<bool>) {
+        self.settings.set_value("theme", dark.map_or("", |dark| if dark { "dark" } else { "light" }));
+    }
+    
+    pub fn get_dark_theme(&self) -> Option<bool> {
+        match self.settings.get_value("theme") {
+            Some("dark") => Some(true),
+            Some("light") => Some(false),
+            _ => None,
+        }
+    }
+    
+    /// 表示言語（Noneならシステムの言語に合わせる）
+    pub fn set_language(&mut self, language: Option<Language>) {
+        self.settings.set_value("language", language.map_or("", |language| language.code()));
+    }
+    
+    pub fn get_language(&self) -> Option<Language> {
+        self.settings.get_value("language").and_then(Language::from_code)
+    }
+    
+    /// 色のメタデータに従ってRGBに変換するか（オフにすると以前のffmpeg任せの変換）
+    pub fn set_color_correction(&mut self, enabled: bool) {
+        self.color_correction = enabled;
+        self.settings.set_value("color_correction", enabled);
+        self.video_restart_at = Some(Instant::now() + Duration::from_millis(300));
+    }
+    
+    pub fn get_color_correction(&self) -> bool {
+        self.color_correction
+    }
+    
+    /// ピクセルの縦横比（DVDや放送の横長・縦長のピクセル）を無視して、画素数のままの大きさで表示するか
+    /// 表示する大きさが変わるので、開いている動画は今の位置から開き直す
+    pub fn set_raw_pixel_aspect(&mut self, enabled: bool) {
+        if self.raw_pixel_aspect == enabled {
+            return;
+        }
+        self.raw_pixel_aspect = enabled;
+        self.settings.set_value("raw_pixel_aspect", enabled);
+        if let Some(path) = self.video_path.clone() {
+            let position = self.get_current_time();
+            let playing = self.is_playing();
+            self.load_video(path);
+            self.resume_at = Some(position);
+            self.play_when_loaded = playing;
+        }
+    }
+    
+    pub fn get_raw_pixel_aspect(&self) -> bool {
+        self.raw_pixel_aspect
+    }
+    
+    /// HDRの動画に使うトーンマッピングの方法（Offなら変換しない）
+    pub fn set_tone_mapping(&mut self, mode: ToneMapping) {
+        self.tone_mapping = mode;
+        self.settings.set_value("tone_mapping", mode.name());
+        if self.video_color.hdr_kind().is_some() {
+            self.video_restart_at = Some(Instant::now() + Duration::from_millis(300));
+        }
+    }
+    
+    pub fn get_tone_mapping(&self) -> ToneMapping {
+        self.tone_mapping
+    }
+    
+    /// HDRの動画ならトーンマッピングの状態を返す（SDRなら空）
+    pub fn get_hdr_status(&self) -> String {
+        let Some(kind) = self.video_color.hdr_kind() else {
+            return String::new();
+        };
+        if self.tone_mapping == ToneMapping::Off {
+            trf("HDR ({}): no tone mapping", &[&kind])
+        } else if !zscale_available() {
+            trf("HDR ({}): cannot convert because ffmpeg lacks zscale", &[&kind])
+        } else {
+            trf("HDR ({}) → SDR: {}", &[&kind, &self.tone_mapping.name()])
+        }
+    }
+    
+    /// ノイズ除去の切り替えと強さ（0〜10）。スライダー操作中は少し待ってからまとめて反映する（保存も反映するときに行う）
+    pub fn set_denoise(&mut self, enabled: bool, strength: f32) {
+        self.denoise_strength = strength.clamp(0.0, MAX_DENOISE_STRENGTH);
+        self.denoise = enabled;
+        self.video_restart_at = Some(Instant::now() + Duration::from_millis(300));
+    }
+    
+    pub fn get_denoise(&self) -> (bool, f32) {
+        (self.denoise, self.denoise_strength)
+    }
+    
+    /// シャープの切り替えと強さ（0〜2）
+    pub fn set_sharpen(&mut self, enabled: bool, strength: f32) {
+        self.sharpen_strength = strength.clamp(0.0, MAX_SHARPEN_STRENGTH);
+        self.sharpen = enabled;
+        self.video_restart_at = Some(Instant::now() + Duration::from_millis(300));
+    }
+    
+    pub fn get_sharpen(&self) -> (bool, f32) {
+        (self.sharpen, self.sharpen_strength)
+    }
+    
+    // ノイズ除去・シャープの強さを保存する（保存済みの値と同じなら書き込まない）
+    fn save_filter_strengths(&mut self) {
+        for (key, value) in [
+            ("denoise_strength", format!("{:.1}", self.denoise_strength)),
+            ("sharpen_strength", format!("{:.2}", self.sharpen_strength)),
+        ] {
+            if self.settings.get_value(key) != Some(value.as_str()) {
+                self.settings.set_value(key, value);
+            }
+        }
+    }
+    
+    /// 回転・縦横比・色の調整を変えて、このファイル用に保存する
+    /// 縦横比は表示だけの設定なので、デコードし直すのはフィルターが変わったときだけ
+    pub fn set_adjustments(&mut self, adjustments: VideoAdjustments) {
+        let adjustments = adjustments.clamped();
+        let refilter = adjustments.filters() != self.adjustments.filters();
+        self.adjustments = adjustments;
+        if let Some(path) = self.video_path.clone() {
+            adjustments.save(&mut self.settings, &path);
+        }
+        if refilter {
+            self.video_restart_at = Some(Instant::now() + Duration::from_millis(300));
+        }
+    }
+    
+    pub fn get_adjustments(&self) -> VideoAdjustments {
+        self.adjustments
+    }
+    
+    /// 時計回りに90度回す（0°→90°→180°→270°→0°。横向きで撮ったスマートフォンの動画用。ファイルごとに保存する）
+    /// 表示中のフレームはその場で回して差し替え、デコードも待たずにやり直す
+    /// （幅と高さが入れ替わる前のフレームを、新しい大きさで一瞬でも表示しないように）
+    pub fn rotate_clockwise(&mut self) {
+        let rotated = self.current_frame.lock().unwrap().current().map(image::imageops::rotate90);
+        self.adjustments.rotation = (self.adjustments.rotation + 90) % 360;
+        if let Some(path) = self.video_path.clone() {
+            self.adjustments.save(&mut self.settings, &path);
+        }
+        if self.is_playing() || self.is_paused() {
+            self.video_restart_at = None;
+            self.restart_from_current();
+        } else {
+            // 停止中は回したフレームで足りる（前の向きのフレームに戻れないよう、コマ戻し用の履歴は捨てる）
+            self.frame_history.lock().unwrap().frames.clear();
+        }
+        if let Some(frame) = rotated {
+            self.current_frame.lock().unwrap().replace(frame);
+        }
+        println!("{}", trf("Rotation: {}°", &[&self.adjustments.rotation]));
+    }
+    
+    /// 現在の調整を同じフォルダの動画の既定にする（ファイルごとの保存があればそちらを優先）
+    pub fn save_adjustments_for_folder(&mut self) -> Result<(), String> {
+        let path = self.video_path.clone().ok_or(tr("No video file is loaded"))?;
+        let folder = path.parent().ok_or(tr("No video file is loaded"))?;
+        self.adjustments.save(&mut self.settings, folder);
+        // フォルダの設定が効くよう、このファイルの保存は消しておく
+        VideoAdjustments::remove(&mut self.settings, &path);
+        Ok(())
+    }
+    
+    /// このファイルとフォルダに保存した調整を消して既定値に戻す
+    pub fn reset_adjustments(&mut self) {
+        if let Some(path) = self.video_path.clone() {
+            VideoAdjustments::remove(&mut self.settings, &path);
+            if let Some(folder) = path.parent() {
+                VideoAdjustments::remove(&mut self.settings, folder);
+            }
+        }
+        let refilter = !self.adjustments.filters().is_empty();
+        self.adjustments = VideoAdjustments::default();
+        if refilter {
+            self.video_restart_at = Some(Instant::now() + Duration::from_millis(300));
+        }
+    }
+    
+    /// 保留中の映像の再起動を、待ち時間が過ぎていれば実行する（タイマーから呼ぶ）
+    /// 停止中は表示中のフレームだけを新しいフィルターで読み直す
+    pub fn update_video_restart(&mut self) {
+        if self.video_restart_at.is_some_and(|at| Instant::now() >= at) {
+            self.video_restart_at = None;
+            self.save_filter_strengths();
+            if self.is_playing() || self.is_paused() {
+                self.restart_from_current();
+            } else if self.video_path.is_some() {
+                self.seek(self.get_current_time());
+            }
+        }
+    }
+    
+    /// 全チャンネルを1つにまとめて再生（片耳で聴く場合など）
+    pub fn set_mono(&mut self, mono: bool) {
+        self.mono = mono;
+        println!("{}", trf("Mono playback: {}", &[&if mono { tr("On") } else { tr("Off") }]));
+        
+        // 再生中の場合は音声を再起動
+        if self.is_playing() {
+            self.stop_audio();
+            let _ = self.start_audio_playback();
+        }
+    }
+    
+    /// 左右バランス（-1.0: 左のみ, 0.0: 中央, 1.0: 右のみ）
+    pub fn set_balance(&mut self, balance: f32) {
+        self.balance = balance.clamp(-1.0, 1.0);
+        println!("{}", trf("Balance: {}", &[&format!("{:+.2}", self.balance)]));
+        // スライダー操作中に音声を何度も再起動しないよう、少し待ってからまとめて反映する
+        self.audio_restart_at = Some(Instant::now() + Duration::from_millis(300));
+    }
+    
+    pub fn load_subtitles(&mut self, path: PathBuf) -> Result<(), String> {
+        let subtitles = Subtitles::load(&path)?;
+        // 字幕ファイルごとに保存されたオフセットを復元
+        self.subtitle_offset = self.settings.get_file_f32(&path, "subtitle_offset").unwrap_or(0.0);
+        self.subtitles = Some(subtitles);
+        Ok(())
+    }
+    
+    pub fn get_subtitle_path(&self) -> Option<&Path> {
+        self.subtitles.as_ref().map(|subtitles| subtitles.path.as_path())
+    }
+    
+    /// 字幕の表示タイミングをずらす（正の値で字幕を遅らせ、負の値で早める。保存はsave_subtitle_offset）
+    pub fn set_subtitle_offset(&mut self, seconds: f32) {
+        self.subtitle_offset = seconds.clamp(-10.0, 10.0);
+    }
+    
+    /// 字幕オフセットを字幕ファイルごとに保存する（スライダーを離したとき・キーで調整したときに呼ぶ）
+    pub fn save_subtitle_offset(&mut self) {
+        println!("{}", trf("Subtitle offset: {}s", &[&format!("{:+.1}", self.subtitle_offset)]));
+        if let Some(subtitles) = &self.subtitles {
+            self.settings.set_file_value(&subtitles.path, "subtitle_offset", self.subtitle_offset);
+        }
+    }
+    
+    pub fn get_subtitle_offset(&self) -> f32 {
+        self.subtitle_offset
+    }
+    
+    /// 現在の再生位置に表示すべき字幕テキスト（なければ空文字列）
+    pub fn get_subtitle_text(&self) -> String {
+        let Some(subtitles) = &self.subtitles else {
+            return String::new();
+        };
+        // 再解析せず、検索する時刻の側にオフセットを適用する
+        let time = self.get_current_time() - self.subtitle_offset;
+        subtitles
+            .active_cue(time)
+            .map(|cue| cue.text.clone())
+            .unwrap_or_default()
+    }
+    
+    /// 字幕の一覧（開始時刻はオフセットを含まない字幕ファイル上の時刻, テキスト）。字幕がなければ空
+    pub fn get_subtitle_cues(&self) -> Vec<(f32, String)> {
+        self.subtitles
+            .as_ref()
+            .map(|subtitles| subtitles.cues.iter().map(|cue| (cue.start, cue.text.clone())).collect())
+            .unwrap_or_default()
+    }
+    
+    /// 今の位置で始まっている最後の字幕の番号（最初の字幕より前か、字幕がなければNone）
+    pub fn current_cue_index(&self) -> Option<usize> {
+        let subtitles = self.subtitles.as_ref()?;
+        let time = self.get_current_time() - self.subtitle_offset + CUE_POSITION_TOLERANCE;
+        subtitles.cues.partition_point(|cue| cue.start <= time).checked_sub(1)
+    }
+    
+    /// 前後の字幕の始まりへ移る（delta=-1で前、1で次。最初・最後の字幕より先へは進まない）
+    /// 最初の字幕より前にいるときは、どちらでも最初の字幕へ移る
+    pub fn seek_to_adjacent_cue(&mut self, delta: i32) {
+        let count = self.subtitles.as_ref().map_or(0, |subtitles| subtitles.cues.len());
+        if count == 0 {
+            return;
+        }
+        let index = match self.current_cue_index() {
+            Some(index) => (index as i64 + delta as i64).clamp(0, count as i64 - 1) as usize,
+            None => 0,
+        };
+        self.seek_to_cue(index);
+    }
+    
+    /// index番目の字幕の始まりへ移る（オフセットを反映する）。シャドーイング中ならその文から繰り返す
+    pub fn seek_to_cue(&mut self, index: usize) {
+        let Some(start) = self.subtitles.as_ref().and_then(|subtitles| subtitles.cues.get(index)).map(|cue| cue.start) else {
+            return;
+        };
+        if self.shadowing.is_some() {
+            self.play_shadowing_cue(index);
+            return;
+        }
+        let time = (start + self.subtitle_offset).clamp(0.0, self.duration.max(0.0));
+        println!("{}", trf("Moving to the start of subtitle {}: {}s", &[&(index + 1), &format!("{:.2}", time)]));
+        self.seek(time);
+    }
+    
+    /// シーク方法を切り替える（次のシーク・再生開始から適用）
+    pub fn set_seek_mode(&mut self, mode: SeekMode) {
+        let value = match mode {
+            SeekMode::Fast => "fast",
+            SeekMode::Accurate => "accurate",
+            SeekMode::Disabled => return,
+        };
+        self.seek_mode = mode;
+        self.settings.set_value("seek_mode", value);
+        println!("{}", trf("Seek mode: {}", &[&format!("{:?}", mode)]));
+    }
+    
+    pub fn get_seek_mode(&self) -> SeekMode {
+        self.seek_mode
+    }
+    
+    /// 開いている入力がシークできるか（できなければシークバーとスキップを無効にする）
+    pub fn is_seekable(&self) -> bool {
+        self.seekable
+    }
+    
+    // 入力に合わせたシークの仕方（ネットワークでは-iの後の-ssだと先頭から受信し直すことになるので、
+    // 常に入力側でシークする。シークできない配信には-ssを付けない）
+    fn effective_seek_mode(&self) -> SeekMode {
+        match self.video_path.as_deref() {
+            _ if !self.seekable => SeekMode::Disabled,
+            Some(path) if is_network_source(path) => SeekMode::Fast,
+            _ => self.seek_mode,
+        }
+    }
+    
+    // ローカルのファイルは常にシークできる。ネットワークでは、ライブ配信専用のプロトコルと、
+    // ffprobeで長さがわからない配信（HLSのライブなど）はシークできないものとする
+    fn source_seekable(path: &Path, duration: f32) -> bool {
+        match network_protocol(path) {
+            None => true,
+            Some(protocol) => !LIVE_PROTOCOLS.contains(&protocol.as_str()) && duration > 0.0,
+        }
+    }
+    
+    /// 新しく読み込んだ動画の冒頭を飛ばす秒数を設定する（this_file_onlyなら現在の動画だけに適用）
+    pub fn set_skip_intro(&mut self, seconds: f32, this_file_only: bool) {
+        let seconds = seconds.clamp(0.0, MAX_SKIP_INTRO);
+        match &self.video_path {
+            Some(path) if this_file_only => {
+                self.settings.set_file_value(path, "skip_intro", seconds);
+                println!("{}", trf("Skip intro (this video only): {}s", &[&seconds]));
+            }
+            Some(path) if self.settings.get_file_f32(path, "skip_intro").is_some() => {
+                // 個別設定をやめた場合は全体の設定に戻す
+                self.settings.remove_file_value(path, "skip_intro");
+                println!("{}", trf("Skip intro: back to the global setting ({}s)", &[&self.skip_intro]));
+            }
+            _ => {
+                self.skip_intro = seconds;
+                self.settings.set_value("skip_intro", seconds);
+                println!("{}", trf("Skip intro: {}s", &[&seconds]));
+            }
+        }
+    }
+    
+    /// 現在の動画に適用されるイントロスキップの秒数と、それが動画ごとの設定かどうか
+    pub fn get_skip_intro(&self) -> (f32, bool) {
+        match self.video_path.as_ref().and_then(|path| self.settings.get_file_f32(path, "skip_intro")) {
+            Some(seconds) => (seconds, true),
+            None => (self.skip_intro, false),
+        }
+    }
+    
+    /// イントロを飛ばした直後に一度だけ、飛ばした秒数を返す（通知表示用）
+    pub fn take_intro_skipped(&mut self) -> Option<f32> {
+        self.intro_skipped.take()
+    }
+    
+    /// 無音部分の自動スキップを切り替える（解析済みなら再解析しない）
+    pub fn set_skip_silence(&mut self, enabled: bool) {
+        self.skip_silence = enabled;
+        self.settings.set_value("skip_silence", enabled);
+        println!("{}", trf("Skip silence: {}", &[&if enabled { tr("On") } else { tr("Off") }]));
+        if enabled && !self.silence_scan_started {
+            self.start_silence_detection();
+        }
+    }
+    
+    pub fn get_skip_silence(&self) -> bool {
+        self.skip_silence
+    }
+    
+    /// この長さ以上の無音だけを飛ばす（自然な間を飛ばさないように）
+    pub fn set_min_silence(&mut self, seconds: f32) {
+        self.min_silence = seconds.clamp(SILENCE_DETECT_MIN, 10.0);
+        self.settings.set_value("min_silence", self.min_silence);
+    }
+    
+    pub fn get_min_silence(&self) -> f32 {
+        self.min_silence
+    }
+    
+    /// 無音スキップの状態（無効なら空文字列）
+    pub fn get_silence_status(&self) -> String {
+        if !self.skip_silence {
+            return String::new();
+        }
+        match self.silent_regions.lock().unwrap().as_ref() {
+            Some(regions) => {
+                let count = regions.iter().filter(|(start, end)| end - start >= self.min_silence).count();
+                trf("Silent sections to skip: {}", &[&count])
+            }
+            None if self.video_path.is_some() => tr("Analyzing silent sections...").to_string(),
+            None => String::new(),
+        }
+    }
+    
+    // 無音区間の解析をワーカースレッドで開始（結果はファイルごとにキャッシュする）
+    fn start_silence_detection(&mut self) {
+        let Some(path) = self.video_path.clone() else {
+            return;
+        };
+        self.silence_scan_started = true;
+        let generation = *self.silence_generation.lock().unwrap();
+        let cache = Arc::clone(&self.silence_cache);
+        let silence_generation = Arc::clone(&self.silence_generation);
+        let silent_regions = Arc::clone(&self.silent_regions);
+        let duration = self.duration;
+        
+        self.spawn_worker(move || {
+            // 別の動画に切り替えたら、解析中のffmpegを止める
+            let cancelled = || *silence_generation.lock().unwrap() != generation;
+            let cached = cache.lock().unwrap().get(&path).cloned();
+            let regions = match cached {
+                Some(regions) => regions,
+                None => match Self::detect_silence(&path, duration, &cancelled) {
+                    Ok(regions) => {
+                        cache.lock().unwrap().insert(path.clone(), regions.clone());
+                        regions
+                    }
+                    Err(e) => {
+                        println!("{}", trf("Could not analyze the silent sections: {}", &[&e]));
+                        return;
+                    }
+                },
+            };
+            if !cancelled() {
+                *silent_regions.lock().unwrap() = Some(regions);
+            }
+        });
+    }
+    
+    // silencedetectフィルターが付けた無音区間の印を、ametadataで標準出力に書き出して読み取る
+    // （標準エラーはログとして読むので、キャンセルできる実行でも結果を受け取れるように）
+    fn detect_silence(path: &Path, duration: f32, cancelled: &dyn Fn() -> bool) -> Result<SilentRegions, String> {
+        let output = Self::output_cancelable(ffmpeg_command("ffmpeg")
+            .args(["-hide_banner", "-nostats", "-i"])
+            .arg(path)
+            .args([
+                "-vn",
+                "-af", &format!("silencedetect=noise={}dB:d={},ametadata=mode=print:file=-", SILENCE_NOISE_DB, SILENCE_DETECT_MIN),
+                "-f", "null",
+                "-",
+            ]), cancelled)?;
+        if !output.status.success() {
+            return Err(ffmpeg_log::with_detail(trf("ffmpeg exited with an error ({})", &[&output.status]), Self::failure_detail(&output)));
+        }
+        
+        let value_after = |line: &str, key: &str| {
+            line.split(key)
+                .nth(1)
+                .and_then(|rest| rest.split_whitespace().next())
+                .and_then(|value| value.parse::<f32>().ok())
+        };
+        
+        let mut regions = Vec::new();
+        let mut start: Option<f32> = None;
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            if let Some(time) = value_after(line, "lavfi.silence_start=") {
+                start = Some(time.max(0.0));
+            } else if let Some(end) = value_after(line, "lavfi.silence_end=") {
+                if let Some(start) = start.take() {
+                    regions.push((start, end));
+                }
+            }
+        }
+        // 末尾まで無音が続く場合はsilence_endが出力されない
+        if let Some(start) = start {
+            regions.push((start, duration));
+        }
+        
+        println!("{}", trf("Found {} silent sections", &[&regions.len()]));
+        Ok(regions)
+    }
+    
+    /// 再生位置が無音区間に入っていれば、その終わりまで飛ばす（飛ばした場合はtrue）
+    pub fn update_silence_skip(&mut self) -> bool {
+        if !self.skip_silence || !self.is_playing() {
+            return false;
+        }
+        let current = self.get_current_time();
+        let target = {
+            let regions = self.silent_regions.lock().unwrap();
+            let Some(regions) = regions.as_ref() else {
+                return false;
+            };
+            // 終わりの少し手前に着地させ、音の立ち上がりを切らないようにする
+            regions
+                .iter()
+                .find(|(start, end)| end - start >= self.min_silence && current >= *start && current < end - 0.3)
+                .map(|(_, end)| end - 0.2)
+        };
+        
+        match target {
+            Some(time) => {
+                println!("{}", trf("Skipping silence: {}s → {}s", &[&format!("{:.2}", current), &format!("{:.2}", time)]));
+                self.seek(time);
+                true
+            }
+            None => false,
+        }
+    }
+    
+    /// 現在の再生位置をA点にする（B点より後ならB点は解除）
+    pub fn set_loop_a(&mut self) {
+        self.stop_shadowing();
+        let time = self.get_current_time();
+        self.loop_a = Some(time);
+        if self.loop_b.is_some_and(|b| b <= time) {
+            self.loop_b = None;
+        }
+        println!("{}", trf("Set point A: {}s", &[&format!("{:.2}", time)]));
+    }
+    
+    /// 現在の再生位置をB点にする（A点未設定なら先頭をA点とする）
+    pub fn set_loop_b(&mut self) -> Result<(), String> {
+        if !self.seekable {
+            return Err(tr("This stream does not support seeking").to_string());
+        }
+        self.stop_shadowing();
+        let time = self.get_current_time();
+        let a = self.loop_a.unwrap_or(0.0);
+        if time <= a {
+            return Err(tr("Set point B after point A").to_string());
+        }
+        self.loop_a = Some(a);
+        self.loop_b = Some(time);
+        self.loop_remaining = self.loop_count;
+        println!("{}", trf("Set point B: {}s (A/B repeat: {}–{}s)", &[&format!("{:.2}", time), &format!("{:.2}", a), &format!("{:.2}", time)]));
+        Ok(())
+    }
+    
+    pub fn clear_loop(&mut self) {
+        self.loop_a = None;
+        self.loop_b = None;
+        self.loop_remaining = self.loop_count;
+        println!("{}", tr("Cleared the A/B repeat"));
+    }
+    
+    pub fn get_loop_points(&self) -> (Option<f32>, Option<f32>) {
+        (self.loop_a, self.loop_b)
+    }
+    
+    /// A/B区間を再生する回数（-1なら無限。数え直しになる）
+    pub fn set_loop_count(&mut self, count: i32) {
+        self.loop_count = if count < 1 { -1 } else { count };
+        self.loop_remaining = self.loop_count;
+        if self.loop_count == -1 {
+            println!("{}", tr("A/B repeat count: infinite"));
+        } else {
+            println!("{}", trf("A/B repeat count: {}", &[&self.loop_count]));
+        }
+    }
+    
+    /// A/B区間の残りの再生回数（無限なら-1）
+    pub fn get_loop_remaining(&self) -> i32 {
+        self.loop_remaining
+    }
+    
+    // B点に達したときに回数を数える（まだ繰り返すならtrue、回数に達したら区間を解除してfalse）
+    fn count_loop_pass(&mut self) -> bool {
+        if self.loop_remaining == -1 {
+            return true;
+        }
+        self.loop_remaining -= 1;
+        if self.loop_remaining > 0 {
+            println!("{}", trf("A/B repeat ({} left)", &[&self.loop_remaining]));
+            return true;
+        }
+        self.loop_a = None;
+        self.loop_b = None;
+        self.loop_remaining = self.loop_count;
+        println!("{}", tr("The A/B repeat count was reached, continuing past point B"));
+        false
+    }
+    
+    /// シャドーイングの回数と間の秒数（次の文から適用する）
+    pub fn set_shadowing_options(&mut self, repeats: u32, gap: f32) {
+        self.shadowing_repeats = repeats.clamp(1, MAX_SHADOWING_REPEATS);
+        self.shadowing_gap = if gap.is_finite() { gap.clamp(0.0, MAX_SHADOWING_GAP) } else { DEFAULT_SHADOWING_GAP };
+        self.settings.set_value("shadowing_repeats", self.shadowing_repeats);
+        self.settings.set_value("shadowing_gap", format!("{:.1}", self.shadowing_gap));
+    }
+    
+    pub fn get_shadowing_options(&self) -> (u32, f32) {
+        (self.shadowing_repeats, self.shadowing_gap)
+    }
+    
+    /// シャドーイングを始める（字幕の区切りを文として使うので、字幕が必要）
+    /// 今の位置の文から、なければ次の文から始める。A/Bリピートは解除する
+    pub fn start_shadowing(&mut self) -> Result<(), String> {
+        if !self.seekable {
+            return Err(tr("This stream does not support seeking").to_string());
+        }
+        let Some(subtitles) = &self.subtitles else {
+            return Err(tr("Shadowing needs subtitles to split the video into sentences").to_string());
+        };
+        let time = self.get_current_time() - self.subtitle_offset;
+        let cue = subtitles
+            .cues
+            .iter()
+            .position(|cue| time < cue.end)
+            .ok_or(tr("There are no more subtitles after this position"))?;
+        self.clear_loop();
+        self.play_shadowing_cue(cue);
+        Ok(())
+    }
+    
+    pub fn stop_shadowing(&mut self) {
+        if self.shadowing.take().is_some() {
+            println!("{}", tr("Shadowing ended"));
+        }
+    }
+    
+    pub fn is_shadowing(&self) -> bool {
+        self.shadowing.is_some()
+    }
+    
+    /// 手動で前後の文へ移る（delta=1で次、-1で前。範囲外なら何もしない）
+    pub fn skip_shadowing(&mut self, delta: i32) {
+        let (Some(shadowing), Some(subtitles)) = (&self.shadowing, &self.subtitles) else {
+            return;
+        };
+        let cue = shadowing.cue as i64 + delta as i64;
+        if cue >= 0 && (cue as usize) < subtitles.cues.len() {
+            self.play_shadowing_cue(cue as usize);
+        }
+    }
+    
+    // 文の頭から再生し、回数を数え直す
+    fn play_shadowing_cue(&mut self, cue: usize) {
+        self.shadowing = Some(Shadowing {
+            cue,
+            remaining: self.shadowing_repeats,
+            gap_until: None,
+        });
+        self.replay_shadowing_cue();
+    }
+    
+    fn replay_shadowing_cue(&mut self) {
+        let Some((start, _)) = self.shadowing_range() else {
+            return;
+        };
+        self.clear_video_finished();
+        self.seek(start);
+        let _ = self.play();
+    }
+    
+    // 繰り返している文の再生範囲（字幕のオフセットと前後の余白を含む）
+    fn shadowing_range(&self) -> Option<(f32, f32)> {
+        let cue = self.subtitles.as_ref()?.cues.get(self.shadowing.as_ref()?.cue)?;
+        let start = (cue.start + self.subtitle_offset - SHADOWING_LEAD_IN).max(0.0);
+        let end = cue.end + self.subtitle_offset + SHADOWING_TAIL;
+        Some((start, if self.duration > 0.0 { end.min(self.duration) } else { end }))
+    }
+    
+    /// シャドーイングの状態（していなければ空）
+    pub fn get_shadowing_status(&self) -> String {
+        let (Some(shadowing), Some(subtitles)) = (&self.shadowing, &self.subtitles) else {
+            return String::new();
+        };
+        let sentence = format!("{} / {}", shadowing.cue + 1, subtitles.cues.len());
+        if shadowing.gap_until.is_some() {
+            trf("Sentence {}: repeat aloud ({} left)", &[&sentence, &shadowing.remaining])
+        } else {
+            trf("Sentence {} ({} left)", &[&sentence, &shadowing.remaining])
+        }
+    }
+    
+    /// シャドーイングを進める（タイマーから呼ぶ。文の終わりで止めて間を空け、回数に達したら次の文へ）
+    /// 再生位置を動かした・止めた場合はtrue
+    pub fn update_shadowing(&mut self) -> bool {
+        let Some((_, end)) = self.shadowing_range() else {
+            return false;
+        };
+        let Some(shadowing) = &self.shadowing else {
+            return false;
+        };
+        
+        if let Some(until) = shadowing.gap_until {
+            if Instant::now() < until {
+                return false;
+            }
+            let (cue, remaining) = (shadowing.cue, shadowing.remaining);
+            if remaining > 0 {
+                if let Some(shadowing) = &mut self.shadowing {
+                    shadowing.gap_until = None;
+                }
+                self.replay_shadowing_cue();
+            } else if cue + 1 < self.subtitles.as_ref().map_or(0, |subtitles| subtitles.cues.len()) {
+                self.play_shadowing_cue(cue + 1);
+            } else {
+                // 最後の文を終えたら、そのまま続きを再生する
+                println!("{}", tr("Shadowing: finished the last sentence"));
+                self.shadowing = None;
+                let _ = self.play();
+            }
+            return true;
+        }
+        
+        // 文の終わり（動画の終わりを含む）に達したら止めて、声に出すための間を空ける
+        let reached_end = self.is_video_finished() || (self.is_playing() && self.get_current_time() >= end);
+        if !reached_end {
+            return false;
+        }
+        let gap = Duration::from_secs_f32(self.shadowing_gap);
+        if let Some(shadowing) = &mut self.shadowing {
+            shadowing.remaining = shadowing.remaining.saturating_sub(1);
+            shadowing.gap_until = Some(Instant::now() + gap);
+        }
+        self.clear_video_finished();
+        self.pause();
+        self.cue();
+        true
+    }
+    
+    /// A/B区間の終わりに達していればA点に戻る（戻った場合と、回数に達して区間を解除した場合はtrue）
+    pub fn update_ab_loop(&mut self) -> bool {
+        // 逆再生の区間が終わった場合、A/B区間があればB点から繰り返す
+        if std::mem::take(&mut *self.reverse_finished.lock().unwrap()) {
+            if let (Some(_), Some(b)) = (self.loop_a, self.loop_b) {
+                *self.current_time.lock().unwrap() = b;
+                let _ = self.play();
+                self.cue();
+                return true;
+            }
+            return false;
+        }
+        
+        let (Some(a), Some(b)) = (self.loop_a, self.loop_b) else {
+            return false;
+        };
+        if self.reverse {
+            return false;
+        }
+        
+        // B点が動画の終端付近だと、B点より先に再生が終わることがある
+        // （回数に達した場合は通常の終了処理に任せる）
+        if self.is_video_finished() {
+            if !self.count_loop_pass() {
+                return false;
+            }
+            self.clear_video_finished();
+            self.seek(a);
+            let _ = self.play();
+            self.cue();
+            return true;
+        }
+        if self.is_playing() && self.get_current_time() >= b {
+            if self.count_loop_pass() {
+                self.seek(a);
+                self.cue();
+            }
+            return true;
+        }
+        false
+    }
+    
+    /// プレイリストの末尾に追加する（動画が読み込まれていなければ最初の1つを読み込む）
+    pub fn add_to_playlist(&mut self, paths: Vec<PathBuf>) {
+        let first_added = self.playlist.len();
+        self.playlist.extend(paths);
+        println!("{}", trf("Added to the playlist: {} ({} in total)", &[&(self.playlist.len() - first_added), &self.playlist.len()]));
+        
+        if self.video_path.is_none() && first_added < self.playlist.len() {
+            self.load_video(self.playlist[first_added].clone());
+        }
+    }
+    
+    pub fn remove_from_playlist(&mut self, index: usize) {
+        if index >= self.playlist.len() {
+            return;
+        }
+        self.playlist.remove(index);
+        // 再生中の項目の位置を合わせる
+        self.playlist_index = match self.playlist_index {
+            Some(current) if current == index => None,
+            Some(current) if current > index => Some(current - 1),
+            other => other,
+        };
+    }
+    
+    pub fn clear_playlist(&mut self) {
+        self.playlist.clear();
+        self.playlist_index = None;
+    }
+    
+    /// プレイリストを入れ替えて、最初の項目から再生する（フォルダを開いた場合）
+    pub fn replace_playlist(&mut self, paths: Vec<PathBuf>) {
+        println!("{}", trf("Replaced the playlist: {} items", &[&paths.len()]));
+        self.playlist = paths;
+        self.playlist_index = None;
+        self.play_playlist_item(0);
+    }
+    
+    pub fn get_playlist(&self) -> &[PathBuf] {
+        &self.playlist
+    }
+    
+    pub fn get_playlist_index(&self) -> Option<usize> {
+        self.playlist_index
+    }
+    
+    /// プレイリストの項目を読み込み、読み込みが終わったら再生する
+    pub fn play_playlist_item(&mut self, index: usize) -> bool {
+        let Some(path) = self.playlist.get(index).cloned() else {
+            return false;
+        };
+        self.load_video(path);
+        self.play_when_loaded = true;
+        true
+    }
+    
+    /// 次の項目へ進む（最後の項目、またはプレイリスト外の動画を再生中ならfalse）
+    pub fn play_next(&mut self) -> bool {
+        match self.playlist_index {
+            Some(index) => self.play_playlist_item(index + 1),
+            None => false,
+        }
+    }
+    
+    /// 次の項目が再生中と同じファイルか（同じファイルを続けて並べた場合は暗転を挟まない）
+    /// 最後の項目で最初に戻る設定なら、最初の項目と比べる
+    pub fn next_is_same_file(&self) -> bool {
+        self.playlist_index
+            .and_then(|index| match self.playlist.get(index + 1) {
+                None if self.playlist_end_action() == PlaylistEnd::RepeatAll => self.playlist.first(),
+                next => next,
+            })
+            .is_some_and(|next| Some(next) == self.video_path.as_ref())
+    }
+    
+    /// 再生位置を「続きから見る」用にファイルごとの設定へ保存する（再生中・一時停止中のみ）
+    /// 一時停止中なら、開き直したときに止めた状態に戻せるようにそのことも残す
+    pub fn save_position(&mut self) {
+        let state = self.get_state();
+        if state == PlaybackState::Stopped {
+            return;
+        }
+        let position = self.get_current_time();
+        self.save_watch_entry(position);
+        self.save_paused(state == PlaybackState::Paused);
+    }
+    
+    /// 最後まで再生したことを記録する（一覧では見終わったものとして表示する）
+    pub fn mark_finished(&mut self) {
+        self.save_watch_entry(self.duration);
+        self.save_paused(false);
+    }
+    
+    fn save_paused(&mut self, paused: bool) {
+        let Some(path) = self.video_path.clone() else {
+            return;
+        };
+        if paused {
+            self.settings.set_file_value(&path, "paused", true);
+        } else {
+            self.settings.remove_file_value(&path, "paused");
+        }
+    }
+    
+    fn save_watch_entry(&mut self, position: f32) {
+        let Some(path) = self.video_path.clone() else {
+            return;
+        };
+        if self.duration <= 0.0 {
+            return;
+        }
+        let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+        self.settings.set_file_value(&path, "position", format!("{:.2}", position));
+        self.settings.set_file_value(&path, "duration", format!("{:.2}", self.duration));
+        self.settings.set_file_value(&path, "last_watched", now);
+    }
+    
+    /// 最近見た動画（新しい順）。削除されたファイルは一覧から消す
+    pub fn watch_history(&mut self) -> Vec<WatchEntry> {
+        let mut entries: Vec<WatchEntry> = self
+            .settings
+            .files_with_value("last_watched")
+            .into_iter()
+            .filter_map(|(path, last_watched)| {
+                Some(WatchEntry {
+                    position: self.settings.get_file_f32(&path, "position")?,
+                    duration: self.settings.get_file_f32(&path, "duration")?,
+                    last_watched: last_watched.parse().ok()?,
+                    path,
+                })
+            })
+            .collect();
+        
+        let (existing, deleted): (Vec<_>, Vec<_>) = entries.drain(..).partition(|entry| entry.path.is_file());
+        for entry in deleted {
+            println!("{}", trf("Removing a deleted file from the history: {}", &[&entry.path.display()]));
+            self.remove_from_history(&entry.path);
+        }
+        entries = existing;
+        entries.sort_by_key(|entry| Reverse(entry.last_watched));
+        entries.truncate(WATCH_HISTORY_LEN);
+        entries
+    }
+    
+    pub fn remove_from_history(&mut self, path: &Path) {
+        for key in ["position", "duration", "last_watched", "paused"] {
+            self.settings.remove_file_value(path, key);
+        }
+    }
+    
+    /// 履歴の動画を保存した位置から再生する（見終わっていれば先頭から）
+    pub fn resume(&mut self, path: PathBuf) {
+        let entry = WatchEntry {
+            position: self.settings.get_file_f32(&path, "position").unwrap_or(0.0),
+            duration: self.settings.get_file_f32(&path, "duration").unwrap_or(0.0),
+            last_watched: 0,
+            path: path.clone(),
+        };
+        self.load_video(path);
+        if !entry.finished() && entry.position > 0.0 {
+            self.resume_at = Some(entry.position);
+        }
+        self.play_when_loaded = true;
+    }
+    
+    /// 履歴のサムネイルをワーカースレッドで作る（保存した位置のフレーム。結果はtake_history_thumbnailsで受け取る）
+    pub fn start_history_thumbnails(&mut self, entries: &[WatchEntry]) {
+        let targets: Vec<(PathBuf, f32)> = entries.iter().map(|entry| (entry.path.clone(), entry.position)).collect();
+        let disk_cache = Arc::clone(&self.disk_cache);
+        let pending_thumbnails = Arc::clone(&self.pending_thumbnails);
+        let shutting_down = Arc::clone(&self.shutting_down);
+        self.spawn_worker(move || {
+            for (path, position) in targets {
+                if *shutting_down.lock().unwrap() {
+                    break;
+                }
+                // 見終わった動画は最後のフレームが黒いことが多いので、少し手前から取る
+                let time = (position - WATCHED_END_MARGIN).max(0.0);
+                if let Some(thumbnail) = Self::history_thumbnail(&path, time, &disk_cache) {
+                    pending_thumbnails.lock().unwrap().push((path, thumbnail));
+                }
+            }
+        });
+    }
+    
+    // サムネイルはPNGのままディスクキャッシュに置く（なければffmpegで抽出する）
+    fn history_thumbnail(path: &Path, time: f32, disk_cache: &DiskCache) -> Option<RgbaImage> {
+        let key = DiskCache::key(path, "thumbnail", &format!("{:.0}-{}", time, HISTORY_THUMBNAIL_WIDTH));
+        let png = match key.as_deref().and_then(|key| disk_cache.get(key)) {
+            Some(png) => png,
+            None => {
+                let output = ffmpeg_log::configure(ffmpeg_command("ffmpeg")
+                    .args(SeekMode::Fast.input_args(path, time))
+                    .args(&[
+                        "-vf", &format!("scale={}:-2", HISTORY_THUMBNAIL_WIDTH),
+                        "-frames:v", "1",
+                        "-f", "image2pipe",
+                        "-vcodec", "png",
+                        "-",
+                    ]))
+                    .output()
+                    .ok()
+                    .inspect(|output| {
+                        ffmpeg_log::record_output("ffmpeg", &output.stderr);
+                    })
+                    .filter(|output| output.status.success() && !output.stdout.is_empty())?;
+                if let Some(key) = &key {
+                    disk_cache.put(key, &output.stdout);
+                }
+                output.stdout
+            }
+        };
+        image::load_from_memory(&png).ok().map(|image| image.to_rgba8())
+    }
+    
+    /// 作り終わった履歴のサムネイルを取り出す
+    pub fn take_history_thumbnails(&mut self) -> Vec<(PathBuf, RgbaImage)> {
+        std::mem::take(&mut *self.pending_thumbnails.lock().unwrap())
+    }
+    
+    /// 前の項目に戻る（最初の項目ならfalse）
+    pub fn play_previous(&mut self) -> bool {
+        match self.playlist_index {
+            Some(index) if index > 0 => self.play_playlist_item(index - 1),
+            _ => false,
+        }
+    }
+    
+    fn start_audio_playback(&mut self) -> Result<(), String> {
+        // 既存の音声プロセスを停止
+        self.stop_audio();
+        
+        // ffplayがなければ映像だけを再生する（起動時に一度だけ通知している）
+        // スキャンでは映像が実時間より速く進むので、音声は鳴らさない
+        if !self.audio_available || self.scan_mode {
+            return Ok(());
+        }
+        
+        if let Some(path) = &self.video_path {
+            let start_position = *self.current_time.lock().unwrap();
+            let volume = *self.volume.lock().unwrap();
+            
+            // 無限リピートを先頭から再生するときは、ffplay自身にループさせて周回ごとの起動の隙間をなくす
+            // （途中から始めた場合、ffplayは開始位置に戻ってしまうので、最初の継ぎ目で先頭から起動し直す）
+            let looping = *self.seamless_loop.lock().unwrap() && !self.reverse && start_position < 0.05;
+            self.audio_looping = looping;
+            
+            // 音量を変えたための再起動なら、それまで聞こえていた大きさから移る
+            // （ffplayのループでは時刻が0に戻り、周回ごとに変化をやり直してしまうので一度に変える）
+            let now = Instant::now();
+            let ramp = self.volume_ramp.take().filter(|_| !looping);
+            self.volume_envelope = match ramp {
+                Some(duration) => VolumeRamp {
+                    from: self.volume_envelope.level_at(now),
+                    to: volume,
+                    started: now,
+                    duration,
+                },
+                None => VolumeRamp::constant(volume),
+            };
+            
+            let (seek_position, length, filters) = if self.reverse {
+                // 逆再生: 区間の始まりから現在位置までを読み、areverseで反転する（音声は任意）
+                let length = start_position - self.reverse_window_start;
+                if !self.reverse_audio || length < 0.05 {
+                    return Ok(());
+                }
+                let mut filters = vec!["areverse".to_string()];
+                filters.extend(self.build_audio_filters(0.0));
+                (self.reverse_window_start, Some(length), filters)
+            } else {
+                // 音声オフセットを考慮した音声側の開始位置
+                let audio_start = start_position - self.audio_offset;
+                // ffplayは入力側のシークしかできないため、正確なシークでは先頭から読んでatrimで切り出す
+                let seek_position = match self.effective_seek_mode() {
+                    SeekMode::Fast => audio_start.max(0.0),
+                    SeekMode::Accurate | SeekMode::Disabled => 0.0,
+                };
+                let mut filters = self.build_audio_filters(audio_start);
+                if looping {
+                    // 周回の継ぎ目でプツッと鳴らないよう、終端と先頭を10msだけフェードする
+                    // （ffplayのループではタイムスタンプが0に戻るので、フェードインも毎周回かかる）
+                    filters.insert(0, format!("afade=t=in:d=0.01,afade=t=out:st={}:d=0.01", (self.duration - 0.01).max(0.0)));
+                }
+                (seek_position, None, filters)
+            };
+            
+            // 音声世代をインクリメント
+            let audio_gen = {
+                let mut gen = self.audio_generation.lock().unwrap();
+                *gen += 1;
+                *gen
+            };
+            
+            println!("{}", trf("Starting audio (position: {}s, offset: {}ms, volume: {}%, generation: {})", &[&start_position, &format!("{:+.0}", self.audio_offset * 1000.0), &((volume * 100.0) as i32), &audio_gen]));
+            
+            // ffplayで音声のみを再生（ビデオは非表示）
+            let mut command = ffmpeg_command("ffplay");
+            if looping {
+                command.args(&["-loop", "0"]);
+            }
+            // 選択した出力デバイス（外されていたら既定のデバイスに戻す）
+            if let Some(device) = self.get_audio_device() {
+                if audio_device::output_devices().contains(&device) {
+                    command.env("AUDIODEV", &device);
+                } else {
+                    println!("{}", trf("Audio device \"{}\" was not found, playing on the default device", &[&device]));
+                }
+            }
+            if self.seekable {
+                command.args(&["-ss", &seek_position.to_string()]);
+            }
+            if let Some(length) = length {
+                command.args(&["-t", &length.to_string()]);
+            }
+            let child = command
+                .args(network_input_options(path))
+                .arg("-i")
+                .arg(path)
+                .args(&[
+                    "-vn", // ビデオなし
+                    "-nodisp", // ウィンドウを表示しない
+                    "-af", &filters.join(","),
+                    "-autoexit", // 終了時に自動で閉じる
+                ]);
+            let child = ffmpeg_log::configure(child)
+                .stdout(Stdio::null())
+                .spawn();
+            
+            match child {
+                Ok(mut process) => {
+                    ffmpeg_log::capture("ffplay", &mut process);
+                    *self.audio_process.lock().unwrap() = Some(process);
+                    Ok(())
+                }
+                Err(e) => {
+                    println!("{}", trf("Failed to start audio playback: {}", &[&e]));
+                    Err(trf("Failed to start ffplay: {}", &[&e]))
+                }
+            }
+        } else {
+            Ok(())
+        }
+    }
+    
+    /// 音声をPCMにデコードし、`samples`個の区間ごとのピーク値（0.0〜1.0）を返す
+    /// 音声がない・無音のファイルやシークできない配信では空のVecを返す
+    pub fn generate_waveform(&self, samples: usize) -> Vec<f32> {
+        self.waveform_task(samples).map(|task| task()).unwrap_or_default()
+    }
+    
+    // 波形を作る処理（ワーカースレッドでも動かせるよう、必要なものを複製して持つ）
+    // シークできない配信は終わりがないので作らない
+    fn waveform_task(&self, samples: usize) -> Option<impl FnOnce() -> Vec<f32> + Send + 'static> {
+        let path = self.video_path.clone().filter(|_| self.seekable)?;
+        let duration = self.duration;
+        let cache = Arc::clone(&self.waveform_cache);
+        let disk_cache = Arc::clone(&self.disk_cache);
+        Some(move || Self::generate_waveform_cached(&path, duration, samples, &cache, &disk_cache))
+    }
+
+    /// 波形生成をワーカースレッドで開始（完了後はtake_waveformで取得）
+    pub fn start_waveform_generation(&mut self, samples: usize) {
+        // 世代番号をインクリメント（前のファイルの波形が後から届いても無視する）
+        let generation = {
+            let mut gen = self.waveform_generation.lock().unwrap();
+            *gen += 1;
+            *gen
+        };
+        *self.pending_waveform.lock().unwrap() = None;
+
+        if let Some(task) = self.waveform_task(samples) {
+            let waveform_generation = Arc::clone(&self.waveform_generation);
+            let pending_waveform = Arc::clone(&self.pending_waveform);
+
+            self.spawn_worker(move || {
+                let peaks = task();
+                if *waveform_generation.lock().unwrap() == generation {
+                    *pending_waveform.lock().unwrap() = Some(peaks);
+                }
+            });
         }
     }
 
-    pub fn load_video(&mut self, path: PathBuf) -> Result<(), String> {
-        self.video_path = Some(path.clone());
-        
-        // 動画の情報を取得
-        match self.get_video_info(&path) {
-            Ok((duration, fps, width, height)) => {
-                self.duration = duration;
-                self.fps = fps;
-                self.video_width = width;
-                self.video_height = height;
-                println!("動画を読み込みました: {} ({}秒, {}x{}, {:.2}fps)", path.display(), duration, width, height, fps);
-                
-                // 最初のフレームを読み込む
-                self.load_first_frame(&path)?;
-                
-                Ok(())
+    /// ディスクキャッシュの上限（MB）
+    pub fn set_cache_size_mb(&mut self, megabytes: u64) {
+        self.disk_cache.set_max_bytes(megabytes * 1024 * 1024);
+        self.settings.set_value("cache_size_mb", megabytes);
+    }
+    
+    pub fn get_cache_size_mb(&self) -> u64 {
+        self.disk_cache.max_bytes() / (1024 * 1024)
+    }
+    
+    /// 設定をJSONファイルに書き出す（別のPCでimport_configで読み込める）
+    pub fn export_config(&self, path: &Path) -> Result<(), String> {
+        let values: Vec<(&str, String)> = CONFIG_KEYS
+            .iter()
+            .filter_map(|&key| self.settings.get_value(key).map(|value| (key, value.to_string())))
+            .collect();
+        std::fs::write(path, config_file::to_json(&values)).map_err(|e| trf("Cannot save the settings file: {}", &[&e]))?;
+        println!("{}", trf("Exported settings: {} ({} items)", &[&path.display(), &values.len()]));
+        Ok(())
+    }
+    
+    /// JSONファイルの設定を読み込んで、すぐに反映する（反映した数と、値が範囲外などで飛ばした数）
+    /// 知らないキーは新しいバージョンで書き出されたものとして無視する
+    pub fn import_config(&mut self, path: &Path) -> Result<(usize, usize), String> {
+        let text = std::fs::read_to_string(path).map_err(|e| trf("Cannot open the file: {}", &[&e]))?;
+        let values = config_file::parse(&text)?;
+        let mut applied = 0;
+        let mut skipped = 0;
+        for (key, value) in values {
+            if !CONFIG_KEYS.contains(&key.as_str()) {
+                println!("{}", trf("Importing settings: ignoring an unknown key ({})", &[&key]));
+                continue;
             }
-            Err(e) => {
-                println!("警告: {}", e);
-                self.duration = 300.0;
-                self.video_width = 960;
-                self.video_height = 600;
-                Ok(())
+            if self.apply_config_value(&key, &value) {
+                applied += 1;
+            } else {
+                println!("{}", trf("Importing settings: skipping an invalid value ({}={})", &[&key, &value]));
+                skipped += 1;
+            }
+        }
+        println!("{}", trf("Imported settings: {} ({} items, {} skipped)", &[&path.display(), &applied, &skipped]));
+        Ok((applied, skipped))
+    }
+    
+    // 読み込んだ設定の値を確かめて、通常の設定変更と同じ経路で反映する（正しくない値ならfalse）
+    fn apply_config_value(&mut self, key: &str, value: &str) -> bool {
+        let flag = match value {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        };
+        let number = value.parse::<f32>().ok().filter(|number| number.is_finite());
+        let in_range = |min: f32, max: f32| number.filter(|number| (min..=max).contains(number));
+        match key {
+            "seek_mode" => match value {
+                "fast" => self.set_seek_mode(SeekMode::Fast),
+                "accurate" => self.set_seek_mode(SeekMode::Accurate),
+                _ => return false,
+            },
+            "slow_motion_smoothing" => match value {
+                "off" => self.set_slow_motion_smoothing(SlowMotionSmoothing::Off),
+                "blend" => self.set_slow_motion_smoothing(SlowMotionSmoothing::Blend),
+                "interpolate" => self.set_slow_motion_smoothing(SlowMotionSmoothing::Interpolate),
+                _ => return false,
+            },
+            "ui_refresh_rate" => match value.parse::<u32>() {
+                Ok(rate) if rate == 0 || (MIN_UI_REFRESH_RATE..=MAX_UI_REFRESH_RATE).contains(&(rate as f32)) => self.set_ui_refresh_rate(rate),
+                _ => return false,
+            },
+            "aspect_lock" => match flag {
+                Some(enabled) => self.set_aspect_lock(enabled),
+                None => return false,
+            },
+            "fade_transition" => match flag {
+                Some(enabled) => self.set_fade_transition(enabled),
+                None => return false,
+            },
+            "scan_subfolders" => match flag {
+                Some(enabled) => self.set_scan_subfolders(enabled),
+                None => return false,
+            },
+            "playlist_end" => match value {
+                "stop" => self.set_playlist_end(PlaylistEnd::Stop),
+                "repeat_all" => self.set_playlist_end(PlaylistEnd::RepeatAll),
+                "repeat_one" => self.set_playlist_end(PlaylistEnd::RepeatOne),
+                _ => return false,
+            },
+            "ffmpeg_log_level" => match ffmpeg_log::LogLevel::from_code(value) {
+                Some(level) => self.set_ffmpeg_log_level(level),
+                None => return false,
+            },
+            "restore_paused" => match flag {
+                Some(enabled) => self.set_restore_paused(enabled),
+                None => return false,
+            },
+            "raw_pixel_aspect" => match flag {
+                Some(enabled) => self.set_raw_pixel_aspect(enabled),
+                None => return false,
+            },
+            "restore_session" => match flag {
+                Some(enabled) => self.set_restore_session(enabled, self.restore_session_play),
+                None => return false,
+            },
+            "restore_session_play" => match flag {
+                Some(play) => self.set_restore_session(self.restore_session, play),
+                None => return false,
+            },
+            "cue_sound" => match flag {
+                Some(enabled) => self.set_cue_sound(enabled),
+                None => return false,
+            },
+            "cue_flash" => match flag {
+                Some(enabled) => self.set_cue_flash(enabled),
+                None => return false,
+            },
+            "video_extensions" => {
+                if self.set_video_extensions(value).is_err() {
+                    return false;
+                }
             }
+            "color_correction" => match flag {
+                Some(enabled) => self.set_color_correction(enabled),
+                None => return false,
+            },
+            "skip_silence" => match flag {
+                Some(enabled) => self.set_skip_silence(enabled),
+                None => return false,
+            },
+            "match_loudness" => match flag {
+                Some(enabled) => self.set_match_loudness(enabled),
+                None => return false,
+            },
+            "image_sequence" => match flag {
+                Some(enabled) => self.set_image_sequence(enabled),
+                None => return false,
+            },
+            "decode_ahead" => match value.parse::<usize>() {
+                Ok(frames) if frames <= MAX_DECODE_AHEAD => {
+                    let _ = self.set_decode_ahead(frames);
+                }
+                _ => return false,
+            },
+            "image_sequence_fps" => match in_range(MIN_IMAGE_SEQUENCE_FPS, MAX_IMAGE_SEQUENCE_FPS) {
+                Some(fps) => {
+                    let _ = self.set_image_sequence_fps(fps);
+                }
+                None => return false,
+            },
+            "equalizer" => {
+                let gains: Vec<f32> = value.split(',').filter_map(|gain| gain.trim().parse::<f32>().ok()).collect();
+                if gains.len() != equalizer::BANDS.len() || gains.iter().any(|gain| gain.abs() > equalizer::MAX_GAIN) {
+                    return false;
+                }
+                self.set_equalizer(gains);
+            }
+            // デバイス・モニターは読み込んだPCになければ既定のものが使われる
+            "audio_device" => self.set_audio_device(Some(value.to_string()).filter(|name| !name.is_empty())),
+            "fullscreen_monitor" => self.set_fullscreen_monitor(Some(value.to_string()).filter(|name| !name.is_empty())),
+            "theme" => match value {
+                "" => self.set_dark_theme(None),
+                "dark" => self.set_dark_theme(Some(true)),
+                "light" => self.set_dark_theme(Some(false)),
+                _ => return false,
+            },
+            "language" => match value {
+                "" => self.set_language(None),
+                code => match Language::from_code(code) {
+                    Some(language) => self.set_language(Some(language)),
+                    None => return false,
+                },
+            },
+            "tone_mapping" => match ToneMapping::from_name(value) {
+                Some(mode) => self.set_tone_mapping(mode),
+                None => return false,
+            },
+            "denoise_strength" => match in_range(0.0, MAX_DENOISE_STRENGTH) {
+                Some(strength) => self.set_denoise(self.denoise, strength),
+                None => return false,
+            },
+            "sharpen_strength" => match in_range(0.0, MAX_SHARPEN_STRENGTH) {
+                Some(strength) => self.set_sharpen(self.sharpen, strength),
+                None => return false,
+            },
+            // 動画ごとのイントロスキップは変えずに、全体の設定だけを置き換える
+            "skip_intro" => match in_range(0.0, MAX_SKIP_INTRO) {
+                Some(seconds) => {
+                    self.skip_intro = seconds;
+                    self.settings.set_value("skip_intro", seconds);
+                }
+                None => return false,
+            },
+            "seek_step_short" => match in_range(MIN_SEEK_STEP, MAX_SEEK_STEP) {
+                Some(seconds) => {
+                    self.seek_step_short = seconds;
+                    self.settings.set_value("seek_step_short", seconds);
+                }
+                None => return false,
+            },
+            "seek_step_long" => match in_range(MIN_SEEK_STEP, MAX_SEEK_STEP) {
+                Some(seconds) => {
+                    self.seek_step_long = seconds;
+                    self.settings.set_value("seek_step_long", seconds);
+                }
+                None => return false,
+            },
+            "shadowing_repeats" => match in_range(1.0, MAX_SHADOWING_REPEATS as f32) {
+                Some(repeats) => self.set_shadowing_options(repeats as u32, self.shadowing_gap),
+                None => return false,
+            },
+            "shadowing_gap" => match in_range(0.0, MAX_SHADOWING_GAP) {
+                Some(gap) => self.set_shadowing_options(self.shadowing_repeats, gap),
+                None => return false,
+            },
+            "min_silence" => match in_range(SILENCE_DETECT_MIN, 10.0) {
+                Some(seconds) => self.set_min_silence(seconds),
+                None => return false,
+            },
+            "cache_size_mb" => match value.parse::<u64>() {
+                Ok(megabytes) if (1..=MAX_CACHE_SIZE_MB).contains(&megabytes) => self.set_cache_size_mb(megabytes),
+                _ => return false,
+            },
+            _ => return false,
         }
+        true
+    }
+    
+    /// ディスクキャッシュの使用量（バイト）
+    pub fn get_cache_usage(&self) -> u64 {
+        self.disk_cache.size()
+    }
+    
+    /// 保存した波形などをすべて消す（必要になったときに生成し直す）
+    pub fn clear_cache(&mut self) -> Result<(), String> {
+        self.waveform_cache.lock().unwrap().clear();
+        self.disk_cache.clear()
+    }
+    
+    /// 生成済みの波形を一度だけ取り出す（未完了ならNone）
+    pub fn take_waveform(&mut self) -> Option<Vec<f32>> {
+        self.pending_waveform.lock().unwrap().take()
     }
 
-    fn get_video_info(&self, path: &PathBuf) -> Result<(f32, f32, u32, u32), String> {
-        // ffprobeで動画情報を取得（avg_frame_rateで正確なfpsを取得）
-        let output = Command::new("ffprobe")
+    /// キーフレームの解析をワーカースレッドで開始（結果はファイルごとにキャッシュする）
+    pub fn start_keyframe_scan(&mut self) {
+        let generation = {
+            let mut gen = self.keyframe_generation.lock().unwrap();
+            *gen += 1;
+            *gen
+        };
+        *self.keyframes.lock().unwrap() = None;
+        *self.pending_keyframes.lock().unwrap() = None;
+        
+        // シークできない配信は終わりがなく、キーフレームへの移動もできないので調べない
+        if let Some(path) = self.video_path.clone().filter(|_| self.seekable) {
+            let cache = Arc::clone(&self.keyframe_cache);
+            let keyframe_generation = Arc::clone(&self.keyframe_generation);
+            let keyframes = Arc::clone(&self.keyframes);
+            let pending_keyframes = Arc::clone(&self.pending_keyframes);
+            
+            self.spawn_worker(move || {
+                let cached = cache.lock().unwrap().get(&path).cloned();
+                let times = match cached {
+                    Some(times) => times,
+                    None => {
+                        let times = Self::probe_keyframes(&path);
+                        cache.lock().unwrap().insert(path.clone(), times.clone());
+                        times
+                    }
+                };
+                if *keyframe_generation.lock().unwrap() == generation {
+                    *keyframes.lock().unwrap() = Some(times.clone());
+                    *pending_keyframes.lock().unwrap() = Some(times);
+                }
+            });
+        }
+    }
+    
+    /// 解析済みのキーフレーム時刻を一度だけ取り出す（タイムラインの目盛り表示用）
+    pub fn take_keyframes(&mut self) -> Option<Vec<f32>> {
+        self.pending_keyframes.lock().unwrap().take()
+    }
+    
+    // キーフレームだけをデコードして時刻の一覧を取得する
+    fn probe_keyframes(path: &PathBuf) -> Vec<f32> {
+        let output = ffmpeg_log::configure(ffmpeg_command("ffprobe")
             .args(&[
                 "-v", "error",
                 "-select_streams", "v:0",
-                "-show_entries", "stream=width,height,avg_frame_rate",
-                "-show_entries", "format=duration",
-                "-of", "default=noprint_wrappers=1",
-                path.to_str().unwrap(),
+                "-skip_frame", "nokey",
+                "-show_entries", "frame=pts_time,best_effort_timestamp_time",
+                "-of", "csv=p=0",
             ])
+            .arg(path))
             .output();
+        
+        let Ok(output) = output else {
+            return Vec::new();
+        };
+        ffmpeg_log::record_output("ffprobe", &output.stderr);
+        
+        // pts_timeがN/Aのコンテナもあるので、読める方を使う
+        let mut times: Vec<f32> = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split(',').find_map(|value| value.trim().parse::<f32>().ok()))
+            .collect();
+        times.sort_by(|a, b| a.total_cmp(b));
+        times.dedup();
+        println!("{}", trf("Found {} keyframes", &[&times.len()]));
+        times
+    }
+    
+    /// 現在位置より後の最も近いキーフレームへ移動（最後のキーフレームより後なら何もしない）
+    pub fn next_keyframe(&mut self) -> Result<(), String> {
+        let current = self.get_current_time();
+        let target = {
+            let keyframes = self.keyframes.lock().unwrap();
+            let Some(times) = keyframes.as_ref() else {
+                return Err(tr("Keyframes are still being analyzed").to_string());
+            };
+            times.iter().copied().find(|&time| time > current + 0.01)
+        };
+        if let Some(time) = target {
+            self.seek(time);
+        }
+        Ok(())
+    }
+    
+    /// 現在位置から指定した秒数だけ移動する（負なら戻る。先頭・終端で止める）
+    pub fn seek_by(&mut self, seconds: f32) {
+        if self.video_path.is_none() {
+            return;
+        }
+        let target = (self.get_current_time() + seconds).clamp(0.0, self.duration.max(0.0));
+        self.seek(target);
+    }
+    
+    /// ←/→とスキップボタンで移動する秒数（短い方, 長い方）を変える（小数も可）
+    pub fn set_seek_steps(&mut self, short: f32, long: f32) -> Result<(), String> {
+        for seconds in [short, long] {
+            if !seconds.is_finite() || !(MIN_SEEK_STEP..=MAX_SEEK_STEP).contains(&seconds) {
+                return Err(trf("The skip amount must be between {} and {} seconds ({})", &[&MIN_SEEK_STEP, &MAX_SEEK_STEP, &seconds]));
+            }
+        }
+        self.seek_step_short = short;
+        self.seek_step_long = long;
+        self.settings.set_value("seek_step_short", short);
+        self.settings.set_value("seek_step_long", long);
+        Ok(())
+    }
+    
+    /// ←/→とスキップボタンで移動する秒数（短い方, 長い方）
+    pub fn get_seek_steps(&self) -> (f32, f32) {
+        (self.seek_step_short, self.seek_step_long)
+    }
+    
+    /// 1フレーム戻る（一時停止する）。直近に表示したフレームが残っていればデコードし直さずに表示する
+    pub fn step_backward(&mut self) {
+        if self.video_path.is_none() {
+            return;
+        }
+        let frame_duration = 1.0 / self.fps;
+        let current = self.get_current_time();
+        if current < frame_duration * 0.5 {
+            return;
+        }
+        let target = (current - frame_duration).max(0.0);
+        self.pause();
+        
+        // 一時停止中の順方向の再生でだけ使える（逆再生・停止中はフレームを残していない）
+        let cached = if self.is_paused() && !self.reverse {
+            let mut history = self.frame_history.lock().unwrap();
+            while history.frames.back().is_some_and(|(time, _)| *time > target + frame_duration * 0.5) {
+                history.frames.pop_back();
+            }
+            match history.frames.back() {
+                Some((time, _)) if (*time - target).abs() < frame_duration * 0.5 => history.frames.pop_back(),
+                _ => None,
+            }
+        } else {
+            None
+        };
+        
+        let Some((time, frame)) = cached else {
+            // 残っているフレームより前に戻る場合は-ssで取り出す
+            println!("{}", trf("Previous frame: {}s", &[&format!("{:.3}", target)]));
+            self.seek(target);
+            return;
+        };
+        
+        // 一時停止中の再生スレッドを戻った位置から起動し直す（再開したときに続きから再生できるように）
+        let frames = std::mem::take(&mut self.frame_history.lock().unwrap().frames);
+        *self.seek_time.lock().unwrap() = Some(time);
+        *self.current_time.lock().unwrap() = time;
+        let _ = self.start_playback(true);
+        self.observers.notify_frame(&frame);
+        self.current_frame.lock().unwrap().replace(frame);
+        *self.frame_history.lock().unwrap() = FrameHistory {
+            generation: *self.playback_generation.lock().unwrap(),
+            frames,
+        };
+        println!("{}", trf("Previous frame (cached): {}s", &[&format!("{:.3}", time)]));
+    }
+    
+    /// 1フレーム進む（一時停止する）
+    pub fn step_forward(&mut self) {
+        if self.video_path.is_none() {
+            return;
+        }
+        let target = self.get_current_time() + 1.0 / self.fps;
+        if target >= self.duration {
+            return;
+        }
+        self.pause();
+        self.seek(target);
+    }
+    
+    /// 現在位置より前の最も近いキーフレームへ移動（なければ先頭へ）
+    pub fn prev_keyframe(&mut self) -> Result<(), String> {
+        let current = self.get_current_time();
+        let target = {
+            let keyframes = self.keyframes.lock().unwrap();
+            let Some(times) = keyframes.as_ref() else {
+                return Err(tr("Keyframes are still being analyzed").to_string());
+            };
+            // 再生中は時刻が進むので、直前のキーフレームで止まり続けないよう少し余裕を持たせる
+            let margin = if self.is_playing() { 0.5 } else { 0.01 };
+            times.iter().rev().copied().find(|&time| time < current - margin).unwrap_or(0.0)
+        };
+        self.seek(target);
+        Ok(())
+    }
+    
+    fn generate_waveform_cached(
+        path: &PathBuf,
+        duration: f32,
+        samples: usize,
+        cache: &Mutex<WaveformCache>,
+        disk_cache: &DiskCache,
+    ) -> Vec<f32> {
+        let key = (path.clone(), samples);
+        if let Some(peaks) = cache.lock().unwrap().get(&key) {
+            return peaks;
+        }
 
-        match output {
-            Ok(output) if output.status.success() => {
-                let info_str = String::from_utf8_lossy(&output.stdout);
-                
-                let mut width: u32 = 1280;
-                let mut height: u32 = 720;
-                let mut duration: f32 = 0.0;
-                let mut fps: f32 = 30.0;
-                
-                // key=value 形式で解析
-                for line in info_str.lines() {
-                    let line = line.trim();
-                    if let Some((key, value)) = line.split_once('=') {
-                        match key {
-                            "width" => {
-                                if let Ok(w) = value.parse::<u32>() {
-                                    width = w;
-                                }
-                            }
-                            "height" => {
-                                if let Ok(h) = value.parse::<u32>() {
-                                    height = h;
-                                }
-                            }
-                            "avg_frame_rate" => {
-                                if let Some(parsed_fps) = Self::parse_frame_rate(value) {
-                                    fps = parsed_fps;
-                                }
-                            }
-                            "duration" => {
-                                if let Ok(d) = value.parse::<f32>() {
-                                    if d > 0.0 {
-                                        duration = d;
-                                    }
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
+        // ディスクにあればデコードせずに使う（ピーク値をf32のリトルエンディアンで並べたもの）
+        let disk_key = DiskCache::key(path, "waveform", &samples.to_string());
+        let stored = disk_key
+            .as_deref()
+            .and_then(|key| disk_cache.get(key))
+            .filter(|data| data.len() == samples * 4)
+            .map(|data| data.chunks_exact(4).map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])).collect());
+        let peaks = match stored {
+            Some(peaks) => peaks,
+            None => {
+                let peaks = Self::decode_waveform(path, duration, samples);
+                // 音声のないファイルは保存しない（空のデータは読み込み時に壊れたものと区別できない）
+                if let (Some(key), false) = (&disk_key, peaks.is_empty()) {
+                    let data: Vec<u8> = peaks.iter().flat_map(|peak| peak.to_le_bytes()).collect();
+                    disk_cache.put(key, &data);
                 }
-                
-                if duration > 0.0 {
-                    let (scaled_width, scaled_height) = self.calculate_scaled_size(width, height);
-                    println!("動画情報: {}x{} → {}x{}, duration={:.2}秒, avg_fps={:.2}", width, height, scaled_width, scaled_height, duration, fps);
-                    Ok((duration, fps, scaled_width, scaled_height))
-                } else {
-                    Err("動画のdurationを取得できませんでした".to_string())
+                peaks
+            }
+        };
+        cache.lock().unwrap().insert(key, peaks.clone());
+        peaks
+    }
+
+    fn decode_waveform(path: &PathBuf, duration: f32, samples: usize) -> Vec<f32> {
+        if samples == 0 || duration <= 0.0 {
+            return Vec::new();
+        }
+
+        // モノラル・16bitのPCMとして標準出力に書き出す
+        let mut child = match ffmpeg_log::configure(ffmpeg_command("ffmpeg")
+            .args(["-v", "error", "-i"])
+            .arg(path)
+            .args(&[
+                "-vn",
+                "-ac", "1",
+                "-ar", &WAVEFORM_SAMPLE_RATE.to_string(),
+                "-f", "s16le",
+                "-",
+            ]))
+            .stdout(Stdio::piped())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) => {
+                println!("{}", trf("Failed to start ffmpeg for the waveform: {}", &[&e]));
+                return Vec::new();
+            }
+        };
+        ffmpeg_log::capture("ffmpeg", &mut child);
+
+        let mut stdout = child.stdout.take().unwrap();
+
+        // 全体を保持せず、区間ごとのピークだけを保持する（メモリは動画の長さに依存しない）
+        let total_samples = ((duration * WAVEFORM_SAMPLE_RATE as f32) as usize).max(1);
+        let mut peaks = vec![0.0f32; samples];
+        let mut buffer = vec![0u8; 64 * 1024];
+        let mut leftover: Option<u8> = None;
+        let mut sample_index: usize = 0;
+
+        loop {
+            let n = match stdout.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) => {
+                    println!("{}", trf("Waveform read error: {}", &[&e]));
+                    break;
+                }
+            };
+
+            let mut bytes = &buffer[..n];
+            // 前回の読み込みで半端に残ったバイトを補完
+            if let Some(low) = leftover.take() {
+                let value = i16::from_le_bytes([low, bytes[0]]);
+                Self::accumulate_peak(&mut peaks, sample_index, total_samples, value);
+                sample_index += 1;
+                bytes = &bytes[1..];
+            }
+
+            let mut chunks = bytes.chunks_exact(2);
+            for pair in &mut chunks {
+                let value = i16::from_le_bytes([pair[0], pair[1]]);
+                Self::accumulate_peak(&mut peaks, sample_index, total_samples, value);
+                sample_index += 1;
+            }
+            leftover = chunks.remainder().first().copied();
+        }
+
+        let _ = child.wait();
+
+        // 音声ストリームがない、または完全な無音の場合は波形を表示しない
+        let max_peak = peaks.iter().cloned().fold(0.0f32, f32::max);
+        if sample_index == 0 || max_peak < 0.001 {
+            println!("{}", tr("Waveform: not shown because there is no audio or it is silent"));
+            return Vec::new();
+        }
+
+        // 一番大きなピークを1.0として正規化
+        for peak in peaks.iter_mut() {
+            *peak /= max_peak;
+        }
+
+        println!("{}", trf("Generated the waveform ({} sections, {} samples)", &[&samples, &sample_index]));
+        peaks
+    }
+
+    fn accumulate_peak(peaks: &mut [f32], sample_index: usize, total_samples: usize, value: i16) {
+        let bucket = (sample_index * peaks.len() / total_samples).min(peaks.len() - 1);
+        let amplitude = (value as f32).abs() / i16::MAX as f32;
+        if amplitude > peaks[bucket] {
+            peaks[bucket] = amplitude;
+        }
+    }
+
+    // ffplayに渡す音声フィルターチェーンを組み立てる
+    fn build_audio_filters(&self, audio_start: f32) -> Vec<String> {
+        let mut filters: Vec<String> = Vec::new();
+        
+        // 正確なシークでは先頭からデコードし、開始位置までを捨てる
+        if self.effective_seek_mode() == SeekMode::Accurate && audio_start > 0.0 {
+            filters.push(format!("atrim=start={},asetpts=PTS-STARTPTS", audio_start));
+        }
+        
+        // 開始位置が負になる（先頭より前）場合は、その分だけ無音で遅らせる
+        if audio_start < 0.0 {
+            filters.push(format!("adelay=delays={}:all=1", (-audio_start * 1000.0).round() as i64));
+        }
+        
+        // モノラル化（元がモノラルなら不要、3ch以上はまとめてダウンミックス）
+        let is_mono_source = self.audio_channels == 1;
+        if self.mono && !is_mono_source {
+            if self.audio_channels == 2 {
+                filters.push("pan=mono|c0=0.5*c0+0.5*c1".to_string());
+            } else {
+                filters.push("aformat=channel_layouts=mono".to_string());
+            }
+        }
+        
+        // 左右バランス（モノラルの場合は1チャンネルを左右に振り分ける）
+        if self.balance != 0.0 {
+            let left = (1.0 - self.balance).min(1.0);
+            let right = (1.0 + self.balance).min(1.0);
+            if self.mono || is_mono_source {
+                filters.push(format!("pan=stereo|c0={:.3}*c0|c1={:.3}*c0", left, right));
+            } else {
+                if self.audio_channels > 2 {
+                    filters.push("aformat=channel_layouts=stereo".to_string());
                 }
+                filters.push(format!("pan=stereo|c0={:.3}*c0|c1={:.3}*c1", left, right));
+            }
+        }
+        
+        // イコライザー
+        filters.extend(equalizer::filters(&self.equalizer));
+        
+        // 再生速度（atempoは1段あたり0.5倍まで）
+        if self.playback_speed != 1.0 && !self.reverse {
+            let mut tempo = self.playback_speed;
+            while tempo < 0.5 {
+                filters.push("atempo=0.5".to_string());
+                tempo /= 0.5;
+            }
+            filters.push(format!("atempo={}", tempo));
+        }
+        
+        // 動画ごとに測ったゲインで音量を揃える
+        if let Some(gain) = self.loudness_gain.filter(|_| self.match_loudness) {
+            filters.push(format!("volume={:.2}dB", gain));
+        }
+        
+        // 均一化の後に音量を掛ける（音量スライダーが最終的な大きさを決める）
+        if self.normalize_loudness {
+            filters.push("dynaudnorm".to_string());
+        }
+        
+        filters.push(self.volume_envelope.filter(self.volume_boost)); // ボリュームフィルター（ブースト込み）
+        filters
+    }
+    
+    /// interval秒ごとのフレームをPNGとしてout_dirに書き出す（ワーカースレッドで実行）
+    pub fn export_frames(&self, interval: f32, out_dir: PathBuf) -> Result<(), String> {
+        let Some(path) = self.video_path.clone() else {
+            return Err(tr("No video file is loaded").to_string());
+        };
+        if interval <= 0.0 {
+            return Err(tr("The interval must be greater than 0").to_string());
+        }
+        
+        let duration = self.duration;
+        self.start_task(tr("Exporting frames..."), move |task_progress| {
+            Self::export_frames_worker(&path, interval, &out_dir, duration, task_progress)
+        })
+    }
+    
+    /// timeの位置のフレームを正確なシークで抽出し、PNGとして保存する（ワーカースレッドで実行）
+    /// original_sizeなら元の解像度のまま、そうでなければ表示と同じ大きさ・画質調整で保存する
+    pub fn save_frame_at(&self, time: f32, path: PathBuf, original_size: bool) -> Result<(), String> {
+        let Some(video_path) = self.video_path.clone() else {
+            return Err(tr("No video file is loaded").to_string());
+        };
+        if !(0.0..=self.duration).contains(&time) {
+            return Err(trf("The time is outside the video ({}s)", &[&format!("{:.3}", time)]));
+        }
+        
+        let filters = if original_size { None } else { Some(self.build_image_filters()) };
+        self.start_task(tr("Saving the frame..."), move |_| {
+            let mut command = ffmpeg_command("ffmpeg");
+            command.args(&["-v", "error", "-y"]);
+            // 表示中のフレームはキーフレームに寄せたものかもしれないので、常に-iの後に-ssを置く
+            command.args(SeekMode::Accurate.input_args(&video_path, time));
+            if let Some(filters) = &filters {
+                command.args(&["-vf", filters]);
+            }
+            command.args(&["-frames:v", "1", "-update", "1"]).arg(&path);
+            
+            let output = ffmpeg_log::configure(&mut command)
+                .stdout(Stdio::null())
+                .output()
+                .map_err(|e| trf("Failed to start ffmpeg: {}", &[&e]))?;
+            let detail = ffmpeg_log::record_output("ffmpeg", &output.stderr);
+            if !output.status.success() || !path.exists() {
+                return Err(ffmpeg_log::with_detail(trf("ffmpeg exited with an error ({})", &[&output.status]), detail));
+            }
+            println!("{}", trf("Saved the frame: {}s → {}", &[&format!("{:.3}", time), &path.display()]));
+            Ok(trf("Saved the frame: {}", &[&path.display()]))
+        })
+    }
+    
+    /// start〜endの区間をアニメーションGIFとして書き出す（widthが0なら元の幅、高さは縦横比を維持）
+    pub fn export_gif(&self, start: f32, end: f32, fps: u32, width: u32, out: PathBuf) -> Result<(), String> {
+        let Some(path) = self.video_path.clone() else {
+            return Err(tr("No video file is loaded").to_string());
+        };
+        let start = start.max(0.0);
+        let end = end.min(self.duration);
+        if end <= start {
+            return Err(tr("The export range is invalid").to_string());
+        }
+        // GIFは長くするとファイルサイズが膨れ上がるため制限する
+        if end - start > MAX_GIF_DURATION {
+            return Err(trf("A GIF can be at most {}s long (selected range: {}s)", &[&MAX_GIF_DURATION, &format!("{:.1}", end - start)]));
+        }
+        if fps == 0 || fps > 50 {
+            return Err(tr("The GIF frame rate must be between 1 and 50").to_string());
+        }
+        
+        self.start_task(tr("Exporting GIF..."), move |task_progress| {
+            Self::export_gif_worker(&path, start, end, fps, width, &out, task_progress)
+        })
+    }
+    
+    fn export_gif_worker(
+        path: &Path,
+        start: f32,
+        end: f32,
+        fps: u32,
+        width: u32,
+        out: &Path,
+        task_progress: &Mutex<Option<TaskProgress>>,
+    ) -> Result<String, String> {
+        let scale = if width > 0 {
+            format!(",scale={}:-1:flags=lanczos", width)
+        } else {
+            String::new()
+        };
+        let filters = format!("fps={}{}", fps, scale);
+        let length = end - start;
+        
+        // 1回目: 区間の色から最適なパレットを生成
+        let palette = std::env::temp_dir().join(format!("video-player-ui-palette-{}.png", std::process::id()));
+        let mut command = ffmpeg_command("ffmpeg");
+        command.args(image_sequence::input_options(path));
+        command.args(&[
+            "-v", "error",
+            "-nostats",
+            "-y",
+            "-ss", &start.to_string(),
+            "-t", &length.to_string(),
+            "-i",
+        ]);
+        command.arg(path);
+        command.args([
+            "-vf", &format!("{},palettegen", filters),
+            "-progress", "pipe:1",
+        ]);
+        command.arg(&palette);
+        let result = Self::run_ffmpeg_with_progress(&mut command, ProgressTotal::duration(length), task_progress, (0.0, 0.5)).and_then(|_| {
+            // 2回目: パレットを使って減色しながらGIFを生成
+            let mut command = ffmpeg_command("ffmpeg");
+            command.args(image_sequence::input_options(path));
+            command.args(&[
+                "-v", "error",
+                "-nostats",
+                "-y",
+                "-ss", &start.to_string(),
+                "-t", &length.to_string(),
+                "-i",
+            ]);
+            command.arg(path).arg("-i").arg(&palette);
+            command.args([
+                "-lavfi", &format!("{}[x];[x][1:v]paletteuse", filters),
+                "-progress", "pipe:1",
+            ]);
+            command.arg(out);
+            Self::run_ffmpeg_with_progress(&mut command, ProgressTotal::duration(length), task_progress, (0.5, 1.0))
+        });
+        let _ = std::fs::remove_file(&palette);
+        result?;
+        
+        println!("{}", trf("Exported GIF: {}", &[&out.display()]));
+        Ok(trf("Exported GIF: {}", &[&out.display()]))
+    }
+    
+    /// 動画をrotation度（時計回り）回転させたコピーを保存する（ワーカースレッドで実行）
+    /// MP4・MOVなら回転情報だけを書き換えて無劣化（-c copy）で、できなければ再エンコードする
+    pub fn save_rotated_copy(&self, rotation: u32, out: PathBuf) -> Result<(), String> {
+        let Some(path) = self.video_path.clone() else {
+            return Err(tr("No video file is loaded").to_string());
+        };
+        if path == out {
+            return Err(tr("Cannot write over the source video").to_string());
+        }
+        let rotation = rotation % 360 / 90 * 90;
+        if rotation == 0 {
+            return Err(tr("Choose a rotation other than 0°").to_string());
+        }
+        
+        let duration = self.duration;
+        self.start_task(tr("Saving a rotated copy..."), move |task_progress| {
+            Self::save_rotated_copy_worker(&path, rotation, &out, duration, task_progress)
+        })
+    }
+    
+    fn save_rotated_copy_worker(
+        path: &Path,
+        rotation: u32,
+        out: &Path,
+        duration: f32,
+        task_progress: &Mutex<Option<TaskProgress>>,
+    ) -> Result<String, String> {
+        // 回転情報を持てるのはMP4系のコンテナだけ（連番の画像は元がファイルでないのでコピーできない）
+        let supports_metadata = out
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| ["mp4", "m4v", "mov"].iter().any(|e| e.eq_ignore_ascii_case(extension)));
+        if supports_metadata && !image_sequence::is_sequence(path) {
+            // 元の回転情報に足した角度を書き込む（再生時の自動回転で、表示中と同じ向きになる）
+            let target = (Self::probe_rotation(path) + rotation) % 360;
+            Self::set_task_label(task_progress, tr("Saving a rotated copy (lossless)..."));
+            if Self::rotate_by_metadata(path, target, out, duration, task_progress) {
+                println!("{}", trf("Saved a rotated copy (rotation metadata only, {}°): {}", &[&target, &out.display()]));
+                println!("{}", tr("Warning: players that ignore rotation metadata will show it in the original orientation"));
+                return Ok(trf("Saved a rotated copy without re-encoding (players that ignore rotation metadata will show it unrotated): {}", &[&out.display()]));
+            }
+            println!("{}", tr("Warning: could not write the rotation metadata, re-encoding instead"));
+        }
+        
+        Self::set_task_label(task_progress, tr("Re-encoding a rotated copy..."));
+        let transpose = match rotation {
+            90 => "transpose=1",
+            180 => "hflip,vflip",
+            _ => "transpose=2",
+        };
+        let mut command = ffmpeg_command("ffmpeg");
+        command.args(image_sequence::input_options(path));
+        command.args(&[
+            "-v", "error",
+            "-nostats",
+            "-y",
+            "-i",
+        ]);
+        command.arg(path);
+        command.args([
+            "-map", "0:v:0",
+            "-map", "0:a?",
+            "-vf", transpose,
+            "-c:v", "libx264",
+            "-preset", "veryfast",
+            "-crf", "20",
+            "-c:a", "aac",
+            "-b:a", "192k",
+            "-progress", "pipe:1",
+        ]);
+        command.arg(out);
+        Self::run_ffmpeg_with_progress(&mut command, ProgressTotal::duration(duration), task_progress, (0.0, 1.0))?;
+        println!("{}", trf("Saved a rotated copy (re-encoded, {}°): {}", &[&rotation, &out.display()]));
+        Ok(trf("Saved a rotated copy (re-encoded): {}", &[&out.display()]))
+    }
+    
+    // 回転情報だけを書き換えてコピーし、書き込めたかを出力を読み直して確かめる
+    // 新しいffmpegは-display_rotation（反時計回り）、古いffmpegはrotateタグで指定する
+    fn rotate_by_metadata(path: &Path, target: u32, out: &Path, duration: f32, task_progress: &Mutex<Option<TaskProgress>>) -> bool {
+        let counter_clockwise = ((360 - target) % 360).to_string();
+        let clockwise = format!("rotate={}", target);
+        let attempts: [(&[&str], &[&str]); 2] = [
+            (&["-display_rotation:v:0", &counter_clockwise], &[]),
+            (&[], &["-metadata:s:v:0", &clockwise]),
+        ];
+        for (input_args, output_args) in attempts {
+            let mut command = ffmpeg_command("ffmpeg");
+            command
+                .args(&["-v", "error", "-nostats", "-y"])
+                .args(input_args)
+                .arg("-i")
+                .arg(path)
+                .args(["-map", "0", "-c", "copy"])
+                .args(output_args)
+                .args(["-progress", "pipe:1"])
+                .arg(out);
+            if Self::run_ffmpeg_with_progress(&mut command, ProgressTotal::duration(duration), task_progress, (0.0, 1.0)).is_ok()
+                && Self::probe_rotation(out) == target
+            {
+                return true;
             }
-            _ => Err("ffprobeが利用できません".to_string())
         }
+        let _ = std::fs::remove_file(out);
+        false
     }
     
-    fn parse_frame_rate(s: &str) -> Option<f32> {
-        if let Some((num_str, den_str)) = s.split_once('/') {
-            if let (Ok(num), Ok(den)) = (num_str.trim().parse::<f32>(), den_str.trim().parse::<f32>()) {
-                if den > 0.0 {
-                    return Some(num / den);
-                }
-            }
+    // 動画に記録された回転（時計回りの角度。ディスプレイ行列は反時計回りなので向きを直す）
+    fn probe_rotation(path: &Path) -> u32 {
+        let Ok(output) = ffmpeg_command("ffprobe")
+            .args(&[
+                "-v", "error",
+                "-select_streams", "v:0",
+                "-show_entries", "stream_tags=rotate:stream_side_data=rotation",
+                "-print_format", "json",
+            ])
+            .arg(path)
+            .output()
+        else {
+            return 0;
+        };
+        json::parse(&String::from_utf8_lossy(&output.stdout))
+            .ok()
+            .and_then(|root| root.get("streams").and_then(|streams| streams.items().first()).map(Self::stream_rotation))
+            .unwrap_or(0)
+    }
+    
+    // ffprobeのJSONのストリームから、時計回りの回転角度（0・90・180・270）を読む
+    // 表示行列（side_data_listのrotation）は反時計回り、古いrotateタグは時計回り
+    fn stream_rotation(stream: &json::Value) -> u32 {
+        let side_data = stream
+            .get("side_data_list")
+            .and_then(|list| list.items().iter().find_map(|data| data.get("rotation")?.as_str()?.parse::<f32>().ok()));
+        let tag = stream
+            .get("tags")
+            .and_then(|tags| tags.get("rotate")?.as_str()?.parse::<f32>().ok());
+        let clockwise = match (side_data, tag) {
+            (Some(rotation), _) => -rotation,
+            (None, Some(rotate)) => rotate,
+            (None, None) => 0.0,
+        };
+        ((clockwise / 90.0).round() as i32 * 90).rem_euclid(360) as u32
+    }
+    
+    /// プレイリストの動画を1つのファイルに結合する
+    /// 形式がすべて同じなら無劣化（-c copy）で、異なれば最初の動画に合わせて再エンコードする
+    pub fn concat_playlist(&self, out: PathBuf) -> Result<(), String> {
+        if self.playlist.len() < 2 {
+            return Err(tr("Joining needs at least two videos in the playlist").to_string());
         }
-        // 分数でなければ直接パース
-        s.trim().parse::<f32>().ok().filter(|&v| v > 0.0)
+        if self.playlist.iter().any(|path| path == &out) {
+            return Err(tr("Cannot write over one of the source videos").to_string());
+        }
+        
+        let paths = self.playlist.clone();
+        self.start_task(tr("Checking the videos to join..."), move |task_progress| {
+            Self::concat_worker(&paths, &out, task_progress)
+        })
     }
-
-    fn calculate_scaled_size(&self, orig_width: u32, orig_height: u32) -> (u32, u32) {
-        let max_width = 960u32;
-        let max_height = 600u32;
+    
+    fn concat_worker(paths: &[PathBuf], out: &Path, task_progress: &Mutex<Option<TaskProgress>>) -> Result<String, String> {
+        let sources = paths
+            .iter()
+            .map(|path| Self::probe_concat_source(path))
+            .collect::<Result<Vec<_>, _>>()?;
+        let total_duration: f32 = sources.iter().map(|source| source.duration).sum();
+        
+        let can_copy = sources.iter().all(|source| source.signature == sources[0].signature);
+        if can_copy {
+            Self::set_task_label(task_progress, tr("Joining videos (lossless)..."));
+            Self::concat_copy(&sources, out, total_duration, task_progress)?;
+            println!("{}", trf("Joined the videos (lossless): {}", &[&out.display()]));
+            Ok(trf("Joined {} videos", &[&sources.len()]))
+        } else {
+            println!("{}", tr("Warning: the video formats differ, re-encoding to join them"));
+            Self::set_task_label(task_progress, tr("Re-encoding and joining because the formats differ..."));
+            Self::concat_reencode(&sources, out, total_duration, task_progress)?;
+            println!("{}", trf("Joined the videos (re-encoded): {}", &[&out.display()]));
+            Ok(trf("Joined {} videos (re-encoded because the formats differ)", &[&sources.len()]))
+        }
+    }
+    
+    // 結合の可否判定に使うストリーム情報を取得する
+    fn probe_concat_source(path: &Path) -> Result<ConcatSource, String> {
+        let probe = |select: &str, entries: &str| -> Result<String, String> {
+            let output = ffmpeg_command("ffprobe")
+                .args(&[
+                    "-v", "error",
+                    "-select_streams", select,
+                    "-show_entries", entries,
+                    "-of", "default=noprint_wrappers=1",
+                ])
+                .arg(path)
+                .output()
+                .map_err(|e| trf("Failed to start ffprobe: {}", &[&e]))?;
+            if !output.status.success() {
+                return Err(trf("Cannot read video info: {}", &[&path.display()]));
+            }
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        };
+        
+        let video = probe("v:0", "stream=codec_name,profile,width,height,pix_fmt,avg_frame_rate,time_base")?;
+        let audio = probe("a:0", "stream=codec_name,sample_rate,channels")?;
+        let format = probe("v:0", "format=duration")?;
         
-        if orig_width <= max_width && orig_height <= max_height {
-            return (orig_width, orig_height);
+        let value = |text: &str, key: &str| {
+            text.lines()
+                .find_map(|line| line.split_once('=').filter(|(k, _)| *k == key).map(|(_, v)| v.to_string()))
+                .unwrap_or_default()
+        };
+        let width = value(&video, "width").parse::<u32>().unwrap_or(0);
+        let height = value(&video, "height").parse::<u32>().unwrap_or(0);
+        if width == 0 || height == 0 {
+            return Err(trf("No video stream found: {}", &[&path.display()]));
         }
         
-        let width_ratio = max_width as f32 / orig_width as f32;
-        let height_ratio = max_height as f32 / orig_height as f32;
-        let ratio = width_ratio.min(height_ratio);
+        Ok(ConcatSource {
+            path: path.to_path_buf(),
+            signature: format!("{}\n{}", video, audio),
+            duration: value(&format, "duration").parse::<f32>().unwrap_or(0.0),
+            has_audio: !audio.is_empty(),
+            width,
+            height,
+            fps: Self::parse_frame_rate(&value(&video, "avg_frame_rate")).unwrap_or(30.0),
+        })
+    }
+    
+    // concatデマルチプレクサで無劣化結合する
+    fn concat_copy(sources: &[ConcatSource], out: &Path, total_duration: f32, task_progress: &Mutex<Option<TaskProgress>>) -> Result<(), String> {
+        // 一時的なリストファイル（他のインスタンスと衝突しないよう一意な名前にする）
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        let list_path = std::env::temp_dir().join(format!("video-player-ui-concat-{}-{}.txt", std::process::id(), nanos));
         
-        let new_width = (orig_width as f32 * ratio) as u32;
-        let new_height = (orig_height as f32 * ratio) as u32;
+        let list = sources
+            .iter()
+            .map(|source| Self::concat_list_entry(&source.path))
+            .collect::<Result<String, String>>()?;
+        std::fs::write(&list_path, list).map_err(|e| trf("Cannot create a temporary file: {}", &[&e]))?;
         
-        // 偶数にする（ffmpegの要件）
-        (new_width & !1, new_height & !1)
+        let mut command = ffmpeg_command("ffmpeg");
+        command.args(&[
+            "-v", "error",
+            "-nostats",
+            "-y",
+            "-f", "concat",
+            "-safe", "0",
+            "-i",
+        ]);
+        command.arg(&list_path);
+        command.args(["-c", "copy", "-progress", "pipe:1"]);
+        command.arg(out);
+        let result = Self::run_ffmpeg_with_progress(&mut command, ProgressTotal::duration(total_duration), task_progress, (0.0, 1.0));
+        let _ = std::fs::remove_file(&list_path);
+        result
     }
-
-    fn load_first_frame(&mut self, path: &PathBuf) -> Result<(), String> {
-        // 最初のフレームを抽出
-        let output = Command::new("ffmpeg")
-            .args(&[
-                "-i", path.to_str().unwrap(),
-                "-vf", &format!("scale={}:{}", self.video_width, self.video_height),
-                "-vframes", "1",
-                "-f", "image2pipe",
-                "-vcodec", "ppm",
-                "-"
-            ])
-            .stdout(Stdio::piped())
-            .stderr(Stdio::null())
-            .output();
-
-        match output {
-            Ok(output) if output.status.success() && !output.stdout.is_empty() => {
-                match image::load_from_memory(&output.stdout) {
-                    Ok(img) => {
-                        let rgba = img.to_rgba8();
-                        *self.current_frame.lock().unwrap() = Some(rgba);
-                        Ok(())
-                    }
-                    Err(e) => Err(format!("画像の読み込みエラー: {}", e))
-                }
-            }
-            Ok(_) => Err("フレームの抽出に失敗".to_string()),
-            Err(e) => Err(format!("ffmpegエラー: {}", e))
+    
+    // concatリストの1行（パスは「'」で囲み、中の「'」は一度閉じてから「\'」で書く）
+    // 1行ずつ読まれるので改行を含むパスは書けない。表せない文字を含むパスも別のファイルを指してしまうので断る
+    fn concat_list_entry(path: &Path) -> Result<String, String> {
+        match path.to_str() {
+            Some(path) if !path.contains(['\n', '\r']) => Ok(format!("file '{}'\n", path.replace('\'', "'\\''"))),
+            _ => Err(trf("This file name cannot be used for joining: {}", &[&path.display()])),
         }
     }
-
-    pub fn play(&mut self) -> Result<(), String> {
-        // 一時停止からの再開
-        if *self.is_paused.lock().unwrap() {
-            *self.is_paused.lock().unwrap() = false;
-            *self.is_playing.lock().unwrap() = true;
-            self.start_audio_playback()?;
-            println!("一時停止から再開");
-            return Ok(());
+    
+    // image2の連番の出力先（フォルダ名・ファイル名の「%」は連番の指定と解釈されるので「%%」にする）
+    fn image_sequence_pattern(out_dir: &Path, stem: &str) -> String {
+        let prefix = out_dir.join(stem).to_string_lossy().replace('%', "%%");
+        format!("{}_%06d.png", prefix)
+    }
+    
+    // 解像度・fps・音声形式を最初の動画に揃えながらconcatフィルターで結合する
+    fn concat_reencode(sources: &[ConcatSource], out: &Path, total_duration: f32, task_progress: &Mutex<Option<TaskProgress>>) -> Result<(), String> {
+        let width = sources[0].width / 2 * 2;
+        let height = sources[0].height / 2 * 2;
+        let fps = sources[0].fps;
+        let any_audio = sources.iter().any(|source| source.has_audio);
+        
+        let mut args: Vec<String> = vec!["-v".into(), "error".into(), "-nostats".into(), "-y".into()];
+        for source in sources {
+            args.extend(["-i".to_string(), source.path.display().to_string()]);
         }
         
-        if let Some(path) = &self.video_path {
-            *self.is_playing.lock().unwrap() = true;
-            *self.is_paused.lock().unwrap() = false;
-            *self.video_finished.lock().unwrap() = false;
-            
-            // メインスレッドでseek_timeを読み取り・クリア（レースコンディション防止）
-            let start_position = {
-                let mut seek = self.seek_time.lock().unwrap();
-                let pos = seek.unwrap_or(0.0);
-                *seek = None;
-                pos
-            };
+        let mut filters = String::new();
+        let mut concat_inputs = String::new();
+        let mut silent_inputs = 0;
+        for (i, source) in sources.iter().enumerate() {
+            filters.push_str(&format!(
+                "[{i}:v:0]scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,setsar=1,fps={fps},format=yuv420p[v{i}];",
+                i = i, w = width, h = height, fps = fps
+            ));
+            concat_inputs.push_str(&format!("[v{}]", i));
             
-            // 再生世代をインクリメント
-            let generation = {
-                let mut gen = self.playback_generation.lock().unwrap();
-                *gen += 1;
-                *gen
-            };
-            
-            let path_str = path.to_str().unwrap().to_string();
-            let is_playing = Arc::clone(&self.is_playing);
-            let is_paused = Arc::clone(&self.is_paused);
-            let video_finished = Arc::clone(&self.video_finished);
-            let current_time = Arc::clone(&self.current_time);
-            let current_frame = Arc::clone(&self.current_frame);
-            let playback_generation = Arc::clone(&self.playback_generation);
-            let duration = self.duration;
-            let fps = self.fps;
-            let width = self.video_width;
-            let height = self.video_height;
-            
-            // 別スレッドで動画を再生（start_positionをパラメータとして渡す）
-            // 音声より先にスレッドを生成（音声失敗で動画が止まるのを防止）
-            thread::spawn(move || {
-                Self::play_video_with_frames(&path_str, is_playing, is_paused, video_finished, current_time, current_frame, playback_generation, generation, duration, fps, width, height, start_position);
-            });
-            
-            // 音声再生を開始（失敗しても動画は再生し続ける）
-            if let Err(e) = self.start_audio_playback() {
-                println!("音声再生の開始に失敗（動画は継続）: {}", e);
+            if any_audio {
+                // 音声のない動画には同じ長さの無音を割り当てる
+                let audio_input = if source.has_audio {
+                    format!("{}:a:0", i)
+                } else {
+                    args.extend([
+                        "-f".to_string(), "lavfi".to_string(),
+                        "-t".to_string(), source.duration.to_string(),
+                        "-i".to_string(), "anullsrc=r=48000:cl=stereo".to_string(),
+                    ]);
+                    silent_inputs += 1;
+                    format!("{}:a", sources.len() + silent_inputs - 1)
+                };
+                filters.push_str(&format!("[{}]aresample=48000,aformat=sample_fmts=fltp:channel_layouts=stereo[a{}];", audio_input, i));
+                concat_inputs.push_str(&format!("[a{}]", i));
             }
-            
-            Ok(())
-        } else {
-            Err("動画ファイルが読み込まれていません".to_string())
         }
+        filters.push_str(&format!(
+            "{}concat=n={}:v=1:a={}{}",
+            concat_inputs,
+            sources.len(),
+            if any_audio { 1 } else { 0 },
+            if any_audio { "[v][a]" } else { "[v]" }
+        ));
+        
+        args.extend(["-filter_complex".to_string(), filters, "-map".to_string(), "[v]".to_string()]);
+        if any_audio {
+            args.extend(["-map".to_string(), "[a]".to_string(), "-c:a".to_string(), "aac".to_string(), "-b:a".to_string(), "192k".to_string()]);
+        }
+        args.extend([
+            "-c:v".to_string(), "libx264".to_string(),
+            "-preset".to_string(), "veryfast".to_string(),
+            "-crf".to_string(), "20".to_string(),
+            "-progress".to_string(), "pipe:1".to_string(),
+            out.display().to_string(),
+        ]);
+        
+        let mut command = ffmpeg_command("ffmpeg");
+        command.args(&args);
+        Self::run_ffmpeg_with_progress(&mut command, ProgressTotal::duration(total_duration), task_progress, (0.0, 1.0))
     }
-
-    fn play_video_with_frames(
-        path: &str,
-        is_playing: Arc<Mutex<bool>>,
-        is_paused: Arc<Mutex<bool>>,
-        video_finished: Arc<Mutex<bool>>,
-        current_time: Arc<Mutex<f32>>,
-        current_frame: Arc<Mutex<Option<RgbaImage>>>,
-        playback_generation: Arc<Mutex<u64>>,
-        my_generation: u64,
-        duration: f32,
-        fps: f32,
-        width: u32,
-        height: u32,
-        start_position: f32,
-    ) {
-        // start_positionはplay()のメインスレッドで確定済み（レースコンディション防止）
+    
+    fn set_task_label(task_progress: &Mutex<Option<TaskProgress>>, label: &str) {
+        if let Some(task) = task_progress.lock().unwrap().as_mut() {
+            task.label = label.to_string();
+        }
+    }
+    
+    // 書き出し処理をワーカースレッドで開始する（同時に実行できるのは1つまで）
+    fn start_task<F>(&self, label: &str, worker: F) -> Result<(), String>
+    where
+        F: FnOnce(&Mutex<Option<TaskProgress>>) -> Result<String, String> + Send + 'static,
+    {
+        let mut current = self.task_progress.lock().unwrap();
+        if current.is_some() {
+            return Err(tr("Another export is already running").to_string());
+        }
+        *current = Some(TaskProgress {
+            label: label.to_string(),
+            progress: Some(0.0),
+        });
+        drop(current);
         
-        println!("ffmpegで動画を再生中... (開始位置: {}秒, 世代: {})", start_position, my_generation);
+        let task_progress = Arc::clone(&self.task_progress);
+        let task_result = Arc::clone(&self.task_result);
+        self.spawn_worker(move || {
+            let result = worker(&task_progress);
+            *task_progress.lock().unwrap() = None;
+            *task_result.lock().unwrap() = Some(result);
+        });
         
-        // ffmpegでrawvideo形式でフレームを出力（RGBA形式）
-        // -r で出力fps固定、自前でフレームタイミングを制御
-        let mut args = vec![
-            "-ss".to_string(),
-            start_position.to_string(),
-        ];
-        args.extend_from_slice(&[
-            "-i".to_string(),
-            path.to_string(),
-            "-vf".to_string(),
-            format!("scale={}:{}", width, height),
-            "-r".to_string(),
-            format!("{}", fps),
-            "-f".to_string(),
-            "rawvideo".to_string(),
-            "-pix_fmt".to_string(),
-            "rgba".to_string(),
-            "-".to_string(),
+        Ok(())
+    }
+    
+    fn export_frames_worker(
+        path: &Path,
+        interval: f32,
+        out_dir: &Path,
+        duration: f32,
+        task_progress: &Mutex<Option<TaskProgress>>,
+    ) -> Result<String, String> {
+        std::fs::create_dir_all(out_dir).map_err(|e| trf("Cannot create the output folder: {}", &[&e]))?;
+        
+        // ffmpegが連番で直接ディスクに書き出す（長い動画でもメモリを消費しない）
+        let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "frame".to_string());
+        let pattern = Self::image_sequence_pattern(out_dir, &stem);
+        let mut command = ffmpeg_command("ffmpeg");
+        command.args(image_sequence::input_options(path));
+        command.args(&[
+            "-v", "error",
+            "-nostats",
+            "-y",
+            "-i",
         ]);
+        command.arg(path);
+        command.args([
+            "-vf", &format!("fps=1/{}", interval),
+            "-progress", "pipe:1",
+            &pattern,
+        ]);
+        // 書き出す枚数が分かっているのでフレーム数で進行状況を出す（長さが不明なら枚数も不明）
+        let total = if duration > 0.0 {
+            ProgressTotal::Frames((duration / interval).ceil() as u64)
+        } else {
+            ProgressTotal::Unknown
+        };
+        Self::run_ffmpeg_with_progress(&mut command, total, task_progress, (0.0, 1.0))?;
+        
+        // 連番のファイル名を動画内の時刻に置き換える
+        let mut count = 0;
+        loop {
+            let numbered = out_dir.join(format!("{}_{:06}.png", stem, count + 1));
+            if !numbered.exists() {
+                break;
+            }
+            let seconds = count as f32 * interval;
+            let timestamp = format!(
+                "{:02}-{:02}-{:06.3}",
+                (seconds / 3600.0) as u32,
+                ((seconds % 3600.0) / 60.0) as u32,
+                seconds % 60.0
+            );
+            let _ = std::fs::rename(&numbered, out_dir.join(format!("{}_{}.png", stem, timestamp)));
+            count += 1;
+        }
         
-        let mut child = match Command::new("ffmpeg")
-            .args(&args)
+        println!("{}", trf("Exported {} frames → {}", &[&count, &out_dir.display()]));
+        Ok(trf("Exported {} frames", &[&count]))
+    }
+    
+    // ffmpegを実行し、-progress pipe:1の出力（out_time_us / frame）から進行状況を更新する
+    // spanは全体の進行状況のうちこのコマンドが占める範囲（複数回に分けて実行する場合用）
+    // stdoutは終了まで読み切る（パイプが詰まってffmpegが止まらないように）。stderrはログを残す設定なら別スレッドで読む
+    fn run_ffmpeg_with_progress(
+        command: &mut Command,
+        total: ProgressTotal,
+        task_progress: &Mutex<Option<TaskProgress>>,
+        span: (f32, f32),
+    ) -> Result<(), String> {
+        let mut child = ffmpeg_log::configure(command)
             .stdout(Stdio::piped())
-            .stderr(Stdio::null())
             .spawn()
-        {
-            Ok(child) => child,
-            Err(e) => {
-                println!("ffmpegの起動に失敗: {}", e);
-                *is_playing.lock().unwrap() = false;
-                return;
+            .map_err(|e| trf("Failed to start ffmpeg: {}", &[&e]))?;
+        let log = ffmpeg_log::capture("ffmpeg", &mut child);
+        
+        let set_progress = |progress: Option<f32>| {
+            if let Some(task) = task_progress.lock().unwrap().as_mut() {
+                task.progress = progress.map(|ratio| span.0 + (span.1 - span.0) * ratio.clamp(0.0, 1.0));
             }
         };
-
-        let mut stdout = child.stdout.take().unwrap();
-        
-        // フレームカウントと壁時計を併用した時間追跡
-        let mut frame_count: u64 = 0;
-        let frame_duration_secs = 1.0_f64 / fps as f64;
-        let playback_start = Instant::now();
-        let mut total_paused: Duration = Duration::ZERO;
-        let mut pause_start_time: Option<Instant> = None;
+        if matches!(total, ProgressTotal::Unknown) {
+            set_progress(None);
+        }
         
-        // 1フレームのサイズを計算（RGBA = 4バイト/ピクセル）
-        let frame_size = (width * height * 4) as usize;
-        let mut frame_buffer = vec![0u8; frame_size];
-
-        loop {
-            // 世代番号をチェック（新しいシークや再生があれば、このスレッドは古くなっている）
-            if *playback_generation.lock().unwrap() != my_generation {
-                let _ = child.kill();
-                println!("新しい再生が開始されたため、古い再生スレッド（世代: {}）を終了", my_generation);
-                break;
-            }
-            
-            // 一時停止チェック: ffmpegプロセスは生かしたまま待機（停止チェックより先）
-            if *is_paused.lock().unwrap() {
-                if pause_start_time.is_none() {
-                    pause_start_time = Some(Instant::now());
-                }
-                thread::sleep(Duration::from_millis(30));
+        let stdout = child.stdout.take().unwrap();
+        for line in std::io::BufRead::lines(std::io::BufReader::new(stdout)).map_while(Result::ok) {
+            let Some((key, value)) = line.trim().split_once('=') else {
                 continue;
-            } else if let Some(ps) = pause_start_time.take() {
-                // 一時停止から復帰: 停止していた時間を累積
-                total_paused += ps.elapsed();
+            };
+            // 始まったばかりのout_timeは"N/A"になることがあるので、数値だけを使う
+            match (key, total) {
+                // out_time_msも実際はマイクロ秒
+                ("out_time_us" | "out_time_ms", ProgressTotal::Duration(duration)) => {
+                    if let Ok(us) = value.parse::<f64>() {
+                        set_progress(Some((us / 1_000_000.0) as f32 / duration));
+                    }
+                }
+                ("frame", ProgressTotal::Frames(frames)) if frames > 0 => {
+                    if let Ok(frame) = value.parse::<u64>() {
+                        set_progress(Some(frame as f32 / frames as f32));
+                    }
+                }
+                ("progress", ProgressTotal::Duration(_) | ProgressTotal::Frames(_)) if value == "end" => {
+                    set_progress(Some(1.0));
+                }
+                _ => {}
             }
-            
-            // 停止チェック（一時停止でない場合のみ到達）
-            if !*is_playing.lock().unwrap() {
-                let _ = child.kill();
-                println!("再生を停止しました");
-                break;
+        }
+        
+        match child.wait() {
+            Ok(status) if status.success() => Ok(()),
+            Ok(status) => Err(ffmpeg_log::with_detail(trf("ffmpeg exited with an error ({})", &[&status]), log.last_error())),
+            Err(e) => Err(trf("Failed to wait for ffmpeg: {}", &[&e])),
+        }
+    }
+    
+    pub fn get_task_progress(&self) -> Option<TaskProgress> {
+        self.task_progress.lock().unwrap().clone()
+    }
+    
+    /// 完了した書き出し処理の結果を一度だけ取り出す
+    pub fn take_task_result(&mut self) -> Option<Result<String, String>> {
+        self.task_result.lock().unwrap().take()
+    }
+    
+    fn stop_audio(&mut self) {
+        let mut audio_proc = self.audio_process.lock().unwrap();
+        if let Some(mut child) = audio_proc.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+            println!("{}", tr("Stopped the audio process"));
+        }
+    }
+    
+    // ワーカースレッドを起動し、終了処理で待てるようにハンドルを残す（終わったものはここで片付ける）
+    fn spawn_worker(&self, worker: impl FnOnce() + Send + 'static) {
+        let mut workers = self.workers.lock().unwrap();
+        workers.retain(|handle| !handle.is_finished());
+        workers.push(thread::spawn(worker));
+    }
+    
+    /// アプリの終了時に呼ぶ: ワーカースレッドに終了を知らせ、ffmpegを止めてから終わるのを待つ
+    /// 待つのはSHUTDOWN_TIMEOUTまで。待ちきれなかったスレッドが残っても、フレームはもう受け取らない
+    pub fn shutdown(&mut self) {
+        *self.shutting_down.lock().unwrap() = true;
+        // 反映を待っている間に終了しても、変えた設定は残す
+        self.save_equalizer();
+        self.save_filter_strengths();
+        self.end_scrub();
+        *self.frame_request.lock().unwrap() = None;
+        self.set_state(PlaybackState::Stopped);
+        
+        // 世代を進めると、読み込み・解析のスレッドは結果を捨てて終わる（再生中のffmpegもここで止まる）
+        self.advance_playback_generation();
+        for generation in [&self.load_generation, &self.silence_generation, &self.loudness_generation, &self.waveform_generation, &self.keyframe_generation] {
+            *generation.lock().unwrap() += 1;
+        }
+        self.current_frame.lock().unwrap().close();
+        self.stop_audio();
+        
+        let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+        let workers = std::mem::take(&mut *self.workers.lock().unwrap());
+        let mut abandoned = 0;
+        for handle in workers {
+            while !handle.is_finished() && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(10));
             }
-
-            // フレーム数ベースで現在の動画内位置を計算
-            let current_pos = start_position + (frame_count as f32) * (frame_duration_secs as f32);
-            
-            // 世代が一致する場合のみcurrent_timeを更新（古いスレッドが新しい再生の時間を上書きしないように）
-            if *playback_generation.lock().unwrap() != my_generation {
-                let _ = child.kill();
-                println!("current_time更新前に世代不一致を検出（世代: {}）", my_generation);
-                break;
+            if handle.is_finished() {
+                let _ = handle.join();
+            } else {
+                abandoned += 1;
             }
-            *current_time.lock().unwrap() = current_pos;
+        }
+        
+        // 待っている間に起動されたffmpegも止める
+        for (_, mut child) in std::mem::take(&mut *self.decoders.lock().unwrap()) {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        self.stop_audio();
+        if abandoned > 0 {
+            println!("{}", trf("Warning: {} threads did not finish, exiting without waiting", &[&abandoned]));
+        }
+        println!("{}", tr("Worker threads finished"));
+    }
+}
 
-            if current_pos >= duration && duration > 0.0 {
-                // 世代が一致する場合のみ終了フラグを設定（古いスレッドが新しい再生を壊さないように）
-                if *playback_generation.lock().unwrap() == my_generation {
-                    *current_time.lock().unwrap() = duration;
-                    *is_playing.lock().unwrap() = false;
-                    *video_finished.lock().unwrap() = true;
-                    println!("再生が終了しました (frame: {}, pos: {:.2}, duration: {:.2})", frame_count, current_pos, duration);
-                } else {
-                    println!("古いスレッド（世代: {}）がduration超過で終了（フラグ設定スキップ）", my_generation);
-                }
-                break;
-            }
+impl Default for VideoPlayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-            // フレームを読み込む（正確なサイズを読み取る）
-            let mut pos = 0;
-            while pos < frame_size {
-                match stdout.read(&mut frame_buffer[pos..]) {
-                    Ok(0) => {
-                        // EOFに達した - 世代が一致する場合のみ終了フラグを設定
-                        if *playback_generation.lock().unwrap() == my_generation {
-                            *current_time.lock().unwrap() = duration;
-                            *is_playing.lock().unwrap() = false;
-                            *video_finished.lock().unwrap() = true;
-                            println!("動画の終端に達しました（世代: {}）", my_generation);
-                        } else {
-                            println!("古いスレッド（世代: {}）がEOFで終了（フラグ設定スキップ）", my_generation);
-                        }
-                        let _ = child.kill();
-                        return;
-                    }
-                    Ok(n) => {
-                        pos += n;
-                    }
-                    Err(e) => {
-                        println!("読み込みエラー: {}", e);
-                        // 世代が一致する場合のみ終了フラグを設定
-                        if *playback_generation.lock().unwrap() == my_generation {
-                            *current_time.lock().unwrap() = duration;
-                            *is_playing.lock().unwrap() = false;
-                            *video_finished.lock().unwrap() = true;
-                        }
-                        let _ = child.kill();
-                        return;
-                    }
-                }
-            }
+impl Drop for VideoPlayer {
+    fn drop(&mut self) {
+        // VideoPlayerが破棄される際に確実に音声プロセスを停止
+        println!("{}", tr("Cleaning up VideoPlayer..."));
+        self.stop_audio();
+    }
+}
 
-            // フレームをRgbaImageに変換
-            if let Some(rgba_image) = RgbaImage::from_raw(width, height, frame_buffer.clone()) {
-                *current_frame.lock().unwrap() = Some(rgba_image);
-            }
-            
-            // フレームカウントをインクリメント（時間追跡用）
-            frame_count += 1;
+/// 設定をまとめて指定してVideoPlayerを作る（指定しなかった項目はVideoPlayer::new()と同じ）
+#[derive(Default)]
+pub struct VideoPlayerBuilder {
+    max_render_size: Option<(u32, u32)>,
+    hwaccel: Option<String>,
+    ffmpeg_path: Option<PathBuf>,
+    volume: Option<f32>,
+    seek_mode: Option<SeekMode>,
+    frame_format: Option<FrameFormat>,
+    display_frames: Option<bool>,
+}
 
-            // 自前フレームペーシング: 次のフレームの表示タイミングまでスリープ
-            let expected_elapsed = Duration::from_secs_f64(frame_count as f64 * frame_duration_secs);
-            let actual_elapsed = playback_start.elapsed() - total_paused;
-            if expected_elapsed > actual_elapsed {
-                thread::sleep(expected_elapsed - actual_elapsed);
+impl VideoPlayerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// 表示用に縮小する最大サイズ（既定は960x600）
+    pub fn max_render_size(mut self, width: u32, height: u32) -> Self {
+        self.max_render_size = Some((width, height));
+        self
+    }
+    
+    /// 再生に使うハードウェアデコード（ffmpegの-hwaccelに渡す名前。"auto"など）
+    pub fn hwaccel(mut self, name: &str) -> Self {
+        self.hwaccel = Some(name.to_string());
+        self
+    }
+    
+    /// ffmpegの実行ファイル（ffprobe・ffplayも同じフォルダのものを使う）
+    /// プレーヤーごとではなくプロセス全体の設定で、build()の後は他のVideoPlayerやprobe_video・extract_frameもこのffmpegを使う
+    pub fn ffmpeg_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.ffmpeg_path = Some(path.into());
+        self
+    }
+    
+    /// 音量の初期値（0.0〜1.0）
+    pub fn volume(mut self, volume: f32) -> Self {
+        self.volume = Some(volume);
+        self
+    }
+    
+    /// シーク方法（保存されている設定より優先する）
+    pub fn seek_mode(mut self, mode: SeekMode) -> Self {
+        self.seek_mode = Some(mode);
+        self
+    }
+    
+    /// 再生中のフレームの形式（既定はRGBA。on_raw_frameで受け取る形式になる）
+    pub fn frame_format(mut self, format: FrameFormat) -> Self {
+        self.frame_format = Some(format);
+        self
+    }
+    
+    /// 表示用のフレームを作るか（既定はtrue。VideoPlayer::set_display_framesを参照）
+    pub fn display_frames(mut self, enabled: bool) -> Self {
+        self.display_frames = Some(enabled);
+        self
+    }
+    
+    /// 設定を検証してVideoPlayerを作る
+    pub fn build(self) -> Result<VideoPlayer, String> {
+        if let Some((width, height)) = self.max_render_size {
+            if width < 2 || height < 2 {
+                return Err(trf("The render size is too small ({}x{})", &[&width, &height]));
+            }
+            if width > MAX_FRAME_DIMENSION || height > MAX_FRAME_DIMENSION {
+                return Err(trf("The render size is too large ({}x{})", &[&width, &height]));
+            }
+        }
+        if let Some(volume) = self.volume {
+            if !(0.0..=1.0).contains(&volume) {
+                return Err(trf("The volume must be between 0.0 and 1.0 ({})", &[&volume]));
+            }
+        }
+        // ffmpegの場所はプロセス全体で共通（ffprobe・ffplayも同じフォルダから探す）
+        if let Some(path) = &self.ffmpeg_path {
+            if !path.is_file() {
+                return Err(trf("ffmpeg not found: {}", &[&path.display()]));
             }
+            *FFMPEG_PATH.lock().unwrap() = Some(path.clone());
         }
-
-        let _ = child.wait();
+        if let Some(hwaccel) = &self.hwaccel {
+            if hwaccel != "auto" && !Self::hwaccel_supported(hwaccel)? {
+                return Err(trf("This ffmpeg does not support the hardware decoder: {}", &[hwaccel]));
+            }
+        }
+        
+        let mut player = VideoPlayer::new();
+        if let Some(size) = self.max_render_size {
+            player.max_render_size = size;
+        }
+        player.hwaccel = self.hwaccel;
+        if let Some(volume) = self.volume {
+            *player.volume.lock().unwrap() = volume;
+        }
+        if let Some(mode) = self.seek_mode {
+            player.seek_mode = mode;
+        }
+        if let Some(format) = self.frame_format {
+            player.frame_format = format;
+        }
+        if let Some(enabled) = self.display_frames {
+            player.set_display_frames(enabled);
+        }
+        Ok(player)
     }
-
-    pub fn pause(&mut self) {
-        // is_playing=false（UI同期用）、is_paused=true（スレッド維持用）
-        *self.is_playing.lock().unwrap() = false;
-        *self.is_paused.lock().unwrap() = true;
-        self.stop_audio();
-        let current = *self.current_time.lock().unwrap();
-        println!("一時停止（位置: {:.2}秒）", current);
+    
+    // ffmpegが対応しているハードウェアデコードの一覧（-hwaccels）に含まれるか
+    fn hwaccel_supported(name: &str) -> Result<bool, String> {
+        let output = ffmpeg_command("ffmpeg")
+            .args(["-hide_banner", "-hwaccels"])
+            .stderr(Stdio::null())
+            .output()
+            .map_err(|e| trf("Failed to start ffmpeg: {}", &[&e]))?;
+        Ok(String::from_utf8_lossy(&output.stdout).lines().skip(1).any(|line| line.trim() == name))
     }
+}
 
-    pub fn stop(&mut self) {
-        *self.is_paused.lock().unwrap() = false;
-        *self.is_playing.lock().unwrap() = false;
-        *self.video_finished.lock().unwrap() = false;
-        *self.current_time.lock().unwrap() = 0.0;
-        self.stop_audio();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    
+    // 読み込み・再生などを待つ上限（遅いマシンでも通るよう長めに）
+    const TEST_TIMEOUT: Duration = Duration::from_secs(20);
+    
+    // テスト用の一時フォルダ（終わったら中身ごと消す）
+    struct TempDir(PathBuf);
+    
+    impl TempDir {
+        fn new() -> Self {
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+            let name = format!("video-player-ui-test-{}-{}", std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed));
+            let path = std::env::temp_dir().join(name);
+            std::fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
         
-        // 最初のフレームを再読み込み
-        if let Some(path) = self.video_path.clone() {
-            let _ = self.load_first_frame(&path);
+        fn join(&self, name: &str) -> PathBuf {
+            self.0.join(name)
+        }
+    }
+    
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
         }
+    }
+    
+    // ffmpegのtestsrc（映像）とsine（音声）で短い動画を作る（320x240）
+    // ffmpeg・ffprobeがなければNone（呼んだテストは何もせずに終わる）
+    fn synthetic_clip(dir: &TempDir, name: &str, duration: f32, fps: u32) -> Option<PathBuf> {
+        let missing = missing_tools();
+        if !missing.is_empty() {
+            println!("{}が見つからないため、テストをスキップします", missing.join(", "));
+            return None;
+        }
+        let path = dir.join(name);
+        let output = ffmpeg_command("ffmpeg")
+            .args(["-v", "error", "-y"])
+            .args(["-f", "lavfi", "-i", &format!("testsrc=duration={}:size=320x240:rate={}", duration, fps)])
+            .args(["-f", "lavfi", "-i", &format!("sine=frequency=440:duration={}", duration)])
+            .args(["-c:v", "mpeg4", "-q:v", "5", "-pix_fmt", "yuv420p", "-c:a", "aac", "-shortest"])
+            .arg(&path)
+            .output()
+            .expect("ffmpegを起動できません");
+        assert!(output.status.success(), "テスト用の動画を作れません: {}", String::from_utf8_lossy(&output.stderr));
+        Some(path)
+    }
+    
+    // 設定ファイルを読み書きせず、音声（ffplay）も鳴らさないプレイヤー
+    fn test_player() -> VideoPlayer {
+        let mut player = VideoPlayer::with_settings(Settings::in_memory());
+        player.audio_available = false;
+        player
+    }
+    
+    // 読み込みを始めて、終わるまで待つ
+    fn load(player: &mut VideoPlayer, path: &Path) -> Result<PathBuf, String> {
+        player.load_video(path.to_path_buf());
+        let deadline = Instant::now() + TEST_TIMEOUT;
+        loop {
+            if let Some(result) = player.poll_load() {
+                return result;
+            }
+            assert!(Instant::now() < deadline, "読み込みが終わりません: {}", path.display());
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+    
+    // conditionがtrueになるまで待つ（TEST_TIMEOUTを過ぎたらfalse）
+    fn wait_for(mut condition: impl FnMut() -> bool) -> bool {
+        let deadline = Instant::now() + TEST_TIMEOUT;
+        while !condition() {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        true
+    }
+    
+    #[test]
+    fn get_video_info_reads_duration_and_dimensions() {
+        let dir = TempDir::new();
+        let Some(path) = synthetic_clip(&dir, "clip.mp4", 2.0, 25) else {
+            return;
+        };
+        
+        let probe_cache = Mutex::new(ProbeCache::default());
+        let info = VideoPlayer::get_video_info(&path, &probe_cache, DEFAULT_MAX_RENDER_SIZE, false, &|| false).unwrap();
+        assert!((info.duration - 2.0).abs() < 0.1, "duration: {}", info.duration);
+        assert!((info.fps - 25.0).abs() < 0.01, "fps: {}", info.fps);
+        assert_eq!((info.source_width, info.source_height), (320, 240));
+        assert_eq!((info.width, info.height), (320, 240));
+        assert_eq!(info.audio_channels, 1);
         
-        println!("停止");
+        // 表示サイズより大きければ縦横比を保って縮小する（2回目はキャッシュから）
+        let info = VideoPlayer::get_video_info(&path, &probe_cache, (160, 160), false, &|| false).unwrap();
+        assert_eq!((info.width, info.height), (160, 120));
     }
-
-    pub fn seek(&mut self, time: f32) {
-        let was_playing = self.is_playing() || *self.is_paused.lock().unwrap();
+    
+    #[test]
+    fn extract_first_frame_has_the_requested_size() {
+        let dir = TempDir::new();
+        let Some(path) = synthetic_clip(&dir, "clip.mp4", 1.0, 25) else {
+            return;
+        };
         
-        // 一時停止状態をクリア
-        *self.is_paused.lock().unwrap() = false;
+        let frame = VideoPlayer::extract_first_frame(&path, "scale=160:120", &|| false).unwrap();
+        assert_eq!(frame.dimensions(), (160, 120));
+        // testsrcは色の帯なので、真っ黒なフレームにはならない
+        assert!(frame.pixels().any(|pixel| pixel.0[..3] != [0, 0, 0]));
+    }
+    
+    #[test]
+    fn load_video_shows_the_first_frame() {
+        let dir = TempDir::new();
+        let Some(path) = synthetic_clip(&dir, "clip.mp4", 2.0, 25) else {
+            return;
+        };
+        let mut player = test_player();
         
-        // 世代番号をインクリメント（古いスレッドを無効化）
-        *self.playback_generation.lock().unwrap() += 1;
+        assert_eq!(load(&mut player, &path), Ok(path.clone()));
+        assert_eq!(player.get_video_path(), Some(path.as_path()));
+        assert!((player.duration - 2.0).abs() < 0.1, "duration: {}", player.duration);
+        assert_eq!(player.get_state(), PlaybackState::Stopped);
+        assert_eq!(player.get_current_time(), 0.0);
         
-        // 現在の再生を停止（音声も含む）
-        *self.is_playing.lock().unwrap() = false;
-        self.stop_audio();
+        let frame = player.take_frame().expect("最初のフレームがありません");
+        assert_eq!(frame.dimensions(), (320, 240));
+        player.shutdown();
+    }
+    
+    #[test]
+    fn play_pause_and_stop() {
+        let dir = TempDir::new();
+        let Some(path) = synthetic_clip(&dir, "clip.mp4", 3.0, 25) else {
+            return;
+        };
+        let mut player = test_player();
+        load(&mut player, &path).unwrap();
         
-        // シーク時刻を設定
-        *self.current_time.lock().unwrap() = time;
-        *self.seek_time.lock().unwrap() = Some(time);
+        player.play().unwrap();
+        assert_eq!(player.get_state(), PlaybackState::Playing);
+        assert!(wait_for(|| player.get_current_time() >= 0.3), "再生位置が進みません");
+        assert!(player.take_frame().is_some());
         
-        println!("シーク: {}秒", time);
+        // 一時停止中は位置が止まる（止めた直後に読み込み中だった1フレーム分は進むことがある）
+        player.pause();
+        assert_eq!(player.get_state(), PlaybackState::Paused);
+        thread::sleep(Duration::from_millis(100));
+        let paused_at = player.get_current_time();
+        thread::sleep(Duration::from_millis(300));
+        assert_eq!(player.get_current_time(), paused_at);
         
-        // 指定された位置のフレームを非同期で読み込む
-        if let Some(path) = self.video_path.clone() {
-            let current_frame = Arc::clone(&self.current_frame);
-            let width = self.video_width;
-            let height = self.video_height;
-            
-            thread::spawn(move || {
-                Self::load_frame_at_time_async(&path, time, current_frame, width, height);
-            });
-        }
+        // 再開すると止めた位置から進む
+        player.play().unwrap();
+        assert_eq!(player.get_state(), PlaybackState::Playing);
+        assert!(wait_for(|| player.get_current_time() > paused_at), "再開後に再生位置が進みません");
         
-        // 再生中だった場合は、シーク位置から即座に再生を再開
-        if was_playing {
-            let _ = self.play();
+        // 停止すると先頭に戻る
+        player.stop();
+        assert_eq!(player.get_state(), PlaybackState::Stopped);
+        assert_eq!(player.get_current_time(), 0.0);
+        assert!(player.with_current_frame(|_, width, height| assert_eq!((width, height), (320, 240))));
+        player.shutdown();
+    }
+    
+    #[test]
+    fn seek_moves_the_position_and_playback_continues_from_there() {
+        let dir = TempDir::new();
+        let Some(path) = synthetic_clip(&dir, "clip.mp4", 3.0, 25) else {
+            return;
+        };
+        let mut player = test_player();
+        load(&mut player, &path).unwrap();
+        
+        // 止めたままシークすると、位置とフレームだけが変わる
+        player.seek(1.5);
+        assert_eq!(player.get_state(), PlaybackState::Stopped);
+        assert_eq!(player.get_current_time(), 1.5);
+        assert!(wait_for(|| !*player.frame_loader_running.lock().unwrap()), "シーク位置のフレームを読み込めません");
+        assert_eq!(player.take_frame().map(|frame| frame.dimensions()), Some((320, 240)));
+        
+        // 再生はシーク位置から始まる
+        player.play().unwrap();
+        assert!(wait_for(|| player.get_current_time() > 1.5), "シーク位置から再生が進みません");
+        assert!(player.get_current_time() < 2.5);
+        
+        // 再生中にシークすると、その位置から再生を続ける
+        player.seek(0.5);
+        assert_eq!(player.get_state(), PlaybackState::Playing);
+        assert!(player.get_current_time() < 1.0);
+        assert!(wait_for(|| player.get_current_time() > 0.5), "シーク後に再生が進みません");
+        player.shutdown();
+    }
+    
+    #[test]
+    fn rapid_seeks_leave_no_decoders_or_loader_threads() {
+        let dir = TempDir::new();
+        let Some(path) = synthetic_clip(&dir, "clip.mp4", 3.0, 25) else {
+            return;
+        };
+        let mut player = test_player();
+        load(&mut player, &path).unwrap();
+        player.play().unwrap();
+        
+        for i in 0..100 {
+            player.seek((i % 30) as f32 * 0.1);
+            // 古い世代のffmpegはシークのたびに止めているので、残っているのは今の世代のものだけ
+            let generation = *player.playback_generation.lock().unwrap();
+            assert!(player.decoders.lock().unwrap().iter().all(|(g, _)| *g == generation));
         }
+        assert_eq!(player.get_state(), PlaybackState::Playing);
+        
+        // 止めたら、ffmpegもフレームの読み込みスレッドも残らない
+        player.stop();
+        assert!(
+            wait_for(|| player.decoders.lock().unwrap().is_empty() && !*player.frame_loader_running.lock().unwrap()),
+            "ffmpegまたはフレームの読み込みスレッドが残っています"
+        );
+        assert!(
+            wait_for(|| player.workers.lock().unwrap().iter().all(|worker| worker.is_finished())),
+            "終わらないワーカースレッドがあります"
+        );
+        player.shutdown();
     }
     
-    fn load_frame_at_time_async(
-        path: &PathBuf,
-        time: f32,
-        current_frame: Arc<Mutex<Option<RgbaImage>>>,
-        width: u32,
-        height: u32,
-    ) {
-        // 指定された時刻のフレームを抽出（高速化のため-ssを-iの前に配置）
-        let output = Command::new("ffmpeg")
-            .args(&[
-                "-ss", &time.to_string(),
-                "-i", path.to_str().unwrap(),
-                "-vf", &format!("scale={}:{}", width, height),
-                "-vframes", "1",
-                "-f", "rawvideo",
-                "-pix_fmt", "rgba",
-                "-"
-            ])
-            .stdout(Stdio::piped())
-            .stderr(Stdio::null())
-            .output();
-
-        match output {
-            Ok(output) if output.status.success() && !output.stdout.is_empty() => {
-                let frame_size = (width * height * 4) as usize;
-                if output.stdout.len() >= frame_size {
-                    if let Some(rgba_image) = RgbaImage::from_raw(width, height, output.stdout) {
-                        *current_frame.lock().unwrap() = Some(rgba_image);
-                    }
-                }
-            }
-            Err(e) => {
-                println!("フレームの読み込みエラー: {}", e);
-            }
-            _ => {}
+    #[test]
+    fn empty_file_is_not_a_valid_video() {
+        let dir = TempDir::new();
+        let path = dir.join("empty.mp4");
+        std::fs::write(&path, b"").unwrap();
+        let mut player = test_player();
+        
+        let error = load(&mut player, &path).unwrap_err();
+        assert!(error.starts_with("This file is not a valid video"), "{}", error);
+        assert_eq!(player.get_video_path(), None);
+        assert_eq!(player.duration, 0.0);
+        player.shutdown();
+    }
+    
+    #[test]
+    fn invalid_files_keep_the_previous_video() {
+        let dir = TempDir::new();
+        let Some(clip) = synthetic_clip(&dir, "clip.mp4", 10.0, 25) else {
+            return;
+        };
+        let empty = dir.join("empty.mp4");
+        std::fs::write(&empty, b"").unwrap();
+        let text = dir.join("notes.mp4");
+        std::fs::write(&text, "This is not a video.\n").unwrap();
+        // 途中で切れたファイル（mp4の目次は末尾にあるので、ffprobeでも読めない）
+        let truncated = dir.join("truncated.mp4");
+        let bytes = std::fs::read(&clip).unwrap();
+        std::fs::write(&truncated, &bytes[..bytes.len() / 2]).unwrap();
+        
+        let mut player = test_player();
+        load(&mut player, &clip).unwrap();
+        let duration = player.duration;
+        player.play().unwrap();
+        
+        for path in [&empty, &text, &truncated] {
+            let error = load(&mut player, path).unwrap_err();
+            assert!(error.starts_with("This file is not a valid video"), "{}: {}", path.display(), error);
+            assert_eq!(player.get_video_path(), Some(clip.as_path()));
+            assert_eq!(player.duration, duration);
+            assert_eq!(player.get_state(), PlaybackState::Playing);
         }
+        assert!(wait_for(|| player.get_current_time() > 0.0), "前の動画の再生が止まっています");
+        player.shutdown();
     }
-
-    pub fn get_current_time(&self) -> f32 {
-        *self.current_time.lock().unwrap()
+    
+    #[test]
+    fn seek_while_paused_stays_paused() {
+        let dir = TempDir::new();
+        let Some(path) = synthetic_clip(&dir, "clip.mp4", 5.0, 25) else {
+            return;
+        };
+        let mut player = test_player();
+        load(&mut player, &path).unwrap();
+        player.play().unwrap();
+        assert!(wait_for(|| player.get_current_time() >= 0.3), "再生位置が進みません");
+        player.pause();
+        
+        player.seek(2.0);
+        assert_eq!(player.get_state(), PlaybackState::Paused);
+        assert_eq!(player.get_current_time(), 2.0);
+        // 待っていても勝手に再開しない（表示はシーク位置のフレームになる）
+        thread::sleep(Duration::from_millis(300));
+        assert_eq!(player.get_state(), PlaybackState::Paused);
+        assert_eq!(player.get_current_time(), 2.0);
+        assert!(wait_for(|| !*player.frame_loader_running.lock().unwrap()), "シーク位置のフレームを読み込めません");
+        assert!(player.take_frame().is_some());
+        
+        // 再開するとシーク位置から進む
+        player.play().unwrap();
+        assert!(wait_for(|| player.get_current_time() > 2.0), "シーク位置から再開しません");
+        player.shutdown();
     }
-
-    pub fn is_playing(&self) -> bool {
-        *self.is_playing.lock().unwrap()
+    
+    #[test]
+    fn stream_end_uses_eof_and_duration_only_as_a_fallback() {
+        let duration = 10.0;
+        // 出力が続いている間は、durationを少し超えても終わりにしない（目安のdurationで切らない）
+        assert_eq!(VideoPlayer::stream_end(5.0, duration, None), StreamEnd::Continue);
+        assert_eq!(VideoPlayer::stream_end(duration, duration, None), StreamEnd::Continue);
+        assert_eq!(VideoPlayer::stream_end(duration + END_OVERSHOOT_GRACE - 0.01, duration, None), StreamEnd::Continue);
+        // 大きく超えても続く場合だけ打ち切る
+        assert_eq!(VideoPlayer::stream_end(duration + END_OVERSHOOT_GRACE, duration, None), StreamEnd::Overshot);
+        // 長さが分からない入力（ライブ配信など）は打ち切らない
+        assert_eq!(VideoPlayer::stream_end(1000.0, 0.0, None), StreamEnd::Continue);
     }
     
-    pub fn is_video_finished(&self) -> bool {
-        *self.video_finished.lock().unwrap()
+    #[test]
+    fn stream_end_after_the_output_ends() {
+        let duration = 10.0;
+        // 正常に終わったら、durationより手前でも終端（durationは目安なので、EOFを優先する）
+        assert_eq!(VideoPlayer::stream_end(duration - 3.0, duration, Some(true)), StreamEnd::Finished);
+        assert_eq!(VideoPlayer::stream_end(duration + 0.5, duration, Some(true)), StreamEnd::Finished);
+        // 異常終了は、終端の近くなら終端、それより前なら起動し直す
+        assert_eq!(VideoPlayer::stream_end(duration - NEAR_END_MARGIN, duration, Some(false)), StreamEnd::Finished);
+        assert_eq!(VideoPlayer::stream_end(duration - NEAR_END_MARGIN - 0.01, duration, Some(false)), StreamEnd::Interrupted);
+        assert_eq!(VideoPlayer::stream_end(0.0, duration, Some(false)), StreamEnd::Interrupted);
+        // 長さが分からなければ、異常終了でも終端とみなす
+        assert_eq!(VideoPlayer::stream_end(3.0, 0.0, Some(false)), StreamEnd::Finished);
     }
     
-    pub fn clear_video_finished(&mut self) {
-        *self.video_finished.lock().unwrap() = false;
+    #[test]
+    fn duration_is_at_least_one_frame() {
+        assert_eq!(VideoPlayer::playable_duration(0.5, 25.0), 0.5);
+        assert_eq!(VideoPlayer::playable_duration(0.01, 25.0), 1.0 / 25.0);
+        // 長さが分からない入力はそのまま
+        assert_eq!(VideoPlayer::playable_duration(0.0, 25.0), 0.0);
     }
-
-    pub fn get_current_frame(&self) -> Option<RgbaImage> {
-        self.current_frame.lock().unwrap().clone()
+    
+    #[test]
+    fn start_position_keeps_the_last_frame() {
+        // 1フレームだけの動画は先頭から
+        assert_eq!(VideoPlayer::clamp_start_position(0.0, 1.0 / 25.0, 25.0), 0.0);
+        assert_eq!(VideoPlayer::clamp_start_position(1.0, 1.0 / 25.0, 25.0), 0.0);
+        // 終端より後は最後のフレームの位置に戻す
+        assert!((VideoPlayer::clamp_start_position(5.0, 0.5, 25.0) - 0.46).abs() < 1e-6);
+        assert_eq!(VideoPlayer::clamp_start_position(0.2, 0.5, 25.0), 0.2);
+        assert_eq!(VideoPlayer::clamp_start_position(-1.0, 10.0, 25.0), 0.0);
     }
     
-    pub fn set_volume(&mut self, volume: f32) {
-        *self.volume.lock().unwrap() = volume.clamp(0.0, 1.0);
-        println!("音量を設定: {}%", (volume * 100.0) as i32);
+    // 最後まで再生して、表示したフレームの数を返す
+    fn play_to_end(player: &mut VideoPlayer) -> usize {
+        let frames = Arc::new(Mutex::new(0));
+        let counter = Arc::clone(&frames);
+        player.on_frame(move |_| *counter.lock().unwrap() += 1);
+        player.play().unwrap();
+        assert!(wait_for(|| player.is_video_finished()), "終端に達しません");
+        assert_eq!(player.get_state(), PlaybackState::Stopped);
+        assert_eq!(player.get_current_time(), player.duration);
+        let count = *frames.lock().unwrap();
+        count
+    }
+    
+    #[test]
+    fn very_short_clips_show_their_frames_and_finish() {
+        let dir = TempDir::new();
+        let Some(one_frame) = synthetic_clip(&dir, "one_frame.mp4", 1.0 / 25.0, 25) else {
+            return;
+        };
+        let half_second = synthetic_clip(&dir, "half_second.mp4", 0.5, 25).unwrap();
+        let mut player = test_player();
         
-        // 再生中の場合は音声を再起動
-        if self.is_playing() {
-            self.stop_audio();
-            let _ = self.start_audio_playback();
+        load(&mut player, &one_frame).unwrap();
+        assert!(player.duration >= 1.0 / 25.0, "duration: {}", player.duration);
+        assert!(play_to_end(&mut player) >= 1);
+        
+        load(&mut player, &half_second).unwrap();
+        assert!((player.duration - 0.5).abs() < 0.1, "duration: {}", player.duration);
+        // 12〜13フレーム（すぐに終端とみなして途中で切れていない）
+        assert!(play_to_end(&mut player) >= 10);
+        player.shutdown();
+    }
+    
+    #[test]
+    fn still_image_shows_one_frame_and_stops() {
+        let dir = TempDir::new();
+        if synthetic_clip(&dir, "clip.mp4", 1.0, 25).is_none() {
+            return;
         }
+        let image = dir.join("still.png");
+        let status = ffmpeg_command("ffmpeg")
+            .args(["-v", "error", "-y", "-f", "lavfi", "-i", "testsrc=size=320x240", "-frames:v", "1"])
+            .arg(&image)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let mut player = test_player();
+        
+        load(&mut player, &image).unwrap();
+        assert!(player.duration > 0.0);
+        assert!(play_to_end(&mut player) >= 1);
+        assert!(player.with_current_frame(|_, width, height| assert_eq!((width, height), (320, 240))));
+        player.shutdown();
     }
     
-    fn start_audio_playback(&mut self) -> Result<(), String> {
-        // 既存の音声プロセスを停止
-        self.stop_audio();
+    #[test]
+    fn pausing_many_times_does_not_drift() {
+        let dir = TempDir::new();
+        let Some(path) = synthetic_clip(&dir, "clip.mp4", 10.0, 25) else {
+            return;
+        };
+        let mut player = test_player();
+        // 表示した各フレームの表示時刻と、計算した再生位置を並べて取れるようにする
+        player.set_pts_debug(true);
+        load(&mut player, &path).unwrap();
+        player.play().unwrap();
+        assert!(wait_for(|| player.get_current_time() >= 0.2), "再生位置が進みません");
         
-        if let Some(path) = &self.video_path {
-            let start_position = *self.current_time.lock().unwrap();
-            let volume = *self.volume.lock().unwrap();
-            
-            // 音声世代をインクリメント
-            let audio_gen = {
-                let mut gen = self.audio_generation.lock().unwrap();
-                *gen += 1;
-                *gen
-            };
-            
-            println!("音声再生を開始（位置: {}秒, 音量: {}%, 世代: {}）", start_position, (volume * 100.0) as i32, audio_gen);
-            
-            // ffplayで音声のみを再生（ビデオは非表示）
-            let child = Command::new("ffplay")
-                .args(&[
-                    "-ss", &start_position.to_string(),
-                    "-i", path.to_str().unwrap(),
-                    "-vn", // ビデオなし
-                    "-nodisp", // ウィンドウを表示しない
-                    "-af", &format!("volume={}", volume), // ボリュームフィルター
-                    "-autoexit", // 終了時に自動で閉じる
-                ])
-                .stdout(Stdio::null())
-                .stderr(Stdio::null())
-                .spawn();
-            
-            match child {
-                Ok(process) => {
-                    *self.audio_process.lock().unwrap() = Some(process);
-                    Ok(())
-                }
-                Err(e) => {
-                    println!("音声再生の開始に失敗: {}", e);
-                    Err(format!("ffplayの起動に失敗: {}", e))
-                }
+        for _ in 0..20 {
+            player.pause();
+            thread::sleep(Duration::from_millis(30));
+            player.play().unwrap();
+            thread::sleep(Duration::from_millis(80));
+            // 表示する位置は、デコードしたフレームの表示時刻から2フレーム以上ずれない
+            if let Some((pts, position)) = player.get_frame_pts() {
+                assert!((pts - position).abs() <= 2.0 / 25.0 + 0.001, "pts: {}, 位置: {}", pts, position);
             }
-        } else {
-            Ok(())
         }
+        
+        // 何度止めても、再開した後は実時間どおりに進む（取り戻そうと速く進んだり、遅れたりしない）
+        let start = player.get_current_time();
+        let started = Instant::now();
+        thread::sleep(Duration::from_secs(1));
+        let advanced = player.get_current_time() - start;
+        let elapsed = started.elapsed().as_secs_f32();
+        assert!((advanced - elapsed).abs() < 0.25, "進んだ位置: {:.3}秒, 経過: {:.3}秒", advanced, elapsed);
+        player.shutdown();
     }
     
-    fn stop_audio(&mut self) {
-        let mut audio_proc = self.audio_process.lock().unwrap();
-        if let Some(mut child) = audio_proc.take() {
-            let _ = child.kill();
-            let _ = child.wait();
-            println!("音声プロセスを停止しました");
+    #[test]
+    fn concat_list_entry_quotes_tricky_paths() {
+        let entry = |path: &str| VideoPlayer::concat_list_entry(Path::new(path)).unwrap();
+        assert_eq!(entry("/videos/my clip.mp4"), "file '/videos/my clip.mp4'\n");
+        assert_eq!(entry("/videos/it's.mp4"), "file '/videos/it'\\''s.mp4'\n");
+        assert_eq!(entry("/videos/100% done.mp4"), "file '/videos/100% done.mp4'\n");
+        assert_eq!(entry("/動画/テスト 映像.mp4"), "file '/動画/テスト 映像.mp4'\n");
+        // 「'」の中では「\」はそのまま読まれるので、Windowsのパスは変えない
+        assert_eq!(entry(r"C:\Users\me\My Videos\a'b.mp4"), "file 'C:\\Users\\me\\My Videos\\a'\\''b.mp4'\n");
+    }
+    
+    #[test]
+    fn concat_list_entry_rejects_paths_it_cannot_write() {
+        assert!(VideoPlayer::concat_list_entry(Path::new("/videos/line\nbreak.mp4")).is_err());
+        assert!(VideoPlayer::concat_list_entry(Path::new("/videos/carriage\rreturn.mp4")).is_err());
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            let path = Path::new(std::ffi::OsStr::from_bytes(b"/videos/\xff.mp4"));
+            assert!(VideoPlayer::concat_list_entry(path).is_err());
         }
     }
-}
+    
+    #[test]
+    fn image_sequence_pattern_escapes_percent() {
+        let pattern = VideoPlayer::image_sequence_pattern(Path::new("/frames/100% done"), "clip %d 動画");
+        assert!(pattern.starts_with("/frames/100%% done"), "{}", pattern);
+        assert!(pattern.ends_with("clip %%d 動画_%06d.png"), "{}", pattern);
+        // 連番の指定として残るのは末尾の1つだけ
+        assert_eq!(pattern.replace("%%", "").matches('%').count(), 1);
+        
+        let pattern = VideoPlayer::image_sequence_pattern(Path::new(r"C:\Users\me\Frames"), "it's");
+        assert!(pattern.starts_with(r"C:\Users\me\Frames"), "{}", pattern);
+        assert!(pattern.ends_with("it's_%06d.png"), "{}", pattern);
+    }
+    
+    #[test]
+    fn tricky_file_names_work_with_ffmpeg() {
+        let dir = TempDir::new();
+        let Some(first) = synthetic_clip(&dir, "it's 100% 動画.mp4", 1.0, 25) else {
+            return;
+        };
+        let second = synthetic_clip(&dir, "two  spaces [1] 'quoted'.mp4", 1.0, 25).unwrap();
+        
+        let mut player = test_player();
+        assert_eq!(load(&mut player, &first), Ok(first.clone()));
+        assert!(player.take_frame().is_some());
+        player.shutdown();
+        
+        // 同じ形式なのでconcatリストで無劣化結合する
+        let joined = dir.join("joined 'out' 100%.mp4");
+        VideoPlayer::concat_worker(&[first.clone(), second], &joined, &Mutex::new(None)).unwrap();
+        assert!(joined.is_file());
+        
+        // 連番の出力先のフォルダ名・ファイル名に「%」「'」があっても、そのままの名前で書き出す
+        let out_dir = dir.join("frames 100% 'x'");
+        VideoPlayer::export_frames_worker(&first, 0.5, &out_dir, 1.0, &Mutex::new(None)).unwrap();
+        let exported = std::fs::read_dir(&out_dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with("it's 100% 動画_"))
+            .count();
+        assert!(exported >= 2, "書き出したフレーム: {}枚", exported);
+    }
 
-impl Drop for VideoPlayer {
-    fn drop(&mut self) {
-        // VideoPlayerが破棄される際に確実に音声プロセスを停止
-        println!("VideoPlayerをクリーンアップ中...");
-        self.stop_audio();
+    // 左半分が白・右半分が黒の動画（フレームの区切りや縦横がずれると白黒の位置が崩れる）
+    fn half_white_clip(dir: &TempDir, duration: f32) -> Option<PathBuf> {
+        let missing = missing_tools();
+        if !missing.is_empty() {
+            println!("{}が見つからないため、テストをスキップします", missing.join(", "));
+            return None;
+        }
+        let path = dir.join("half.mp4");
+        let output = ffmpeg_command("ffmpeg")
+            .args(["-v", "error", "-y"])
+            .args(["-f", "lavfi", "-i", &format!("color=c=white:size=160x240:rate=25:duration={}", duration)])
+            .args(["-vf", "pad=320:240:0:0:black", "-c:v", "mpeg4", "-q:v", "2", "-pix_fmt", "yuv420p"])
+            .arg(&path)
+            .output()
+            .expect("ffmpegを起動できません");
+        assert!(output.status.success(), "テスト用の動画を作れません: {}", String::from_utf8_lossy(&output.stderr));
+        Some(path)
+    }
+
+    // half_white_clipのフレームがどの回転で出たものか（どれにも合わなければ崩れている）
+    fn half_white_rotation(frame: &RgbaImage) -> Option<u32> {
+        let (width, height) = frame.dimensions();
+        let white = |x: u32, y: u32| frame.get_pixel(x, y).0[..3].iter().all(|&c| c > 200);
+        let black = |x: u32, y: u32| frame.get_pixel(x, y).0[..3].iter().all(|&c| c < 50);
+        // 長辺方向に白と黒を3点ずつ、短辺方向に3列ずつ調べる
+        let near = [20, 80, 140];
+        let far = [180, 240, 300];
+        let across = [30, 120, 210];
+        let all = |check: &dyn Fn(u32, u32) -> bool, along: &[u32], swap: bool| {
+            along.iter().all(|&a| across.iter().all(|&b| if swap { check(b, a) } else { check(a, b) }))
+        };
+        match (width, height) {
+            (320, 240) if all(&white, &near, false) && all(&black, &far, false) => Some(0),
+            (240, 320) if all(&white, &near, true) && all(&black, &far, true) => Some(90),
+            (320, 240) if all(&black, &near, false) && all(&white, &far, false) => Some(180),
+            (240, 320) if all(&black, &near, true) && all(&white, &far, true) => Some(270),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn rotating_during_playback_never_delivers_broken_frames() {
+        let dir = TempDir::new();
+        let Some(path) = half_white_clip(&dir, 10.0) else { return };
+        let mut player = test_player();
+        load(&mut player, &path).unwrap();
+
+        let rotations = Arc::new(Mutex::new(Vec::new()));
+        let recorder = Arc::clone(&rotations);
+        player.on_frame(move |frame| recorder.lock().unwrap().push(half_white_rotation(frame)));
+        player.play().unwrap();
+        assert!(wait_for(|| rotations.lock().unwrap().len() >= 10), "再生が始まりません");
+
+        // 320x240 → 240x320（バイト数は同じ）→ 320x240 と、再生中に出力の大きさを変える
+        for expected in [90, 180] {
+            player.rotate_clockwise();
+            assert!(
+                wait_for(|| rotations.lock().unwrap().iter().rev().take(10).filter(|&&r| r == Some(expected)).count() == 10),
+                "{}°のフレームが続けて出ません",
+                expected
+            );
+            let mut frame = player.take_frame().expect("表示するフレームがありません");
+            while let Some(next) = player.take_frame() {
+                frame = next;
+            }
+            assert_eq!(half_white_rotation(&frame), Some(expected));
+        }
+        player.stop();
+
+        // 回す前後の向きのフレームは出てよいが、崩れたフレームや回していない向きのフレームは出ない
+        let rotations = rotations.lock().unwrap();
+        assert!(rotations.iter().all(|r| matches!(r, Some(0 | 90 | 180))), "崩れたフレームがあります: {:?}", rotations);
+        // 一度新しい向きになったら、前の向きには戻らない
+        let changes: Vec<_> = rotations.windows(2).filter(|w| w[0] != w[1]).map(|w| (w[0], w[1])).collect();
+        assert_eq!(changes, vec![(Some(0), Some(90)), (Some(90), Some(180))]);
+    }
+
+    #[test]
+    fn raw_frames_skip_the_rgba_conversion_without_a_display() {
+        let observers = Observers::default();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let recorder = Arc::clone(&received);
+        observers.raw_frame.lock().unwrap().push(Arc::new(move |data: &[u8], width, height, format| {
+            recorder.lock().unwrap().push((data.len(), width, height, format));
+        }));
+        // 2x2のNV12（Yが4バイト、UVが2バイト）
+        let data = [235, 235, 16, 16, 128, 128];
+
+        let frame = observers.notify_decoded_frame(&data, 2, 2, FrameFormat::Nv12).expect("表示用のフレームがありません");
+        assert_eq!(frame.dimensions(), (2, 2));
+
+        observers.no_display.store(true, Ordering::Relaxed);
+        assert!(observers.notify_decoded_frame(&data, 2, 2, FrameFormat::Nv12).is_none());
+
+        // on_frameが登録されていれば、表示しなくても変換する
+        observers.frame.lock().unwrap().push(Arc::new(|_: &RgbaImage| {}));
+        assert!(observers.notify_decoded_frame(&data, 2, 2, FrameFormat::Nv12).is_some());
+
+        assert_eq!(*received.lock().unwrap(), vec![(6, 2, 2, FrameFormat::Nv12); 3]);
+    }
+
+    #[test]
+    fn nv12_decoding_converts_to_the_bt601_limited_range() {
+        let mut player = test_player();
+        player.video_color = ColorInfo { matrix: "bt709", full_range: true, transfer: None, tagged: true };
+        player.color_correction = true;
+        assert!(!player.build_video_filters().contains(NV12_SCALE_OUTPUT));
+
+        player.frame_format = FrameFormat::Nv12;
+        let filters = player.build_video_filters();
+        assert!(filters.contains("in_color_matrix=bt709:in_range=full:out_color_matrix=bt601:out_range=tv,"), "{}", filters);
+        // 静止画・サムネイルはRGBAで取り出すので変換しない
+        assert!(!player.build_image_filters().contains(NV12_SCALE_OUTPUT));
+    }
+
+    #[test]
+    fn waveform_cache_keeps_only_the_most_recent_entries() {
+        let mut cache = WaveformCache::default();
+        let key = |n: usize| (PathBuf::from(format!("video{}.mp4", n)), 480);
+        for n in 0..MAX_WAVEFORM_CACHE_ENTRIES {
+            cache.insert(key(n), vec![n as f32]);
+        }
+        // 最初のものを使ってから1つ足すと、2番目に古いものが消える
+        assert_eq!(cache.get(&key(0)), Some(vec![0.0]));
+        cache.insert(key(MAX_WAVEFORM_CACHE_ENTRIES), vec![0.5]);
+        assert_eq!(cache.entries.len(), MAX_WAVEFORM_CACHE_ENTRIES);
+        assert_eq!(cache.get(&key(1)), None);
+        assert_eq!(cache.get(&key(0)), Some(vec![0.0]));
+        // 区間数が違えば別の波形
+        assert_eq!(cache.get(&(PathBuf::from("video0.mp4"), 240)), None);
+    }
+
+    #[test]
+    fn audio_offset_restart_waits_and_saves_on_release() {
+        let mut player = test_player();
+        let path = PathBuf::from("video.mp4");
+        player.video_path = Some(path.clone());
+
+        // スライダーの操作中は値だけを変え、再起動は待たせる
+        for offset in [0.25, 0.5, 0.75, -0.5, 1.5] {
+            player.set_audio_offset(offset);
+            assert!(player.audio_restart_at.is_some());
+        }
+        assert_eq!(player.settings.get_file_f32(&path, "audio_offset"), None);
+
+        player.save_audio_offset();
+        assert_eq!(player.settings.get_file_f32(&path, "audio_offset"), Some(1.5));
+
+        // 待ち時間が過ぎたら、最後の値でまとめて再起動する
+        assert!(wait_for(|| {
+            player.update_audio_restart();
+            player.audio_restart_at.is_none()
+        }));
+    }
+
+    #[test]
+    fn volume_boost_restart_waits_and_saves_on_release() {
+        let mut player = test_player();
+        let path = PathBuf::from("video.mp4");
+        player.video_path = Some(path.clone());
+
+        for boost in [1.5, 2.0, 2.5, 5.0] {
+            player.set_volume_boost(boost);
+            assert!(player.audio_restart_at.is_some());
+        }
+        assert_eq!(player.get_volume_boost(), MAX_VOLUME_BOOST);
+        assert_eq!(player.settings.get_file_f32(&path, "volume_boost"), None);
+
+        player.save_volume_boost();
+        assert_eq!(player.settings.get_file_f32(&path, "volume_boost"), Some(MAX_VOLUME_BOOST));
+
+        assert!(wait_for(|| {
+            player.update_audio_restart();
+            player.audio_restart_at.is_none()
+        }));
+    }
+
+    #[test]
+    fn balance_restart_waits_until_the_slider_settles() {
+        let mut player = test_player();
+
+        for balance in [-0.25, -0.5, -1.5] {
+            player.set_balance(balance);
+            assert!(player.audio_restart_at.is_some());
+        }
+        assert_eq!(player.balance, -1.0);
+
+        assert!(wait_for(|| {
+            player.update_audio_restart();
+            player.audio_restart_at.is_none()
+        }));
+    }
+
+    #[test]
+    fn subtitle_offset_is_saved_on_release() {
+        let mut player = test_player();
+        let dir = TempDir::new();
+        let path = dir.join("video.srt");
+        std::fs::write(&path, "1\n00:00:01,000 --> 00:00:02,000\nHello\n").unwrap();
+        player.load_subtitles(path.clone()).unwrap();
+
+        // スライダーの操作中は値だけを変える
+        for offset in [0.5, 1.0, 12.0] {
+            player.set_subtitle_offset(offset);
+        }
+        assert_eq!(player.get_subtitle_offset(), 10.0);
+        assert_eq!(player.settings.get_file_f32(&path, "subtitle_offset"), None);
+
+        player.save_subtitle_offset();
+        assert_eq!(player.settings.get_file_f32(&path, "subtitle_offset"), Some(10.0));
+    }
+
+    #[test]
+    fn equalizer_is_saved_when_the_restart_fires() {
+        let mut player = test_player();
+
+        for gain in [1.0, 2.0, 3.0] {
+            player.set_equalizer(vec![gain; equalizer::BANDS.len()]);
+        }
+        assert_eq!(player.settings.get_value("equalizer"), None);
+
+        assert!(wait_for(|| {
+            player.update_audio_restart();
+            player.audio_restart_at.is_none()
+        }));
+        let saved = vec!["3.0"; equalizer::BANDS.len()].join(",");
+        assert_eq!(player.settings.get_value("equalizer"), Some(saved.as_str()));
+    }
+
+    #[test]
+    fn filter_strengths_are_saved_when_the_restart_fires() {
+        let mut player = test_player();
+
+        for strength in [1.0, 2.0, 12.0] {
+            player.set_denoise(true, strength);
+            player.set_sharpen(true, strength / 4.0);
+        }
+        assert_eq!(player.get_denoise(), (true, MAX_DENOISE_STRENGTH));
+        assert_eq!(player.settings.get_value("denoise_strength"), None);
+        assert_eq!(player.settings.get_value("sharpen_strength"), None);
+
+        player.update_video_restart();
+        assert!(player.video_restart_at.is_some());
+        assert!(wait_for(|| {
+            player.update_video_restart();
+            player.video_restart_at.is_none()
+        }));
+        assert_eq!(player.settings.get_value("denoise_strength"), Some("10.0"));
+        assert_eq!(player.settings.get_value("sharpen_strength"), Some("2.00"));
+    }
+
+    #[test]
+    fn skip_intro_uses_the_same_limit_as_the_config_import() {
+        let mut player = test_player();
+        player.set_skip_intro(5000.0, false);
+        assert_eq!(player.get_skip_intro(), (MAX_SKIP_INTRO, false));
+        player.set_skip_intro(-5.0, false);
+        assert_eq!(player.get_skip_intro(), (0.0, false));
     }
 }
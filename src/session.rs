@@ -0,0 +1,73 @@
+// 終了時の状態（開いていた動画・プレイリスト・位置・音量・フィルター）を保存し、次の起動で元に戻す
+// 設定ファイルとは別のsession.iniに、終了するたびに書き直す
+use std::fs;
+use std::path::PathBuf;
+
+use crate::i18n::trf;
+use crate::settings::Settings;
+
+#[derive(Clone, Debug, Default)]
+pub struct Session {
+    pub playlist: Vec<PathBuf>,
+    pub current: Option<PathBuf>,
+    pub position: f32,
+    pub volume: f32,
+    pub denoise: bool,
+    pub sharpen: bool,
+}
+
+impl Session {
+    fn file_path() -> Option<PathBuf> {
+        Settings::config_dir().map(|dir| dir.join("session.ini"))
+    }
+
+    /// 保存されたセッション（ファイルがなければNone）
+    pub fn load() -> Option<Self> {
+        let content = fs::read_to_string(Self::file_path()?).ok()?;
+        let mut session = Session {
+            volume: 1.0,
+            ..Default::default()
+        };
+        for line in content.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            match key.trim() {
+                // パスの前後の空白はファイル名の一部なのでtrimしない
+                "playlist" => session.playlist.push(PathBuf::from(value)),
+                "current" => session.current = Some(PathBuf::from(value)),
+                "position" => session.position = value.trim().parse().unwrap_or(0.0),
+                "volume" => session.volume = value.trim().parse().unwrap_or(1.0),
+                "denoise" => session.denoise = value.trim() == "true",
+                "sharpen" => session.sharpen = value.trim() == "true",
+                _ => {}
+            }
+        }
+        Some(session)
+    }
+
+    pub fn save(&self) {
+        let Some(path) = Self::file_path() else {
+            return;
+        };
+
+        let mut content = String::new();
+        if let Some(current) = &self.current {
+            content.push_str(&format!("current={}\n", current.display()));
+        }
+        content.push_str(&format!("position={:.2}\n", self.position));
+        content.push_str(&format!("volume={:.2}\n", self.volume));
+        content.push_str(&format!("denoise={}\n", self.denoise));
+        content.push_str(&format!("sharpen={}\n", self.sharpen));
+        for item in &self.playlist {
+            content.push_str(&format!("playlist={}\n", item.display()));
+        }
+
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        if let Err(e) = fs::write(path, content) {
+            println!("{}", trf("Failed to save the session: {}", &[&e]));
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::i18n::trf;
+
+// 設定ファイル（key=value形式、[ファイルパス]のセクションでファイルごとの設定を保持）
+pub struct Settings {
+    file_path: Option<PathBuf>,
+    values: HashMap<String, String>,
+    file_values: HashMap<String, HashMap<String, String>>,
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        let file_path = Self::config_dir().map(|dir| dir.join("settings.ini"));
+        let mut settings = Self {
+            file_path,
+            values: HashMap::new(),
+            file_values: HashMap::new(),
+        };
+
+        if let Some(path) = &settings.file_path {
+            if let Ok(content) = fs::read_to_string(path) {
+                settings.parse(&content);
+            }
+        }
+
+        settings
+    }
+
+    /// ファイルに読み書きしない設定（テスト用）
+    #[cfg(test)]
+    pub fn in_memory() -> Self {
+        Self {
+            file_path: None,
+            values: HashMap::new(),
+            file_values: HashMap::new(),
+        }
+    }
+
+    pub fn config_dir() -> Option<PathBuf> {
+        // Windowsは%APPDATA%、それ以外はXDG_CONFIG_HOMEまたは~/.config
+        let base = std::env::var_os("APPDATA")
+            .or_else(|| std::env::var_os("XDG_CONFIG_HOME"))
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(base.join("video-player-ui"))
+    }
+
+    fn parse(&mut self, content: &str) {
+        let mut section: Option<String> = None;
+
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                section = Some(line[1..line.len() - 1].to_string());
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=') {
+                let target = match &section {
+                    Some(name) => self.file_values.entry(name.clone()).or_default(),
+                    None => &mut self.values,
+                };
+                target.insert(key.trim().to_string(), value.trim().to_string());
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let Some(path) = &self.file_path else {
+            return;
+        };
+
+        let mut content = String::new();
+        for (key, value) in &self.values {
+            content.push_str(&format!("{}={}\n", key, value));
+        }
+        for (name, values) in &self.file_values {
+            if values.is_empty() {
+                continue;
+            }
+            content.push_str(&format!("\n[{}]\n", name));
+            for (key, value) in values {
+                content.push_str(&format!("{}={}\n", key, value));
+            }
+        }
+
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        if let Err(e) = fs::write(path, content) {
+            println!("{}", trf("Failed to save the settings: {}", &[&e]));
+        }
+    }
+
+    // ファイルごとの設定のキー（同じファイルを別の表記で開いても一致するよう正規化）
+    fn file_key(path: &Path) -> String {
+        fs::canonicalize(path)
+            .unwrap_or_else(|_| path.to_path_buf())
+            .display()
+            .to_string()
+    }
+
+    pub fn get_value(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    pub fn set_value(&mut self, key: &str, value: impl ToString) {
+        self.values.insert(key.to_string(), value.to_string());
+        self.save();
+    }
+
+    /// ファイル（またはフォルダ）ごとの設定
+    pub fn get_file_value(&self, path: &Path, key: &str) -> Option<&str> {
+        self.file_values
+            .get(&Self::file_key(path))
+            .and_then(|values| values.get(key))
+            .map(String::as_str)
+    }
+
+    pub fn get_file_f32(&self, path: &Path, key: &str) -> Option<f32> {
+        self.get_file_value(path, key).and_then(|value| value.parse::<f32>().ok())
+    }
+
+    /// keyが保存されているファイルと、その値の一覧
+    pub fn files_with_value(&self, key: &str) -> Vec<(PathBuf, &str)> {
+        self.file_values
+            .iter()
+            .filter_map(|(name, values)| values.get(key).map(|value| (PathBuf::from(name), value.as_str())))
+            .collect()
+    }
+
+    pub fn remove_file_value(&mut self, path: &Path, key: &str) {
+        if let Some(values) = self.file_values.get_mut(&Self::file_key(path)) {
+            if values.remove(key).is_some() {
+                self.save();
+            }
+        }
+    }
+
+    pub fn set_file_value(&mut self, path: &Path, key: &str, value: impl ToString) {
+        self.file_values
+            .entry(Self::file_key(path))
+            .or_default()
+            .insert(key.to_string(), value.to_string());
+        self.save();
+    }
+}
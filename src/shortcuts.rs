@@ -0,0 +1,105 @@
+// キーボードショートカットの一覧（キー操作の判定とヘルプの表示はどちらもこの表を見るので、食い違わない）
+use device_query::Keycode;
+
+use crate::i18n::tr;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Action {
+    ToggleFullscreen,
+    ToggleHistogram,
+    RotateClockwise,
+    StepBackward,
+    StepForward,
+    SeekBackward,
+    SeekForward,
+    SeekBackwardLong,
+    SeekForwardLong,
+    VolumeUp,
+    VolumeDown,
+    SubtitleEarlier,
+    SubtitleLater,
+    ToggleHelp,
+    CloseHelp,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Keys {
+    Single(Keycode),
+    Shift(Keycode),
+    CtrlAlt, // CtrlとAltの同時押し（キー監視のタイマーで押し始めを見る）
+}
+
+pub struct Shortcut {
+    pub action: Action,
+    pub keys: Keys,
+    description: &'static str,
+}
+
+// 同じ操作に複数のキーを割り当てる場合は行を分ける（ヘルプにも1行ずつ表示する）
+pub const SHORTCUTS: &[Shortcut] = &[
+    Shortcut { action: Action::ToggleFullscreen, keys: Keys::CtrlAlt, description: "Toggle fullscreen" },
+    Shortcut { action: Action::SeekBackward, keys: Keys::Single(Keycode::Left), description: "Seek back (short step)" },
+    Shortcut { action: Action::SeekForward, keys: Keys::Single(Keycode::Right), description: "Seek forward (short step)" },
+    Shortcut { action: Action::SeekBackwardLong, keys: Keys::Shift(Keycode::Left), description: "Seek back (long step)" },
+    Shortcut { action: Action::SeekForwardLong, keys: Keys::Shift(Keycode::Right), description: "Seek forward (long step)" },
+    Shortcut { action: Action::StepBackward, keys: Keys::Single(Keycode::Comma), description: "Previous frame" },
+    Shortcut { action: Action::StepForward, keys: Keys::Single(Keycode::Dot), description: "Next frame" },
+    Shortcut { action: Action::VolumeUp, keys: Keys::Single(Keycode::Up), description: "Volume up" },
+    Shortcut { action: Action::VolumeDown, keys: Keys::Single(Keycode::Down), description: "Volume down" },
+    Shortcut { action: Action::SubtitleEarlier, keys: Keys::Single(Keycode::G), description: "Show subtitles 100 ms earlier" },
+    Shortcut { action: Action::SubtitleLater, keys: Keys::Single(Keycode::H), description: "Show subtitles 100 ms later" },
+    Shortcut { action: Action::ToggleHistogram, keys: Keys::Single(Keycode::S), description: "Show or hide the histogram" },
+    Shortcut { action: Action::RotateClockwise, keys: Keys::Single(Keycode::R), description: "Rotate 90° clockwise" },
+    Shortcut { action: Action::ToggleHelp, keys: Keys::Shift(Keycode::Slash), description: "Show or hide this list" },
+    Shortcut { action: Action::ToggleHelp, keys: Keys::Single(Keycode::F1), description: "Show or hide this list" },
+    Shortcut { action: Action::CloseHelp, keys: Keys::Single(Keycode::Escape), description: "Close this list" },
+];
+
+impl Shortcut {
+    /// 表示する操作の説明（表示言語に合わせる）
+    pub fn description(&self) -> &'static str {
+        tr(self.description)
+    }
+}
+
+impl Keys {
+    /// ヘルプに表示するキーの名前
+    pub fn label(self) -> String {
+        match self {
+            Keys::Single(key) => key_name(key).to_string(),
+            // Shift+/は「?」として見せる（キーボードの刻印に合わせる）
+            Keys::Shift(Keycode::Slash) => "?".to_string(),
+            Keys::Shift(key) => format!("Shift+{}", key_name(key)),
+            Keys::CtrlAlt => "Ctrl+Alt".to_string(),
+        }
+    }
+}
+
+fn key_name(key: Keycode) -> &'static str {
+    match key {
+        Keycode::Left => "←",
+        Keycode::Right => "→",
+        Keycode::Up => "↑",
+        Keycode::Down => "↓",
+        Keycode::Comma => ",",
+        Keycode::Dot => ".",
+        Keycode::Slash => "/",
+        Keycode::Escape => "Esc",
+        Keycode::F1 => "F1",
+        Keycode::G => "G",
+        Keycode::H => "H",
+        Keycode::R => "R",
+        Keycode::S => "S",
+        _ => "?",
+    }
+}
+
+/// actionに割り当てたキーのどれかが押されたか（just_pressedは押された瞬間だけtrue）
+/// Shiftなしの割り当ては、同じキーにShift付きの割り当てがなければShiftを押していても反応する
+pub fn triggered(action: Action, just_pressed: impl Fn(Keycode) -> bool, shift: bool) -> bool {
+    SHORTCUTS.iter().filter(|shortcut| shortcut.action == action).any(|shortcut| match shortcut.keys {
+        Keys::Single(key) => just_pressed(key) && !(shift && SHORTCUTS.iter().any(|other| other.keys == Keys::Shift(key))),
+        Keys::Shift(key) => just_pressed(key) && shift,
+        Keys::CtrlAlt => false,
+    })
+}
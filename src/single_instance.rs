@@ -0,0 +1,197 @@
+// 単一インスタンス: 2つ目の起動はファイルのパスを起動中のウィンドウへ送って終了する
+// 起動中のインスタンスはローカルのTCPポートで待ち受け、そのポート番号と合言葉（ランダムな文字列）をロックファイルに書いておく
+// 送る側は最初の行に合言葉を送り、受け取った側の応答（ACK）を待つ。異常終了でロックファイルだけが残り、
+// 同じポートを別のプログラムが使っていても、そちらにパスを送ったまま終了しないように
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::i18n::trf;
+use crate::settings::Settings;
+
+// 受け取った側が最後まで読んだことを知らせる応答
+const ACK: &str = "video-player-ui ok";
+
+pub enum Instance {
+    Primary(SingleInstance), // このプロセスが最初のインスタンス
+    Forwarded,               // 起動中のインスタンスにファイルを渡した（このプロセスは終了する）
+}
+
+pub struct SingleInstance {
+    lock_path: PathBuf,
+    received: Arc<Mutex<Option<Vec<PathBuf>>>>,
+}
+
+impl SingleInstance {
+    /// 起動中のインスタンスを探してfilesを送る。見つからなければ待ち受けを始める
+    /// 待ち受けを始められない場合（設定フォルダがないなど）はNone（通常どおり起動する）
+    pub fn acquire(files: &[PathBuf]) -> Option<Instance> {
+        let lock_path = Settings::config_dir()?.join("instance.lock");
+        
+        if let Some((port, token)) = fs::read_to_string(&lock_path).ok().as_deref().and_then(Self::parse_lock) {
+            match Self::forward(port, &token, files) {
+                Ok(()) => return Some(Instance::Forwarded),
+                // 前回のインスタンスが異常終了してロックファイルだけ残っている
+                Err(e) => eprintln!("{}", trf("Cannot connect to the running instance, removing the stale lock file: {}", &[&e])),
+            }
+            let _ = fs::remove_file(&lock_path);
+        }
+        
+        let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, 0)) {
+            Ok(listener) => listener,
+            Err(e) => {
+                eprintln!("{}", trf("Cannot start listening for other launches: {}", &[&e]));
+                return None;
+            }
+        };
+        let port = listener.local_addr().ok()?.port();
+        if let Some(dir) = lock_path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let token = Self::new_token();
+        if let Err(e) = fs::write(&lock_path, format!("{} {}", port, token)) {
+            eprintln!("{}", trf("Cannot write the lock file: {}", &[&e]));
+            return None;
+        }
+        
+        let received = Arc::new(Mutex::new(None));
+        let received_clone = Arc::clone(&received);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                // 合言葉のない接続（別のプログラムなど）は無視する
+                let Some(paths) = Self::receive(stream, &token) else {
+                    continue;
+                };
+                println!("{}", trf("Received {} files from another launch", &[&paths.len()]));
+                received_clone.lock().unwrap().get_or_insert_with(Vec::new).extend(paths);
+            }
+        });
+        
+        Some(Instance::Primary(Self { lock_path, received }))
+    }
+    
+    // ロックファイルの内容（"ポート番号 合言葉"）
+    fn parse_lock(text: &str) -> Option<(u16, String)> {
+        let (port, token) = text.trim().split_once(' ')?;
+        Some((port.parse().ok()?, token.to_string())).filter(|(_, token)| !token.is_empty())
+    }
+    
+    // 推測されにくい合言葉（起動ごとに鍵の変わるRandomStateのハッシュを2つつなげる）
+    fn new_token() -> String {
+        let seed = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+        let part = || {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u128(seed);
+            hasher.write_u32(std::process::id());
+            hasher.finish()
+        };
+        format!("{:016x}{:016x}", part(), part())
+    }
+    
+    // 1行目が合言葉で、以降は1行に1ファイル（ファイルなしの起動はウィンドウを前面に出すだけ）
+    // 最後まで受け取ったらACKを返す。合言葉が違えば何も返さずに切断する
+    fn receive(stream: TcpStream, token: &str) -> Option<Vec<PathBuf>> {
+        // 送信側が途中で止まっても待ち受けが詰まらないように
+        let _ = stream.set_read_timeout(Some(Duration::from_secs(2)));
+        let mut lines = BufReader::new(stream.try_clone().ok()?).lines();
+        if lines.next()?.ok()? != token {
+            return None;
+        }
+        let paths: Vec<PathBuf> = lines
+            .map_while(Result::ok)
+            .filter(|line| !line.is_empty())
+            .map(PathBuf::from)
+            .collect();
+        writeln!(&stream, "{}", ACK).ok()?;
+        Some(paths)
+    }
+    
+    // 合言葉とファイルを送り、受け取った側のACKを待つ（ACKがなければ起動中のインスタンスではない）
+    fn forward(port: u16, token: &str, files: &[PathBuf]) -> std::io::Result<()> {
+        let mut stream = TcpStream::connect_timeout(&(Ipv4Addr::LOCALHOST, port).into(), Duration::from_millis(500))?;
+        stream.set_read_timeout(Some(Duration::from_secs(2)))?;
+        writeln!(stream, "{}", token)?;
+        for file in files {
+            // 相対パスは受け取る側の作業ディレクトリでは解決できないので絶対パスにする
+            let path = fs::canonicalize(file).unwrap_or_else(|_| file.clone());
+            writeln!(stream, "{}", path.display())?;
+        }
+        stream.shutdown(std::net::Shutdown::Write)?;
+        let mut reply = String::new();
+        BufReader::new(stream).read_line(&mut reply)?;
+        if reply.trim_end() == ACK {
+            Ok(())
+        } else {
+            Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "no acknowledgement from the running instance"))
+        }
+    }
+    
+    /// 別の起動から受け取ったファイル（前回の呼び出し以降に起動がなければNone）
+    pub fn take_files(&self) -> Option<Vec<PathBuf>> {
+        self.received.lock().unwrap().take()
+    }
+}
+
+impl Drop for SingleInstance {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.lock_path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1回だけ接続を受け付けてreceiveで処理するスレッド
+    fn serve_once(token: &str) -> (u16, thread::JoinHandle<Option<Vec<PathBuf>>>) {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let token = token.to_string();
+        (port, thread::spawn(move || SingleInstance::receive(listener.accept().unwrap().0, &token)))
+    }
+
+    #[test]
+    fn files_are_forwarded_with_the_right_token() {
+        let (port, server) = serve_once("secret");
+        let files = [PathBuf::from("/videos/a.mp4"), PathBuf::from("/videos/b c.mkv")];
+        SingleInstance::forward(port, "secret", &files).unwrap();
+        assert_eq!(server.join().unwrap(), Some(files.to_vec()));
+    }
+
+    #[test]
+    fn a_wrong_token_is_dropped_and_not_acknowledged() {
+        let (port, server) = serve_once("secret");
+        assert!(SingleInstance::forward(port, "guess", &[PathBuf::from("/videos/a.mp4")]).is_err());
+        assert_eq!(server.join().unwrap(), None);
+    }
+
+    #[test]
+    fn an_unrelated_program_on_the_port_is_not_a_running_instance() {
+        // ロックファイルが残ったまま、別のプログラムが同じポートで待ち受けている（読むだけで応答しない）
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let other = thread::spawn(move || {
+            let mut text = String::new();
+            let _ = BufReader::new(listener.accept().unwrap().0).read_line(&mut text);
+        });
+        assert!(SingleInstance::forward(port, "secret", &[]).is_err());
+        other.join().unwrap();
+    }
+
+    #[test]
+    fn lock_file_holds_the_port_and_token() {
+        assert_eq!(SingleInstance::parse_lock("4321 abc\n"), Some((4321, "abc".to_string())));
+        // 合言葉のない古い形式のロックファイルは使わない
+        assert_eq!(SingleInstance::parse_lock("4321"), None);
+        assert_eq!(SingleInstance::parse_lock("port abc"), None);
+        let token = SingleInstance::new_token();
+        assert_eq!(token.len(), 32);
+        assert_ne!(token, SingleInstance::new_token());
+    }
+}
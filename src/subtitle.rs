@@ -0,0 +1,124 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::i18n::{tr, trf};
+
+#[derive(Clone, Debug)]
+pub struct SubtitleCue {
+    pub start: f32,
+    pub end: f32,
+    pub text: String,
+}
+
+pub struct Subtitles {
+    pub path: PathBuf,
+    pub cues: Vec<SubtitleCue>,
+}
+
+impl Subtitles {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| trf("Failed to read the subtitle file: {}", &[&e]))?;
+        let content = String::from_utf8_lossy(&bytes);
+        let mut cues = Self::parse_srt(&content);
+        if cues.is_empty() {
+            return Err(tr("No subtitles found (only SRT is supported)").to_string());
+        }
+        // 開始時刻順に並べる（二分探索のため）
+        cues.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+        println!("{}", trf("Loaded subtitles: {} ({} cues)", &[&path.display(), &cues.len()]));
+        Ok(Self {
+            path: path.to_path_buf(),
+            cues,
+        })
+    }
+
+    // 動画と同じ名前の.srtファイルを探す
+    pub fn find_sidecar(video_path: &Path) -> Option<PathBuf> {
+        let candidate = video_path.with_extension("srt");
+        if candidate.is_file() {
+            Some(candidate)
+        } else {
+            None
+        }
+    }
+
+    fn parse_srt(content: &str) -> Vec<SubtitleCue> {
+        let content = content.trim_start_matches('\u{feff}').replace("\r\n", "\n");
+        let mut cues = Vec::new();
+
+        for block in content.split("\n\n") {
+            let mut lines = block.lines().map(str::trim_end).skip_while(|line| line.trim().is_empty());
+
+            // 番号行は省略されている場合もあるので、"-->"を含む行を探す
+            let mut timing = None;
+            for line in lines.by_ref() {
+                if line.contains("-->") {
+                    timing = Some(line);
+                    break;
+                }
+            }
+            let Some(timing) = timing else {
+                continue;
+            };
+
+            let Some((start_str, end_str)) = timing.split_once("-->") else {
+                continue;
+            };
+            // 終了時刻の後ろに位置指定が付くことがある
+            let end_str = end_str.split_whitespace().next().unwrap_or("");
+            let (Some(start), Some(end)) = (Self::parse_timestamp(start_str), Self::parse_timestamp(end_str)) else {
+                continue;
+            };
+
+            let text = lines
+                .map(Self::strip_tags)
+                .collect::<Vec<_>>()
+                .join("\n");
+            if !text.trim().is_empty() {
+                cues.push(SubtitleCue { start, end, text });
+            }
+        }
+
+        cues
+    }
+
+    // "HH:MM:SS,mmm"（"."区切りも許容）を秒に変換
+    fn parse_timestamp(s: &str) -> Option<f32> {
+        let s = s.trim().replace(',', ".");
+        let mut parts = s.split(':');
+        let hours = parts.next()?.parse::<f32>().ok()?;
+        let minutes = parts.next()?.parse::<f32>().ok()?;
+        let seconds = parts.next()?.parse::<f32>().ok()?;
+        Some(hours * 3600.0 + minutes * 60.0 + seconds)
+    }
+
+    // <i>などの書式タグを取り除く
+    fn strip_tags(line: &str) -> String {
+        let mut result = String::with_capacity(line.len());
+        let mut in_tag = false;
+        for c in line.chars() {
+            match c {
+                '<' => in_tag = true,
+                '>' if in_tag => in_tag = false,
+                _ if !in_tag => result.push(c),
+                _ => {}
+            }
+        }
+        result
+    }
+
+    /// 指定時刻に表示すべき字幕（オフセット適用済みの時刻を渡す）
+    pub fn active_cue(&self, time: f32) -> Option<&SubtitleCue> {
+        // 開始時刻がtime以下の最後の字幕を探す
+        let index = self.cues.partition_point(|cue| cue.start <= time);
+        if index == 0 {
+            return None;
+        }
+        // 重なりがある場合に備えて少しだけ遡る
+        self.cues[..index]
+            .iter()
+            .rev()
+            .take(4)
+            .find(|cue| time < cue.end)
+    }
+}
@@ -0,0 +1,104 @@
+// タスクバーのボタンに再生の進行状況を表示する（Windowsのみ、他のOSでは何もしない）
+// サムネイル（ホバー時のプレビュー）はDWMがウィンドウの内容から自動で生成する
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TaskbarState {
+    None,          // 表示なし（停止中）
+    Indeterminate, // 読み込み中など、進み具合が分からない状態
+    Normal(f32),   // 再生中（0.0〜1.0）
+    Paused(f32),   // 一時停止中（0.0〜1.0）
+}
+
+pub struct TaskbarProgress {
+    state: TaskbarState,
+    #[cfg(windows)]
+    taskbar: Option<windows_taskbar::Taskbar>,
+}
+
+impl TaskbarProgress {
+    pub fn new(window: &slint::Window) -> Self {
+        #[cfg(not(windows))]
+        let _ = window;
+
+        Self {
+            state: TaskbarState::None,
+            #[cfg(windows)]
+            taskbar: windows_taskbar::Taskbar::new(window),
+        }
+    }
+
+    pub fn set_state(&mut self, state: TaskbarState) {
+        // 表示が変わらない更新は送らない（進行状況は0.1%単位で比較）
+        let quantize = |state: TaskbarState| match state {
+            TaskbarState::Normal(ratio) => TaskbarState::Normal((ratio * 1000.0).round() / 1000.0),
+            TaskbarState::Paused(ratio) => TaskbarState::Paused((ratio * 1000.0).round() / 1000.0),
+            other => other,
+        };
+        let state = quantize(state);
+        if state == self.state {
+            return;
+        }
+        self.state = state;
+
+        #[cfg(windows)]
+        if let Some(taskbar) = &self.taskbar {
+            taskbar.apply(state);
+        }
+    }
+}
+
+#[cfg(windows)]
+mod windows_taskbar {
+    use super::TaskbarState;
+    use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED};
+    use windows::Win32::UI::Shell::{ITaskbarList3, TaskbarList, TBPF_INDETERMINATE, TBPF_NOPROGRESS, TBPF_NORMAL, TBPF_PAUSED};
+
+    // 進行状況の分母（ITaskbarList3は整数で指定する）
+    const PROGRESS_TOTAL: u64 = 1000;
+
+    pub struct Taskbar {
+        list: ITaskbarList3,
+        hwnd: HWND,
+    }
+
+    impl Taskbar {
+        pub fn new(window: &slint::Window) -> Option<Self> {
+            let handle = window.window_handle();
+            let hwnd = match handle.window_handle().ok()?.as_raw() {
+                RawWindowHandle::Win32(handle) => HWND(handle.hwnd.get() as *mut _),
+                _ => return None,
+            };
+
+            unsafe {
+                // UIスレッドでは既に初期化済みのことが多いので、結果は無視する
+                let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+                let list: ITaskbarList3 = match CoCreateInstance(&TaskbarList, None, CLSCTX_INPROC_SERVER) {
+                    Ok(list) => list,
+                    Err(e) => {
+                        println!("{}", crate::i18n::trf("Cannot show the taskbar progress: {}", &[&e]));
+                        return None;
+                    }
+                };
+                list.HrInit().ok()?;
+                Some(Self { list, hwnd })
+            }
+        }
+
+        pub fn apply(&self, state: TaskbarState) {
+            unsafe {
+                let _ = match state {
+                    TaskbarState::None => self.list.SetProgressState(self.hwnd, TBPF_NOPROGRESS),
+                    TaskbarState::Indeterminate => self.list.SetProgressState(self.hwnd, TBPF_INDETERMINATE),
+                    TaskbarState::Normal(ratio) | TaskbarState::Paused(ratio) => {
+                        let flag = if matches!(state, TaskbarState::Paused(_)) { TBPF_PAUSED } else { TBPF_NORMAL };
+                        let completed = (ratio.clamp(0.0, 1.0) * PROGRESS_TOTAL as f32) as u64;
+                        let _ = self.list.SetProgressState(self.hwnd, flag);
+                        self.list.SetProgressValue(self.hwnd, completed, PROGRESS_TOTAL)
+                    }
+                };
+            }
+        }
+    }
+}
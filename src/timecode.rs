@@ -0,0 +1,103 @@
+// SMPTEタイムコード（HH:MM:SS:FF）とフレーム番号への変換
+
+/// 秒をタイムコードにする。29.97/59.94fpsはドロップフレーム（区切りは「;」）で数える
+/// fpsは平均フレームレートなので、可変フレームレートの動画では近似になる
+/// フレームはframe_atと同じ数え方にする（フレーム番号の表示と食い違わないように）
+pub fn format_timecode(seconds: f32, fps: f32) -> String {
+    if fps <= 0.0 {
+        return "--:--:--:--".to_string();
+    }
+    timecode_of_frame(frame_at(seconds, fps), fps)
+}
+
+// フレーム番号（0始まり）をタイムコードにする
+fn timecode_of_frame(mut frame: u64, fps: f32) -> String {
+    let nominal = fps.round() as u64; // 1秒あたりのフレーム番号の数（29.97なら30）
+    
+    // ドロップフレーム: 10分ごとを除く毎分の先頭で、フレーム番号を2つ（59.94なら4つ）飛ばす
+    let drop_frame = is_drop_frame_rate(fps);
+    if drop_frame {
+        let dropped = nominal / 15;
+        let frames_per_minute = nominal * 60 - dropped;
+        let frames_per_10_minutes = frames_per_minute * 10 + dropped;
+        let tens = frame / frames_per_10_minutes;
+        let remainder = frame % frames_per_10_minutes;
+        frame += dropped * 9 * tens;
+        if remainder > dropped {
+            frame += dropped * ((remainder - dropped) / frames_per_minute);
+        }
+    }
+    
+    let ff = frame % nominal;
+    let total_seconds = frame / nominal;
+    format!(
+        "{:02}:{:02}:{:02}{}{:02}",
+        total_seconds / 3600,
+        total_seconds / 60 % 60,
+        total_seconds % 60,
+        if drop_frame { ';' } else { ':' },
+        ff
+    )
+}
+
+// 29.97（30000/1001）と59.94（60000/1001）
+fn is_drop_frame_rate(fps: f32) -> bool {
+    [30000.0 / 1001.0, 60000.0 / 1001.0].iter().any(|rate: &f32| (fps - rate).abs() < 0.01)
+}
+
+/// 時刻に対応するフレーム番号（0始まり）
+pub fn frame_at(seconds: f32, fps: f32) -> u64 {
+    (seconds.max(0.0) * fps).round() as u64
+}
+
+/// フレーム番号の表示開始時刻
+pub fn time_of_frame(frame: u64, fps: f32) -> f32 {
+    if fps > 0.0 { frame as f32 / fps } else { 0.0 }
+}
+
+/// 動画全体のフレーム数（平均fpsからの概算）
+pub fn total_frames(duration: f32, fps: f32) -> u64 {
+    (duration.max(0.0) * fps).floor() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NTSC: f32 = 30000.0 / 1001.0;
+
+    #[test]
+    fn timecode_and_frame_number_agree_on_every_frame() {
+        // 29.97fpsの最初の1時間分（108000フレーム）
+        for frame in 0..108_000 {
+            let time = time_of_frame(frame, NTSC);
+            assert_eq!(frame_at(time, NTSC), frame);
+            assert_eq!(format_timecode(time, NTSC), timecode_of_frame(frame, NTSC), "frame {}", frame);
+        }
+        // 「32フレームへ移動」した位置は32フレーム目（00:00:01;02）と表示する
+        assert_eq!(format_timecode(time_of_frame(32, NTSC), NTSC), "00:00:01;02");
+    }
+
+    #[test]
+    fn frame_boundaries_round_to_the_nearest_frame() {
+        let frame = 1.0 / 25.0;
+        assert_eq!(format_timecode(25.0 * frame, 25.0), "00:00:01:00");
+        assert_eq!(format_timecode(25.0 * frame - 0.001, 25.0), "00:00:01:00");
+        assert_eq!(format_timecode(24.0 * frame + 0.001, 25.0), "00:00:00:24");
+        assert_eq!(format_timecode(-1.0, 25.0), "00:00:00:00");
+        assert_eq!(format_timecode(1.0, 0.0), "--:--:--:--");
+    }
+
+    #[test]
+    fn drop_frame_skips_two_numbers_except_every_tenth_minute() {
+        // 1分の境目では;00と;01を飛ばす
+        assert_eq!(timecode_of_frame(1799, NTSC), "00:00:59;29");
+        assert_eq!(timecode_of_frame(1800, NTSC), "00:01:00;02");
+        // 10分の境目では飛ばさない
+        assert_eq!(timecode_of_frame(17981, NTSC), "00:09:59;29");
+        assert_eq!(timecode_of_frame(17982, NTSC), "00:10:00;00");
+        assert_eq!(format_timecode(time_of_frame(17982, NTSC), NTSC), "00:10:00;00");
+        // 59.94fpsは4つずつ飛ばす
+        assert_eq!(timecode_of_frame(3600, 60000.0 / 1001.0), "00:01:00;04");
+    }
+}
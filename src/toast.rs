@@ -0,0 +1,87 @@
+// 画面の右上に一時的に表示する通知（エラー・お知らせ）
+// 複数の通知は積み重ねて表示し、表示時間が過ぎたものから消す
+use std::time::{Duration, Instant};
+
+// 通知を表示しておく時間（エラーは読めるように長め）
+const INFO_DURATION: Duration = Duration::from_secs(4);
+const ERROR_DURATION: Duration = Duration::from_secs(8);
+
+// 同時に表示する通知の上限（超えたら古いものから消す）
+const MAX_TOASTS: usize = 5;
+
+pub struct Toast {
+    pub id: i32,
+    pub message: String,
+    pub error: bool,
+    expires_at: Option<Instant>, // Noneなら閉じるまで表示する
+}
+
+impl Toast {
+    pub fn is_persistent(&self) -> bool {
+        self.expires_at.is_none()
+    }
+}
+
+#[derive(Default)]
+pub struct Toasts {
+    items: Vec<Toast>,
+    next_id: i32,
+    changed: bool,
+}
+
+impl Toasts {
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.push(message.into(), false, Some(INFO_DURATION));
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.push(message.into(), true, Some(ERROR_DURATION));
+    }
+
+    /// 閉じるまで消えないエラー（ffmpegが見つからないなど、操作を続けても解決しないもの）
+    pub fn persistent_error(&mut self, message: impl Into<String>) {
+        self.push(message.into(), true, None);
+    }
+
+    fn push(&mut self, message: String, error: bool, duration: Option<Duration>) {
+        let expires_at = duration.map(|duration| Instant::now() + duration);
+
+        // 同じ内容が表示中なら、並べずに表示時間だけ延ばす
+        if let Some(toast) = self.items.iter_mut().find(|toast| toast.message == message && toast.error == error) {
+            if !toast.is_persistent() {
+                toast.expires_at = expires_at;
+            }
+            return;
+        }
+
+        self.next_id += 1;
+        self.items.push(Toast { id: self.next_id, message, error, expires_at });
+        while self.items.len() > MAX_TOASTS {
+            // 閉じるまで残す通知は押し出さない
+            match self.items.iter().position(|toast| !toast.is_persistent()) {
+                Some(index) => self.items.remove(index),
+                None => self.items.remove(0),
+            };
+        }
+        self.changed = true;
+    }
+
+    pub fn dismiss(&mut self, id: i32) {
+        let count = self.items.len();
+        self.items.retain(|toast| toast.id != id);
+        self.changed |= self.items.len() != count;
+    }
+
+    /// 表示時間が過ぎた通知を消す。前回から表示内容が変わっていればtrue（UIに反映する）
+    pub fn update(&mut self) -> bool {
+        let now = Instant::now();
+        let count = self.items.len();
+        self.items.retain(|toast| toast.expires_at.is_none_or(|until| now < until));
+        self.changed |= self.items.len() != count;
+        std::mem::take(&mut self.changed)
+    }
+
+    pub fn items(&self) -> &[Toast] {
+        &self.items
+    }
+}
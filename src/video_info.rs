@@ -0,0 +1,140 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+// ffprobeで取得した動画情報（width/heightは表示用にスケーリング済み）
+#[derive(Clone, Debug)]
+pub struct VideoInfo {
+    pub duration: f32,
+    pub fps: f32,
+    pub variable_frame_rate: bool, // r_frame_rateと平均が食い違う（fpsは平均なので近似になる）
+    pub width: u32,
+    pub height: u32,
+    pub source_width: u32, // 縮小する前の解像度
+    pub source_height: u32,
+    pub pixel_aspect: f32, // 表示する向きでの1ピクセルの横÷縦（SAR。正方形なら1）
+    pub codec: Option<String>, // ffprobeのcodec_name（分からなければNone）
+    pub audio_channels: u32,
+    pub color: ColorInfo,
+}
+
+impl VideoInfo {
+    /// タイトルバーに出す概要（例: "1920x1080 H.264 23.976fps"）
+    pub fn summary(&self) -> String {
+        let mut parts = vec![format!("{}x{}", self.source_width, self.source_height)];
+        if let Some(codec) = &self.codec {
+            parts.push(codec_display_name(codec));
+        }
+        // 23.976のように必要な桁だけ出す（30.000は30）
+        let fps = format!("{:.3}", self.fps);
+        parts.push(format!("{}fps", fps.trim_end_matches('0').trim_end_matches('.')));
+        parts.join(" ")
+    }
+}
+
+// よく見るコーデックは一般的な表記にする（それ以外は大文字にするだけ）
+fn codec_display_name(codec: &str) -> String {
+    match codec {
+        "h264" => "H.264".to_string(),
+        "hevc" => "H.265".to_string(),
+        "mpeg2video" => "MPEG-2".to_string(),
+        "mpeg4" => "MPEG-4".to_string(),
+        "prores" => "ProRes".to_string(),
+        _ => codec.to_uppercase(),
+    }
+}
+
+// RGBへの変換に使う色のメタデータ
+#[derive(Clone, Debug)]
+pub struct ColorInfo {
+    pub matrix: &'static str, // scaleフィルターのin_color_matrixに渡す値
+    pub full_range: bool,
+    pub transfer: Option<String>, // color_transfer（不明ならNone）
+    pub tagged: bool, // color_spaceが指定されている（ffmpegのin_color_matrix=autoでも同じ行列になる）
+}
+
+impl ColorInfo {
+    /// ffprobeのcolor_space/color_range/color_transferから組み立てる
+    /// 行列が不明な場合は元の高さから推定する（HDはBT.709、SDはBT.601）
+    pub fn from_probe(space: Option<&str>, range: Option<&str>, transfer: Option<&str>, source_height: u32) -> Self {
+        let (matrix, tagged) = match space {
+            Some("bt709") => ("bt709", true),
+            Some("smpte170m") | Some("bt470bg") => ("bt601", true),
+            Some("bt2020nc") | Some("bt2020c") => ("bt2020", true),
+            Some("smpte240m") => ("smpte240m", true),
+            Some("fcc") => ("fcc", true),
+            _ if source_height >= 720 => ("bt709", false),
+            _ => ("bt601", false),
+        };
+        Self {
+            matrix,
+            full_range: matches!(range, Some("pc") | Some("jpeg")),
+            transfer: transfer.map(|t| t.to_string()),
+            tagged,
+        }
+    }
+    
+    /// HDRの伝達特性（PQ / HLG）ならその名前
+    pub fn hdr_kind(&self) -> Option<&'static str> {
+        match self.transfer.as_deref() {
+            Some("smpte2084") => Some("PQ"),
+            Some("arib-std-b67") => Some("HLG"),
+            _ => None,
+        }
+    }
+}
+
+// キャッシュの上限（長時間使っても増え続けないように）
+const MAX_PROBE_CACHE_ENTRIES: usize = 64;
+
+// ファイルの同一性（パス・更新日時・サイズのいずれかが変わったら別物とみなす）
+#[derive(Clone, PartialEq, Eq)]
+struct ProbeKey {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    size: u64,
+}
+
+impl ProbeKey {
+    fn new(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(Self {
+            path: path.to_path_buf(),
+            modified: metadata.modified().ok(),
+            size: metadata.len(),
+        })
+    }
+}
+
+// ffprobeの結果のキャッシュ（最近使ったものほど先頭、上限を超えたら末尾から削除）
+#[derive(Default)]
+pub struct ProbeCache {
+    entries: Vec<(ProbeKey, VideoInfo)>,
+}
+
+impl ProbeCache {
+    pub fn get(&mut self, path: &Path) -> Option<VideoInfo> {
+        let key = ProbeKey::new(path)?;
+        let index = self.entries.iter().position(|(k, _)| k.path == key.path)?;
+
+        // 更新日時やサイズが変わっていたら無効
+        if self.entries[index].0 != key {
+            self.entries.remove(index);
+            return None;
+        }
+
+        let entry = self.entries.remove(index);
+        let info = entry.1.clone();
+        self.entries.insert(0, entry);
+        Some(info)
+    }
+
+    pub fn insert(&mut self, path: &Path, info: VideoInfo) {
+        let Some(key) = ProbeKey::new(path) else {
+            return;
+        };
+        self.entries.retain(|(k, _)| k.path != key.path);
+        self.entries.insert(0, (key, info));
+        self.entries.truncate(MAX_PROBE_CACHE_ENTRIES);
+    }
+}